@@ -985,7 +985,8 @@ impl MassaRpcServer for API<Public> {
             .collect::<RpcResult<Vec<SecureShareOperation>>>()?;
         to_send.store_operations(verified_ops.clone());
         let ids: Vec<OperationId> = verified_ops.iter().map(|op| op.id).collect();
-        cmd_sender.add_operations(to_send.clone());
+        // the pool logs the operations it could not add
+        let _ = cmd_sender.add_operations(to_send.clone());
 
         tokio::task::spawn_blocking(move || protocol_sender.propagate_operations(to_send))
             .await
@@ -265,6 +265,14 @@ impl BlockFactoryWorker {
                 break;
             }
 
+            // let the pool drop its expired items, whether or not we produce a block at this slot
+            if let Err(err) = self.channels.pool.notify_new_slot(slot) {
+                warn!(
+                    "block factory could not notify the pool of slot {}: {}",
+                    slot, err
+                );
+            }
+
             // process slot
             self.process_slot(slot);
 
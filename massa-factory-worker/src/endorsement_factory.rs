@@ -202,8 +202,9 @@ impl EndorsementFactoryWorker {
         let mut endo_storage = self.channels.storage.clone_without_refs();
         endo_storage.store_endorsements(endorsements);
 
-        // send endorsement to pool for listing and propagation
-        self.channels.pool.add_endorsements(endo_storage.clone());
+        // send endorsement to pool for listing and propagation,
+        // the pool logging the endorsements it could not add
+        let _ = self.channels.pool.add_endorsements(endo_storage.clone());
 
         if let Err(err) = self.channels.protocol.propagate_endorsements(endo_storage) {
             warn!("could not propagate endorsements to protocol: {}", err);
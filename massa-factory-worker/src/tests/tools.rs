@@ -179,27 +179,35 @@ impl TestFactory {
                 })
                 .unwrap();
         }
-        self.pool_receiver
-            .wait_command(MassaTime::from_millis(100), |command| match command {
-                MockPoolControllerMessage::GetBlockEndorsements {
-                    block_id: _,
-                    slot: _,
-                    response_tx,
-                } => {
-                    if let Some(endorsements) = &endorsements {
-                        let ids = endorsements.iter().map(|endo| Some(endo.id)).collect();
-                        let mut storage = self.storage.clone_without_refs();
-                        storage.store_endorsements(endorsements.clone());
-                        response_tx.send((ids, self.storage.clone())).unwrap();
-                        Some(())
-                    } else {
-                        response_tx.send((vec![], Storage::create_root())).unwrap();
-                        Some(())
+        // the factory notifies the pool of each slot before asking for the block endorsements
+        loop {
+            let endorsements_sent = self
+                .pool_receiver
+                .wait_command(MassaTime::from_millis(100), |command| match command {
+                    MockPoolControllerMessage::NotifyNewSlot { slot: _ } => Some(false),
+                    MockPoolControllerMessage::GetBlockEndorsements {
+                        block_id: _,
+                        slot: _,
+                        response_tx,
+                    } => {
+                        if let Some(endorsements) = &endorsements {
+                            let ids = endorsements.iter().map(|endo| Some(endo.id)).collect();
+                            let mut storage = self.storage.clone_without_refs();
+                            storage.store_endorsements(endorsements.clone());
+                            response_tx.send((ids, self.storage.clone())).unwrap();
+                            Some(true)
+                        } else {
+                            response_tx.send((vec![], Storage::create_root())).unwrap();
+                            Some(true)
+                        }
                     }
-                }
-                _ => panic!("unexpected message"),
-            })
-            .unwrap();
+                    _ => panic!("unexpected message"),
+                })
+                .unwrap();
+            if endorsements_sent {
+                break;
+            }
+        }
 
         self.pool_receiver
             .wait_command(MassaTime::from_millis(100), |command| match command {
@@ -103,8 +103,9 @@ pub(crate) async fn send_endorsements(
                                     endorsement_storage.store_endorsements(
                                         verified_eds.values().cloned().collect(),
                                     );
-                                    // Add the received endorsements to the endorsements pool
-                                    pool_command_sender
+                                    // Add the received endorsements to the endorsements pool,
+                                    // which logs the endorsements it could not add
+                                    let _ = pool_command_sender
                                         .add_endorsements(endorsement_storage.clone());
 
                                     // Propagate the endorsements to the network
@@ -104,8 +104,10 @@ pub(crate) async fn send_operations(
                                     let mut operation_storage = storage.clone_without_refs();
                                     operation_storage
                                         .store_operations(verified_ops.values().cloned().collect());
-                                    // Add the received operations to the operations pool
-                                    pool_controller.add_operations(operation_storage.clone());
+                                    // Add the received operations to the operations pool,
+                                    // which logs the operations it could not add
+                                    let _ =
+                                        pool_controller.add_operations(operation_storage.clone());
 
                                     // Propagate the operations to the network
                                     if let Err(e) =
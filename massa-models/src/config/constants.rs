@@ -231,6 +231,8 @@ pub const POOL_CONTROLLER_OPERATIONS_CHANNEL_SIZE: usize = 1024;
 pub const POOL_CONTROLLER_ENDORSEMENTS_CHANNEL_SIZE: usize = 1024;
 /// Pool controller denunciations channel size
 pub const POOL_CONTROLLER_DENUNCIATIONS_CHANNEL_SIZE: usize = 1024;
+/// Pool events channel size, per subscriber
+pub const POOL_EVENTS_CHANNEL_SIZE: usize = 1024;
 
 // ***********************
 // Constants used for execution module (injected from ConsensusConfig)
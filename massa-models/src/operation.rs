@@ -379,13 +379,21 @@ impl Deserializer<OperationId> for OperationIdDeserializer {
     }
 }
 
-#[derive(IntoPrimitive, Debug, Eq, PartialEq, TryFromPrimitive)]
+/// Identifier of the type of an operation, as serialized
+#[derive(
+    IntoPrimitive, Debug, Clone, Copy, Eq, PartialEq, Hash, TryFromPrimitive, Serialize, Deserialize,
+)]
 #[repr(u32)]
-enum OperationTypeId {
+pub enum OperationTypeId {
+    /// transfer of coins
     Transaction = 0,
+    /// purchase of rolls
     RollBuy = 1,
+    /// sale of rolls
     RollSell = 2,
+    /// execution of a smart contract bytecode
     ExecuteSC = 3,
+    /// call of a stored smart contract
     CallSC = 4,
 }
 
@@ -982,6 +990,17 @@ impl SecureShareOperation {
         }
     }
 
+    /// Get the identifier of the type of the operation
+    pub fn get_type_id(&self) -> OperationTypeId {
+        match &self.content.op {
+            OperationType::Transaction { .. } => OperationTypeId::Transaction,
+            OperationType::RollBuy { .. } => OperationTypeId::RollBuy,
+            OperationType::RollSell { .. } => OperationTypeId::RollSell,
+            OperationType::ExecuteSC { .. } => OperationTypeId::ExecuteSC,
+            OperationType::CallSC { .. } => OperationTypeId::CallSC,
+        }
+    }
+
     /// get the addresses that are involved in this operation from a ledger point of view
     pub fn get_ledger_involved_addresses(&self) -> PreHashSet<Address> {
         let mut res = PreHashSet::<Address>::default();
@@ -328,6 +328,21 @@ impl Slot {
             .ok_or(ModelsError::PeriodOverflowError)?
             .saturating_sub(s.thread as u64))
     }
+
+    /// Returns the number of periods between self and the slot passed in parameter,
+    /// whatever the order of the two slots. Threads are not taken into account:
+    /// two slots of the same period are at distance `0`, even in different threads.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use massa_models::slot::Slot;
+    /// assert_eq!(Slot::new(10, 3).distance_in_periods(&Slot::new(12, 0)), 2);
+    /// assert_eq!(Slot::new(12, 0).distance_in_periods(&Slot::new(10, 3)), 2);
+    /// assert_eq!(Slot::new(10, 0).distance_in_periods(&Slot::new(10, 3)), 0);
+    /// ```
+    pub fn distance_in_periods(&self, other: &Slot) -> u64 {
+        self.period.abs_diff(other.period)
+    }
 }
 
 /// When an address is drawn to create an endorsement it is selected for a specific index
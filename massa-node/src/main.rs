@@ -74,6 +74,7 @@ use massa_models::config::{
     KEEP_EXECUTED_HISTORY_EXTRA_PERIODS, MAX_BOOTSTRAPPED_NEW_ELEMENTS, MAX_EVENT_DATA_SIZE,
     MAX_MESSAGE_SIZE, POOL_CONTROLLER_DENUNCIATIONS_CHANNEL_SIZE,
     POOL_CONTROLLER_ENDORSEMENTS_CHANNEL_SIZE, POOL_CONTROLLER_OPERATIONS_CHANNEL_SIZE,
+    POOL_EVENTS_CHANNEL_SIZE,
 };
 use massa_models::slot::Slot;
 use massa_pool_exports::{PoolChannels, PoolConfig, PoolManager};
@@ -535,12 +536,40 @@ async fn launch(
         max_operations_per_block: MAX_OPERATIONS_PER_BLOCK,
         max_operation_pool_size: SETTINGS.pool.max_operation_pool_size,
         max_operation_pool_excess_items: SETTINGS.pool.max_operation_pool_excess_items,
+        max_ops_per_sender: SETTINGS.pool.max_ops_per_sender,
+        max_ops_added_per_sender_per_slot: SETTINGS.pool.max_ops_added_per_sender_per_slot,
+        hot_tier_size: SETTINGS.pool.hot_tier_size,
+        max_operation_size_bytes: SETTINGS.pool.max_operation_size_bytes,
+        allowed_operation_types: SETTINGS.pool.allowed_operation_types.clone(),
+        denied_operation_types: SETTINGS.pool.denied_operation_types.clone(),
+        min_fee: SETTINGS.pool.min_fee,
+        eviction_policy: SETTINGS.pool.eviction_policy,
+        selection_strategy: SETTINGS.pool.selection_strategy,
+        wal_path: SETTINGS.pool.wal_path.clone(),
+        event_log_path: SETTINGS.pool.event_log_path.clone(),
+        memory_limit_bytes: SETTINGS.pool.memory_limit_bytes,
         operation_pool_refresh_interval: SETTINGS.pool.operation_pool_refresh_interval,
         operation_max_future_start_delay: SETTINGS.pool.operation_max_future_start_delay,
         max_endorsements_pool_size_per_thread: SETTINGS.pool.max_endorsements_pool_size_per_thread,
+        max_endorsement_pool_size: SETTINGS.pool.max_endorsement_pool_size,
+        operation_bloom_filter_size: SETTINGS.pool.operation_bloom_filter_size,
+        endorsement_bloom_filter_size: SETTINGS.pool.endorsement_bloom_filter_size,
+        bloom_filter_false_positive_rate: SETTINGS.pool.bloom_filter_false_positive_rate,
+        operation_pool_shard_count: SETTINGS.pool.operation_pool_shard_count,
+        validation_parallelism: SETTINGS.pool.validation_parallelism,
+        endorsement_sig_cache_size: SETTINGS.pool.endorsement_sig_cache_size,
+        compact_after_evictions: SETTINGS.pool.compact_after_evictions,
+        endorsement_age_histogram_buckets: SETTINGS.pool.endorsement_age_histogram_buckets.clone(),
+        // the node does not set any operation validator
+        revalidation_interval_slots: 0,
+        stale_operation_warn_slots: SETTINGS.pool.stale_operation_warn_slots,
         operations_channel_size: POOL_CONTROLLER_OPERATIONS_CHANNEL_SIZE,
         endorsements_channel_size: POOL_CONTROLLER_ENDORSEMENTS_CHANNEL_SIZE,
         denunciations_channel_size: POOL_CONTROLLER_DENUNCIATIONS_CHANNEL_SIZE,
+        backpressure_warn_threshold: SETTINGS.pool.backpressure_warn_threshold,
+        pool_events_channel_size: POOL_EVENTS_CHANNEL_SIZE,
+        worker_restart_max_attempts: SETTINGS.pool.worker_restart_max_attempts,
+        worker_stop_timeout_ms: SETTINGS.pool.worker_stop_timeout_ms,
         broadcast_enabled: SETTINGS.api.enable_broadcast,
         broadcast_endorsements_channel_capacity: SETTINGS
             .pool
@@ -567,7 +596,8 @@ async fn launch(
         &shared_storage,
         pool_channels.clone(),
         node_wallet.clone(),
-    );
+    )
+    .expect("could not start pool controller");
 
     // launch protocol controller
     let mut listeners = HashMap::default();
@@ -73,7 +73,8 @@ pub fn start_operation_injector(
     println!("Sending init ops len: {}", init_ops.len());
     let mut storage = storage.clone_without_refs();
     storage.store_operations(init_ops);
-    pool_controller.add_operations(storage.clone());
+    // the pool logs the operations it could not add
+    let _ = pool_controller.add_operations(storage.clone());
     protocol_controller
         .propagate_operations(storage.clone())
         .unwrap();
@@ -110,7 +111,7 @@ pub fn start_operation_injector(
                 }
             }
             storage.store_operations(ops);
-            pool_controller.add_operations(storage.clone());
+            let _ = pool_controller.add_operations(storage.clone());
             protocol_controller
                 .propagate_operations(storage.clone())
                 .unwrap();
@@ -4,7 +4,10 @@
 use std::{collections::HashMap, path::PathBuf};
 
 use massa_bootstrap::IpType;
-use massa_models::{config::build_massa_settings, node::NodeId};
+use massa_models::{
+    amount::Amount, config::build_massa_settings, node::NodeId, operation::OperationTypeId,
+};
+use massa_pool_exports::{EvictionPolicy, OperationSelectionStrategy};
 use massa_protocol_exports::PeerCategoryInfo;
 use massa_time::MassaTime;
 use serde::Deserialize;
@@ -90,9 +93,34 @@ pub struct FactorySettings {
 pub struct PoolSettings {
     pub max_operation_pool_size: usize,
     pub max_operation_pool_excess_items: usize,
+    pub max_ops_per_sender: usize,
+    pub max_ops_added_per_sender_per_slot: usize,
+    pub hot_tier_size: usize,
+    pub max_operation_size_bytes: usize,
+    pub allowed_operation_types: Option<Vec<OperationTypeId>>,
+    pub denied_operation_types: Option<Vec<OperationTypeId>>,
+    pub min_fee: Amount,
+    pub eviction_policy: EvictionPolicy,
+    pub selection_strategy: OperationSelectionStrategy,
+    pub wal_path: Option<PathBuf>,
+    pub event_log_path: Option<PathBuf>,
+    pub memory_limit_bytes: Option<u64>,
     pub operation_max_future_start_delay: MassaTime,
     pub operation_pool_refresh_interval: MassaTime,
     pub max_endorsements_pool_size_per_thread: usize,
+    pub max_endorsement_pool_size: usize,
+    pub operation_bloom_filter_size: usize,
+    pub endorsement_bloom_filter_size: usize,
+    pub bloom_filter_false_positive_rate: f64,
+    pub operation_pool_shard_count: usize,
+    pub validation_parallelism: usize,
+    pub endorsement_sig_cache_size: usize,
+    pub compact_after_evictions: usize,
+    pub endorsement_age_histogram_buckets: Vec<u64>,
+    pub stale_operation_warn_slots: u64,
+    pub backpressure_warn_threshold: f32,
+    pub worker_restart_max_attempts: u32,
+    pub worker_stop_timeout_ms: u64,
     pub max_item_return_count: usize,
     /// endorsements channel capacity
     pub broadcast_endorsements_channel_capacity: usize,
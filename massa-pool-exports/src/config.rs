@@ -1,11 +1,35 @@
 //! Copyright (c) 2022 MASSA LABS <info@massa.net>
 
-use massa_models::amount::Amount;
+use massa_models::{amount::Amount, operation::OperationTypeId};
 use massa_time::MassaTime;
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::PoolConfigError;
+
+/// Policy used to choose which operations to evict when the operation pool is full
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EvictionPolicy {
+    /// evict the operations with the lowest fee per byte first
+    #[default]
+    LowestFee,
+    /// evict the operations that were added to the pool first
+    Oldest,
+}
+
+/// Order in which the operations of the pool are selected for inclusion in a block
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OperationSelectionStrategy {
+    /// select the operations with the highest fee per byte first
+    #[default]
+    FeeDescending,
+    /// select the operations that were added to the pool first,
+    /// making block contents deterministic
+    Fifo,
+}
 
 /// Pool configuration
-#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct PoolConfig {
     /// thread count
     pub thread_count: u8,
@@ -27,16 +51,94 @@ pub struct PoolConfig {
     pub max_operation_pool_size: usize,
     /// max excess on pool size (in-between refreshes)
     pub max_operation_pool_excess_items: usize,
+    /// max number of operations kept in the pool per sender address
+    pub max_ops_per_sender: usize,
+    /// max number of operations a sender address can add to the pool during a slot,
+    /// its other operations being skipped until the next slot
+    pub max_ops_added_per_sender_per_slot: usize,
+    /// number of highest fee per byte operations making the "hot tier" of the operation pool,
+    /// used to report how often blocks are filled from the best operations only
+    pub hot_tier_size: usize,
+    /// max serialized size (in bytes) of the operations accepted in the operation pool
+    pub max_operation_size_bytes: usize,
+    /// types of the operations accepted in the operation pool, all types being accepted if not set.
+    /// It takes precedence over `denied_operation_types` when both are set.
+    pub allowed_operation_types: Option<Vec<OperationTypeId>>,
+    /// types of the operations refused by the operation pool,
+    /// ignored if `allowed_operation_types` is set
+    pub denied_operation_types: Option<Vec<OperationTypeId>>,
+    /// minimal fee of the operations accepted in the operation pool.
+    /// It can be raised at runtime through the pool controller.
+    pub min_fee: Amount,
+    /// which operations are evicted first when the operation pool is full
+    pub eviction_policy: EvictionPolicy,
+    /// order in which the operations of the pool are selected for inclusion in a block
+    pub selection_strategy: OperationSelectionStrategy,
+    /// path of the write-ahead log of the operation pool, replayed on startup to restore the pool.
+    /// The operation pool is not persisted if it is not set.
+    pub wal_path: Option<PathBuf>,
+    /// path of the log of the commands processed by the pool workers, one JSON entry per line,
+    /// replayed offline with the `replay_event_log` tool. No command is logged if it is not set.
+    pub event_log_path: Option<PathBuf>,
+    /// max estimated memory (in bytes) held by the operation pool,
+    /// operations being evicted according to `eviction_policy` beyond it.
+    /// No limit if it is not set.
+    pub memory_limit_bytes: Option<u64>,
     /// max endorsement pool size per thread (in number of endorsements)
     pub max_endorsements_pool_size_per_thread: usize,
+    /// max endorsement pool size over all threads (in number of endorsements),
+    /// the endorsements of the first added batches being evicted first
+    pub max_endorsement_pool_size: usize,
+    /// size (in bits) of the bloom filter used to speed up operation pool membership queries
+    pub operation_bloom_filter_size: usize,
+    /// size (in bits) of the bloom filter used to speed up endorsement pool membership queries
+    pub endorsement_bloom_filter_size: usize,
+    /// target false positive rate of the pool bloom filters
+    pub bloom_filter_false_positive_rate: f64,
+    /// number of independently locked shards of the index answering operation membership queries
+    pub operation_pool_shard_count: usize,
+    /// number of threads checking the signatures of incoming operations before they enter the pool:
+    /// 0 disables the check, 1 runs it on the operation pool worker thread,
+    /// and higher values use a dedicated thread pool (requires the `parallel` feature of the pool worker)
+    pub validation_parallelism: usize,
+    /// number of endorsement signature check results kept to skip checking
+    /// the same endorsement again when it is received several times
+    pub endorsement_sig_cache_size: usize,
+    /// number of endorsements removed from the endorsement pool after which its indexes
+    /// are compacted to release their unused capacity (0 disables the automatic compaction)
+    pub compact_after_evictions: usize,
+    /// lower bounds (in periods, strictly increasing) of the buckets of the endorsement age
+    /// histogram of the pool statistics, the age of an endorsement being the number of periods
+    /// since its slot
+    pub endorsement_age_histogram_buckets: Vec<u64>,
+    /// number of slots between two re-validations of the operations of the pool by the
+    /// `OperationValidator` set when starting the pool, if any (0 disables the re-validation)
+    pub revalidation_interval_slots: u64,
+    /// number of slots (not periods) an operation can wait in the pool before it is reported
+    /// as stale in a warning at each new slot (0 disables the warning)
+    pub stale_operation_warn_slots: u64,
     /// max number of endorsements per block
     pub max_block_endorsement_count: u32,
-    /// operations channel capacity
+    /// operations channel capacity.
+    /// A larger channel absorbs bursts of incoming operations,
+    /// at the cost of a longer delay before queued operations are processed.
     pub operations_channel_size: usize,
-    /// endorsements channel capacity
+    /// endorsements channel capacity.
+    /// A larger channel absorbs bursts of incoming endorsements,
+    /// at the cost of a longer delay before queued endorsements are processed.
     pub endorsements_channel_size: usize,
     /// denunciations channel capacity
     pub denunciations_channel_size: usize,
+    /// share (between 0 and 1) of the capacity of a worker channel above which the additions
+    /// waiting in it are logged as a warning, before the channel starts dropping them
+    pub backpressure_warn_threshold: f32,
+    /// capacity of the channel of each pool events subscriber.
+    /// Subscribers that let their channel fill up are dropped.
+    pub pool_events_channel_size: usize,
+    /// number of times a pool worker thread is restarted after a panic before giving up
+    pub worker_restart_max_attempts: u32,
+    /// maximum time (in milliseconds) to wait for each pool worker thread to exit on shutdown
+    pub worker_stop_timeout_ms: u64,
     /// whether operations broadcast is enabled
     pub broadcast_enabled: bool,
     /// endorsements channel capacity
@@ -59,3 +161,447 @@ pub struct PoolConfig {
     /// * If from bootstrap: set during bootstrap
     pub last_start_period: u64,
 }
+
+impl PoolConfig {
+    /// Check that the configuration values are consistent with each other
+    pub fn validate(&self) -> Result<(), PoolConfigError> {
+        if self.thread_count == 0 {
+            return Err(PoolConfigError::ZeroThreadCount);
+        }
+        for (channel, size) in [
+            ("operations", self.operations_channel_size),
+            ("endorsements", self.endorsements_channel_size),
+            ("denunciations", self.denunciations_channel_size),
+            ("pool events", self.pool_events_channel_size),
+        ] {
+            if size == 0 {
+                return Err(PoolConfigError::ZeroChannelSize(channel));
+            }
+        }
+        for (pool, size) in [
+            ("operation", self.operation_bloom_filter_size),
+            ("endorsement", self.endorsement_bloom_filter_size),
+        ] {
+            if size == 0 {
+                return Err(PoolConfigError::ZeroBloomFilterSize(pool));
+            }
+        }
+        if !(self.bloom_filter_false_positive_rate > 0.0
+            && self.bloom_filter_false_positive_rate < 1.0)
+        {
+            return Err(PoolConfigError::InvalidFalsePositiveRate(
+                self.bloom_filter_false_positive_rate,
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.backpressure_warn_threshold) {
+            return Err(PoolConfigError::InvalidBackpressureWarnThreshold(
+                self.backpressure_warn_threshold,
+            ));
+        }
+        if self.max_ops_per_sender == 0 {
+            return Err(PoolConfigError::ZeroMaxOpsPerSender);
+        }
+        if self.max_ops_added_per_sender_per_slot == 0 {
+            return Err(PoolConfigError::ZeroMaxOpsAddedPerSenderPerSlot);
+        }
+        if self.max_operation_size_bytes == 0 {
+            return Err(PoolConfigError::ZeroMaxOperationSize);
+        }
+        if self.operation_pool_shard_count == 0 {
+            return Err(PoolConfigError::ZeroShardCount);
+        }
+        if self.operation_pool_refresh_interval == MassaTime::from_millis(0) {
+            return Err(PoolConfigError::ZeroRefreshInterval);
+        }
+        if self.max_operation_pool_size < self.max_operations_per_block as usize {
+            return Err(PoolConfigError::OperationPoolTooSmall {
+                max_operation_pool_size: self.max_operation_pool_size,
+                max_operations_per_block: self.max_operations_per_block,
+            });
+        }
+        if self
+            .endorsement_age_histogram_buckets
+            .windows(2)
+            .any(|bounds| bounds[0] >= bounds[1])
+        {
+            return Err(PoolConfigError::UnsortedHistogramBuckets);
+        }
+        if self.max_endorsements_pool_size_per_thread < self.max_block_endorsement_count as usize {
+            return Err(PoolConfigError::EndorsementPoolTooSmall {
+                max_endorsements_pool_size_per_thread: self.max_endorsements_pool_size_per_thread,
+                max_block_endorsement_count: self.max_block_endorsement_count,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Builder of a `PoolConfig`, checked by `PoolConfig::validate` when built.
+///
+/// Starts from an existing configuration, or from the test defaults with the `testing` feature.
+#[derive(Debug, Clone)]
+pub struct PoolConfigBuilder {
+    config: PoolConfig,
+}
+
+impl PoolConfigBuilder {
+    /// Start building from the values of `config`
+    pub fn from_config(config: PoolConfig) -> Self {
+        PoolConfigBuilder { config }
+    }
+
+    /// Validate the configuration and return it
+    pub fn build(self) -> Result<PoolConfig, PoolConfigError> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+
+    /// Set [`PoolConfig::thread_count`]
+    pub fn with_thread_count(mut self, thread_count: u8) -> Self {
+        self.config.thread_count = thread_count;
+        self
+    }
+
+    /// Set [`PoolConfig::max_block_size`]
+    pub fn with_max_block_size(mut self, max_block_size: u32) -> Self {
+        self.config.max_block_size = max_block_size;
+        self
+    }
+
+    /// Set [`PoolConfig::max_block_gas`]
+    pub fn with_max_block_gas(mut self, max_block_gas: u64) -> Self {
+        self.config.max_block_gas = max_block_gas;
+        self
+    }
+
+    /// Set [`PoolConfig::roll_price`]
+    pub fn with_roll_price(mut self, roll_price: Amount) -> Self {
+        self.config.roll_price = roll_price;
+        self
+    }
+
+    /// Set [`PoolConfig::operation_validity_periods`]
+    pub fn with_operation_validity_periods(mut self, operation_validity_periods: u64) -> Self {
+        self.config.operation_validity_periods = operation_validity_periods;
+        self
+    }
+
+    /// Set [`PoolConfig::operation_pool_refresh_interval`]
+    pub fn with_operation_pool_refresh_interval(
+        mut self,
+        operation_pool_refresh_interval: MassaTime,
+    ) -> Self {
+        self.config.operation_pool_refresh_interval = operation_pool_refresh_interval;
+        self
+    }
+
+    /// Set [`PoolConfig::operation_max_future_start_delay`]
+    pub fn with_operation_max_future_start_delay(
+        mut self,
+        operation_max_future_start_delay: MassaTime,
+    ) -> Self {
+        self.config.operation_max_future_start_delay = operation_max_future_start_delay;
+        self
+    }
+
+    /// Set [`PoolConfig::max_operations_per_block`]
+    pub fn with_max_operations_per_block(mut self, max_operations_per_block: u32) -> Self {
+        self.config.max_operations_per_block = max_operations_per_block;
+        self
+    }
+
+    /// Set [`PoolConfig::max_operation_pool_size`]
+    pub fn with_max_operation_pool_size(mut self, max_operation_pool_size: usize) -> Self {
+        self.config.max_operation_pool_size = max_operation_pool_size;
+        self
+    }
+
+    /// Set [`PoolConfig::max_operation_pool_excess_items`]
+    pub fn with_max_operation_pool_excess_items(
+        mut self,
+        max_operation_pool_excess_items: usize,
+    ) -> Self {
+        self.config.max_operation_pool_excess_items = max_operation_pool_excess_items;
+        self
+    }
+
+    /// Set [`PoolConfig::max_ops_per_sender`]
+    pub fn with_max_ops_per_sender(mut self, max_ops_per_sender: usize) -> Self {
+        self.config.max_ops_per_sender = max_ops_per_sender;
+        self
+    }
+
+    /// Set [`PoolConfig::max_ops_added_per_sender_per_slot`]
+    pub fn with_max_ops_added_per_sender_per_slot(
+        mut self,
+        max_ops_added_per_sender_per_slot: usize,
+    ) -> Self {
+        self.config.max_ops_added_per_sender_per_slot = max_ops_added_per_sender_per_slot;
+        self
+    }
+
+    /// Set [`PoolConfig::hot_tier_size`]
+    pub fn with_hot_tier_size(mut self, hot_tier_size: usize) -> Self {
+        self.config.hot_tier_size = hot_tier_size;
+        self
+    }
+
+    /// Set [`PoolConfig::max_operation_size_bytes`]
+    pub fn with_max_operation_size_bytes(mut self, max_operation_size_bytes: usize) -> Self {
+        self.config.max_operation_size_bytes = max_operation_size_bytes;
+        self
+    }
+
+    /// Set [`PoolConfig::allowed_operation_types`]
+    pub fn with_allowed_operation_types(
+        mut self,
+        allowed_operation_types: Option<Vec<OperationTypeId>>,
+    ) -> Self {
+        self.config.allowed_operation_types = allowed_operation_types;
+        self
+    }
+
+    /// Set [`PoolConfig::denied_operation_types`]
+    pub fn with_denied_operation_types(
+        mut self,
+        denied_operation_types: Option<Vec<OperationTypeId>>,
+    ) -> Self {
+        self.config.denied_operation_types = denied_operation_types;
+        self
+    }
+
+    /// Set [`PoolConfig::min_fee`]
+    pub fn with_min_fee(mut self, min_fee: Amount) -> Self {
+        self.config.min_fee = min_fee;
+        self
+    }
+
+    /// Set [`PoolConfig::eviction_policy`]
+    pub fn with_eviction_policy(mut self, eviction_policy: EvictionPolicy) -> Self {
+        self.config.eviction_policy = eviction_policy;
+        self
+    }
+
+    /// Set [`PoolConfig::selection_strategy`]
+    pub fn with_selection_strategy(
+        mut self,
+        selection_strategy: OperationSelectionStrategy,
+    ) -> Self {
+        self.config.selection_strategy = selection_strategy;
+        self
+    }
+
+    /// Set [`PoolConfig::wal_path`]
+    pub fn with_wal_path(mut self, wal_path: Option<PathBuf>) -> Self {
+        self.config.wal_path = wal_path;
+        self
+    }
+
+    /// Set [`PoolConfig::event_log_path`]
+    pub fn with_event_log_path(mut self, event_log_path: Option<PathBuf>) -> Self {
+        self.config.event_log_path = event_log_path;
+        self
+    }
+
+    /// Set [`PoolConfig::memory_limit_bytes`]
+    pub fn with_memory_limit_bytes(mut self, memory_limit_bytes: Option<u64>) -> Self {
+        self.config.memory_limit_bytes = memory_limit_bytes;
+        self
+    }
+
+    /// Set [`PoolConfig::max_endorsements_pool_size_per_thread`]
+    pub fn with_max_endorsements_pool_size_per_thread(
+        mut self,
+        max_endorsements_pool_size_per_thread: usize,
+    ) -> Self {
+        self.config.max_endorsements_pool_size_per_thread = max_endorsements_pool_size_per_thread;
+        self
+    }
+
+    /// Set [`PoolConfig::max_endorsement_pool_size`]
+    pub fn with_max_endorsement_pool_size(mut self, max_endorsement_pool_size: usize) -> Self {
+        self.config.max_endorsement_pool_size = max_endorsement_pool_size;
+        self
+    }
+
+    /// Set [`PoolConfig::operation_bloom_filter_size`]
+    pub fn with_operation_bloom_filter_size(mut self, operation_bloom_filter_size: usize) -> Self {
+        self.config.operation_bloom_filter_size = operation_bloom_filter_size;
+        self
+    }
+
+    /// Set [`PoolConfig::endorsement_bloom_filter_size`]
+    pub fn with_endorsement_bloom_filter_size(
+        mut self,
+        endorsement_bloom_filter_size: usize,
+    ) -> Self {
+        self.config.endorsement_bloom_filter_size = endorsement_bloom_filter_size;
+        self
+    }
+
+    /// Set [`PoolConfig::bloom_filter_false_positive_rate`]
+    pub fn with_bloom_filter_false_positive_rate(
+        mut self,
+        bloom_filter_false_positive_rate: f64,
+    ) -> Self {
+        self.config.bloom_filter_false_positive_rate = bloom_filter_false_positive_rate;
+        self
+    }
+
+    /// Set [`PoolConfig::operation_pool_shard_count`]
+    pub fn with_operation_pool_shard_count(mut self, operation_pool_shard_count: usize) -> Self {
+        self.config.operation_pool_shard_count = operation_pool_shard_count;
+        self
+    }
+
+    /// Set [`PoolConfig::validation_parallelism`]
+    pub fn with_validation_parallelism(mut self, validation_parallelism: usize) -> Self {
+        self.config.validation_parallelism = validation_parallelism;
+        self
+    }
+
+    /// Set [`PoolConfig::endorsement_sig_cache_size`]
+    pub fn with_endorsement_sig_cache_size(mut self, endorsement_sig_cache_size: usize) -> Self {
+        self.config.endorsement_sig_cache_size = endorsement_sig_cache_size;
+        self
+    }
+
+    /// Set [`PoolConfig::compact_after_evictions`]
+    pub fn with_compact_after_evictions(mut self, compact_after_evictions: usize) -> Self {
+        self.config.compact_after_evictions = compact_after_evictions;
+        self
+    }
+
+    /// Set [`PoolConfig::endorsement_age_histogram_buckets`]
+    pub fn with_endorsement_age_histogram_buckets(
+        mut self,
+        endorsement_age_histogram_buckets: Vec<u64>,
+    ) -> Self {
+        self.config.endorsement_age_histogram_buckets = endorsement_age_histogram_buckets;
+        self
+    }
+
+    /// Set [`PoolConfig::revalidation_interval_slots`]
+    pub fn with_revalidation_interval_slots(mut self, revalidation_interval_slots: u64) -> Self {
+        self.config.revalidation_interval_slots = revalidation_interval_slots;
+        self
+    }
+
+    /// Set [`PoolConfig::stale_operation_warn_slots`]
+    pub fn with_stale_operation_warn_slots(mut self, stale_operation_warn_slots: u64) -> Self {
+        self.config.stale_operation_warn_slots = stale_operation_warn_slots;
+        self
+    }
+
+    /// Set [`PoolConfig::max_block_endorsement_count`]
+    pub fn with_max_block_endorsement_count(mut self, max_block_endorsement_count: u32) -> Self {
+        self.config.max_block_endorsement_count = max_block_endorsement_count;
+        self
+    }
+
+    /// Set [`PoolConfig::operations_channel_size`]
+    pub fn with_operations_channel_size(mut self, operations_channel_size: usize) -> Self {
+        self.config.operations_channel_size = operations_channel_size;
+        self
+    }
+
+    /// Set [`PoolConfig::endorsements_channel_size`]
+    pub fn with_endorsements_channel_size(mut self, endorsements_channel_size: usize) -> Self {
+        self.config.endorsements_channel_size = endorsements_channel_size;
+        self
+    }
+
+    /// Set [`PoolConfig::denunciations_channel_size`]
+    pub fn with_denunciations_channel_size(mut self, denunciations_channel_size: usize) -> Self {
+        self.config.denunciations_channel_size = denunciations_channel_size;
+        self
+    }
+
+    /// Set [`PoolConfig::backpressure_warn_threshold`]
+    pub fn with_backpressure_warn_threshold(mut self, backpressure_warn_threshold: f32) -> Self {
+        self.config.backpressure_warn_threshold = backpressure_warn_threshold;
+        self
+    }
+
+    /// Set [`PoolConfig::pool_events_channel_size`]
+    pub fn with_pool_events_channel_size(mut self, pool_events_channel_size: usize) -> Self {
+        self.config.pool_events_channel_size = pool_events_channel_size;
+        self
+    }
+
+    /// Set [`PoolConfig::worker_restart_max_attempts`]
+    pub fn with_worker_restart_max_attempts(mut self, worker_restart_max_attempts: u32) -> Self {
+        self.config.worker_restart_max_attempts = worker_restart_max_attempts;
+        self
+    }
+
+    /// Set [`PoolConfig::worker_stop_timeout_ms`]
+    pub fn with_worker_stop_timeout_ms(mut self, worker_stop_timeout_ms: u64) -> Self {
+        self.config.worker_stop_timeout_ms = worker_stop_timeout_ms;
+        self
+    }
+
+    /// Set [`PoolConfig::broadcast_enabled`]
+    pub fn with_broadcast_enabled(mut self, broadcast_enabled: bool) -> Self {
+        self.config.broadcast_enabled = broadcast_enabled;
+        self
+    }
+
+    /// Set [`PoolConfig::broadcast_endorsements_channel_capacity`]
+    pub fn with_broadcast_endorsements_channel_capacity(
+        mut self,
+        broadcast_endorsements_channel_capacity: usize,
+    ) -> Self {
+        self.config.broadcast_endorsements_channel_capacity =
+            broadcast_endorsements_channel_capacity;
+        self
+    }
+
+    /// Set [`PoolConfig::broadcast_operations_channel_capacity`]
+    pub fn with_broadcast_operations_channel_capacity(
+        mut self,
+        broadcast_operations_channel_capacity: usize,
+    ) -> Self {
+        self.config.broadcast_operations_channel_capacity = broadcast_operations_channel_capacity;
+        self
+    }
+
+    /// Set [`PoolConfig::genesis_timestamp`]
+    pub fn with_genesis_timestamp(mut self, genesis_timestamp: MassaTime) -> Self {
+        self.config.genesis_timestamp = genesis_timestamp;
+        self
+    }
+
+    /// Set [`PoolConfig::t0`]
+    pub fn with_t0(mut self, t0: MassaTime) -> Self {
+        self.config.t0 = t0;
+        self
+    }
+
+    /// Set [`PoolConfig::periods_per_cycle`]
+    pub fn with_periods_per_cycle(mut self, periods_per_cycle: u64) -> Self {
+        self.config.periods_per_cycle = periods_per_cycle;
+        self
+    }
+
+    /// Set [`PoolConfig::denunciation_expire_periods`]
+    pub fn with_denunciation_expire_periods(mut self, denunciation_expire_periods: u64) -> Self {
+        self.config.denunciation_expire_periods = denunciation_expire_periods;
+        self
+    }
+
+    /// Set [`PoolConfig::max_denunciations_per_block_header`]
+    pub fn with_max_denunciations_per_block_header(
+        mut self,
+        max_denunciations_per_block_header: u32,
+    ) -> Self {
+        self.config.max_denunciations_per_block_header = max_denunciations_per_block_header;
+        self
+    }
+
+    /// Set [`PoolConfig::last_start_period`]
+    pub fn with_last_start_period(mut self, last_start_period: u64) -> Self {
+        self.config.last_start_period = last_start_period;
+        self
+    }
+}
@@ -1,6 +1,8 @@
 // Copyright (c) 2022 MASSA LABS <info@massa.net>
 
 use massa_models::{
+    address::Address,
+    amount::Amount,
     block_id::BlockId,
     denunciation::{Denunciation, DenunciationPrecursor},
     endorsement::EndorsementId,
@@ -9,13 +11,40 @@ use massa_models::{
 };
 use massa_storage::Storage;
 
+use crate::{
+    BlockFillInfo, EndorsementPoolSnapshot, EndorsementStats, OperationPoolSnapshot,
+    PoolDiagnostic, PoolDump, PoolError, PoolEvent, PoolMemoryUsage, PoolStats,
+};
+use std::{
+    collections::HashMap,
+    sync::{mpsc::Receiver, Arc},
+    time::Duration,
+};
+use tokio::sync::watch;
+
 /// Trait defining a pool controller
 pub trait PoolController: Send + Sync {
-    /// Asynchronously add operations to pool. Simply print a warning on failure.
-    fn add_operations(&mut self, ops: Storage);
+    /// Asynchronously add operations to pool.
+    /// Returns an error if the pool is full and ranks all the new operations below the ones
+    /// it holds, or if the operations could not be sent to the pool worker.
+    fn add_operations(&mut self, ops: Storage) -> Result<(), PoolError>;
+
+    /// Asynchronously add endorsements to pool.
+    /// Returns an error if the pool is full and ranks all the new endorsements below the ones
+    /// it holds, or if the endorsements could not be sent to the pool worker.
+    fn add_endorsements(&mut self, endorsements: Storage) -> Result<(), PoolError>;
+
+    /// Asynchronously add operations to pool without waiting on a full channel.
+    /// Returns `Ok(true)` if the operations were queued,
+    /// `Ok(false)` if the channel was full and the operations were dropped,
+    /// and an error if the worker is unreachable.
+    fn try_add_operations(&mut self, ops: Storage) -> Result<bool, PoolError>;
 
-    /// Asynchronously add endorsements to pool. Simply print a warning on failure.
-    fn add_endorsements(&mut self, endorsements: Storage);
+    /// Asynchronously add endorsements to pool without waiting on a full channel.
+    /// Returns `Ok(true)` if the endorsements were queued,
+    /// `Ok(false)` if the channel was full and the endorsements were dropped,
+    /// and an error if the worker is unreachable.
+    fn try_add_endorsements(&mut self, endorsements: Storage) -> Result<bool, PoolError>;
 
     /// Add denunciation precursor to pool
     fn add_denunciation_precursor(&self, denunciation_precursor: DenunciationPrecursor);
@@ -23,9 +52,208 @@ pub trait PoolController: Send + Sync {
     /// Asynchronously notify of new consensus final periods. Simply print a warning on failure.
     fn notify_final_cs_periods(&mut self, final_cs_periods: &[u64]);
 
+    /// Asynchronously notify of the start of a new slot,
+    /// so that the pool can drop the items that expired.
+    /// Both the operation and endorsement pools are notified even if one of them cannot be.
+    fn notify_new_slot(&mut self, slot: Slot) -> Result<(), PoolError>;
+
+    /// Asynchronously notify that a block became final,
+    /// so that the pool drops the operations and endorsements it includes in one step.
+    /// The final period of the block thread is also updated if the block is known to the storage.
+    /// Both the operation and endorsement pools are notified even if one of them cannot be.
+    fn notify_block_finalized(
+        &mut self,
+        block_id: &BlockId,
+        included_op_ids: &[OperationId],
+        included_endorsement_ids: &[EndorsementId],
+    ) -> Result<(), PoolError>;
+
+    /// Asynchronously re-inject into the pool the operations of a block that was orphaned,
+    /// so that they can be included in a future block.
+    /// `block_storage` must hold the block and the operations to re-inject;
+    /// operations missing from it are ignored.
+    fn reinject_operations_from_block(
+        &mut self,
+        block_id: BlockId,
+        block_storage: Storage,
+    ) -> Result<(), PoolError>;
+
+    /// Re-inject into the pool the operations of `storage`, typically loaded from the local
+    /// database when the node restarts, through the same path as `add_operations`.
+    /// Operations that can no longer be included in a block at `current_slot` or later are skipped.
+    /// Returns the number of operations submitted to the pool worker,
+    /// or an error if they could not be sent to it. The worker filters them like added ones,
+    /// so fewer of them may end up in the pool.
+    fn reinject_operations_from_storage(
+        &mut self,
+        storage: Storage,
+        current_slot: Slot,
+    ) -> Result<usize, PoolError>;
+
+    /// Asynchronously remove operations from the pool,
+    /// typically once they are included in a final block.
+    /// Unknown IDs are ignored.
+    fn remove_operations(&mut self, ids: &[OperationId]) -> Result<(), PoolError>;
+
+    /// Set the minimal fee of the operations accepted in the pool,
+    /// blocking until the operations of the pool paying less are removed.
+    /// Returns the number of removed operations:
+    /// setting the same fee twice removes nothing the second time.
+    fn set_min_fee(&mut self, min_fee: Amount) -> Result<usize, PoolError>;
+
+    /// Asynchronously remove endorsements from the pool,
+    /// typically once they are included in a final block.
+    /// Unknown IDs are ignored.
+    fn remove_endorsements(&mut self, ids: &[EndorsementId]) -> Result<(), PoolError>;
+
+    /// Remove endorsements from the pool, typically once they are included in a final block,
+    /// and wait for their removal.
+    /// Returns the number of endorsements actually removed, unknown IDs being ignored.
+    fn remove_included_endorsements(&mut self, ids: &[EndorsementId]) -> Result<usize, PoolError>;
+
+    /// Pre-load the endorsement pool with the endorsements of a storage, for instance the ones
+    /// kept from a previous run so that blocks produced right after startup are endorsed,
+    /// and wait for their addition. Unlike `restore_from_snapshot`, no snapshot is needed.
+    /// The endorsements go through the usual checks and are not sent to the denunciation pool.
+    /// Returns the number of endorsements that were not in the pool and were kept.
+    fn warm_up(&mut self, endorsements: Storage) -> Result<usize, PoolError>;
+
+    /// Asynchronously compact the indexes of the endorsement pool,
+    /// releasing the capacity left unused after many removals.
+    fn compact_endorsement_pool(&mut self) -> Result<(), PoolError>;
+
+    /// Asynchronously remove all the operations of the pool and reset the pool statistics,
+    /// without restarting the workers
+    #[cfg(feature = "testing")]
+    fn clear_operations(&mut self) -> Result<(), PoolError>;
+
+    /// Asynchronously remove all the endorsements of the pool and reset the pool statistics,
+    /// without restarting the workers
+    #[cfg(feature = "testing")]
+    fn clear_endorsements(&mut self) -> Result<(), PoolError>;
+
+    /// Asynchronously remove all the operations and endorsements of the pool and reset the pool
+    /// statistics, for instance after a hard fork. The indexes of the pool are reallocated
+    /// to release their memory, and the subscribers see every item evicted as `Purged`.
+    fn purge_pool(&mut self) -> Result<(), PoolError>;
+
     /// Get operations for block creation.
     fn get_block_operations(&self, slot: &Slot) -> (Vec<OperationId>, Storage);
 
+    /// Get operations for block creation,
+    /// along with how full the block is relative to its limits.
+    fn get_block_operations_with_fill_info(
+        &self,
+        slot: &Slot,
+    ) -> (Vec<OperationId>, Storage, BlockFillInfo);
+
+    /// Get the total raw fee of the operations that would be selected for a block at `slot`
+    /// holding at most `max_ops` operations and `max_gas` gas, without building it.
+    fn simulate_block_fee_revenue(&self, slot: &Slot, max_ops: usize, max_gas: u64) -> u64;
+
+    /// Get a storage containing the operations of the pool that match the given IDs.
+    /// IDs that are not in the pool are ignored.
+    fn get_operations_by_ids(&self, ids: &[OperationId]) -> Storage;
+
+    /// Get up to `max_count` operations of the pool that a peer does not know yet,
+    /// along with a storage containing them.
+    fn get_operations_for_propagation(
+        &self,
+        peer_known_ids: &[OperationId],
+        max_count: usize,
+    ) -> (Vec<OperationId>, Storage);
+
+    /// Watch the `(operation_count, endorsement_count)` sizes of the pools,
+    /// updated by the pool workers after every change, without taking the pool locks.
+    fn watch_pool_sizes(&self) -> watch::Receiver<(usize, usize)>;
+
+    /// Subscribe to the additions and evictions of operations and endorsements.
+    /// The subscription is dropped if the receiver is dropped or does not keep up
+    /// with the events (its channel holds `pool_events_channel_size` events).
+    fn subscribe_to_pool_events(&self) -> Receiver<PoolEvent>;
+
+    /// Get a lightweight digest of the pool contents, to detect whether two pools diverged.
+    /// It combines the XOR of the operation IDs and the XOR of the endorsement IDs,
+    /// so it does not depend on the insertion order.
+    ///
+    /// This digest is approximate and NOT cryptographically secure:
+    /// different contents can easily be crafted to produce the same value.
+    fn compute_pool_hash(&self) -> [u8; 32];
+
+    /// Get a fingerprint of the operations of the pool, independent of their order,
+    /// maintained incrementally so that reading it is O(1).
+    /// It is NOT cryptographically secure and only meant as a quick equality check between peers.
+    fn operations_fingerprint(&self) -> u64;
+
+    /// Get the raw fee of the operations of the pool at each of the given percentiles (0 to 100).
+    /// For instance, `get_fee_percentiles(&[10, 50, 75, 90, 95, 99])` returns six fees,
+    /// the fourth one being higher than or equal to the fees of 90% of the pooled operations.
+    fn get_fee_percentiles(&self, percentiles: &[u8]) -> Vec<u64>;
+
+    /// Get the operations of the pool that expire between the period of `current_slot`
+    /// and `periods` periods later, soonest expiring first, along with a storage containing them.
+    fn get_operations_expiring_within_periods(
+        &self,
+        current_slot: &Slot,
+        periods: u64,
+    ) -> (Vec<OperationId>, Storage);
+
+    /// Get the operations of the pool whose validity window overlaps the slots from `from` to `to`
+    /// (both included), soonest expiring first, along with a storage containing them.
+    fn get_operations_for_slot_range(&self, from: &Slot, to: &Slot) -> (Vec<OperationId>, Storage);
+
+    /// Get the operations of the pool sent by `address`, along with a storage containing them.
+    fn get_operations_by_sender(&self, address: &Address) -> (Vec<OperationId>, Storage);
+
+    /// Get the (at most) `n` senders having the most operations in the pool,
+    /// with their operation count, by decreasing operation count.
+    fn top_senders_by_operation_count(&self, n: usize) -> Vec<(Address, usize)>;
+
+    /// Get a page of the operations of the pool sorted by decreasing fee per byte,
+    /// skipping the first `offset` ones and returning at most `limit` of them,
+    /// along with a storage containing them.
+    /// The skipped operations are still walked, so deep pages cost O(offset).
+    fn get_operations_sorted_by_fee(
+        &self,
+        limit: usize,
+        offset: usize,
+    ) -> (Vec<OperationId>, Storage);
+
+    /// Get the IDs of the operations of page `page` of the pool sorted by decreasing fee per byte,
+    /// with `page_size` operations per page. Pages beyond the end of the pool are empty.
+    /// The previous pages are still walked, so deep pages cost O(page * page_size).
+    fn get_operation_ids_page(&self, page: usize, page_size: usize) -> Vec<OperationId>;
+
+    /// Iterate over the IDs of the operations of the pool paying at least `min_fee`,
+    /// by decreasing fee per byte.
+    ///
+    /// The iterator holds a read lock on the operation pool until it is dropped:
+    /// the operation pool worker cannot apply any change to the pool in the meantime,
+    /// so it should be consumed quickly and dropped as soon as possible.
+    fn get_operations_above_fee(
+        &self,
+        min_fee: Amount,
+    ) -> Box<dyn Iterator<Item = OperationId> + '_>;
+
+    /// Block until the operation pool worker has processed all the previously sent commands,
+    /// such as additions of operations.
+    fn flush_operations(&self) -> Result<(), PoolError>;
+
+    /// Get the number of operations in the pool once the operation pool worker
+    /// has processed all the previously sent commands.
+    /// Unlike `get_operation_count`, it never observes the pool in the middle of an update.
+    fn sync_get_operation_count(&self) -> Result<usize, PoolError>;
+
+    /// Block until the endorsement pool worker has processed all the previously sent commands,
+    /// such as additions of endorsements.
+    fn flush_endorsements(&self) -> Result<(), PoolError>;
+
+    /// Get a snapshot of all the operations of the pool, for instance to send them to a joining node.
+    fn snapshot_operations(&self) -> OperationPoolSnapshot;
+
+    /// Asynchronously add all the operations of a snapshot to the pool, in a single batch.
+    fn restore_from_snapshot(&mut self, snapshot: OperationPoolSnapshot) -> Result<(), PoolError>;
+
     /// Get endorsements for a block.
     fn get_block_endorsements(
         &self,
@@ -33,21 +261,101 @@ pub trait PoolController: Send + Sync {
         slot: &Slot,
     ) -> (Vec<Option<EndorsementId>>, Storage);
 
+    /// Get endorsements for several blocks at once, given as `(target_block, slot)` pairs.
+    /// Returns one result per target, as `get_block_endorsements` would.
+    /// All the targets are read from the same state of the pool.
+    fn get_endorsements_for_blocks(
+        &self,
+        targets: &[(BlockId, Slot)],
+    ) -> Vec<(Vec<Option<EndorsementId>>, Storage)>;
+
+    /// Get the endorsement indices of a block at `target_slot` for which the pool
+    /// has no endorsement of `target_block`, in increasing order.
+    fn get_missing_endorsement_indices(
+        &self,
+        target_slot: &Slot,
+        target_block: &BlockId,
+    ) -> Vec<u32>;
+
+    /// Get, for each endorsement index of a block at `target_slot`, whether the pool
+    /// has an endorsement of `target_block` at this index
+    fn get_coverage_for_block(&self, target_block: &BlockId, target_slot: &Slot) -> Vec<bool>;
+
+    /// Checks whether the pool has an endorsement for every endorsement index of a block at `slot`
+    fn has_full_endorsement_coverage(&self, slot: &Slot) -> bool;
+
+    /// Get the number of endorsements of the pool for each slot between `from_slot` and `to_slot` included.
+    /// Slots without endorsements are mapped to 0.
+    fn get_endorsement_coverage_map(
+        &self,
+        from_slot: &Slot,
+        to_slot: &Slot,
+    ) -> HashMap<Slot, usize>;
+
+    /// Get the slots and IDs of the endorsements of the pool created by `producer`, by increasing slot
+    fn get_endorsements_by_producer(&self, producer: &Address) -> Vec<(Slot, EndorsementId)>;
+
+    /// Get the IDs of the endorsements of the pool whose inclusion slot is more than
+    /// `max_age_slots` slots before `current_slot`
+    fn get_endorsements_older_than(
+        &self,
+        max_age_slots: u64,
+        current_slot: &Slot,
+    ) -> Vec<EndorsementId>;
+
+    /// Get a storage containing the endorsements of the pool that match the given IDs.
+    /// IDs that are not in the pool are ignored.
+    fn get_endorsements_by_ids(&self, ids: &[EndorsementId]) -> Storage;
+
     /// Get denunciations for a block header.
     fn get_block_denunciations(&self, target_slot: &Slot) -> Vec<Denunciation>;
 
     /// Get the number of endorsements in the pool
     fn get_endorsement_count(&self) -> usize;
 
+    /// Get the number of endorsements of the pool that can be included in a block at `slot`
+    fn get_endorsement_count_for_slot(&self, slot: &Slot) -> usize;
+
+    /// Get the number of endorsements of the pool for each slot, along with coverage statistics
+    fn get_endorsement_stats(&self) -> EndorsementStats;
+
     /// Get the number of operations in the pool
     fn get_operation_count(&self) -> usize;
 
+    /// Get the number of slots an operation has been waiting in the pool,
+    /// or `None` if it is not in the pool
+    fn get_operation_age(&self, id: &OperationId) -> Option<u64>;
+
+    /// Get the IDs of the operations added to the pool more than `stale_after_slots` slots
+    /// before `current_slot`, from the oldest to the most recent
+    fn get_stale_operations(&self, current_slot: &Slot, stale_after_slots: u64)
+        -> Vec<OperationId>;
+
+    /// Get statistics about the content and the activity of the pools
+    fn pool_stats(&self) -> PoolStats;
+
+    /// Get a conservative estimation of the heap memory held by the pools:
+    /// the serialized size of their items plus a fixed overhead per item for the indexes
+    fn get_memory_usage(&self) -> PoolMemoryUsage;
+
+    /// Get a human-readable dump of the content of the pools, for debugging purposes.
+    /// Both pools are read-locked while it is built, so it should not be called on hot paths.
+    fn dump_pool_state(&self) -> PoolDump;
+
     /// Check if the pool contains a list of endorsements. Returns one boolean per item.
     fn contains_endorsements(&self, endorsements: &[EndorsementId]) -> Vec<bool>;
 
     /// Check if the pool contains a list of operations. Returns one boolean per item.
     fn contains_operations(&self, operations: &[OperationId]) -> Vec<bool>;
 
+    /// Check if the pool contains all the operations of a list.
+    /// Unlike `contains_operations`, it stops at the first missing operation.
+    fn contains_all_operations(&self, ids: &[OperationId]) -> bool;
+
+    /// Check if the pool contains at least one of the operations of a list.
+    /// Unlike `contains_operations`, it stops at the first present operation.
+    fn contains_any_operation(&self, ids: &[OperationId]) -> bool;
+
     /// Check if the pool contains a denunciation. Returns a boolean
     #[cfg(feature = "testing")]
     fn contains_denunciation(&self, denunciation: &Denunciation) -> bool;
@@ -59,6 +367,13 @@ pub trait PoolController: Send + Sync {
     /// Useful to allow cloning `Box<dyn PoolController>`.
     fn clone_box(&self) -> Box<dyn PoolController>;
 
+    /// Returns a clone of self shared behind an `Arc`.
+    ///
+    /// `clone_box` gives each caller its own controller, needed by the methods taking `&mut self`.
+    /// Callers that only read the pools can share a single `Arc<dyn PoolController>` between
+    /// async tasks instead, the `Send + Sync` bound of the trait making it shareable.
+    fn arc_clone(&self) -> Arc<dyn PoolController>;
+
     /// Get final cs periods (updated regularly from consensus)
     fn get_final_cs_periods(&self) -> &Vec<u64>;
 }
@@ -75,4 +390,26 @@ impl Clone for Box<dyn PoolController> {
 pub trait PoolManager: Send + Sync {
     /// Stops the worker
     fn stop(&mut self);
+
+    /// Takes a snapshot of the operation and endorsement pools, once the pending commands are processed,
+    /// then stops the workers.
+    /// The operations can be reloaded at next startup with `PoolController::restore_from_snapshot`,
+    /// the endorsements with `PoolController::warm_up`.
+    /// The workers are stopped even if a snapshot could not be taken.
+    fn drain_and_stop(
+        &mut self,
+    ) -> Result<(OperationPoolSnapshot, EndorsementPoolSnapshot), PoolError>;
+
+    /// Returns false if a pool worker has stopped unexpectedly,
+    /// for instance after panicking more than `worker_restart_max_attempts` times
+    fn is_healthy(&self) -> bool;
+
+    /// Blocks until all the pool worker threads have started processing commands,
+    /// the pools being initialized beforehand (including the replay of the write-ahead log).
+    /// Returns false if they are not all ready within `timeout`.
+    fn wait_until_ready(&self, timeout: Duration) -> bool;
+
+    /// Get a point-in-time report on the worker threads and the content of the pools,
+    /// without stopping nor blocking the workers
+    fn diagnostic_report(&self) -> PoolDiagnostic;
 }
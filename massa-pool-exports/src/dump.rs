@@ -0,0 +1,54 @@
+//! Copyright (c) 2022 MASSA LABS <info@massa.net>
+
+//! Human-readable dumps of the pools, meant for operators diagnosing a node
+
+use massa_models::{
+    amount::Amount, block_id::BlockId, endorsement::EndorsementId, operation::OperationId,
+    slot::Slot,
+};
+use serde::Serialize;
+
+/// Information about an operation of the pool
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OperationDump {
+    /// id of the operation
+    pub id: OperationId,
+    /// fee of the operation
+    pub fee: Amount,
+    /// rank of the operation in the order of arrival into the pool
+    pub insertion_index: u64,
+    /// slot at which the operation arrived into the pool,
+    /// `None` if the pool was not notified of any slot yet
+    pub insertion_slot: Option<Slot>,
+    /// last slot at which the operation can be included in a block
+    pub expiry_slot: Slot,
+}
+
+/// Information about an endorsement of the pool
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EndorsementDump {
+    /// id of the endorsement
+    pub id: EndorsementId,
+    /// slot of the endorsement, after which it can no longer be included in a block
+    pub slot: Slot,
+    /// index of the endorsement in its slot
+    pub index: u32,
+    /// block endorsed by the endorsement
+    pub endorsed_block: BlockId,
+}
+
+/// Contents of the pools at a given time
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PoolDump {
+    /// operations of the pool, best operations first
+    pub operations: Vec<OperationDump>,
+    /// endorsements of the pool, by increasing slot in each thread
+    pub endorsements: Vec<EndorsementDump>,
+}
+
+impl PoolDump {
+    /// Formats the dump as pretty-printed JSON
+    pub fn dump_to_json_string(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+}
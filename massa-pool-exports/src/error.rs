@@ -0,0 +1,104 @@
+// Copyright (c) 2022 MASSA LABS <info@massa.net>
+
+use displaydoc::Display;
+use std::sync::mpsc::{RecvError, SendError, TrySendError};
+use thiserror::Error;
+
+/// Pool result
+pub type PoolResult<T, E = PoolError> = core::result::Result<T, E>;
+
+/// Kind of pool an error relates to
+#[derive(Display, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoolKind {
+    /// operations
+    Operations,
+    /// endorsements
+    Endorsements,
+}
+
+/// Pool error
+#[non_exhaustive]
+#[derive(Display, Error, Debug, Clone)]
+pub enum PoolError {
+    /// Communication channel error: {0}
+    ChannelError(String),
+    /// Communication channel error: the receiver was dropped
+    ChannelDisconnected,
+    /// Communication channel error: the channel is full
+    ChannelFull,
+    /// {pool} pool capacity exceeded: {current} items for a limit of {limit}
+    CapacityExceeded {
+        /// pool that is full
+        pool: PoolKind,
+        /// current number of items in the pool, in the thread of the items for endorsements
+        current: usize,
+        /// maximum number of items in the pool, per thread for endorsements
+        limit: usize,
+    },
+}
+
+// The failed message is dropped: commands are neither `Clone` nor `Debug`,
+// and keeping them would make `PoolError` generic.
+impl<T> From<SendError<T>> for PoolError {
+    fn from(_: SendError<T>) -> Self {
+        PoolError::ChannelDisconnected
+    }
+}
+
+impl<T> From<TrySendError<T>> for PoolError {
+    fn from(err: TrySendError<T>) -> Self {
+        match err {
+            TrySendError::Full(_) => PoolError::ChannelFull,
+            TrySendError::Disconnected(_) => PoolError::ChannelDisconnected,
+        }
+    }
+}
+
+// A reply channel is only disconnected when the worker stopped before answering.
+impl From<RecvError> for PoolError {
+    fn from(_: RecvError) -> Self {
+        PoolError::ChannelDisconnected
+    }
+}
+
+/// Inconsistency found in a pool configuration
+#[non_exhaustive]
+#[derive(Display, Error, Debug, Clone, PartialEq)]
+pub enum PoolConfigError {
+    /// thread count must be greater than zero
+    ZeroThreadCount,
+    /// {0} channel size must be greater than zero
+    ZeroChannelSize(&'static str),
+    /// {0} bloom filter size must be greater than zero
+    ZeroBloomFilterSize(&'static str),
+    /// bloom filter false positive rate must be strictly between 0 and 1, got {0}
+    InvalidFalsePositiveRate(f64),
+    /// backpressure warning threshold must be between 0 and 1, got {0}
+    InvalidBackpressureWarnThreshold(f32),
+    /// max number of operations per sender must be greater than zero
+    ZeroMaxOpsPerSender,
+    /// max number of operations added per sender and per slot must be greater than zero
+    ZeroMaxOpsAddedPerSenderPerSlot,
+    /// max operation size must be greater than zero
+    ZeroMaxOperationSize,
+    /// operation pool shard count must be greater than zero
+    ZeroShardCount,
+    /// operation pool refresh interval must be greater than zero
+    ZeroRefreshInterval,
+    /// endorsement age histogram bucket bounds must be strictly increasing
+    UnsortedHistogramBuckets,
+    /// max operation pool size ({max_operation_pool_size}) is smaller than the max number of operations per block ({max_operations_per_block})
+    OperationPoolTooSmall {
+        /// max number of operations in the pool
+        max_operation_pool_size: usize,
+        /// max number of operations per block
+        max_operations_per_block: u32,
+    },
+    /// max endorsement pool size per thread ({max_endorsements_pool_size_per_thread}) is smaller than the max number of endorsements per block ({max_block_endorsement_count})
+    EndorsementPoolTooSmall {
+        /// max number of endorsements in the pool, per thread
+        max_endorsements_pool_size_per_thread: usize,
+        /// max number of endorsements per block
+        max_block_endorsement_count: u32,
+    },
+}
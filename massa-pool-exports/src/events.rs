@@ -0,0 +1,46 @@
+// Copyright (c) 2022 MASSA LABS <info@massa.net>
+
+use massa_models::{endorsement::EndorsementId, operation::OperationId};
+
+/// Reason why an item left a pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionReason {
+    /// the pool, or the share of the pool of the item sender, is full
+    Capacity,
+    /// the operation pays less than the minimal fee of the pool,
+    /// raised with `PoolController::set_min_fee`
+    BelowMinFee,
+    /// the item can no longer be included in a block
+    Expired,
+    /// the item was included in a block, or removed from the pool on request
+    Included,
+    /// the operation was found invalid at a refresh of the pool: already executed,
+    /// not affordable by its sender, or rejected by the operation validator
+    Invalid,
+    /// the whole pool was purged with `PoolController::purge_pool`,
+    /// or cleared with `clear_operations` or `clear_endorsements` in tests
+    Purged,
+}
+
+/// Change in the contents of the pools, sent to the subscribers of `PoolController::subscribe_to_pool_events`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoolEvent {
+    /// An operation entered the operation pool
+    OperationAdded(OperationId),
+    /// An endorsement entered the endorsement pool
+    EndorsementAdded(EndorsementId),
+    /// An operation left the operation pool
+    OperationEvicted {
+        /// id of the operation
+        id: OperationId,
+        /// reason why the operation left the pool
+        reason: EvictionReason,
+    },
+    /// An endorsement left the endorsement pool
+    EndorsementEvicted {
+        /// id of the endorsement
+        id: EndorsementId,
+        /// reason why the endorsement left the pool
+        reason: EvictionReason,
+    },
+}
@@ -8,10 +8,29 @@
 mod channels;
 mod config;
 mod controller_traits;
+mod dump;
+mod error;
+mod events;
+mod snapshot;
+mod stats;
+mod validator;
 
 pub use channels::PoolChannels;
-pub use config::PoolConfig;
+pub use config::{EvictionPolicy, OperationSelectionStrategy, PoolConfig, PoolConfigBuilder};
 pub use controller_traits::{PoolController, PoolManager};
+pub use dump::{EndorsementDump, OperationDump, PoolDump};
+pub use error::{PoolConfigError, PoolError, PoolKind, PoolResult};
+pub use events::{EvictionReason, PoolEvent};
+pub use snapshot::{
+    EndorsementPoolSnapshot, EndorsementPoolSnapshotDeserializer,
+    EndorsementPoolSnapshotSerializer, OperationPoolSnapshot, OperationPoolSnapshotDeserializer,
+    OperationPoolSnapshotSerializer,
+};
+pub use stats::{
+    BlockFillInfo, EndorsementStats, OperationTypeStats, PoolDiagnostic, PoolMemoryUsage,
+    PoolStats, WorkerDiagnostic, WorkerStatus,
+};
+pub use validator::OperationValidator;
 
 /// Test utils
 #[cfg(feature = "testing")]
@@ -0,0 +1,232 @@
+//! Copyright (c) 2022 MASSA LABS <info@massa.net>
+
+//! Snapshots of the pools, sent to joining nodes in a single payload
+//! or persisted on shutdown
+
+use massa_models::{
+    endorsement::{Endorsement, EndorsementDeserializer, EndorsementId, SecureShareEndorsement},
+    operation::{OperationId, OperationsDeserializer, OperationsSerializer, SecureShareOperation},
+    secure_share::{SecureShareDeserializer, SecureShareSerializer},
+};
+use massa_serialization::{
+    Deserializer, SerializeError, Serializer, U32VarIntDeserializer, U32VarIntSerializer,
+};
+use massa_storage::Storage;
+use nom::{
+    error::{context, ContextError, ParseError},
+    multi::length_count,
+    IResult, Parser,
+};
+use std::ops::Bound::Included;
+
+/// Contents of the operation pool at a given time
+#[derive(Debug)]
+pub struct OperationPoolSnapshot {
+    /// ids of the operations of the pool, best operations first
+    pub operation_ids: Vec<OperationId>,
+    /// storage owning references to the operations of `operation_ids`
+    pub storage: Storage,
+}
+
+/// Serializer for `OperationPoolSnapshot`
+#[derive(Clone, Default)]
+pub struct OperationPoolSnapshotSerializer {
+    operations_serializer: OperationsSerializer,
+}
+
+impl OperationPoolSnapshotSerializer {
+    /// Creates a new `OperationPoolSnapshotSerializer`
+    pub const fn new() -> Self {
+        Self {
+            operations_serializer: OperationsSerializer::new(),
+        }
+    }
+}
+
+impl Serializer<OperationPoolSnapshot> for OperationPoolSnapshotSerializer {
+    fn serialize(
+        &self,
+        value: &OperationPoolSnapshot,
+        buffer: &mut Vec<u8>,
+    ) -> Result<(), SerializeError> {
+        let operations: Vec<SecureShareOperation> = value
+            .storage
+            .batch_get_operations(&value.operation_ids)
+            .map(|(id, op)| {
+                op.map(|op| op.as_ref().clone()).ok_or_else(|| {
+                    SerializeError::GeneralError(format!(
+                        "operation {} of the pool snapshot is missing from its storage",
+                        id
+                    ))
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        self.operations_serializer.serialize(&operations, buffer)
+    }
+}
+
+/// Deserializer for `OperationPoolSnapshot`
+pub struct OperationPoolSnapshotDeserializer {
+    operations_deserializer: OperationsDeserializer,
+    storage: Storage,
+}
+
+impl OperationPoolSnapshotDeserializer {
+    /// Creates a new `OperationPoolSnapshotDeserializer`.
+    /// Deserialized operations are stored in a new reference set of `storage`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        storage: &Storage,
+        max_operations: u32,
+        max_datastore_value_length: u64,
+        max_function_name_length: u16,
+        max_parameters_size: u32,
+        max_op_datastore_entry_count: u64,
+        max_op_datastore_key_length: u8,
+        max_op_datastore_value_length: u64,
+    ) -> Self {
+        Self {
+            operations_deserializer: OperationsDeserializer::new(
+                max_operations,
+                max_datastore_value_length,
+                max_function_name_length,
+                max_parameters_size,
+                max_op_datastore_entry_count,
+                max_op_datastore_key_length,
+                max_op_datastore_value_length,
+            ),
+            storage: storage.clone_without_refs(),
+        }
+    }
+}
+
+impl Deserializer<OperationPoolSnapshot> for OperationPoolSnapshotDeserializer {
+    fn deserialize<'a, E: ParseError<&'a [u8]> + ContextError<&'a [u8]>>(
+        &self,
+        buffer: &'a [u8],
+    ) -> IResult<&'a [u8], OperationPoolSnapshot, E> {
+        let (rest, operations) = self.operations_deserializer.deserialize(buffer)?;
+        let operation_ids = operations.iter().map(|op| op.id).collect();
+        let mut storage = self.storage.clone_without_refs();
+        storage.store_operations(operations);
+        Ok((
+            rest,
+            OperationPoolSnapshot {
+                operation_ids,
+                storage,
+            },
+        ))
+    }
+}
+
+/// Contents of the endorsement pool at a given time
+#[derive(Debug)]
+pub struct EndorsementPoolSnapshot {
+    /// ids of the endorsements of the pool, by increasing slot
+    pub endorsement_ids: Vec<EndorsementId>,
+    /// storage owning references to the endorsements of `endorsement_ids`
+    pub storage: Storage,
+}
+
+/// Serializer for `EndorsementPoolSnapshot`
+#[derive(Clone, Default)]
+pub struct EndorsementPoolSnapshotSerializer {
+    length_serializer: U32VarIntSerializer,
+    endorsement_serializer: SecureShareSerializer,
+}
+
+impl EndorsementPoolSnapshotSerializer {
+    /// Creates a new `EndorsementPoolSnapshotSerializer`
+    pub const fn new() -> Self {
+        Self {
+            length_serializer: U32VarIntSerializer::new(),
+            endorsement_serializer: SecureShareSerializer::new(),
+        }
+    }
+}
+
+impl Serializer<EndorsementPoolSnapshot> for EndorsementPoolSnapshotSerializer {
+    fn serialize(
+        &self,
+        value: &EndorsementPoolSnapshot,
+        buffer: &mut Vec<u8>,
+    ) -> Result<(), SerializeError> {
+        let length: u32 = value.endorsement_ids.len().try_into().map_err(|_| {
+            SerializeError::NumberTooBig(
+                "could not encode the endorsement pool snapshot length as u32".into(),
+            )
+        })?;
+        self.length_serializer.serialize(&length, buffer)?;
+        let stored_endorsements = value.storage.read_endorsements();
+        for id in &value.endorsement_ids {
+            let endorsement = stored_endorsements.get(id).ok_or_else(|| {
+                SerializeError::GeneralError(format!(
+                    "endorsement {} of the pool snapshot is missing from its storage",
+                    id
+                ))
+            })?;
+            self.endorsement_serializer.serialize(endorsement, buffer)?;
+        }
+        Ok(())
+    }
+}
+
+/// Deserializer for `EndorsementPoolSnapshot`
+pub struct EndorsementPoolSnapshotDeserializer {
+    length_deserializer: U32VarIntDeserializer,
+    endorsement_deserializer: SecureShareDeserializer<Endorsement, EndorsementDeserializer>,
+    storage: Storage,
+}
+
+impl EndorsementPoolSnapshotDeserializer {
+    /// Creates a new `EndorsementPoolSnapshotDeserializer`.
+    /// Deserialized endorsements are stored in a new reference set of `storage`.
+    pub fn new(
+        storage: &Storage,
+        max_endorsements: u32,
+        thread_count: u8,
+        endorsement_count: u32,
+    ) -> Self {
+        Self {
+            length_deserializer: U32VarIntDeserializer::new(
+                Included(0),
+                Included(max_endorsements),
+            ),
+            endorsement_deserializer: SecureShareDeserializer::new(EndorsementDeserializer::new(
+                thread_count,
+                endorsement_count,
+            )),
+            storage: storage.clone_without_refs(),
+        }
+    }
+}
+
+impl Deserializer<EndorsementPoolSnapshot> for EndorsementPoolSnapshotDeserializer {
+    fn deserialize<'a, E: ParseError<&'a [u8]> + ContextError<&'a [u8]>>(
+        &self,
+        buffer: &'a [u8],
+    ) -> IResult<&'a [u8], EndorsementPoolSnapshot, E> {
+        let (rest, endorsements): (&'a [u8], Vec<SecureShareEndorsement>) = context(
+            "Failed EndorsementPoolSnapshot deserialization",
+            length_count(
+                context("Failed length deserialization", |input| {
+                    self.length_deserializer.deserialize(input)
+                }),
+                context("Failed endorsement deserialization", |input| {
+                    self.endorsement_deserializer.deserialize(input)
+                }),
+            ),
+        )
+        .parse(buffer)?;
+        let endorsement_ids = endorsements.iter().map(|endo| endo.id).collect();
+        let mut storage = self.storage.clone_without_refs();
+        storage.store_endorsements(endorsements);
+        Ok((
+            rest,
+            EndorsementPoolSnapshot {
+                endorsement_ids,
+                storage,
+            },
+        ))
+    }
+}
@@ -0,0 +1,138 @@
+// Copyright (c) 2022 MASSA LABS <info@massa.net>
+
+use massa_models::{address::Address, amount::Amount, operation::OperationTypeId, slot::Slot};
+use massa_time::MassaTime;
+use std::collections::HashMap;
+
+/// Statistics about the operations of a given type in the pool
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OperationTypeStats {
+    /// number of operations of this type in the pool
+    pub count: usize,
+    /// sum of the fees of the operations of this type in the pool
+    pub total_fee: Amount,
+    /// average fee per serialized byte of the operations of this type in the pool
+    pub avg_fee_per_byte: Amount,
+}
+
+/// Statistics about the content and the activity of the pools
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// number of operations in the pool
+    pub operation_count: usize,
+    /// number of endorsements in the pool
+    pub endorsement_count: usize,
+    /// total serialized size (in bytes) of the operations in the pool
+    pub operation_pool_memory_bytes: usize,
+    /// total serialized size (in bytes) of the endorsements in the pool
+    pub endorsement_pool_memory_bytes: usize,
+    /// number of operations added to the pool since startup
+    pub total_ops_added: u64,
+    /// number of operations removed from the pool since startup, whatever the reason
+    pub total_ops_evicted: u64,
+    /// number of incoming operations skipped because they were already in the pool
+    pub duplicates_skipped: u64,
+    /// number of operations evicted because their sender exceeded `max_ops_per_sender`
+    pub sender_limit_evicted: u64,
+    /// number of incoming operations dropped because their size exceeded `max_operation_size_bytes`
+    pub oversized_ops_dropped: u64,
+    /// number of incoming operations dropped because their type is not accepted,
+    /// see `allowed_operation_types` and `denied_operation_types`
+    pub type_filtered_ops_dropped: u64,
+    /// number of blocks filled from the `hot_tier_size` best operations of the pool only
+    pub hot_tier_hits: u64,
+    /// number of blocks filled with operations beyond the `hot_tier_size` best ones.
+    /// The hot tier hit rate is `hot_tier_hits / (hot_tier_hits + hot_tier_misses)`.
+    pub hot_tier_misses: u64,
+    /// statistics of the operations in the pool, by operation type
+    pub operation_type_stats: HashMap<OperationTypeId, OperationTypeStats>,
+    /// the (at most) 10 senders having the most operations in the pool, with their operation count,
+    /// by decreasing operation count: a few senders dominating the pool is a sign of spam
+    pub top_10_senders: Vec<(Address, usize)>,
+    /// number of endorsements added to the pool since startup
+    pub total_endorsements_added: u64,
+    /// number of endorsements removed from the pool since startup, whatever the reason
+    pub total_endorsements_evicted: u64,
+    /// number of endorsements in the pool by age (in periods since their slot), as
+    /// `(bucket_min_periods, count)` pairs following `endorsement_age_histogram_buckets`.
+    /// Endorsements younger than the first bucket are not counted.
+    pub endorsements_by_age: Vec<(u64, usize)>,
+    /// number of endorsement signatures checked since startup, the cached results excluded
+    pub endorsement_signature_checks: u64,
+    /// number of commands dropped since startup because a pool worker channel was full,
+    /// a sign that the worker threads do not keep up with their inputs
+    pub channel_full_events: u64,
+}
+
+/// Statistics about the slots of the endorsements in the pool
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EndorsementStats {
+    /// number of endorsements in the pool for each slot having some, whatever the endorsed block
+    pub endorsements_per_slot: HashMap<Slot, usize>,
+    /// number of endorsements in the pool
+    pub total_endorsements: usize,
+    /// lowest slot of the endorsements in the pool
+    pub min_slot: Option<Slot>,
+    /// highest slot of the endorsements in the pool
+    pub max_slot: Option<Slot>,
+    /// number of slots for which the pool has an endorsement
+    /// for each of the `max_block_endorsement_count` indices
+    pub slots_with_full_coverage: usize,
+}
+
+/// Estimation of the heap memory held by the pools
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolMemoryUsage {
+    /// bytes held by the operation pool
+    pub operation_pool_bytes: usize,
+    /// bytes held by the endorsement pool
+    pub endorsement_pool_bytes: usize,
+}
+
+/// How full a block built from the operations returned by the pool is, relative to its limits
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlockFillInfo {
+    /// number of selected operations
+    pub ops_count: usize,
+    /// max number of operations per block
+    pub ops_limit: usize,
+    /// total max gas of the selected operations
+    pub gas_used: u64,
+    /// max gas per block
+    pub gas_limit: u64,
+    /// total serialized size of the selected operations
+    pub size_bytes: usize,
+}
+
+/// Status of a pool worker thread
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkerStatus {
+    /// the thread is processing commands
+    Running,
+    /// the thread has exited, or was stopped
+    Finished,
+}
+
+/// Activity of a pool worker thread
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkerDiagnostic {
+    /// name of the pool the thread works on
+    pub name: String,
+    /// status of the thread
+    pub status: WorkerStatus,
+    /// number of commands received by the thread since startup
+    pub commands_processed: u64,
+    /// time at which the thread received its last command, `None` if it received none yet
+    pub last_command_timestamp: Option<MassaTime>,
+}
+
+/// Point-in-time report on the pool workers, for operator tooling
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolDiagnostic {
+    /// activity of the operation, endorsement and denunciation worker threads
+    pub workers: Vec<WorkerDiagnostic>,
+    /// time elapsed since the pool was started, in seconds
+    pub uptime_secs: u64,
+    /// statistics of the pools
+    pub stats: PoolStats,
+}
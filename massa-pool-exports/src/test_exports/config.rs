@@ -1,5 +1,6 @@
 // Copyright (c) 2022 MASSA LABS <info@massa.net>
 
+use massa_models::amount::Amount;
 use massa_models::config::{
     DENUNCIATION_EXPIRE_PERIODS, ENDORSEMENT_COUNT, MAX_BLOCK_SIZE,
     MAX_DENUNCIATIONS_PER_BLOCK_HEADER, MAX_GAS_PER_BLOCK, MAX_OPERATIONS_PER_BLOCK,
@@ -7,7 +8,7 @@ use massa_models::config::{
 };
 use massa_time::MassaTime;
 
-use crate::PoolConfig;
+use crate::{EvictionPolicy, OperationSelectionStrategy, PoolConfig, PoolConfigBuilder};
 
 impl Default for PoolConfig {
     fn default() -> Self {
@@ -19,12 +20,39 @@ impl Default for PoolConfig {
             max_block_size: MAX_BLOCK_SIZE,
             max_operation_pool_size: 32000,
             max_operation_pool_excess_items: 10000,
+            max_ops_per_sender: 1000,
+            max_ops_added_per_sender_per_slot: 1000,
+            hot_tier_size: 1000,
+            max_operation_size_bytes: MAX_BLOCK_SIZE as usize,
+            allowed_operation_types: None,
+            denied_operation_types: None,
+            min_fee: Amount::zero(),
+            eviction_policy: EvictionPolicy::LowestFee,
+            selection_strategy: OperationSelectionStrategy::FeeDescending,
+            wal_path: None,
+            event_log_path: None,
+            memory_limit_bytes: None,
             max_endorsements_pool_size_per_thread: 1000,
+            max_endorsement_pool_size: 10000,
+            operation_bloom_filter_size: 420_000,
+            endorsement_bloom_filter_size: 320_000,
+            bloom_filter_false_positive_rate: 0.01,
+            operation_pool_shard_count: 16,
+            validation_parallelism: 0,
+            endorsement_sig_cache_size: 10_000,
+            compact_after_evictions: 0,
+            endorsement_age_histogram_buckets: vec![0, 1, 2, 4, 8, 16],
+            revalidation_interval_slots: 0,
+            stale_operation_warn_slots: 0,
             max_operations_per_block: MAX_OPERATIONS_PER_BLOCK,
             max_block_endorsement_count: ENDORSEMENT_COUNT,
             operations_channel_size: 1024,
             endorsements_channel_size: 1024,
             denunciations_channel_size: 1024,
+            backpressure_warn_threshold: 0.8,
+            pool_events_channel_size: 1024,
+            worker_restart_max_attempts: 3,
+            worker_stop_timeout_ms: 5000,
             broadcast_enabled: false,
             broadcast_endorsements_channel_capacity: 2000,
             broadcast_operations_channel_capacity: 5000,
@@ -39,3 +67,9 @@ impl Default for PoolConfig {
         }
     }
 }
+
+impl Default for PoolConfigBuilder {
+    fn default() -> Self {
+        PoolConfigBuilder::from_config(PoolConfig::default())
+    }
+}
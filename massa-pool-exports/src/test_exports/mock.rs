@@ -1,6 +1,7 @@
 // Copyright (c) 2022 MASSA LABS <info@massa.net>
 
 use crossbeam_channel::{Receiver, Sender};
+use std::collections::HashMap;
 use std::sync::{
     mpsc::{
         self,
@@ -12,12 +13,17 @@ use std::sync::{
 use massa_models::config::THREAD_COUNT;
 use massa_models::denunciation::{Denunciation, DenunciationPrecursor};
 use massa_models::{
-    block_id::BlockId, endorsement::EndorsementId, operation::OperationId, slot::Slot,
+    address::Address, amount::Amount, block_id::BlockId, endorsement::EndorsementId,
+    operation::OperationId, slot::Slot,
 };
 use massa_storage::Storage;
 use massa_time::MassaTime;
+use tokio::sync::watch;
 
-use crate::PoolController;
+use crate::{
+    BlockFillInfo, EndorsementStats, OperationPoolSnapshot, PoolController, PoolDump, PoolError,
+    PoolEvent, PoolMemoryUsage, PoolStats,
+};
 
 /// Test tool to mock pool controller responses
 pub struct PoolEventReceiver(pub Receiver<MockPoolControllerMessage>);
@@ -52,6 +58,13 @@ pub enum MockPoolControllerMessage {
         /// Response channel
         response_tx: mpsc::Sender<(Vec<Option<EndorsementId>>, Storage)>,
     },
+    /// Get endorsements of several blocks
+    GetEndorsementsForBlocks {
+        /// (block id of the block endorsed, slot of the endorsement) pairs
+        targets: Vec<(BlockId, Slot)>,
+        /// Response channel
+        response_tx: mpsc::Sender<Vec<(Vec<Option<EndorsementId>>, Storage)>>,
+    },
     /// Get operations of a block
     GetBlockOperations {
         /// Slot of the block to search operations in
@@ -59,6 +72,200 @@ pub enum MockPoolControllerMessage {
         /// Response channel
         response_tx: mpsc::Sender<(Vec<OperationId>, Storage)>,
     },
+    /// Get block operations along with the block fill information
+    GetBlockOperationsWithFillInfo {
+        /// Slot of the block to search operations in
+        slot: Slot,
+        /// Response channel
+        response_tx: mpsc::Sender<(Vec<OperationId>, Storage, BlockFillInfo)>,
+    },
+    /// Get the fee revenue of a simulated block
+    SimulateBlockFeeRevenue {
+        /// Slot of the simulated block
+        slot: Slot,
+        /// Max number of operations of the simulated block
+        max_ops: usize,
+        /// Max gas of the simulated block
+        max_gas: u64,
+        /// Response channel
+        response_tx: mpsc::Sender<u64>,
+    },
+    /// Get operations by IDs
+    GetOperationsByIds {
+        /// ids to search
+        ids: Vec<OperationId>,
+        /// Response channel
+        response_tx: mpsc::Sender<Storage>,
+    },
+    /// Get operations to propagate to a peer
+    GetOperationsForPropagation {
+        /// ids of the operations known by the peer
+        peer_known_ids: Vec<OperationId>,
+        /// maximal number of operations to return
+        max_count: usize,
+        /// Response channel
+        response_tx: mpsc::Sender<(Vec<OperationId>, Storage)>,
+    },
+    /// Watch the sizes of the pools
+    WatchPoolSizes {
+        /// Sender of the `(operation_count, endorsement_count)` sizes watched by the caller
+        sizes_sender: watch::Sender<(usize, usize)>,
+    },
+    /// Subscribe to the pool events
+    SubscribeToPoolEvents {
+        /// Channel the events of the subscription should be sent to
+        event_sender: mpsc::SyncSender<PoolEvent>,
+    },
+    /// Get a digest of the pool contents
+    ComputePoolHash {
+        /// Response channel
+        response_tx: mpsc::Sender<[u8; 32]>,
+    },
+    /// Get the fingerprint of the operations of the pool
+    GetOperationsFingerprint {
+        /// Response channel
+        response_tx: mpsc::Sender<u64>,
+    },
+    /// Get the fees of the pool at the given percentiles
+    GetFeePercentiles {
+        /// requested percentiles
+        percentiles: Vec<u8>,
+        /// Response channel
+        response_tx: mpsc::Sender<Vec<u64>>,
+    },
+    /// Get the operations expiring soon
+    GetOperationsExpiringWithinPeriods {
+        /// current slot
+        current_slot: Slot,
+        /// number of periods after the current one
+        periods: u64,
+        /// Response channel
+        response_tx: mpsc::Sender<(Vec<OperationId>, Storage)>,
+    },
+    /// Get the operations valid in a range of slots
+    GetOperationsForSlotRange {
+        /// first slot of the range
+        from: Slot,
+        /// last slot of the range
+        to: Slot,
+        /// Response channel
+        response_tx: mpsc::Sender<(Vec<OperationId>, Storage)>,
+    },
+    /// Get the operations of a sender
+    GetOperationsBySender {
+        /// address of the sender
+        address: Address,
+        /// Response channel
+        response_tx: mpsc::Sender<(Vec<OperationId>, Storage)>,
+    },
+    /// Get the senders having the most operations
+    TopSendersByOperationCount {
+        /// maximum number of senders
+        n: usize,
+        /// Response channel
+        response_tx: mpsc::Sender<Vec<(Address, usize)>>,
+    },
+    /// Get a page of the operations sorted by fee
+    GetOperationsSortedByFee {
+        /// maximum number of operations
+        limit: usize,
+        /// number of operations to skip
+        offset: usize,
+        /// Response channel
+        response_tx: mpsc::Sender<(Vec<OperationId>, Storage)>,
+    },
+    /// Get the IDs of a page of the operations sorted by fee
+    GetOperationIdsPage {
+        /// index of the page
+        page: usize,
+        /// number of operations per page
+        page_size: usize,
+        /// Response channel
+        response_tx: mpsc::Sender<Vec<OperationId>>,
+    },
+    /// Get the operations paying at least a minimal fee
+    GetOperationsAboveFee {
+        /// minimal fee
+        min_fee: Amount,
+        /// Response channel
+        response_tx: mpsc::Sender<Vec<OperationId>>,
+    },
+    /// Get a snapshot of the operations of the pool
+    SnapshotOperations {
+        /// Response channel
+        response_tx: mpsc::Sender<OperationPoolSnapshot>,
+    },
+    /// Wait for the operation pool worker to process the previous commands
+    FlushOperations,
+    /// Get the number of operations once the operation pool worker processed the previous commands
+    SyncGetOperationCount {
+        /// Response channel
+        response_tx: mpsc::Sender<usize>,
+    },
+    /// Wait for the endorsement pool worker to process the previous commands
+    FlushEndorsements,
+    /// Restore operations from a snapshot
+    RestoreFromSnapshot {
+        /// Snapshot to restore
+        snapshot: OperationPoolSnapshot,
+    },
+    /// Get the missing endorsement indices of a block
+    GetMissingEndorsementIndices {
+        /// Slot of the block
+        target_slot: Slot,
+        /// Endorsed block
+        target_block: BlockId,
+        /// Response channel
+        response_tx: mpsc::Sender<Vec<u32>>,
+    },
+    /// Get the endorsement indices of a block that are covered by the pool
+    GetCoverageForBlock {
+        /// Endorsed block
+        target_block: BlockId,
+        /// Slot of the block
+        target_slot: Slot,
+        /// Response channel
+        response_tx: mpsc::Sender<Vec<bool>>,
+    },
+    /// Check whether the pool has all the endorsements of a slot
+    HasFullEndorsementCoverage {
+        /// Slot of the block
+        slot: Slot,
+        /// Response channel
+        response_tx: mpsc::Sender<bool>,
+    },
+    /// Get the endorsement coverage of a range of slots
+    GetEndorsementCoverageMap {
+        /// first slot of the range
+        from_slot: Slot,
+        /// last slot of the range
+        to_slot: Slot,
+        /// Response channel
+        response_tx: mpsc::Sender<HashMap<Slot, usize>>,
+    },
+    /// Get the endorsements of a producer
+    GetEndorsementsByProducer {
+        /// creator of the endorsements
+        producer: Address,
+        /// Response channel
+        response_tx: mpsc::Sender<Vec<(Slot, EndorsementId)>>,
+    },
+    /// Get the endorsements older than a given age
+    GetEndorsementsOlderThan {
+        /// maximum age in slots
+        max_age_slots: u64,
+        /// slot the age is computed from
+        current_slot: Slot,
+        /// Response channel
+        response_tx: mpsc::Sender<Vec<EndorsementId>>,
+    },
+    /// Get endorsements by IDs
+    GetEndorsementsByIds {
+        /// ids to search
+        ids: Vec<EndorsementId>,
+        /// Response channel
+        response_tx: mpsc::Sender<Storage>,
+    },
     /// Get denunciations for a block header
     GetBlockDenunciations {
         /// Slot of the block to search denunciations in
@@ -71,11 +278,34 @@ pub enum MockPoolControllerMessage {
         /// Response channel
         response_tx: mpsc::Sender<usize>,
     },
+    /// Get the number of endorsements for a slot
+    GetEndorsementCountForSlot {
+        /// Slot
+        slot: Slot,
+        /// Response channel
+        response_tx: mpsc::Sender<usize>,
+    },
     /// Get operations by ids
     GetOperationCount {
         /// Response channel
         response_tx: mpsc::Sender<usize>,
     },
+    /// Get the age of an operation
+    GetOperationAge {
+        /// id of the operation
+        id: OperationId,
+        /// Response channel
+        response_tx: mpsc::Sender<Option<u64>>,
+    },
+    /// Get the stale operations
+    GetStaleOperations {
+        /// current slot
+        current_slot: Slot,
+        /// number of slots after which an operation is stale
+        stale_after_slots: u64,
+        /// Response channel
+        response_tx: mpsc::Sender<Vec<OperationId>>,
+    },
     /// Get denunciation count
     GetDenunciationCount {
         /// Response channel
@@ -95,16 +325,119 @@ pub enum MockPoolControllerMessage {
         /// Response channel
         response_tx: mpsc::Sender<Vec<bool>>,
     },
+    /// Contains all the operations
+    ContainsAllOperations {
+        /// ids to search
+        ids: Vec<OperationId>,
+        /// Response channel
+        response_tx: mpsc::Sender<bool>,
+    },
+    /// Contains at least one of the operations
+    ContainsAnyOperation {
+        /// ids to search
+        ids: Vec<OperationId>,
+        /// Response channel
+        response_tx: mpsc::Sender<bool>,
+    },
     /// Get stats of the pool
     GetStats {
         /// Response channel
         response_tx: mpsc::Sender<(usize, usize)>,
     },
+    /// Get statistics about the pools
+    GetPoolStats {
+        /// Response channel
+        response_tx: mpsc::Sender<PoolStats>,
+    },
+    /// Get the endorsement statistics of the pool
+    GetEndorsementStats {
+        /// Response channel
+        response_tx: mpsc::Sender<EndorsementStats>,
+    },
+    /// Get the memory usage of the pools
+    GetMemoryUsage {
+        /// Response channel
+        response_tx: mpsc::Sender<PoolMemoryUsage>,
+    },
+    /// Get a dump of the content of the pools
+    DumpPoolState {
+        /// Response channel
+        response_tx: mpsc::Sender<PoolDump>,
+    },
     /// Notify that periods became final
     NotifyFinalCsPeriods {
         /// Periods that are final
         periods: Vec<u64>,
     },
+    /// Notify of the start of a new slot
+    NotifyNewSlot {
+        /// The new slot
+        slot: Slot,
+    },
+    /// Notify that a block became final
+    NotifyBlockFinalized {
+        /// id of the final block
+        block_id: BlockId,
+        /// ids of the operations included in the block
+        included_op_ids: Vec<OperationId>,
+        /// ids of the endorsements included in the block
+        included_endorsement_ids: Vec<EndorsementId>,
+    },
+    /// Re-inject the operations of an orphaned block
+    ReInjectOperationsFromBlock {
+        /// id of the orphaned block
+        block_id: BlockId,
+        /// Storage that contains the block and its operations
+        block_storage: Storage,
+    },
+    /// Re-inject operations loaded from storage
+    ReInjectOperationsFromStorage {
+        /// Storage that contains the operations
+        storage: Storage,
+        /// Current slot
+        current_slot: Slot,
+        /// Response channel, receiving the number of operations submitted to the pool
+        response_tx: mpsc::Sender<usize>,
+    },
+    /// Remove operations from the pool
+    RemoveOperations {
+        /// ids of the operations to remove
+        ids: Vec<OperationId>,
+    },
+    /// Set the minimal fee of the operation pool, waiting for the pruning
+    SetMinFee {
+        /// new minimal fee
+        min_fee: Amount,
+        /// Response channel
+        response_tx: mpsc::Sender<usize>,
+    },
+    /// Remove endorsements from the pool
+    RemoveEndorsements {
+        /// ids of the endorsements to remove
+        ids: Vec<EndorsementId>,
+    },
+    /// Remove endorsements from the pool, waiting for their removal
+    RemoveIncludedEndorsements {
+        /// ids of the endorsements to remove
+        ids: Vec<EndorsementId>,
+        /// Response channel
+        response_tx: mpsc::Sender<usize>,
+    },
+    /// Pre-load the endorsement pool
+    WarmUp {
+        /// storage containing the endorsements
+        endorsements: Storage,
+        /// Response channel
+        response_tx: mpsc::Sender<usize>,
+    },
+    /// Compact the endorsement pool
+    CompactEndorsementPool,
+    /// Clear the operation pool
+    ClearOperations,
+    /// Clear the endorsement pool
+    ClearEndorsements,
+    /// Purge the operation and endorsement pools
+    PurgePool,
     /// No need to specify the response
     Any,
 }
@@ -154,20 +487,32 @@ impl PoolEventReceiver {
 /// a response from that channel is read and returned as return value.
 /// See the documentation of `PoolController` for details on each function.
 impl PoolController for MockPoolController {
-    fn add_endorsements(&mut self, endorsements: Storage) {
+    fn add_endorsements(&mut self, endorsements: Storage) -> Result<(), PoolError> {
         self.q
             .lock()
             .unwrap()
             .send(MockPoolControllerMessage::AddEndorsements { endorsements })
             .unwrap();
+        Ok(())
     }
 
-    fn add_operations(&mut self, operations: Storage) {
+    fn add_operations(&mut self, operations: Storage) -> Result<(), PoolError> {
         self.q
             .lock()
             .unwrap()
             .send(MockPoolControllerMessage::AddOperations { operations })
             .unwrap();
+        Ok(())
+    }
+
+    fn try_add_operations(&mut self, operations: Storage) -> Result<bool, PoolError> {
+        self.add_operations(operations)?;
+        Ok(true)
+    }
+
+    fn try_add_endorsements(&mut self, endorsements: Storage) -> Result<bool, PoolError> {
+        self.add_endorsements(endorsements)?;
+        Ok(true)
     }
 
     fn get_block_endorsements(
@@ -188,6 +533,22 @@ impl PoolController for MockPoolController {
         response_rx.recv().unwrap()
     }
 
+    fn get_endorsements_for_blocks(
+        &self,
+        targets: &[(BlockId, Slot)],
+    ) -> Vec<(Vec<Option<EndorsementId>>, Storage)> {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::GetEndorsementsForBlocks {
+                targets: targets.to_vec(),
+                response_tx,
+            })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
     fn get_block_operations(&self, slot: &Slot) -> (Vec<OperationId>, Storage) {
         let (response_tx, response_rx) = mpsc::channel();
         self.q
@@ -201,65 +562,707 @@ impl PoolController for MockPoolController {
         response_rx.recv().unwrap()
     }
 
-    fn get_endorsement_count(&self) -> usize {
+    fn get_block_operations_with_fill_info(
+        &self,
+        slot: &Slot,
+    ) -> (Vec<OperationId>, Storage, BlockFillInfo) {
         let (response_tx, response_rx) = mpsc::channel();
         self.q
             .lock()
             .unwrap()
-            .send(MockPoolControllerMessage::GetEndorsementCount { response_tx })
+            .send(MockPoolControllerMessage::GetBlockOperationsWithFillInfo {
+                slot: *slot,
+                response_tx,
+            })
             .unwrap();
         response_rx.recv().unwrap()
     }
 
-    fn get_operation_count(&self) -> usize {
+    fn simulate_block_fee_revenue(&self, slot: &Slot, max_ops: usize, max_gas: u64) -> u64 {
         let (response_tx, response_rx) = mpsc::channel();
         self.q
             .lock()
             .unwrap()
-            .send(MockPoolControllerMessage::GetOperationCount { response_tx })
+            .send(MockPoolControllerMessage::SimulateBlockFeeRevenue {
+                slot: *slot,
+                max_ops,
+                max_gas,
+                response_tx,
+            })
             .unwrap();
         response_rx.recv().unwrap()
     }
 
-    fn contains_endorsements(&self, endorsements: &[EndorsementId]) -> Vec<bool> {
+    fn get_operations_by_ids(&self, ids: &[OperationId]) -> Storage {
         let (response_tx, response_rx) = mpsc::channel();
         self.q
             .lock()
             .unwrap()
-            .send(MockPoolControllerMessage::ContainsEndorsements {
-                ids: endorsements.to_vec(),
+            .send(MockPoolControllerMessage::GetOperationsByIds {
+                ids: ids.to_vec(),
                 response_tx,
             })
             .unwrap();
         response_rx.recv().unwrap()
     }
 
-    fn contains_operations(&self, operations: &[OperationId]) -> Vec<bool> {
+    fn get_operations_for_propagation(
+        &self,
+        peer_known_ids: &[OperationId],
+        max_count: usize,
+    ) -> (Vec<OperationId>, Storage) {
         let (response_tx, response_rx) = mpsc::channel();
         self.q
             .lock()
             .unwrap()
-            .send(MockPoolControllerMessage::ContainsOperations {
-                ids: operations.to_vec(),
+            .send(MockPoolControllerMessage::GetOperationsForPropagation {
+                peer_known_ids: peer_known_ids.to_vec(),
+                max_count,
                 response_tx,
             })
             .unwrap();
         response_rx.recv().unwrap()
     }
 
-    fn notify_final_cs_periods(&mut self, final_cs_periods: &[u64]) {
-        self.last_final_cs_periods = final_cs_periods.to_vec();
+    fn watch_pool_sizes(&self) -> watch::Receiver<(usize, usize)> {
+        let (sizes_sender, sizes_receiver) = watch::channel((0, 0));
         self.q
             .lock()
             .unwrap()
-            .send(MockPoolControllerMessage::NotifyFinalCsPeriods {
-                periods: final_cs_periods.to_vec(),
+            .send(MockPoolControllerMessage::WatchPoolSizes { sizes_sender })
+            .unwrap();
+        sizes_receiver
+    }
+
+    fn subscribe_to_pool_events(&self) -> mpsc::Receiver<PoolEvent> {
+        let (event_sender, event_receiver) = mpsc::sync_channel(1024);
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::SubscribeToPoolEvents { event_sender })
+            .unwrap();
+        event_receiver
+    }
+
+    fn compute_pool_hash(&self) -> [u8; 32] {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::ComputePoolHash { response_tx })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn operations_fingerprint(&self) -> u64 {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::GetOperationsFingerprint { response_tx })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn get_fee_percentiles(&self, percentiles: &[u8]) -> Vec<u64> {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::GetFeePercentiles {
+                percentiles: percentiles.to_vec(),
+                response_tx,
             })
             .unwrap();
+        response_rx.recv().unwrap()
     }
 
-    fn clone_box(&self) -> Box<dyn PoolController> {
-        Box::new(self.clone())
+    fn get_operations_expiring_within_periods(
+        &self,
+        current_slot: &Slot,
+        periods: u64,
+    ) -> (Vec<OperationId>, Storage) {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(
+                MockPoolControllerMessage::GetOperationsExpiringWithinPeriods {
+                    current_slot: *current_slot,
+                    periods,
+                    response_tx,
+                },
+            )
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn get_operations_for_slot_range(&self, from: &Slot, to: &Slot) -> (Vec<OperationId>, Storage) {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::GetOperationsForSlotRange {
+                from: *from,
+                to: *to,
+                response_tx,
+            })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn get_operations_by_sender(&self, address: &Address) -> (Vec<OperationId>, Storage) {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::GetOperationsBySender {
+                address: *address,
+                response_tx,
+            })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn top_senders_by_operation_count(&self, n: usize) -> Vec<(Address, usize)> {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::TopSendersByOperationCount { n, response_tx })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn get_operations_sorted_by_fee(
+        &self,
+        limit: usize,
+        offset: usize,
+    ) -> (Vec<OperationId>, Storage) {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::GetOperationsSortedByFee {
+                limit,
+                offset,
+                response_tx,
+            })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn get_operation_ids_page(&self, page: usize, page_size: usize) -> Vec<OperationId> {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::GetOperationIdsPage {
+                page,
+                page_size,
+                response_tx,
+            })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn get_operations_above_fee(
+        &self,
+        min_fee: Amount,
+    ) -> Box<dyn Iterator<Item = OperationId> + '_> {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::GetOperationsAboveFee {
+                min_fee,
+                response_tx,
+            })
+            .unwrap();
+        Box::new(response_rx.recv().unwrap().into_iter())
+    }
+
+    fn snapshot_operations(&self) -> OperationPoolSnapshot {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::SnapshotOperations { response_tx })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn flush_operations(&self) -> Result<(), PoolError> {
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::FlushOperations)
+            .unwrap();
+        Ok(())
+    }
+
+    fn sync_get_operation_count(&self) -> Result<usize, PoolError> {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::SyncGetOperationCount { response_tx })
+            .unwrap();
+        Ok(response_rx.recv().unwrap())
+    }
+
+    fn flush_endorsements(&self) -> Result<(), PoolError> {
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::FlushEndorsements)
+            .unwrap();
+        Ok(())
+    }
+
+    fn restore_from_snapshot(&mut self, snapshot: OperationPoolSnapshot) -> Result<(), PoolError> {
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::RestoreFromSnapshot { snapshot })
+            .unwrap();
+        Ok(())
+    }
+
+    fn get_missing_endorsement_indices(
+        &self,
+        target_slot: &Slot,
+        target_block: &BlockId,
+    ) -> Vec<u32> {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::GetMissingEndorsementIndices {
+                target_slot: *target_slot,
+                target_block: *target_block,
+                response_tx,
+            })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn get_coverage_for_block(&self, target_block: &BlockId, target_slot: &Slot) -> Vec<bool> {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::GetCoverageForBlock {
+                target_block: *target_block,
+                target_slot: *target_slot,
+                response_tx,
+            })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn has_full_endorsement_coverage(&self, slot: &Slot) -> bool {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::HasFullEndorsementCoverage {
+                slot: *slot,
+                response_tx,
+            })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn get_endorsement_coverage_map(
+        &self,
+        from_slot: &Slot,
+        to_slot: &Slot,
+    ) -> HashMap<Slot, usize> {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::GetEndorsementCoverageMap {
+                from_slot: *from_slot,
+                to_slot: *to_slot,
+                response_tx,
+            })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn get_endorsements_by_producer(&self, producer: &Address) -> Vec<(Slot, EndorsementId)> {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::GetEndorsementsByProducer {
+                producer: *producer,
+                response_tx,
+            })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn get_endorsements_older_than(
+        &self,
+        max_age_slots: u64,
+        current_slot: &Slot,
+    ) -> Vec<EndorsementId> {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::GetEndorsementsOlderThan {
+                max_age_slots,
+                current_slot: *current_slot,
+                response_tx,
+            })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn get_endorsements_by_ids(&self, ids: &[EndorsementId]) -> Storage {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::GetEndorsementsByIds {
+                ids: ids.to_vec(),
+                response_tx,
+            })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn get_endorsement_count(&self) -> usize {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::GetEndorsementCount { response_tx })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn get_endorsement_count_for_slot(&self, slot: &Slot) -> usize {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::GetEndorsementCountForSlot {
+                slot: *slot,
+                response_tx,
+            })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn get_endorsement_stats(&self) -> EndorsementStats {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::GetEndorsementStats { response_tx })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn pool_stats(&self) -> PoolStats {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::GetPoolStats { response_tx })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn get_memory_usage(&self) -> PoolMemoryUsage {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::GetMemoryUsage { response_tx })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn dump_pool_state(&self) -> PoolDump {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::DumpPoolState { response_tx })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn get_operation_count(&self) -> usize {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::GetOperationCount { response_tx })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn get_operation_age(&self, id: &OperationId) -> Option<u64> {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::GetOperationAge {
+                id: *id,
+                response_tx,
+            })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn get_stale_operations(
+        &self,
+        current_slot: &Slot,
+        stale_after_slots: u64,
+    ) -> Vec<OperationId> {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::GetStaleOperations {
+                current_slot: *current_slot,
+                stale_after_slots,
+                response_tx,
+            })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn contains_endorsements(&self, endorsements: &[EndorsementId]) -> Vec<bool> {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::ContainsEndorsements {
+                ids: endorsements.to_vec(),
+                response_tx,
+            })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn contains_operations(&self, operations: &[OperationId]) -> Vec<bool> {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::ContainsOperations {
+                ids: operations.to_vec(),
+                response_tx,
+            })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn contains_all_operations(&self, ids: &[OperationId]) -> bool {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::ContainsAllOperations {
+                ids: ids.to_vec(),
+                response_tx,
+            })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn contains_any_operation(&self, ids: &[OperationId]) -> bool {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::ContainsAnyOperation {
+                ids: ids.to_vec(),
+                response_tx,
+            })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn notify_final_cs_periods(&mut self, final_cs_periods: &[u64]) {
+        self.last_final_cs_periods = final_cs_periods.to_vec();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::NotifyFinalCsPeriods {
+                periods: final_cs_periods.to_vec(),
+            })
+            .unwrap();
+    }
+
+    fn notify_new_slot(&mut self, slot: Slot) -> Result<(), PoolError> {
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::NotifyNewSlot { slot })
+            .unwrap();
+        Ok(())
+    }
+
+    fn notify_block_finalized(
+        &mut self,
+        block_id: &BlockId,
+        included_op_ids: &[OperationId],
+        included_endorsement_ids: &[EndorsementId],
+    ) -> Result<(), PoolError> {
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::NotifyBlockFinalized {
+                block_id: *block_id,
+                included_op_ids: included_op_ids.to_vec(),
+                included_endorsement_ids: included_endorsement_ids.to_vec(),
+            })
+            .unwrap();
+        Ok(())
+    }
+
+    fn reinject_operations_from_block(
+        &mut self,
+        block_id: BlockId,
+        block_storage: Storage,
+    ) -> Result<(), PoolError> {
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::ReInjectOperationsFromBlock {
+                block_id,
+                block_storage,
+            })
+            .unwrap();
+        Ok(())
+    }
+
+    fn reinject_operations_from_storage(
+        &mut self,
+        storage: Storage,
+        current_slot: Slot,
+    ) -> Result<usize, PoolError> {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::ReInjectOperationsFromStorage {
+                storage,
+                current_slot,
+                response_tx,
+            })
+            .unwrap();
+        Ok(response_rx.recv().unwrap())
+    }
+
+    fn remove_operations(&mut self, ids: &[OperationId]) -> Result<(), PoolError> {
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::RemoveOperations { ids: ids.to_vec() })
+            .unwrap();
+        Ok(())
+    }
+
+    fn set_min_fee(&mut self, min_fee: Amount) -> Result<usize, PoolError> {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::SetMinFee {
+                min_fee,
+                response_tx,
+            })
+            .unwrap();
+        Ok(response_rx.recv().unwrap())
+    }
+
+    fn remove_endorsements(&mut self, ids: &[EndorsementId]) -> Result<(), PoolError> {
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::RemoveEndorsements { ids: ids.to_vec() })
+            .unwrap();
+        Ok(())
+    }
+
+    fn remove_included_endorsements(&mut self, ids: &[EndorsementId]) -> Result<usize, PoolError> {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::RemoveIncludedEndorsements {
+                ids: ids.to_vec(),
+                response_tx,
+            })
+            .unwrap();
+        Ok(response_rx.recv().unwrap())
+    }
+
+    fn warm_up(&mut self, endorsements: Storage) -> Result<usize, PoolError> {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::WarmUp {
+                endorsements,
+                response_tx,
+            })
+            .unwrap();
+        Ok(response_rx.recv().unwrap())
+    }
+
+    fn compact_endorsement_pool(&mut self) -> Result<(), PoolError> {
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::CompactEndorsementPool)
+            .unwrap();
+        Ok(())
+    }
+
+    fn clear_operations(&mut self) -> Result<(), PoolError> {
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::ClearOperations)
+            .unwrap();
+        Ok(())
+    }
+
+    fn clear_endorsements(&mut self) -> Result<(), PoolError> {
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::ClearEndorsements)
+            .unwrap();
+        Ok(())
+    }
+
+    fn purge_pool(&mut self) -> Result<(), PoolError> {
+        self.q
+            .lock()
+            .unwrap()
+            .send(MockPoolControllerMessage::PurgePool)
+            .unwrap();
+        Ok(())
+    }
+
+    fn clone_box(&self) -> Box<dyn PoolController> {
+        Box::new(self.clone())
+    }
+
+    fn arc_clone(&self) -> Arc<dyn PoolController> {
+        Arc::new(self.clone())
     }
 
     fn get_final_cs_periods(&self) -> &Vec<u64> {
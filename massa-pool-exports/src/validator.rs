@@ -0,0 +1,12 @@
+//! Copyright (c) 2022 MASSA LABS <info@massa.net>
+
+//! Hook re-checking the operations of the pool against the current ledger state
+
+use massa_models::operation::OperationId;
+
+/// Re-validates the operations of the pool every `revalidation_interval_slots` slots.
+/// It is set when starting the pool: the operations it rejects are evicted from the pool.
+pub trait OperationValidator {
+    /// Returns false if the operation is no longer valid and should be evicted from the pool
+    fn validate(&self, op_id: &OperationId) -> bool;
+}
@@ -0,0 +1,15 @@
+// Copyright (c) 2022 MASSA LABS <info@massa.net>
+
+//! Compile-time checks that the pool trait objects can be shared between threads.
+
+use massa_pool_exports::{PoolController, PoolManager};
+use std::sync::Arc;
+
+fn assert_send_sync<T: Send + Sync + ?Sized>() {}
+
+#[test]
+fn check() {
+    assert_send_sync::<Box<dyn PoolController>>();
+    assert_send_sync::<Box<dyn PoolManager>>();
+    assert_send_sync::<Arc<dyn PoolController>>();
+}
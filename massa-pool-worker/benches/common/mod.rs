@@ -0,0 +1,123 @@
+// Copyright (c) 2022 MASSA LABS <info@massa.net>
+
+//! Setup shared by the pool benchmarks
+
+// each benchmark only uses some of the helpers
+#![allow(dead_code)]
+
+use massa_execution_exports::MockExecutionController;
+use massa_models::{
+    address::Address,
+    amount::Amount,
+    operation::{Operation, OperationSerializer, OperationType, SecureShareOperation},
+    secure_share::SecureShareContent,
+};
+use massa_pool_exports::{PoolChannels, PoolConfig, PoolController, PoolManager};
+use massa_pool_worker::start_pool_controller;
+use massa_pos_exports::MockSelectorController;
+use massa_signature::KeyPair;
+use massa_storage::Storage;
+use massa_time::MassaTime;
+use massa_wallet::{test_exports::create_test_wallet, Wallet};
+use parking_lot::RwLock;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+/// Configuration of an operation pool able to hold `operation_count` operations of a single sender.
+/// The pool is never refreshed during a benchmark, so the mocks are never queried.
+pub fn operation_pool_config(operation_count: usize) -> PoolConfig {
+    PoolConfig {
+        max_operation_pool_size: operation_count,
+        max_ops_per_sender: operation_count,
+        max_ops_added_per_sender_per_slot: operation_count,
+        operation_bloom_filter_size: 20 * operation_count,
+        operation_pool_refresh_interval: MassaTime::from_millis(3_600_000),
+        ..Default::default()
+    }
+}
+
+/// Execution controller mock whose clones are never queried
+pub fn idle_execution_controller() -> Box<MockExecutionController> {
+    let mut execution_controller = Box::new(MockExecutionController::new());
+    execution_controller
+        .expect_clone_box()
+        .returning(|| Box::new(MockExecutionController::new()));
+    execution_controller
+}
+
+/// Selector controller mock whose clones are never queried
+pub fn idle_selector_controller() -> Box<MockSelectorController> {
+    let mut selector_controller = Box::new(MockSelectorController::new());
+    selector_controller
+        .expect_clone_box()
+        .returning(|| Box::new(MockSelectorController::new()));
+    selector_controller
+}
+
+/// Start the pool workers on `storage`
+pub fn start_pool(
+    config: PoolConfig,
+    storage: &Storage,
+    execution_controller: Box<MockExecutionController>,
+    selector_controller: Box<MockSelectorController>,
+    wallet: Wallet,
+) -> (Box<dyn PoolManager>, Box<dyn PoolController>) {
+    start_pool_controller(
+        config,
+        storage,
+        PoolChannels {
+            execution_controller,
+            endorsement_sender: broadcast::channel(2000).0,
+            operation_sender: broadcast::channel(5000).0,
+            selector: selector_controller,
+        },
+        Arc::new(RwLock::new(wallet)),
+    )
+    .unwrap()
+}
+
+/// Start the pool workers on `storage`, with idle mocks and an empty wallet
+pub fn start_idle_pool(
+    config: PoolConfig,
+    storage: &Storage,
+) -> (Box<dyn PoolManager>, Box<dyn PoolController>) {
+    start_pool(
+        config,
+        storage,
+        idle_execution_controller(),
+        idle_selector_controller(),
+        create_test_wallet(None),
+    )
+}
+
+/// Add `operations` to the pool and wait until the operation pool worker has processed them
+pub fn add_operations_and_wait(
+    pool_controller: &mut dyn PoolController,
+    storage: &Storage,
+    operations: Vec<SecureShareOperation>,
+) {
+    let mut ops_storage = storage.clone_without_refs();
+    ops_storage.store_operations(operations);
+    pool_controller.add_operations(ops_storage).unwrap();
+    pool_controller.flush_operations().unwrap();
+}
+
+/// Create `count` transactions of the same sender, the `i`-th one paying a raw fee of `i`
+pub fn create_transactions(count: usize) -> Vec<SecureShareOperation> {
+    let creator = KeyPair::generate(0).unwrap();
+    let recipient_address =
+        Address::from_public_key(&KeyPair::generate(0).unwrap().get_public_key());
+    (0..count as u64)
+        .map(|index| {
+            let content = Operation {
+                fee: Amount::from_raw(index),
+                op: OperationType::Transaction {
+                    recipient_address,
+                    amount: Amount::zero(),
+                },
+                expire_period: 10,
+            };
+            Operation::new_verifiable(content, OperationSerializer::new(), &creator).unwrap()
+        })
+        .collect()
+}
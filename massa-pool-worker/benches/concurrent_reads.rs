@@ -0,0 +1,90 @@
+// Copyright (c) 2022 MASSA LABS <info@massa.net>
+
+//! Measures `contains_operations` while another thread keeps removing and re-adding operations,
+//! for several values of `operation_pool_shard_count`: run it with `--features benchmarking`.
+
+#[cfg(feature = "benchmarking")]
+mod common;
+
+#[cfg(feature = "benchmarking")]
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+#[cfg(feature = "benchmarking")]
+fn criterion_benchmark(c: &mut Criterion) {
+    use crate::common::{
+        add_operations_and_wait, create_transactions, operation_pool_config, start_idle_pool,
+    };
+    use massa_models::operation::OperationId;
+    use massa_pool_exports::PoolConfig;
+    use massa_storage::Storage;
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    const POOL_OPERATION_COUNT: usize = 100_000;
+    const CHURN_OPERATION_COUNT: usize = 1_000;
+    const QUERY_SIZE: usize = 10_000;
+
+    let operations = create_transactions(POOL_OPERATION_COUNT);
+    let queried_ids: Vec<OperationId> = operations
+        .iter()
+        .step_by(POOL_OPERATION_COUNT / QUERY_SIZE)
+        .map(|op| op.id)
+        .collect();
+
+    let mut group = c.benchmark_group("concurrent_contains_operations");
+    for shard_count in [1, 8, 32] {
+        let config = PoolConfig {
+            operation_pool_shard_count: shard_count,
+            ..operation_pool_config(POOL_OPERATION_COUNT)
+        };
+        let storage = Storage::create_root();
+        let (mut pool_manager, mut pool_controller) = start_idle_pool(config, &storage);
+        add_operations_and_wait(pool_controller.as_mut(), &storage, operations.clone());
+
+        // keep the operation pool worker busy while the queries run
+        let stop = Arc::new(AtomicBool::new(false));
+        let writer = {
+            let stop = stop.clone();
+            let mut writer_controller = pool_controller.clone_box();
+            let churn_ids: Vec<OperationId> = operations[..CHURN_OPERATION_COUNT]
+                .iter()
+                .map(|op| op.id)
+                .collect();
+            let mut churn_storage = storage.clone_without_refs();
+            churn_storage.store_operations(operations[..CHURN_OPERATION_COUNT].to_vec());
+            std::thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    writer_controller.remove_operations(&churn_ids).unwrap();
+                    writer_controller
+                        .add_operations(churn_storage.clone())
+                        .unwrap();
+                    writer_controller.flush_operations().unwrap();
+                }
+            })
+        };
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(shard_count),
+            &queried_ids,
+            |b, ids| b.iter(|| pool_controller.contains_operations(black_box(ids))),
+        );
+
+        stop.store(true, Ordering::Relaxed);
+        writer.join().unwrap();
+        pool_manager.stop();
+    }
+    group.finish();
+}
+
+#[cfg(feature = "benchmarking")]
+criterion_group!(benches, criterion_benchmark);
+
+#[cfg(feature = "benchmarking")]
+criterion_main!(benches);
+
+#[cfg(not(feature = "benchmarking"))]
+fn main() {
+    println!("Please use the `--features benchmarking` flag to run this benchmark.");
+}
@@ -0,0 +1,57 @@
+// Copyright (c) 2022 MASSA LABS <info@massa.net>
+
+//! Compares `contains_operations` with and without the `parallel` feature:
+//! run it once with `--features benchmarking` and once with `--features benchmarking,parallel`.
+
+#[cfg(feature = "benchmarking")]
+mod common;
+
+#[cfg(feature = "benchmarking")]
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+#[cfg(feature = "benchmarking")]
+fn criterion_benchmark(c: &mut Criterion) {
+    use crate::common::{
+        add_operations_and_wait, create_transactions, operation_pool_config, start_idle_pool,
+    };
+    use massa_models::operation::OperationId;
+    use massa_storage::Storage;
+
+    const POOL_OPERATION_COUNT: usize = 100_000;
+
+    let storage = Storage::create_root();
+    let (mut pool_manager, mut pool_controller) =
+        start_idle_pool(operation_pool_config(POOL_OPERATION_COUNT), &storage);
+
+    // half of the queried IDs are in the pool
+    let operations = create_transactions(2 * POOL_OPERATION_COUNT);
+    let queried_ids: Vec<OperationId> = operations.iter().map(|op| op.id).collect();
+    add_operations_and_wait(
+        pool_controller.as_mut(),
+        &storage,
+        operations.into_iter().step_by(2).collect(),
+    );
+
+    let mut group = c.benchmark_group("contains_operations");
+    for query_size in [1_000, 10_000, 100_000] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(query_size),
+            &queried_ids[..query_size],
+            |b, ids| b.iter(|| pool_controller.contains_operations(black_box(ids))),
+        );
+    }
+    group.finish();
+
+    pool_manager.stop();
+}
+
+#[cfg(feature = "benchmarking")]
+criterion_group!(benches, criterion_benchmark);
+
+#[cfg(feature = "benchmarking")]
+criterion_main!(benches);
+
+#[cfg(not(feature = "benchmarking"))]
+fn main() {
+    println!("Please use the `--features benchmarking` flag to run this benchmark.");
+}
@@ -0,0 +1,144 @@
+// Copyright (c) 2022 MASSA LABS <info@massa.net>
+
+//! Compares `get_block_endorsements`, read from the slot index of the endorsement pool,
+//! with `get_coverage_for_block`, read under the lock of the pool, while another thread keeps
+//! removing and re-adding endorsements at other slots: run it with `--features benchmarking`.
+
+#[cfg(feature = "benchmarking")]
+mod common;
+
+#[cfg(feature = "benchmarking")]
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+#[cfg(feature = "benchmarking")]
+fn criterion_benchmark(c: &mut Criterion) {
+    use crate::common::{idle_execution_controller, start_pool};
+    use massa_models::{
+        address::Address,
+        block_id::BlockId,
+        endorsement::{Endorsement, EndorsementId, EndorsementSerializer},
+        prehash::PreHashMap,
+        secure_share::SecureShareContent,
+        slot::Slot,
+    };
+    use massa_pool_exports::PoolConfig;
+    use massa_pos_exports::{MockSelectorController, PosResult, Selection};
+    use massa_signature::KeyPair;
+    use massa_storage::Storage;
+    use massa_wallet::test_exports::create_test_wallet;
+    use std::{
+        collections::BTreeMap,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+    };
+
+    const SLOT_COUNT: u64 = 1_000;
+    const CHURN_SLOT_COUNT: u64 = 100;
+
+    let config = PoolConfig {
+        max_endorsements_pool_size_per_thread: 100_000,
+        ..Default::default()
+    };
+    let endorsement_count = config.max_block_endorsement_count;
+
+    // the same address produces the blocks and the endorsements of every slot,
+    // and the wallet holds its key so that the pool keeps the endorsements
+    let keypair = KeyPair::generate(0).unwrap();
+    let address = Address::from_public_key(&keypair.get_public_key());
+    let selection = move |_: Slot| -> PosResult<Selection> {
+        Ok(Selection {
+            producer: address,
+            endorsements: vec![address; endorsement_count as usize],
+        })
+    };
+    let mut selector_controller = Box::new(MockSelectorController::new());
+    selector_controller
+        .expect_get_selection()
+        .returning(selection);
+    selector_controller.expect_clone_box().returning(move || {
+        let mut story = MockSelectorController::new();
+        story
+            .expect_get_available_selections_in_range()
+            .returning(|_, _| Ok(BTreeMap::new()));
+        story.expect_get_selection().returning(selection);
+        Box::new(story)
+    });
+    let mut wallet_keys = PreHashMap::default();
+    wallet_keys.insert(address, keypair.clone());
+
+    let storage = Storage::create_root();
+    let (mut pool_manager, mut pool_controller) = start_pool(
+        config,
+        &storage,
+        idle_execution_controller(),
+        selector_controller,
+        create_test_wallet(Some(wallet_keys)),
+    );
+
+    let endorsed_block = BlockId::generate_from_hash(massa_hash::Hash::compute_from(b"block"));
+    let endorsements: Vec<_> = (1..=SLOT_COUNT)
+        .flat_map(|period| (0..endorsement_count).map(move |index| (period, index)))
+        .map(|(period, index)| {
+            let content = Endorsement {
+                slot: Slot::new(period, 0),
+                index,
+                endorsed_block,
+            };
+            Endorsement::new_verifiable(content, EndorsementSerializer::new(), &keypair).unwrap()
+        })
+        .collect();
+    let mut endo_storage = storage.clone_without_refs();
+    endo_storage.store_endorsements(endorsements.clone());
+    pool_controller.add_endorsements(endo_storage).unwrap();
+    pool_controller.flush_endorsements().unwrap();
+
+    // keep the endorsement pool worker busy at the last slots while the first one is read
+    let stop = Arc::new(AtomicBool::new(false));
+    let writer = {
+        let stop = stop.clone();
+        let mut writer_controller = pool_controller.clone_box();
+        let churn_endorsements =
+            &endorsements[((SLOT_COUNT - CHURN_SLOT_COUNT) * endorsement_count as u64) as usize..];
+        let churn_ids: Vec<EndorsementId> = churn_endorsements.iter().map(|endo| endo.id).collect();
+        let mut churn_storage = storage.clone_without_refs();
+        churn_storage.store_endorsements(churn_endorsements.to_vec());
+        std::thread::spawn(move || {
+            while !stop.load(Ordering::Relaxed) {
+                writer_controller
+                    .remove_included_endorsements(&churn_ids)
+                    .unwrap();
+                writer_controller
+                    .add_endorsements(churn_storage.clone())
+                    .unwrap();
+                writer_controller.flush_endorsements().unwrap();
+            }
+        })
+    };
+
+    let read_slot = Slot::new(1, 0);
+    let mut group = c.benchmark_group("concurrent_block_endorsement_reads");
+    group.bench_function("slot_index", |b| {
+        b.iter(|| pool_controller.get_block_endorsements(black_box(&endorsed_block), &read_slot))
+    });
+    group.bench_function("pool_lock", |b| {
+        b.iter(|| pool_controller.get_coverage_for_block(black_box(&endorsed_block), &read_slot))
+    });
+    group.finish();
+
+    stop.store(true, Ordering::Relaxed);
+    writer.join().unwrap();
+    pool_manager.stop();
+}
+
+#[cfg(feature = "benchmarking")]
+criterion_group!(benches, criterion_benchmark);
+
+#[cfg(feature = "benchmarking")]
+criterion_main!(benches);
+
+#[cfg(not(feature = "benchmarking"))]
+fn main() {
+    println!("Please use the `--features benchmarking` flag to run this benchmark.");
+}
@@ -0,0 +1,76 @@
+// Copyright (c) 2022 MASSA LABS <info@massa.net>
+
+//! Measures the removal of the operations included in a final block from a full operation pool:
+//! run it with `--features benchmarking`.
+
+#[cfg(feature = "benchmarking")]
+mod common;
+
+#[cfg(feature = "benchmarking")]
+use criterion::{criterion_group, criterion_main, Criterion};
+
+#[cfg(feature = "benchmarking")]
+fn criterion_benchmark(c: &mut Criterion) {
+    use crate::common::{
+        add_operations_and_wait, create_transactions, operation_pool_config, start_idle_pool,
+    };
+    use massa_models::operation::OperationId;
+    use massa_storage::Storage;
+    use std::time::{Duration, Instant};
+
+    const POOL_OPERATION_COUNT: usize = 100_000;
+    const INCLUDED_OPERATION_COUNT: usize = 1_000;
+
+    let storage = Storage::create_root();
+    let (mut pool_manager, mut pool_controller) =
+        start_idle_pool(operation_pool_config(POOL_OPERATION_COUNT), &storage);
+
+    // fill the pool, keeping a storage of the operations that get included
+    let operations = create_transactions(POOL_OPERATION_COUNT);
+    let included_ids: Vec<OperationId> = operations
+        .iter()
+        .step_by(POOL_OPERATION_COUNT / INCLUDED_OPERATION_COUNT)
+        .map(|op| op.id)
+        .collect();
+    let mut included_storage = storage.clone_without_refs();
+    included_storage.store_operations(
+        operations
+            .iter()
+            .step_by(POOL_OPERATION_COUNT / INCLUDED_OPERATION_COUNT)
+            .cloned()
+            .collect(),
+    );
+    add_operations_and_wait(pool_controller.as_mut(), &storage, operations);
+
+    c.bench_function("remove_included_operations", |b| {
+        b.iter_custom(|iters| {
+            let mut elapsed = Duration::ZERO;
+            for _ in 0..iters {
+                let start = Instant::now();
+                pool_controller.remove_operations(&included_ids).unwrap();
+                pool_controller.flush_operations().unwrap();
+                elapsed += start.elapsed();
+
+                // put the removed operations back for the next iteration
+                pool_controller
+                    .add_operations(included_storage.clone())
+                    .unwrap();
+                pool_controller.flush_operations().unwrap();
+            }
+            elapsed
+        })
+    });
+
+    pool_manager.stop();
+}
+
+#[cfg(feature = "benchmarking")]
+criterion_group!(benches, criterion_benchmark);
+
+#[cfg(feature = "benchmarking")]
+criterion_main!(benches);
+
+#[cfg(not(feature = "benchmarking"))]
+fn main() {
+    println!("Please use the `--features benchmarking` flag to run this benchmark.");
+}
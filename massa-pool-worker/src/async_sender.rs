@@ -0,0 +1,62 @@
+//! Copyright (c) 2022 MASSA LABS <info@massa.net>
+
+//! Sender of operations to the operation pool, for async contexts
+
+use massa_pool_exports::PoolError;
+use massa_storage::Storage;
+use std::{
+    sync::{mpsc::SyncSender, Arc},
+    thread,
+};
+use tokio::sync::mpsc;
+use tracing::debug;
+
+use crate::controller_impl::{Command, WorkerActivity};
+
+/// Cheaply cloneable sender of operations to the operation pool, for async tasks.
+///
+/// `PoolController::add_operations` drops the operations when the channel of the
+/// operation pool worker is full, whereas `add_operations_async` waits for room in it.
+#[derive(Clone)]
+pub struct AsyncOperationsSender {
+    sender: mpsc::Sender<Storage>,
+}
+
+impl AsyncOperationsSender {
+    /// Spawn the thread forwarding the operations sent through the returned sender to the
+    /// operation pool worker. The thread exits once all the senders are dropped,
+    /// or when it forwards operations after the worker stopped.
+    pub(crate) fn spawn(
+        operations_input_sender: SyncSender<Command>,
+        operations_activity: Arc<WorkerActivity>,
+        channel_size: usize,
+    ) -> Self {
+        let (sender, mut receiver) = mpsc::channel(channel_size);
+        thread::Builder::new()
+            .name("operation-pool-async".into())
+            .spawn(move || {
+                while let Some(ops) = receiver.blocking_recv() {
+                    operations_activity.addition_queued();
+                    if operations_input_sender
+                        .send(Command::AddItems(ops))
+                        .is_err()
+                    {
+                        operations_activity.addition_dropped();
+                        debug!("operation pool worker stopped: stop forwarding async operations");
+                        break;
+                    }
+                }
+            })
+            .expect("failed to spawn thread: operation-pool-async");
+        AsyncOperationsSender { sender }
+    }
+
+    /// Asynchronously add operations to the pool,
+    /// waiting for room in the channel of the operation pool worker.
+    pub async fn add_operations_async(&mut self, ops: Storage) -> Result<(), PoolError> {
+        self.sender
+            .send(ops)
+            .await
+            .map_err(|_| PoolError::ChannelDisconnected)
+    }
+}
@@ -0,0 +1,94 @@
+// Copyright (c) 2022 MASSA LABS <info@massa.net>
+
+//! Replays a pool event log (see `PoolConfig::event_log_path`) and prints statistics
+//! about the content of the pools at the end of the log, or at a given time:
+//!
+//! ```text
+//! replay_event_log <log path> [last timestamp in nanoseconds]
+//! ```
+//!
+//! The log is replayed through the real operation and endorsement pools,
+//! with the default pool configuration, which requires the `testing` feature.
+//! The periodic refreshes of the operation pool are not logged, so the evictions they decide
+//! (sender balances, executed operations) are not replayed, and the PoS draws are not logged,
+//! so each endorsement is considered drawn at its index.
+
+use massa_pool_exports::PoolConfig;
+use massa_pool_worker::{EventLogEntry, LoggedPool, ReplayedPools};
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader},
+    process::ExitCode,
+};
+
+fn replay(path: &str, last_timestamp_ns: u64) -> io::Result<()> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut entries = Vec::new();
+    let mut operation_entries = 0usize;
+    let mut endorsement_entries = 0usize;
+    let mut time_span: Option<(u64, u64)> = None;
+    for (line_index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.is_empty() {
+            continue;
+        }
+        let entry: EventLogEntry = serde_json::from_str(&line).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid entry at line {}: {}", line_index + 1, err),
+            )
+        })?;
+        if entry.timestamp_ns > last_timestamp_ns {
+            break;
+        }
+        match entry.pool {
+            LoggedPool::Operations => operation_entries += 1,
+            LoggedPool::Endorsements => endorsement_entries += 1,
+        }
+        time_span = Some(match time_span {
+            Some((first, _)) => (first, entry.timestamp_ns),
+            None => (entry.timestamp_ns, entry.timestamp_ns),
+        });
+        entries.push(entry);
+    }
+
+    let pools = ReplayedPools::replay(PoolConfig::default(), entries);
+    println!(
+        "replayed {} operation pool entries and {} endorsement pool entries",
+        operation_entries, endorsement_entries
+    );
+    if let Some((first, last)) = time_span {
+        println!("from timestamp {} to timestamp {} (ns)", first, last);
+    }
+    println!("operations: {}", pools.operation_count());
+    println!("total operation fees: {}", pools.total_operation_fees());
+    println!("minimal operation fee: {}", pools.min_fee());
+    println!("endorsements: {}", pools.endorsement_count());
+    println!("final consensus periods: {:?}", pools.final_cs_periods());
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().collect();
+    let (path, last_timestamp_ns) = match args.as_slice() {
+        [_, path] => (path, u64::MAX),
+        [_, path, last_timestamp_ns] => match last_timestamp_ns.parse() {
+            Ok(last_timestamp_ns) => (path, last_timestamp_ns),
+            Err(err) => {
+                eprintln!("invalid timestamp {}: {}", last_timestamp_ns, err);
+                return ExitCode::FAILURE;
+            }
+        },
+        _ => {
+            eprintln!("usage: replay_event_log <log path> [last timestamp in nanoseconds]");
+            return ExitCode::FAILURE;
+        }
+    };
+    match replay(path, last_timestamp_ns) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("could not replay the pool event log {}: {}", path, err);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,98 @@
+//! Copyright (c) 2022 MASSA LABS <info@massa.net>
+
+//! Lock-free bloom filter used as a fast path for pool membership queries.
+
+use massa_models::secure_share::Id;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// Bloom filter over item IDs.
+///
+/// The filter can be queried concurrently without any lock.
+/// It never returns false negatives for items inserted before the query,
+/// so a negative answer means that the item is definitely absent.
+pub struct BloomFilter {
+    /// bit set, stored as 64-bit words
+    bits: Vec<AtomicU64>,
+    /// number of bits set per item
+    hash_count: u32,
+    /// number of removed IDs whose bits are still set
+    stale_count: AtomicUsize,
+}
+
+impl BloomFilter {
+    /// Creates an empty filter of `size` bits with a number of hash functions
+    /// that targets the given false positive rate.
+    pub fn new(size: usize, false_positive_rate: f64) -> Self {
+        let word_count = (size.saturating_add(63) / 64).max(1);
+        let hash_count = ((-false_positive_rate.log2()).ceil().clamp(1.0, 32.0) as u32).max(1);
+        BloomFilter {
+            bits: (0..word_count).map(|_| AtomicU64::new(0)).collect(),
+            hash_count,
+            stale_count: AtomicUsize::new(0),
+        }
+    }
+
+    /// Get the positions of the bits associated to an ID.
+    /// IDs are hashes, so their bytes can be used directly for double hashing.
+    fn bit_positions<T: Id>(&self, id: &T) -> impl Iterator<Item = usize> {
+        let bytes = id.get_hash().to_bytes();
+        let h1 = u64::from_le_bytes(bytes[0..8].try_into().expect("hash is too short"));
+        let h2 = u64::from_le_bytes(bytes[8..16].try_into().expect("hash is too short"));
+        let bit_count = (self.bits.len() as u64).saturating_mul(64);
+        (0..self.hash_count as u64)
+            .map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % bit_count) as usize)
+    }
+
+    /// Adds an ID to the filter
+    pub fn insert<T: Id>(&self, id: &T) {
+        for pos in self.bit_positions(id) {
+            self.bits[pos / 64].fetch_or(1 << (pos % 64), Ordering::Release);
+        }
+    }
+
+    /// Returns false if the ID is definitely absent, true if it might be present.
+    pub fn contains<T: Id>(&self, id: &T) -> bool {
+        self.bit_positions(id)
+            .all(|pos| self.bits[pos / 64].load(Ordering::Acquire) & (1 << (pos % 64)) != 0)
+    }
+
+    /// Rebuilds the filter from the full set of remaining IDs, forgetting the removed ones.
+    ///
+    /// The new content is computed aside then written word by word:
+    /// every intermediate state still contains the bits of all the remaining IDs,
+    /// so concurrent readers never get false negatives for them.
+    pub fn rebuild<'a, T: Id + 'a, I: IntoIterator<Item = &'a T>>(&self, ids: I) {
+        let mut words = vec![0u64; self.bits.len()];
+        for id in ids {
+            for pos in self.bit_positions(id) {
+                words[pos / 64] |= 1 << (pos % 64);
+            }
+        }
+        for (bits, word) in self.bits.iter().zip(words) {
+            bits.store(word, Ordering::Release);
+        }
+        self.stale_count.store(0, Ordering::Relaxed);
+    }
+
+    /// Records the removal of `removed_count` IDs, without clearing their bits.
+    ///
+    /// Removed IDs only cause false positives, so the filter is rebuilt from the `remaining`
+    /// IDs once they are outnumbered by the removed ones: the cost of a rebuild is then
+    /// amortized over at least as many removals, and at most half of the set bits are stale.
+    /// Returns true if the filter was rebuilt.
+    pub fn remove_lazily<'a, T: Id + 'a, I: ExactSizeIterator<Item = &'a T>>(
+        &self,
+        removed_count: usize,
+        remaining: I,
+    ) -> bool {
+        let stale_count = self
+            .stale_count
+            .fetch_add(removed_count, Ordering::Relaxed)
+            .saturating_add(removed_count);
+        if stale_count < remaining.len() {
+            return false;
+        }
+        self.rebuild(remaining);
+        true
+    }
+}
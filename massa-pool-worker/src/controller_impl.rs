@@ -2,22 +2,44 @@
 
 //! Pool controller implementation
 
+use massa_hash::Hash;
 use massa_models::{
-    block_id::BlockId, denunciation::Denunciation, denunciation::DenunciationPrecursor,
-    endorsement::EndorsementId, operation::OperationId, slot::Slot,
+    address::Address, amount::Amount, block_id::BlockId, denunciation::Denunciation,
+    denunciation::DenunciationPrecursor, endorsement::EndorsementId, operation::OperationId,
+    prehash::PreHashSet, slot::Slot,
+};
+use massa_pool_exports::{
+    BlockFillInfo, EndorsementPoolSnapshot, EndorsementStats, OperationPoolSnapshot, PoolConfig,
+    PoolController, PoolDiagnostic, PoolDump, PoolError, PoolEvent, PoolKind, PoolManager,
+    PoolMemoryUsage, PoolStats, WorkerDiagnostic, WorkerStatus,
 };
-use massa_pool_exports::{PoolConfig, PoolController, PoolManager};
 use massa_storage::Storage;
-use parking_lot::RwLock;
+use massa_time::MassaTime;
+use parking_lot::{Condvar, Mutex, RwLock, RwLockReadGuard};
+#[cfg(feature = "parallel")]
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::collections::HashMap;
+use std::fmt;
 use std::sync::mpsc::TrySendError;
-use std::sync::{mpsc::SyncSender, Arc};
-use tracing::{info, warn};
+use std::sync::{
+    atomic::{AtomicU64, AtomicUsize, Ordering},
+    mpsc::{self, Receiver, Sender, SyncSender},
+    Arc,
+};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tracing::{debug, error, info, warn};
 
 use crate::{
-    denunciation_pool::DenunciationPool, endorsement_pool::EndorsementPool,
-    operation_pool::OperationPool,
+    bloom_filter::BloomFilter, denunciation_pool::DenunciationPool,
+    endorsement_pool::EndorsementPool, endorsement_slots::EndorsementSlots, metrics,
+    operation_pool::OperationPool, operation_shards::OperationShards, types::OperationCursor,
 };
 
+/// Number of senders reported in `PoolStats::top_10_senders`
+const POOL_STATS_TOP_SENDERS: usize = 10;
+
 /// A generic command to send commands to a pool
 #[allow(clippy::large_enum_variant)]
 pub enum Command {
@@ -27,21 +49,108 @@ pub enum Command {
     AddDenunciationPrecursor(DenunciationPrecursor),
     /// Notify of new final consensus periods
     NotifyFinalCsPeriods(Vec<u64>),
+    /// Notify of the start of a new slot
+    NotifyNewSlot(Slot),
+    /// Notify that a block became final, with the items it includes
+    BlockFinalized {
+        /// id of the final block
+        block_id: BlockId,
+        /// ids of the operations included in the block
+        operation_ids: Vec<OperationId>,
+        /// ids of the endorsements included in the block
+        endorsement_ids: Vec<EndorsementId>,
+    },
+    /// Re-inject the operations of an orphaned block, found in the attached storage
+    ReInjectOperations(BlockId, Storage),
+    /// Remove operations from the pool
+    RemoveOperations(Vec<OperationId>),
+    /// Remove endorsements from the pool.
+    /// The number of removed endorsements is sent to the attached channel, if any.
+    RemoveEndorsements(Vec<EndorsementId>, Option<Sender<usize>>),
+    /// Pre-load the endorsement pool with the endorsements of the attached storage.
+    /// The number of endorsements kept is sent to the attached channel.
+    WarmUpEndorsements(Storage, Sender<usize>),
+    /// Compact the indexes of the endorsement pool
+    CompactEndorsements,
+    /// Remove all the operations of the pool and reset its statistics
+    #[cfg(feature = "testing")]
+    ClearOperations,
+    /// Remove all the endorsements of the pool and reset its statistics
+    #[cfg(feature = "testing")]
+    ClearEndorsements,
+    /// Remove all the items of the pool, release the memory of its indexes and reset its statistics
+    PurgeAll,
+    /// Set the minimal fee of the operation pool, pruning the operations paying less.
+    /// The number of pruned operations is sent to the attached channel.
+    SetMinFee(Amount, Sender<usize>),
+    /// Send a snapshot of the operation pool to the attached channel
+    SnapshotOperations(Sender<OperationPoolSnapshot>),
+    /// Send a snapshot of the endorsement pool to the attached channel
+    SnapshotEndorsements(Sender<EndorsementPoolSnapshot>),
+    /// Acknowledge on the attached channel once all the previously sent commands are processed
+    Flush(SyncSender<()>),
+    /// Send the number of operations in the pool to the attached channel,
+    /// once all the previously sent commands are processed
+    QueryOperationCount(SyncSender<usize>),
     /// Stop the worker
     Stop,
 }
 
+/// Send a `Command::Flush` to a pool worker and wait for its acknowledgment
+fn flush_worker(input_sender: &SyncSender<Command>) -> Result<(), PoolError> {
+    let (ack_tx, ack_rx) = mpsc::sync_channel(1);
+    input_sender
+        .send(Command::Flush(ack_tx))
+        .map_err(PoolError::from)?;
+    ack_rx.recv().map_err(PoolError::from)
+}
+
+/// Iterator over the IDs of the operations of the pool paying at least a minimal fee,
+/// holding a read lock on the operation pool until it is dropped
+struct OperationsAboveFee<'a> {
+    /// read guard of the operation pool
+    operation_pool: RwLockReadGuard<'a, OperationPool>,
+    /// minimal fee of the returned operations
+    min_fee: Amount,
+    /// sorting key of the last returned operation
+    last: Option<OperationCursor>,
+}
+
+impl Iterator for OperationsAboveFee<'_> {
+    type Item = OperationId;
+
+    fn next(&mut self) -> Option<OperationId> {
+        let cursor = self
+            .operation_pool
+            .next_operation_above_fee(self.min_fee, self.last.as_ref())?;
+        self.last = Some(cursor);
+        Some(cursor.id)
+    }
+}
+
 /// Pool controller
 #[derive(Clone)]
 pub struct PoolControllerImpl {
     /// Config
-    pub(crate) _config: PoolConfig,
+    pub(crate) config: PoolConfig,
     /// Shared reference to the operation pool
     pub(crate) operation_pool: Arc<RwLock<OperationPool>>,
     /// Shared reference to the endorsement pool
     pub(crate) endorsement_pool: Arc<RwLock<EndorsementPool>>,
     /// Shared reference to the denunciation pool
     pub(crate) denunciation_pool: Arc<RwLock<DenunciationPool>>,
+    /// Bloom filter of the operation pool, queried without locking the pool
+    pub(crate) operations_bloom_filter: Arc<BloomFilter>,
+    /// Sharded index of the operation pool, queried without locking the pool
+    pub(crate) operation_shards: Arc<OperationShards>,
+    /// Estimated memory usage of the operation pool, read without locking the pool
+    pub(crate) operations_estimated_memory: Arc<AtomicUsize>,
+    /// Bloom filter of the endorsement pool, queried without locking the pool
+    pub(crate) endorsements_bloom_filter: Arc<BloomFilter>,
+    /// Slot index of the endorsement pool, queried without locking the pool
+    pub(crate) endorsement_slots: Arc<EndorsementSlots>,
+    /// Storage the endorsements of the pool are claimed from
+    pub(crate) endorsements_storage: Storage,
     /// Operation write worker command sender
     pub(crate) operations_input_sender: SyncSender<Command>,
     /// Endorsement write worker command sender
@@ -50,54 +159,222 @@ pub struct PoolControllerImpl {
     pub(crate) denunciations_input_sender: SyncSender<Command>,
     /// Last final periods from Consensus
     pub last_cs_final_periods: Vec<u64>,
+    /// `(operation_count, endorsement_count)`, updated by the pool worker threads
+    pub(crate) pool_sizes_receiver: watch::Receiver<(usize, usize)>,
+    /// Number of commands dropped because a worker channel was full, shared by all the clones
+    pub(crate) channel_full_events: Arc<AtomicU64>,
+    /// Commands sent to the operation thread
+    pub(crate) operations_activity: Arc<WorkerActivity>,
+    /// Commands sent to the endorsement thread
+    pub(crate) endorsements_activity: Arc<WorkerActivity>,
+    /// Commands sent to the denunciation thread
+    pub(crate) denunciations_activity: Arc<WorkerActivity>,
+}
+
+/// Shows the size of the pools without locking them, as last published by the workers.
+/// The occupancy of the worker channels cannot be read from their senders:
+/// the number of commands dropped because a channel was full is shown instead.
+impl fmt::Debug for PoolControllerImpl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (operation_count, endorsement_count) = *self.pool_sizes_receiver.borrow();
+        f.debug_struct("PoolControllerImpl")
+            .field("operation_count", &operation_count)
+            .field("endorsement_count", &endorsement_count)
+            .field(
+                "channel_full_events",
+                &self.channel_full_events.load(Ordering::Relaxed),
+            )
+            .field("last_cs_final_periods", &self.last_cs_final_periods)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PoolControllerImpl {
+    /// Count a command dropped because the channel of the `pool` worker was full
+    fn record_channel_full(&self, pool: &str) {
+        self.channel_full_events.fetch_add(1, Ordering::Relaxed);
+        metrics::inc_channel_full(pool);
+    }
+
+    /// Send `items` to the `pool` worker through `sender` without blocking.
+    /// Warns if the additions waiting in its channel of `capacity` commands exceed
+    /// the `backpressure_warn_threshold` share of it.
+    ///
+    /// Only the `AddItems` commands are counted, as they make most of the traffic:
+    /// this is a cheap lower bound of the occupancy of the channel.
+    fn try_send_items(
+        &self,
+        pool: &str,
+        sender: &SyncSender<Command>,
+        activity: &WorkerActivity,
+        capacity: usize,
+        items: Storage,
+    ) -> Result<(), TrySendError<Command>> {
+        let pending = activity.addition_queued();
+        if let Err(err) = sender.try_send(Command::AddItems(items)) {
+            activity.addition_dropped();
+            return Err(err);
+        }
+        let occupancy = pending as f32 / capacity as f32;
+        if occupancy > self.config.backpressure_warn_threshold {
+            warn!(
+                "{} pool worker channel is {:.0}% full ({} of {} commands), above the warning threshold of {:.0}%",
+                pool,
+                occupancy * 100.0,
+                pending,
+                capacity,
+                self.config.backpressure_warn_threshold * 100.0
+            );
+        }
+        Ok(())
+    }
+
+    /// Send operations to the operation pool worker, see `try_send_items`
+    fn try_send_operations(&self, ops: Storage) -> Result<(), TrySendError<Command>> {
+        self.try_send_items(
+            "operation",
+            &self.operations_input_sender,
+            &self.operations_activity,
+            self.config.operations_channel_size,
+            ops,
+        )
+    }
+
+    /// Send endorsements to the endorsement pool worker, see `try_send_items`
+    fn try_send_endorsements(&self, endorsements: Storage) -> Result<(), TrySendError<Command>> {
+        self.try_send_items(
+            "endorsement",
+            &self.endorsements_input_sender,
+            &self.endorsements_activity,
+            self.config.endorsements_channel_size,
+            endorsements,
+        )
+    }
+
+    /// Send endorsements to the denunciation pool worker, see `try_send_items`
+    fn try_send_denunciation_endorsements(
+        &self,
+        endorsements: Storage,
+    ) -> Result<(), TrySendError<Command>> {
+        self.try_send_items(
+            "denunciation",
+            &self.denunciations_input_sender,
+            &self.denunciations_activity,
+            self.config.denunciations_channel_size,
+            endorsements,
+        )
+    }
 }
 
 impl PoolController for PoolControllerImpl {
-    /// Asynchronously add operations to pool. Simply print a warning on failure.
-    fn add_operations(&mut self, ops: Storage) {
-        match self
-            .operations_input_sender
-            .try_send(Command::AddItems(ops))
-        {
-            Err(TrySendError::Disconnected(_)) => {
+    /// Asynchronously add operations to pool.
+    /// Returns an error if the pool is full and ranks all the new operations below the ones
+    /// it holds, or if the operations could not be sent to the pool worker.
+    ///
+    /// The pool is only locked if it was full when its size was last published,
+    /// and it may change before the worker adds the operations: the capacity check is best-effort.
+    fn add_operations(&mut self, ops: Storage) -> Result<(), PoolError> {
+        if self.pool_sizes_receiver.borrow().0 >= self.config.max_operation_pool_size {
+            let rejected = self.operation_pool.read().check_capacity(&ops);
+            if let Some((current, limit)) = rejected {
+                debug!("Could not add operations to pool: all of them rank below the full pool.");
+                return Err(PoolError::CapacityExceeded {
+                    pool: PoolKind::Operations,
+                    current,
+                    limit,
+                });
+            }
+        }
+
+        match self.try_send_operations(ops) {
+            Err(err @ TrySendError::Disconnected(_)) => {
                 warn!("Could not add operations to pool: worker is unreachable.");
+                Err(err.into())
             }
-            Err(TrySendError::Full(_)) => {
+            Err(err @ TrySendError::Full(_)) => {
+                self.record_channel_full("operations");
                 warn!("Could not add operations to pool: worker channel is full.");
+                Err(err.into())
             }
-            Ok(_) => {}
+            Ok(_) => Ok(()),
         }
     }
 
-    /// Asynchronously add endorsements to pool. Simply print a warning on failure.
-    fn add_endorsements(&mut self, endorsements: Storage) {
+    /// Asynchronously add endorsements to pool.
+    /// Returns an error if the pool is full and ranks all the new endorsements below the ones
+    /// it holds, or if the endorsements could not be sent to the endorsement pool worker.
+    /// Simply prints a warning if they could not be sent to the denunciation pool worker.
+    ///
+    /// As in `add_operations`, the capacity check is best-effort.
+    fn add_endorsements(&mut self, endorsements: Storage) -> Result<(), PoolError> {
         // Send endorsements to the denunciation pool - so we got unfiltered endorsements
         // from protocol & endorsement factory
-        match self
-            .denunciations_input_sender
-            .try_send(Command::AddItems(endorsements.clone()))
-        {
+        match self.try_send_denunciation_endorsements(endorsements.clone()) {
             Err(TrySendError::Disconnected(_)) => {
                 warn!("Could not add endorsements to pool: worker is unreachable.");
             }
             Err(TrySendError::Full(_)) => {
+                self.record_channel_full("denunciations");
                 warn!("Could not add endorsements to pool: worker channel is full.");
             }
             Ok(_) => {}
         }
 
-        // Now send endorsements to endorsement pool - storage is cleaned up
-        match self
-            .endorsements_input_sender
-            .try_send(Command::AddItems(endorsements))
+        if self.pool_sizes_receiver.borrow().1 >= self.config.max_endorsements_pool_size_per_thread
         {
-            Err(TrySendError::Disconnected(_)) => {
+            let rejected = self.endorsement_pool.read().check_capacity(&endorsements);
+            if let Some((current, limit)) = rejected {
+                debug!("Could not add endorsements to pool: all of them rank below the full pool.");
+                return Err(PoolError::CapacityExceeded {
+                    pool: PoolKind::Endorsements,
+                    current,
+                    limit,
+                });
+            }
+        }
+
+        // Now send endorsements to endorsement pool - storage is cleaned up
+        match self.try_send_endorsements(endorsements) {
+            Err(err @ TrySendError::Disconnected(_)) => {
                 warn!("Could not add endorsements to pool: worker is unreachable.");
+                Err(err.into())
             }
-            Err(TrySendError::Full(_)) => {
+            Err(err @ TrySendError::Full(_)) => {
+                self.record_channel_full("endorsements");
                 warn!("Could not add endorsements to pool: worker channel is full.");
+                Err(err.into())
             }
-            Ok(_) => {}
+            Ok(_) => Ok(()),
+        }
+    }
+
+    /// Asynchronously add operations to pool, dropping them if the channel is full.
+    fn try_add_operations(&mut self, ops: Storage) -> Result<bool, PoolError> {
+        match self.try_send_operations(ops) {
+            Ok(_) => Ok(true),
+            Err(TrySendError::Full(_)) => {
+                self.record_channel_full("operations");
+                Ok(false)
+            }
+            Err(TrySendError::Disconnected(_)) => Err(PoolError::ChannelDisconnected),
+        }
+    }
+
+    /// Asynchronously add endorsements to pool, dropping them if the channel is full.
+    fn try_add_endorsements(&mut self, endorsements: Storage) -> Result<bool, PoolError> {
+        // Send endorsements to the denunciation pool - so we got unfiltered endorsements
+        // from protocol & endorsement factory
+        if let Err(err) = self.try_send_denunciation_endorsements(endorsements.clone()) {
+            warn!("Could not add endorsements to denunciation pool: {}", err);
+        }
+
+        match self.try_send_endorsements(endorsements) {
+            Ok(_) => Ok(true),
+            Err(TrySendError::Full(_)) => {
+                self.record_channel_full("endorsements");
+                Ok(false)
+            }
+            Err(TrySendError::Disconnected(_)) => Err(PoolError::ChannelDisconnected),
         }
     }
 
@@ -111,6 +388,7 @@ impl PoolController for PoolControllerImpl {
                 warn!("Could not add denunciation precursor to pool: worker is unreachable.");
             }
             Err(TrySendError::Full(_)) => {
+                self.record_channel_full("denunciations");
                 warn!("Could not add denunciation precursor to pool: worker channel is full.");
             }
             Ok(_) => {}
@@ -129,6 +407,7 @@ impl PoolController for PoolControllerImpl {
                 warn!("Could not notify operation pool of new final slots: worker is unreachable.");
             }
             Err(TrySendError::Full(_)) => {
+                self.record_channel_full("operations");
                 warn!(
                     "Could not notify operation pool of new final slots: worker channel is full."
                 );
@@ -146,6 +425,7 @@ impl PoolController for PoolControllerImpl {
                 );
             }
             Err(TrySendError::Full(_)) => {
+                self.record_channel_full("endorsements");
                 warn!(
                     "Could not notify endorsement pool of new final slots: worker channel is full."
                 );
@@ -163,6 +443,7 @@ impl PoolController for PoolControllerImpl {
                 );
             }
             Err(TrySendError::Full(_)) => {
+                self.record_channel_full("denunciations");
                 warn!(
                     "Could not notify endorsement pool of new final slots: worker channel is full."
                 );
@@ -171,20 +452,436 @@ impl PoolController for PoolControllerImpl {
         }
     }
 
+    /// Asynchronously notify of the start of a new slot.
+    /// Both pools are notified even if the operation pool could not be:
+    /// the error of the first failed notification is returned.
+    fn notify_new_slot(&mut self, slot: Slot) -> Result<(), PoolError> {
+        let operations_res = self
+            .operations_input_sender
+            .try_send(Command::NotifyNewSlot(slot));
+        let endorsements_res = self
+            .endorsements_input_sender
+            .try_send(Command::NotifyNewSlot(slot));
+        operations_res
+            .and(endorsements_res)
+            .map_err(PoolError::from)
+    }
+
+    /// Asynchronously notify that a block became final.
+    /// Both pools are notified even if the operation pool could not be:
+    /// the error of the first failed notification is returned.
+    fn notify_block_finalized(
+        &mut self,
+        block_id: &BlockId,
+        included_op_ids: &[OperationId],
+        included_endorsement_ids: &[EndorsementId],
+    ) -> Result<(), PoolError> {
+        let operations_res = self
+            .operations_input_sender
+            .try_send(Command::BlockFinalized {
+                block_id: *block_id,
+                operation_ids: included_op_ids.to_vec(),
+                endorsement_ids: Vec::new(),
+            });
+        let endorsements_res = self
+            .endorsements_input_sender
+            .try_send(Command::BlockFinalized {
+                block_id: *block_id,
+                operation_ids: Vec::new(),
+                endorsement_ids: included_endorsement_ids.to_vec(),
+            });
+        operations_res
+            .and(endorsements_res)
+            .map_err(PoolError::from)
+    }
+
+    /// Asynchronously re-inject the operations of an orphaned block
+    fn reinject_operations_from_block(
+        &mut self,
+        block_id: BlockId,
+        block_storage: Storage,
+    ) -> Result<(), PoolError> {
+        self.operations_input_sender
+            .try_send(Command::ReInjectOperations(block_id, block_storage))
+            .map_err(PoolError::from)
+    }
+
+    /// Re-inject the operations of `storage` that did not expire at `current_slot`,
+    /// through the same path as `add_operations`.
+    /// Returns the number of operations submitted to the worker, or the error of `add_operations`.
+    fn reinject_operations_from_storage(
+        &mut self,
+        mut storage: Storage,
+        current_slot: Slot,
+    ) -> Result<usize, PoolError> {
+        let expired: PreHashSet<OperationId> = {
+            let stored_ops = storage.read_operations();
+            storage
+                .get_op_refs()
+                .iter()
+                .filter(|id| {
+                    stored_ops.get(id).map_or(true, |op| {
+                        let thread = op
+                            .content_creator_address
+                            .get_thread(self.config.thread_count);
+                        Slot::new(op.content.expire_period, thread) < current_slot
+                    })
+                })
+                .copied()
+                .collect()
+        };
+        storage.drop_operation_refs(&expired);
+        let submitted = storage.get_op_refs().len();
+        if submitted > 0 {
+            self.add_operations(storage)?;
+        }
+        Ok(submitted)
+    }
+
+    /// Asynchronously remove operations from the pool
+    fn remove_operations(&mut self, ids: &[OperationId]) -> Result<(), PoolError> {
+        self.operations_input_sender
+            .try_send(Command::RemoveOperations(ids.to_vec()))
+            .map_err(PoolError::from)
+    }
+
+    /// Set the minimal fee of the operation pool and wait for the worker to prune the pool.
+    /// Returns the number of pruned operations.
+    fn set_min_fee(&mut self, min_fee: Amount) -> Result<usize, PoolError> {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.operations_input_sender
+            .send(Command::SetMinFee(min_fee, response_tx))
+            .map_err(PoolError::from)?;
+        response_rx.recv().map_err(PoolError::from)
+    }
+
+    /// Asynchronously remove endorsements from the pool
+    fn remove_endorsements(&mut self, ids: &[EndorsementId]) -> Result<(), PoolError> {
+        self.endorsements_input_sender
+            .try_send(Command::RemoveEndorsements(ids.to_vec(), None))
+            .map_err(PoolError::from)
+    }
+
+    /// Remove endorsements from the pool and wait for the worker to remove them.
+    /// Returns the number of removed endorsements.
+    fn remove_included_endorsements(&mut self, ids: &[EndorsementId]) -> Result<usize, PoolError> {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.endorsements_input_sender
+            .send(Command::RemoveEndorsements(ids.to_vec(), Some(response_tx)))
+            .map_err(PoolError::from)?;
+        response_rx.recv().map_err(PoolError::from)
+    }
+
+    /// Pre-load the endorsement pool and wait for the worker to add the endorsements.
+    /// Returns the number of endorsements kept.
+    fn warm_up(&mut self, endorsements: Storage) -> Result<usize, PoolError> {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.endorsements_input_sender
+            .send(Command::WarmUpEndorsements(endorsements, response_tx))
+            .map_err(PoolError::from)?;
+        response_rx.recv().map_err(PoolError::from)
+    }
+
+    /// Asynchronously compact the indexes of the endorsement pool
+    fn compact_endorsement_pool(&mut self) -> Result<(), PoolError> {
+        self.endorsements_input_sender
+            .try_send(Command::CompactEndorsements)
+            .map_err(PoolError::from)
+    }
+
+    /// Asynchronously remove all the operations of the pool and reset its statistics
+    #[cfg(feature = "testing")]
+    fn clear_operations(&mut self) -> Result<(), PoolError> {
+        self.channel_full_events.store(0, Ordering::Relaxed);
+        self.operations_input_sender
+            .try_send(Command::ClearOperations)
+            .map_err(PoolError::from)
+    }
+
+    /// Asynchronously remove all the endorsements of the pool and reset its statistics
+    #[cfg(feature = "testing")]
+    fn clear_endorsements(&mut self) -> Result<(), PoolError> {
+        self.channel_full_events.store(0, Ordering::Relaxed);
+        self.endorsements_input_sender
+            .try_send(Command::ClearEndorsements)
+            .map_err(PoolError::from)
+    }
+
+    /// Asynchronously purge the operation and endorsement pools and reset their statistics
+    fn purge_pool(&mut self) -> Result<(), PoolError> {
+        self.channel_full_events.store(0, Ordering::Relaxed);
+        self.operations_input_sender
+            .try_send(Command::PurgeAll)
+            .map_err(PoolError::from)?;
+        self.endorsements_input_sender
+            .try_send(Command::PurgeAll)
+            .map_err(PoolError::from)
+    }
+
     /// get operations for block creation
     fn get_block_operations(&self, slot: &Slot) -> (Vec<OperationId>, Storage) {
         self.operation_pool.read().get_block_operations(slot)
     }
 
+    /// get operations for block creation, along with how full the block is
+    fn get_block_operations_with_fill_info(
+        &self,
+        slot: &Slot,
+    ) -> (Vec<OperationId>, Storage, BlockFillInfo) {
+        self.operation_pool
+            .read()
+            .get_block_operations_with_fill_info(slot)
+    }
+
+    /// get the total raw fee of the operations that would be selected for a block
+    fn simulate_block_fee_revenue(&self, slot: &Slot, max_ops: usize, max_gas: u64) -> u64 {
+        self.operation_pool
+            .read()
+            .simulate_block_fee_revenue(slot, max_ops, max_gas)
+    }
+
+    /// get the operations of the pool that match the given IDs
+    fn get_operations_by_ids(&self, ids: &[OperationId]) -> Storage {
+        self.operation_pool.read().get_operations_by_ids(ids)
+    }
+
+    /// get operations of the pool that a peer does not know yet
+    fn get_operations_for_propagation(
+        &self,
+        peer_known_ids: &[OperationId],
+        max_count: usize,
+    ) -> (Vec<OperationId>, Storage) {
+        self.operation_pool
+            .read()
+            .get_operations_for_propagation(peer_known_ids, max_count)
+    }
+
+    /// watch the number of operations and endorsements of the pools
+    fn watch_pool_sizes(&self) -> watch::Receiver<(usize, usize)> {
+        self.pool_sizes_receiver.clone()
+    }
+
+    /// subscribe to the operation and endorsement pool events
+    fn subscribe_to_pool_events(&self) -> Receiver<PoolEvent> {
+        let (event_sender, event_receiver) =
+            mpsc::sync_channel(self.config.pool_events_channel_size);
+        self.operation_pool
+            .write()
+            .subscribe_to_events(event_sender.clone());
+        self.endorsement_pool
+            .write()
+            .subscribe_to_events(event_sender);
+        event_receiver
+    }
+
+    /// get an order-independent digest of the operations and endorsements of the pool
+    fn compute_pool_hash(&self) -> [u8; 32] {
+        let operations_xor = self.operation_pool.read().ids_xor();
+        let endorsements_xor = self.endorsement_pool.read().ids_xor();
+        Hash::compute_from(&[operations_xor, endorsements_xor].concat()).into_bytes()
+    }
+
+    /// get the incrementally maintained fingerprint of the operations of the pool
+    fn operations_fingerprint(&self) -> u64 {
+        self.operation_pool.read().incremental_pool_fingerprint()
+    }
+
+    /// get the raw fees of the pool at the given percentiles
+    fn get_fee_percentiles(&self, percentiles: &[u8]) -> Vec<u64> {
+        self.operation_pool.read().get_fee_percentiles(percentiles)
+    }
+
+    /// get the operations of the pool that expire within the given number of periods
+    fn get_operations_expiring_within_periods(
+        &self,
+        current_slot: &Slot,
+        periods: u64,
+    ) -> (Vec<OperationId>, Storage) {
+        self.operation_pool
+            .read()
+            .get_operations_expiring_within_periods(current_slot, periods)
+    }
+
+    /// get the operations of the pool valid in a range of slots
+    fn get_operations_for_slot_range(&self, from: &Slot, to: &Slot) -> (Vec<OperationId>, Storage) {
+        self.operation_pool
+            .read()
+            .get_operations_for_slot_range(from, to)
+    }
+
+    /// get the operations of the pool sent by an address
+    fn get_operations_by_sender(&self, address: &Address) -> (Vec<OperationId>, Storage) {
+        self.operation_pool.read().get_operations_by_sender(address)
+    }
+
+    /// get the senders having the most operations in the pool
+    fn top_senders_by_operation_count(&self, n: usize) -> Vec<(Address, usize)> {
+        self.operation_pool.read().top_senders_by_operation_count(n)
+    }
+
+    /// get a page of the operations of the pool sorted by decreasing fee per byte
+    fn get_operations_sorted_by_fee(
+        &self,
+        limit: usize,
+        offset: usize,
+    ) -> (Vec<OperationId>, Storage) {
+        self.operation_pool
+            .read()
+            .get_operations_sorted_by_fee(limit, offset)
+    }
+
+    /// get the IDs of a page of the operations of the pool sorted by decreasing fee per byte
+    fn get_operation_ids_page(&self, page: usize, page_size: usize) -> Vec<OperationId> {
+        self.operation_pool
+            .read()
+            .get_operation_ids_page(page, page_size)
+    }
+
+    /// iterate over the operations of the pool paying at least `min_fee`,
+    /// holding a read lock on the operation pool until the iterator is dropped
+    fn get_operations_above_fee(
+        &self,
+        min_fee: Amount,
+    ) -> Box<dyn Iterator<Item = OperationId> + '_> {
+        Box::new(OperationsAboveFee {
+            operation_pool: self.operation_pool.read(),
+            min_fee,
+            last: None,
+        })
+    }
+
+    /// block until the operation pool worker has processed all the previously sent commands
+    fn flush_operations(&self) -> Result<(), PoolError> {
+        flush_worker(&self.operations_input_sender)
+    }
+
+    /// get the number of operations in the pool once the operation pool worker
+    /// has processed all the previously sent commands
+    fn sync_get_operation_count(&self) -> Result<usize, PoolError> {
+        let (response_tx, response_rx) = mpsc::sync_channel(1);
+        self.operations_input_sender
+            .send(Command::QueryOperationCount(response_tx))
+            .map_err(PoolError::from)?;
+        response_rx.recv().map_err(PoolError::from)
+    }
+
+    /// block until the endorsement pool worker has processed all the previously sent commands
+    fn flush_endorsements(&self) -> Result<(), PoolError> {
+        flush_worker(&self.endorsements_input_sender)
+    }
+
+    /// get a snapshot of the operations of the pool
+    fn snapshot_operations(&self) -> OperationPoolSnapshot {
+        self.operation_pool.read().snapshot()
+    }
+
+    /// asynchronously add the operations of a snapshot to the pool
+    fn restore_from_snapshot(&mut self, snapshot: OperationPoolSnapshot) -> Result<(), PoolError> {
+        self.try_send_operations(snapshot.storage)
+            .map_err(PoolError::from)
+    }
+
     /// get endorsements for a block
     fn get_block_endorsements(
         &self,
         target_block: &BlockId,
         target_slot: &Slot,
     ) -> (Vec<Option<EndorsementId>>, Storage) {
+        self.endorsement_slots.get_block_endorsements(
+            target_slot,
+            target_block,
+            self.config.max_block_endorsement_count,
+            &self.endorsements_storage,
+        )
+    }
+
+    /// get endorsements for several blocks from a consistent snapshot of the endorsement pool
+    fn get_endorsements_for_blocks(
+        &self,
+        targets: &[(BlockId, Slot)],
+    ) -> Vec<(Vec<Option<EndorsementId>>, Storage)> {
+        // the slot entries are only modified by the endorsement pool under its write lock,
+        // so holding its read lock keeps them unchanged across all the targets
+        let _endorsement_pool = self.endorsement_pool.read();
+        targets
+            .iter()
+            .map(|(target_block, target_slot)| {
+                self.endorsement_slots.get_block_endorsements(
+                    target_slot,
+                    target_block,
+                    self.config.max_block_endorsement_count,
+                    &self.endorsements_storage,
+                )
+            })
+            .collect()
+    }
+
+    /// get the endorsement indices for which the pool has no endorsement of `target_block` at `target_slot`
+    fn get_missing_endorsement_indices(
+        &self,
+        target_slot: &Slot,
+        target_block: &BlockId,
+    ) -> Vec<u32> {
         self.endorsement_pool
             .read()
-            .get_block_endorsements(target_slot, target_block)
+            .get_missing_endorsement_indices(
+                target_slot,
+                target_block,
+                self.config.max_block_endorsement_count as usize,
+            )
+    }
+
+    /// get which endorsement indices have an endorsement of `target_block` at `target_slot`
+    fn get_coverage_for_block(&self, target_block: &BlockId, target_slot: &Slot) -> Vec<bool> {
+        self.endorsement_pool.read().get_coverage_for_block(
+            target_block,
+            target_slot,
+            self.config.max_block_endorsement_count as usize,
+        )
+    }
+
+    /// get the number of endorsements of the pool for each slot of a range
+    fn get_endorsement_coverage_map(
+        &self,
+        from_slot: &Slot,
+        to_slot: &Slot,
+    ) -> HashMap<Slot, usize> {
+        self.endorsement_pool
+            .read()
+            .get_endorsement_coverage_map(from_slot, to_slot)
+    }
+
+    /// get the endorsements of the pool created by an address
+    fn get_endorsements_by_producer(&self, producer: &Address) -> Vec<(Slot, EndorsementId)> {
+        self.endorsement_pool
+            .read()
+            .get_endorsements_by_producer(producer)
+    }
+
+    /// get the endorsements of the pool more than `max_age_slots` slots older than `current_slot`
+    fn get_endorsements_older_than(
+        &self,
+        max_age_slots: u64,
+        current_slot: &Slot,
+    ) -> Vec<EndorsementId> {
+        self.endorsement_pool
+            .read()
+            .get_endorsements_older_than(max_age_slots, current_slot)
+    }
+
+    /// check whether the pool has an endorsement for every endorsement index of a block at `slot`
+    fn has_full_endorsement_coverage(&self, slot: &Slot) -> bool {
+        self.endorsement_pool
+            .read()
+            .has_full_endorsement_coverage_for_slot(
+                slot,
+                self.config.max_block_endorsement_count as usize,
+            )
+    }
+
+    /// get the endorsements of the pool that match the given IDs
+    fn get_endorsements_by_ids(&self, ids: &[EndorsementId]) -> Storage {
+        self.endorsement_pool.read().get_endorsements_by_ids(ids)
     }
 
     /// get denunciationsq for a block
@@ -199,21 +896,197 @@ impl PoolController for PoolControllerImpl {
         self.endorsement_pool.read().len()
     }
 
+    /// Get the number of endorsements of the pool that can be included in a block at `slot`
+    fn get_endorsement_count_for_slot(&self, slot: &Slot) -> usize {
+        self.endorsement_pool
+            .read()
+            .get_endorsement_count_for_slot(slot)
+    }
+
+    /// Get the number of endorsements of the pool for each slot, along with coverage statistics
+    fn get_endorsement_stats(&self) -> EndorsementStats {
+        self.endorsement_pool
+            .read()
+            .get_endorsement_stats(self.config.max_block_endorsement_count as usize)
+    }
+
     /// Get the number of operations in the pool
     fn get_operation_count(&self) -> usize {
         self.operation_pool.read().len()
     }
 
+    /// Get the number of slots an operation has been waiting in the pool
+    fn get_operation_age(&self, id: &OperationId) -> Option<u64> {
+        self.operation_pool.read().get_operation_age(id)
+    }
+
+    /// Get the IDs of the operations waiting in the pool for more than `stale_after_slots` slots
+    fn get_stale_operations(
+        &self,
+        current_slot: &Slot,
+        stale_after_slots: u64,
+    ) -> Vec<OperationId> {
+        self.operation_pool
+            .read()
+            .get_stale_operations(current_slot, stale_after_slots)
+    }
+
+    /// Get statistics about the content and the activity of the pools
+    fn pool_stats(&self) -> PoolStats {
+        let (
+            operation_count,
+            operation_pool_memory_bytes,
+            total_ops_added,
+            total_ops_evicted,
+            duplicates_skipped,
+            sender_limit_evicted,
+            oversized_ops_dropped,
+            type_filtered_ops_dropped,
+            hot_tier_hits,
+            hot_tier_misses,
+            operation_type_stats,
+            top_10_senders,
+        ) = {
+            let operation_pool = self.operation_pool.read();
+            (
+                operation_pool.len(),
+                operation_pool.memory_bytes(),
+                operation_pool.total_added(),
+                operation_pool.total_evicted(),
+                operation_pool.duplicates_skipped(),
+                operation_pool.sender_limit_evicted(),
+                operation_pool.oversized_ops_dropped(),
+                operation_pool.type_filtered_ops_dropped(),
+                operation_pool.hot_tier_hits(),
+                operation_pool.hot_tier_misses(),
+                operation_pool.operation_type_stats(),
+                operation_pool.top_senders_by_operation_count(POOL_STATS_TOP_SENDERS),
+            )
+        };
+        let (
+            endorsement_count,
+            endorsement_pool_memory_bytes,
+            total_endorsements_added,
+            total_endorsements_evicted,
+            endorsements_by_age,
+            endorsement_signature_checks,
+        ) = {
+            let endorsement_pool = self.endorsement_pool.read();
+            (
+                endorsement_pool.len(),
+                endorsement_pool.memory_bytes(),
+                endorsement_pool.total_added(),
+                endorsement_pool.total_evicted(),
+                endorsement_pool.get_age_histogram(),
+                endorsement_pool.signature_checks(),
+            )
+        };
+        PoolStats {
+            operation_count,
+            endorsement_count,
+            operation_pool_memory_bytes,
+            endorsement_pool_memory_bytes,
+            total_ops_added,
+            total_ops_evicted,
+            duplicates_skipped,
+            sender_limit_evicted,
+            oversized_ops_dropped,
+            type_filtered_ops_dropped,
+            hot_tier_hits,
+            hot_tier_misses,
+            operation_type_stats,
+            top_10_senders,
+            total_endorsements_added,
+            total_endorsements_evicted,
+            endorsements_by_age,
+            endorsement_signature_checks,
+            channel_full_events: self.channel_full_events.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Get a conservative estimation of the heap memory held by the pools
+    fn get_memory_usage(&self) -> PoolMemoryUsage {
+        PoolMemoryUsage {
+            operation_pool_bytes: self.operations_estimated_memory.load(Ordering::Relaxed),
+            endorsement_pool_bytes: self.endorsement_pool.read().estimated_memory_usage(),
+        }
+    }
+
+    /// Get a human-readable dump of the content of the pools, for debugging purposes
+    fn dump_pool_state(&self) -> PoolDump {
+        PoolDump {
+            operations: self.operation_pool.read().dump(),
+            endorsements: self.endorsement_pool.read().dump(),
+        }
+    }
+
     /// Check if the pool contains a list of endorsements. Returns one boolean per item.
+    /// Items rejected by the bloom filter are reported as absent without locking the pool.
     fn contains_endorsements(&self, endorsements: &[EndorsementId]) -> Vec<bool> {
-        let lck = self.endorsement_pool.read();
-        endorsements.iter().map(|id| lck.contains(id)).collect()
+        let mut res: Vec<bool> = endorsements
+            .iter()
+            .map(|id| self.endorsements_bloom_filter.contains(id))
+            .collect();
+        if res.iter().any(|maybe_present| *maybe_present) {
+            let lck = self.endorsement_pool.read();
+            for (present, id) in res.iter_mut().zip(endorsements) {
+                if *present {
+                    *present = lck.contains(id);
+                }
+            }
+        }
+        res
+    }
+
+    /// Check if the pool contains all the operations of a list, stopping at the first missing one.
+    /// As in `contains_operations`, the pool itself is never locked.
+    fn contains_all_operations(&self, ids: &[OperationId]) -> bool {
+        ids.iter()
+            .all(|id| self.operations_bloom_filter.contains(id))
+            && self.operation_shards.contains_every(ids)
+    }
+
+    /// Check if the pool contains at least one of the operations of a list,
+    /// stopping at the first present one.
+    /// As in `contains_operations`, the pool itself is never locked.
+    fn contains_any_operation(&self, ids: &[OperationId]) -> bool {
+        let maybe_present: Vec<OperationId> = ids
+            .iter()
+            .filter(|id| self.operations_bloom_filter.contains(id))
+            .copied()
+            .collect();
+        self.operation_shards.contains_any(&maybe_present)
     }
 
     /// Check if the pool contains a list of operations. Returns one boolean per item.
+    /// Items rejected by the bloom filter are reported as absent,
+    /// the others are looked up in the sharded index: the pool itself is never locked.
     fn contains_operations(&self, operations: &[OperationId]) -> Vec<bool> {
-        let lck = self.operation_pool.read();
-        operations.iter().map(|id| lck.contains(id)).collect()
+        #[cfg(not(feature = "parallel"))]
+        let mut res: Vec<bool> = operations
+            .iter()
+            .map(|id| self.operations_bloom_filter.contains(id))
+            .collect();
+        #[cfg(feature = "parallel")]
+        let mut res: Vec<bool> = operations
+            .into_par_iter()
+            .map(|id| self.operations_bloom_filter.contains(id))
+            .collect();
+        let maybe_present: Vec<OperationId> = operations
+            .iter()
+            .zip(&res)
+            .filter_map(|(id, maybe_present)| maybe_present.then_some(*id))
+            .collect();
+        if !maybe_present.is_empty() {
+            let mut found = self
+                .operation_shards
+                .contains_all(&maybe_present)
+                .into_iter();
+            for present in res.iter_mut().filter(|present| **present) {
+                *present = found.next().unwrap_or(false);
+            }
+        }
+        res
     }
 
     /// Check if the pool contains a denunciation. Returns a boolean
@@ -233,6 +1106,11 @@ impl PoolController for PoolControllerImpl {
         Box::new(self.clone())
     }
 
+    /// Returns a clone of self shared behind an `Arc`
+    fn arc_clone(&self) -> Arc<dyn PoolController> {
+        Arc::new(self.clone())
+    }
+
     /// Get final consensus periods
     fn get_final_cs_periods(&self) -> &Vec<u64> {
         &self.last_cs_final_periods
@@ -242,6 +1120,7 @@ impl PoolController for PoolControllerImpl {
 /// Implementation of the pool manager.
 ///
 /// Contains the operations and endorsements thread handles.
+#[derive(Debug)]
 pub struct PoolManagerImpl {
     /// Handle used to join the operation thread
     pub(crate) operations_thread_handle: Option<std::thread::JoinHandle<()>>,
@@ -255,30 +1134,267 @@ pub struct PoolManagerImpl {
     pub(crate) endorsements_input_sender: SyncSender<Command>,
     /// Denunciations input data mpsc (used to stop the pool thread)
     pub(crate) denunciations_input_sender: SyncSender<Command>,
+    /// Maximum time to wait for each thread to exit when stopping
+    pub(crate) stop_timeout: Duration,
+    /// Readiness of the worker threads, reported by the threads themselves
+    pub(crate) readiness: Arc<WorkerReadiness>,
+    /// Commands received by the operation thread
+    pub(crate) operations_activity: Arc<WorkerActivity>,
+    /// Commands received by the endorsement thread
+    pub(crate) endorsements_activity: Arc<WorkerActivity>,
+    /// Commands received by the denunciation thread
+    pub(crate) denunciations_activity: Arc<WorkerActivity>,
+    /// Controller of the pools, used to read their statistics
+    pub(crate) controller: PoolControllerImpl,
+    /// Time at which the pools were started
+    pub(crate) started_at: Instant,
+}
+
+/// Commands received by a pool worker thread, updated by the thread itself
+#[derive(Debug, Default)]
+pub(crate) struct WorkerActivity {
+    /// number of commands received since startup
+    commands_processed: AtomicU64,
+    /// time (in milliseconds since the UNIX epoch) of the last received command, 0 if none
+    last_command_millis: AtomicU64,
+    /// number of `AddItems` commands sent to the thread that it did not receive yet
+    pending_additions: AtomicUsize,
+}
+
+impl WorkerActivity {
+    /// Records that an `AddItems` command is about to be sent to the worker thread,
+    /// returning the number of additions waiting in its channel including this one
+    pub(crate) fn addition_queued(&self) -> usize {
+        self.pending_additions.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Records that an `AddItems` command counted by `addition_queued` could not be sent
+    pub(crate) fn addition_dropped(&self) {
+        self.pending_additions.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Records that the worker thread received a command
+    pub(crate) fn record_command(&self, command: &Command) {
+        if matches!(command, Command::AddItems(_)) {
+            self.pending_additions.fetch_sub(1, Ordering::Relaxed);
+        }
+        self.commands_processed.fetch_add(1, Ordering::Relaxed);
+        if let Ok(now) = MassaTime::now() {
+            self.last_command_millis
+                .store(now.to_millis(), Ordering::Relaxed);
+        }
+    }
+
+    /// Get the diagnostic of the worker thread of `name`, joined through `thread_handle`
+    fn diagnostic(&self, name: &str, thread_handle: &Option<JoinHandle<()>>) -> WorkerDiagnostic {
+        let running = thread_handle
+            .as_ref()
+            .map_or(false, |join_handle| !join_handle.is_finished());
+        let last_command_millis = self.last_command_millis.load(Ordering::Relaxed);
+        WorkerDiagnostic {
+            name: name.to_string(),
+            status: if running {
+                WorkerStatus::Running
+            } else {
+                WorkerStatus::Finished
+            },
+            commands_processed: self.commands_processed.load(Ordering::Relaxed),
+            last_command_timestamp: (last_command_millis > 0)
+                .then(|| MassaTime::from_millis(last_command_millis)),
+        }
+    }
+}
+
+/// Number of pool worker threads that started processing commands
+#[derive(Debug, Default)]
+pub(crate) struct WorkerReadiness {
+    ready_count: Mutex<usize>,
+    ready_cvar: Condvar,
+}
+
+impl WorkerReadiness {
+    /// Number of worker threads reporting their readiness: operations, endorsements and denunciations
+    const WORKER_COUNT: usize = 3;
+
+    /// Reports that a worker thread is about to process its first command
+    pub(crate) fn set_ready(&self) {
+        *self.ready_count.lock() += 1;
+        self.ready_cvar.notify_all();
+    }
+
+    /// Waits until all the worker threads are ready, giving up after `timeout`.
+    /// Returns whether they are all ready.
+    fn wait(&self, timeout: Duration) -> bool {
+        let mut ready_count = self.ready_count.lock();
+        if *ready_count < Self::WORKER_COUNT {
+            let _ = self.ready_cvar.wait_while_for(
+                &mut ready_count,
+                |count| *count < Self::WORKER_COUNT,
+                timeout,
+            );
+        }
+        *ready_count >= Self::WORKER_COUNT
+    }
+}
+
+/// Queues a stop command for a pool thread without blocking.
+/// Returns a sender to send it through later if the channel of the thread is full.
+fn request_stop(input_sender: &SyncSender<Command>) -> Option<SyncSender<Command>> {
+    match input_sender.try_send(Command::Stop) {
+        Err(TrySendError::Full(_)) => Some(input_sender.clone()),
+        _ => None,
+    }
+}
+
+/// Joins a pool thread, giving up after `timeout`.
+/// The join is performed by a helper thread so that a hung worker cannot block the shutdown:
+/// on timeout, the helper thread is left behind and the worker is not joined.
+/// If the stop command could not be queued, the helper thread first waits for room
+/// in the channel of the worker to send it through `pending_stop`.
+fn join_with_timeout(
+    pool_name: &str,
+    join_handle: JoinHandle<()>,
+    pending_stop: Option<SyncSender<Command>>,
+    timeout: Duration,
+) {
+    let joined = Arc::new((Mutex::new(None), Condvar::new()));
+    let joined_clone = joined.clone();
+    let spawn_res = thread::Builder::new()
+        .name(format!("{}-join", pool_name.replace(' ', "-")))
+        .spawn(move || {
+            if let Some(input_sender) = pending_stop {
+                let _ = input_sender.send(Command::Stop);
+            }
+            let join_res = join_handle.join();
+            let (lock, cvar) = &*joined_clone;
+            *lock.lock() = Some(join_res.is_ok());
+            cvar.notify_one();
+        });
+    if let Err(err) = spawn_res {
+        error!("could not spawn the {} join thread: {}", pool_name, err);
+        return;
+    }
+    let (lock, cvar) = &*joined;
+    let mut join_res = lock.lock();
+    if join_res.is_none() {
+        let _ = cvar.wait_while_for(&mut join_res, |res| res.is_none(), timeout);
+    }
+    match *join_res {
+        Some(true) => {}
+        Some(false) => error!("{} thread panicked on try to join", pool_name),
+        None => warn!(
+            "{} thread did not stop within {:?}, skipping its join",
+            pool_name, timeout
+        ),
+    }
+}
+
+impl PoolManagerImpl {
+    /// Asks the operation and endorsement workers for a snapshot of their pool
+    /// and waits for them to answer
+    fn snapshot_pools(
+        &self,
+    ) -> Result<(OperationPoolSnapshot, EndorsementPoolSnapshot), PoolError> {
+        let (operations_tx, operations_rx) = mpsc::channel();
+        self.operations_input_sender
+            .send(Command::SnapshotOperations(operations_tx))
+            .map_err(PoolError::from)?;
+        let (endorsements_tx, endorsements_rx) = mpsc::channel();
+        self.endorsements_input_sender
+            .send(Command::SnapshotEndorsements(endorsements_tx))
+            .map_err(PoolError::from)?;
+        let operations = operations_rx.recv().map_err(PoolError::from)?;
+        let endorsements = endorsements_rx.recv().map_err(PoolError::from)?;
+        Ok((operations, endorsements))
+    }
 }
 
 impl PoolManager for PoolManagerImpl {
+    /// Returns false if a worker thread has exited while the pool is running,
+    /// for instance after reaching its panic restart limit
+    fn is_healthy(&self) -> bool {
+        let mut healthy = true;
+        for (pool_name, thread_handle) in [
+            ("operation pool", &self.operations_thread_handle),
+            ("endorsement pool", &self.endorsements_thread_handle),
+            ("denunciation pool", &self.denunciations_thread_handle),
+        ] {
+            if thread_handle
+                .as_ref()
+                .map_or(false, |join_handle| join_handle.is_finished())
+            {
+                error!("{} worker thread has exited unexpectedly", pool_name);
+                healthy = false;
+            }
+        }
+        healthy
+    }
+
+    /// Blocks until the worker threads are ready, giving up after `timeout`
+    fn wait_until_ready(&self, timeout: Duration) -> bool {
+        self.readiness.wait(timeout)
+    }
+
+    /// Get a point-in-time report on the worker threads and the content of the pools
+    fn diagnostic_report(&self) -> PoolDiagnostic {
+        PoolDiagnostic {
+            workers: vec![
+                self.operations_activity
+                    .diagnostic("operation pool", &self.operations_thread_handle),
+                self.endorsements_activity
+                    .diagnostic("endorsement pool", &self.endorsements_thread_handle),
+                self.denunciations_activity
+                    .diagnostic("denunciation pool", &self.denunciations_thread_handle),
+            ],
+            uptime_secs: self.started_at.elapsed().as_secs(),
+            stats: self.controller.pool_stats(),
+        }
+    }
+
     /// Stops the worker
     fn stop(&mut self) {
         info!("stopping pool workers...");
-        let _ = self.operations_input_sender.send(Command::Stop);
-        let _ = self.endorsements_input_sender.send(Command::Stop);
-        let _ = self.denunciations_input_sender.send(Command::Stop);
+        // a full channel must not block the shutdown: it is waited for within the stop timeout
+        let operations_stop = request_stop(&self.operations_input_sender);
+        let endorsements_stop = request_stop(&self.endorsements_input_sender);
+        let denunciations_stop = request_stop(&self.denunciations_input_sender);
         if let Some(join_handle) = self.operations_thread_handle.take() {
-            join_handle
-                .join()
-                .expect("operations pool thread panicked on try to join");
+            join_with_timeout(
+                "operation pool",
+                join_handle,
+                operations_stop,
+                self.stop_timeout,
+            );
         }
         if let Some(join_handle) = self.endorsements_thread_handle.take() {
-            join_handle
-                .join()
-                .expect("endorsements pool thread panicked on try to join");
+            join_with_timeout(
+                "endorsement pool",
+                join_handle,
+                endorsements_stop,
+                self.stop_timeout,
+            );
         }
         if let Some(join_handle) = self.denunciations_thread_handle.take() {
-            join_handle
-                .join()
-                .expect("denunciations pool thread panicked on try to join");
+            join_with_timeout(
+                "denunciation pool",
+                join_handle,
+                denunciations_stop,
+                self.stop_timeout,
+            );
         }
         info!("pool workers stopped");
     }
+
+    /// Takes a snapshot of the operation and endorsement pools then stops the workers
+    fn drain_and_stop(
+        &mut self,
+    ) -> Result<(OperationPoolSnapshot, EndorsementPoolSnapshot), PoolError> {
+        info!("taking pool snapshots before stopping...");
+        let snapshots = self.snapshot_pools();
+        if let Err(err) = &snapshots {
+            error!("could not take pool snapshots: {}", err);
+        }
+        self.stop();
+        snapshots
+    }
 }
@@ -28,9 +28,9 @@ pub struct DenunciationPool {
 impl DenunciationPool {
     pub fn init(config: PoolConfig, channels: PoolChannels) -> Self {
         Self {
+            last_cs_final_periods: vec![0u64; config.thread_count as usize],
             config,
             channels,
-            last_cs_final_periods: vec![0u64; config.thread_count as usize],
             denunciations_cache: Default::default(),
         }
     }
@@ -90,7 +90,9 @@ impl DenunciationPool {
             return;
         }
 
-        if slot.period.saturating_sub(slot_now.period) > self.config.denunciation_expire_periods {
+        if slot_now < *slot
+            && slot.distance_in_periods(&slot_now) > self.config.denunciation_expire_periods
+        {
             // too much in the future - ignored
             return;
         }
@@ -1,41 +1,102 @@
 //! Copyright (c) 2022 MASSA LABS <info@massa.net>
 
+use massa_hash::{Hash, HASH_SIZE_BYTES};
 use massa_models::{
+    address::Address,
     block_id::BlockId,
-    endorsement::EndorsementId,
-    prehash::{CapacityAllocator, PreHashSet},
+    endorsement::{EndorsementId, SecureShareEndorsement},
+    prehash::{CapacityAllocator, PreHashMap, PreHashSet},
     slot::Slot,
+    timeslots::get_closest_slot_to_timestamp,
+};
+use massa_pool_exports::{
+    EndorsementDump, EndorsementPoolSnapshot, EndorsementStats, EvictionReason, PoolChannels,
+    PoolConfig, PoolEvent,
 };
-use massa_pool_exports::{PoolChannels, PoolConfig};
 use massa_storage::Storage;
+use massa_time::MassaTime;
 use massa_wallet::Wallet;
 use parking_lot::RwLock;
+use schnellru::{ByLength, LruMap};
 use std::{
-    collections::{hash_map::Entry, BTreeMap, HashMap},
-    sync::Arc,
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    mem::size_of,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        mpsc::SyncSender,
+        Arc,
+    },
+};
+use tokio::sync::watch;
+use tracing::{debug, info, trace, warn};
+
+use crate::{
+    bloom_filter::BloomFilter, endorsement_slots::EndorsementSlots, metrics,
+    subscribers::PoolEventSubscribers, types::xor_ids,
 };
-use tracing::{trace, warn};
+
+/// Memory held by the indexes of the pool for each endorsement, on top of its serialized size:
+/// its two `(slot, index, block)` keys, its slot in the producer index and the copies of its ID,
+/// doubled to account for the unused capacity of the maps and queues
+const ENDORSEMENT_INDEX_OVERHEAD_BYTES: usize = 2
+    * (2 * size_of::<(Slot, u32, BlockId)>() + size_of::<Slot>() + 5 * size_of::<EndorsementId>());
 
 pub struct EndorsementPool {
     /// configuration
     config: PoolConfig,
 
-    /// endorsements indexed by slot, index and block ID
-    endorsements_indexed: HashMap<(Slot, u32, BlockId), EndorsementId>,
+    /// endorsements indexed by slot, index and block ID, shared with the controller
+    pub(crate) endorsement_slots: Arc<EndorsementSlots>,
 
     /// endorsements sorted by increasing inclusion slot for pruning
     /// indexed by thread, then `BTreeMap<(inclusion_slot, index, target_block), endorsement_id>`
     endorsements_sorted: Vec<BTreeMap<(Slot, u32, BlockId), EndorsementId>>,
 
+    /// IDs of the endorsements by order of the batch that added them to the pool, for eviction.
+    /// The endorsements of a batch come from the unordered refs of its storage,
+    /// so they are listed in no particular order among themselves.
+    /// It may still list removed endorsements, skipped when evicting and regularly compacted.
+    insertion_order: VecDeque<EndorsementId>,
+
+    /// endorsements of the pool indexed by creator address, by increasing slot
+    endorsements_by_producer: PreHashMap<Address, Vec<(Slot, EndorsementId)>>,
+
     /// storage
     storage: Storage,
 
+    /// bloom filter of the endorsements in the pool, shared with the controller
+    pub(crate) bloom_filter: Arc<BloomFilter>,
+
+    /// total serialized size of the endorsements in the pool
+    memory_bytes: AtomicUsize,
+
+    /// number of endorsements added to the pool since startup
+    total_added: AtomicU64,
+
+    /// number of endorsements removed from the pool since startup
+    total_evicted: AtomicU64,
+
+    /// results of the last signature checks of incoming endorsements, `true` if the signature is valid
+    signature_cache: LruMap<EndorsementId, bool>,
+
+    /// number of endorsement signatures checked since startup, cache hits excluded
+    signature_checks: AtomicU64,
+
+    /// number of endorsements removed since the indexes were last compacted
+    removed_since_compaction: usize,
+
     /// last consensus final periods, per thread
     last_cs_final_periods: Vec<u64>,
 
     /// channels used by the pool worker
     channels: PoolChannels,
 
+    /// subscribers to the pool events
+    event_subscribers: PoolEventSubscribers,
+
+    /// `(operation_count, endorsement_count)` watched by the controller, shared with the operation pool
+    pool_sizes: Arc<watch::Sender<(usize, usize)>>,
+
     /// staking wallet, to know which addresses we are using to stake
     wallet: Arc<RwLock<Wallet>>,
 }
@@ -46,14 +107,34 @@ impl EndorsementPool {
         storage: &Storage,
         channels: PoolChannels,
         wallet: Arc<RwLock<Wallet>>,
+        pool_sizes: Arc<watch::Sender<(usize, usize)>>,
     ) -> Self {
         EndorsementPool {
             last_cs_final_periods: vec![0u64; config.thread_count as usize],
-            endorsements_indexed: Default::default(),
+            endorsement_slots: Default::default(),
             endorsements_sorted: vec![Default::default(); config.thread_count as usize],
+            insertion_order: Default::default(),
+            endorsements_by_producer: Default::default(),
+            bloom_filter: Arc::new(BloomFilter::new(
+                config.endorsement_bloom_filter_size,
+                config.bloom_filter_false_positive_rate,
+            )),
+            memory_bytes: Default::default(),
+            total_added: Default::default(),
+            total_evicted: Default::default(),
+            signature_cache: LruMap::new(ByLength::new(
+                config
+                    .endorsement_sig_cache_size
+                    .try_into()
+                    .unwrap_or(u32::MAX),
+            )),
+            signature_checks: Default::default(),
+            removed_since_compaction: 0,
             config,
             storage: storage.clone_without_refs(),
             channels,
+            event_subscribers: Default::default(),
+            pool_sizes,
             wallet,
         }
     }
@@ -63,6 +144,58 @@ impl EndorsementPool {
         self.storage.get_endorsement_refs().len()
     }
 
+    /// Get the number of endorsements of the pool that can be included in a block at `slot`
+    pub fn get_endorsement_count_for_slot(&self, slot: &Slot) -> usize {
+        self.endorsement_slots.count(slot)
+    }
+
+    /// Get the total serialized size of the stored endorsements
+    pub fn memory_bytes(&self) -> usize {
+        self.memory_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Get a conservative estimation of the memory held by the endorsements and the indexes of the pool
+    pub fn estimated_memory_usage(&self) -> usize {
+        self.memory_bytes() + self.len() * ENDORSEMENT_INDEX_OVERHEAD_BYTES
+    }
+
+    /// Get the number of endorsements added to the pool since startup
+    pub fn total_added(&self) -> u64 {
+        self.total_added.load(Ordering::Relaxed)
+    }
+
+    /// Get the number of endorsements removed from the pool since startup
+    pub fn total_evicted(&self) -> u64 {
+        self.total_evicted.load(Ordering::Relaxed)
+    }
+
+    /// Get the number of endorsement signatures checked since startup, cache hits excluded
+    pub fn signature_checks(&self) -> u64 {
+        self.signature_checks.load(Ordering::Relaxed)
+    }
+
+    /// Checks whether the signature of an endorsement is valid.
+    /// The result is cached by endorsement ID, so that an endorsement received again,
+    /// for instance from another peer, is not checked twice.
+    fn check_signature(&mut self, endo: &SecureShareEndorsement) -> bool {
+        if let Some(valid) = self.signature_cache.get(&endo.id) {
+            return *valid;
+        }
+        self.signature_checks.fetch_add(1, Ordering::Relaxed);
+        let valid = match endo.verify_signature() {
+            Ok(()) => true,
+            Err(err) => {
+                warn!(
+                    "error, endorsement with id {} has an invalid signature: {}",
+                    endo.id, err
+                );
+                false
+            }
+        };
+        self.signature_cache.insert(endo.id, valid);
+        valid
+    }
+
     /// Checks whether an element is stored in the pool.
     pub fn contains(&self, id: &EndorsementId) -> bool {
         self.storage.get_endorsement_refs().contains(id)
@@ -74,23 +207,211 @@ impl EndorsementPool {
         self.last_cs_final_periods = final_cs_periods.to_vec();
 
         // remove all endorsements whose periods <= last_cs_final_periods[endorsement.thread]
+        let final_cs_periods = self.last_cs_final_periods.clone();
+        self.prune_endorsements(|inclusion_slot| {
+            inclusion_slot.period <= final_cs_periods[inclusion_slot.thread as usize]
+        });
+    }
+
+    /// notify that a block became final: drop the endorsements it includes
+    /// and update the final period of its thread if the block is known
+    pub(crate) fn notify_block_finalized(
+        &mut self,
+        block_id: &BlockId,
+        endo_ids: &[EndorsementId],
+    ) {
+        self.remove_endorsements(endo_ids, EvictionReason::Included);
+        let block_slot = self
+            .storage
+            .read_blocks()
+            .get(block_id)
+            .map(|block| block.content.header.content.slot);
+        if let Some(slot) = block_slot {
+            if slot.period > self.last_cs_final_periods[slot.thread as usize] {
+                let mut final_cs_periods = self.last_cs_final_periods.clone();
+                final_cs_periods[slot.thread as usize] = slot.period;
+                self.notify_final_cs_periods(&final_cs_periods);
+            }
+        }
+    }
+
+    /// notify of the start of a new slot
+    pub(crate) fn notify_new_slot(&mut self, slot: &Slot) {
+        // remove all endorsements that can only be included in blocks at slots before the new one
+        self.prune_endorsements(|inclusion_slot| inclusion_slot < slot);
+    }
+
+    /// Remove the endorsements of each thread, from the oldest to the newest inclusion slot,
+    /// as long as `is_expired` returns true for their inclusion slot.
+    fn prune_endorsements<F>(&mut self, is_expired: F)
+    where
+        F: Fn(&Slot) -> bool,
+    {
         let mut removed: PreHashSet<EndorsementId> = Default::default();
         for thread in 0..self.config.thread_count {
             while let Some((&(inclusion_slot, index, block_id), &endo_id)) =
                 self.endorsements_sorted[thread as usize].first_key_value()
             {
-                if inclusion_slot.period <= self.last_cs_final_periods[thread as usize] {
+                if is_expired(&inclusion_slot) {
                     self.endorsements_sorted[thread as usize].pop_first();
-                    self.endorsements_indexed
-                        .remove(&(inclusion_slot, index, block_id))
-                        .expect("endorsement should be in endorsements_indexed at this point");
+                    if !self
+                        .endorsement_slots
+                        .remove(inclusion_slot, index, block_id, &endo_id)
+                    {
+                        panic!("endorsement should be in endorsement_slots at this point");
+                    }
                     removed.insert(endo_id);
                 } else {
                     break;
                 }
             }
         }
-        self.storage.drop_endorsement_refs(&removed);
+        self.drop_endorsements(&removed, EvictionReason::Expired);
+    }
+
+    /// Remove a list of endorsements from the pool for `reason`.
+    /// Unknown IDs are ignored: returns the number of endorsements actually removed.
+    pub(crate) fn remove_endorsements(
+        &mut self,
+        ids: &[EndorsementId],
+        reason: EvictionReason,
+    ) -> usize {
+        let mut removed = PreHashSet::with_capacity(ids.len());
+        {
+            let endo_store = self.storage.read_endorsements();
+            for endo_id in ids {
+                if !self.storage.get_endorsement_refs().contains(endo_id) {
+                    continue;
+                }
+                let Some(endo) = endo_store.get(endo_id) else {
+                    continue;
+                };
+                let key = (
+                    endo.content.slot,
+                    endo.content.index,
+                    endo.content.endorsed_block,
+                );
+                if self.endorsement_slots.remove(key.0, key.1, key.2, endo_id) {
+                    self.endorsements_sorted[endo.content.slot.thread as usize].remove(&key);
+                    removed.insert(*endo_id);
+                }
+            }
+        }
+        self.drop_endorsements(&removed, reason);
+        removed.len()
+    }
+
+    /// Publish the number of endorsements of the pool to the metrics and to the pool sizes watchers
+    fn publish_endorsement_count(&self) {
+        let count = self.len();
+        metrics::set_endorsement_count(count);
+        self.pool_sizes.send_if_modified(|(_, endorsement_count)| {
+            let modified = *endorsement_count != count;
+            *endorsement_count = count;
+            modified
+        });
+    }
+
+    /// Drop endorsements removed for `reason` from storage
+    /// and forget them in the bloom filter and the producer index
+    fn drop_endorsements(&mut self, removed: &PreHashSet<EndorsementId>, reason: EvictionReason) {
+        if removed.is_empty() {
+            return;
+        }
+        let mut removed_bytes = 0;
+        {
+            let endo_store = self.storage.read_endorsements();
+            for endo in removed.iter().filter_map(|endo_id| endo_store.get(endo_id)) {
+                removed_bytes += endo.serialized_size();
+                if let Some(producer_endos) = self
+                    .endorsements_by_producer
+                    .get_mut(&endo.content_creator_address)
+                {
+                    producer_endos.retain(|(_, endo_id)| endo_id != &endo.id);
+                    if producer_endos.is_empty() {
+                        self.endorsements_by_producer
+                            .remove(&endo.content_creator_address);
+                    }
+                }
+            }
+        }
+        self.memory_bytes
+            .fetch_sub(removed_bytes, Ordering::Relaxed);
+        self.total_evicted
+            .fetch_add(removed.len() as u64, Ordering::Relaxed);
+        for endo_id in removed {
+            self.event_subscribers
+                .broadcast(PoolEvent::EndorsementEvicted {
+                    id: *endo_id,
+                    reason,
+                });
+        }
+        self.storage.drop_endorsement_refs(removed);
+        self.bloom_filter
+            .remove_lazily(removed.len(), self.storage.get_endorsement_refs().iter());
+        self.publish_endorsement_count();
+
+        self.removed_since_compaction += removed.len();
+        if self.config.compact_after_evictions > 0
+            && self.removed_since_compaction >= self.config.compact_after_evictions
+        {
+            self.compact();
+        }
+    }
+
+    /// Get the memory allocated by the indexes of the pool, unused capacity included
+    fn index_capacity_bytes(&self) -> usize {
+        let indexed_bytes = self.endorsement_slots.capacity_bytes();
+        let insertion_order_bytes = self.insertion_order.capacity() * size_of::<EndorsementId>();
+        let by_producer_bytes = self.endorsements_by_producer.capacity()
+            * size_of::<(Address, Vec<(Slot, EndorsementId)>)>()
+            + self
+                .endorsements_by_producer
+                .values()
+                .map(|producer_endos| {
+                    producer_endos.capacity() * size_of::<(Slot, EndorsementId)>()
+                })
+                .sum::<usize>();
+        indexed_bytes + insertion_order_bytes + by_producer_bytes
+    }
+
+    /// Release the capacity left unused in the indexes of the pool after many removals,
+    /// also forgetting the removed endorsements still listed in the insertion order
+    pub(crate) fn compact(&mut self) {
+        let bytes_before = self.index_capacity_bytes();
+
+        let endorsement_refs = self.storage.get_endorsement_refs();
+        self.insertion_order
+            .retain(|endo_id| endorsement_refs.contains(endo_id));
+        self.insertion_order.shrink_to_fit();
+        self.endorsement_slots.shrink_to_fit();
+        self.endorsements_by_producer
+            .retain(|_, producer_endos| !producer_endos.is_empty());
+        for producer_endos in self.endorsements_by_producer.values_mut() {
+            producer_endos.shrink_to_fit();
+        }
+        self.endorsements_by_producer.shrink_to_fit();
+        self.removed_since_compaction = 0;
+
+        debug!(
+            "compacted endorsement pool indexes from {} to {} bytes",
+            bytes_before,
+            self.index_capacity_bytes()
+        );
+    }
+
+    /// Pre-load the pool with endorsements, for instance the ones kept from a previous run.
+    /// They go through the same checks as the endorsements received from the network.
+    /// Returns the number of endorsements that were not in the pool and were kept.
+    pub(crate) fn warm_up(&mut self, endorsement_storage: Storage) -> usize {
+        let new_ids: Vec<EndorsementId> = endorsement_storage
+            .get_endorsement_refs()
+            .iter()
+            .filter(|id| !self.contains(id))
+            .copied()
+            .collect();
+        self.add_endorsements(endorsement_storage);
+        new_ids.iter().filter(|id| self.contains(id)).count()
     }
 
     /// Add a list of endorsements to the pool
@@ -143,6 +464,11 @@ impl EndorsementPool {
                     continue;
                 }
 
+                // check signature
+                if !self.check_signature(endo) {
+                    continue;
+                }
+
                 // Broadcast endorsement to active channel subscribers.
                 if self.config.broadcast_enabled {
                     if let Err(err) = self.channels.endorsement_sender.send(endo.clone()) {
@@ -166,8 +492,7 @@ impl EndorsementPool {
                     endo.content.endorsed_block,
                 );
                 // note that we don't want equivalent endorsements (slot, index, block etc...) to overwrite each other
-                if let Entry::Vacant(e) = self.endorsements_indexed.entry(key) {
-                    e.insert(endo.id);
+                if self.endorsement_slots.insert(key.0, key.1, key.2, endo.id) {
                     if self.endorsements_sorted[endo.content.slot.thread as usize]
                         .insert(key, endo.id)
                         .is_some()
@@ -185,16 +510,41 @@ impl EndorsementPool {
                 > self.config.max_endorsements_pool_size_per_thread
             {
                 // won't panic because len was checked above
-                let (_key, endo_id) = self.endorsements_sorted[thread as usize]
+                let ((slot, index, block_id), endo_id) = self.endorsements_sorted[thread as usize]
                     .pop_last()
                     .unwrap();
+                self.endorsement_slots
+                    .remove(slot, index, block_id, &endo_id);
                 if !added.remove(&endo_id) {
                     removed.insert(endo_id);
                 }
             }
         }
 
-        // take ownership on added endorsements
+        // index and take ownership on added endorsements
+        let mut added_bytes = 0;
+        {
+            let endo_store = endorsement_storage.read_endorsements();
+            for endo in added.iter().filter_map(|endo_id| endo_store.get(endo_id)) {
+                added_bytes += endo.serialized_size();
+                let producer_endos = self
+                    .endorsements_by_producer
+                    .entry(endo.content_creator_address)
+                    .or_default();
+                let position =
+                    producer_endos.partition_point(|(slot, _)| slot <= &endo.content.slot);
+                producer_endos.insert(position, (endo.content.slot, endo.id));
+            }
+        }
+        self.memory_bytes.fetch_add(added_bytes, Ordering::Relaxed);
+        self.total_added
+            .fetch_add(added.len() as u64, Ordering::Relaxed);
+        for endo_id in &added {
+            self.bloom_filter.insert(endo_id);
+            self.event_subscribers
+                .broadcast(PoolEvent::EndorsementAdded(*endo_id));
+        }
+        self.insertion_order.extend(added.iter().copied());
         self.storage.extend(endorsement_storage.split_off(
             &Default::default(),
             &Default::default(),
@@ -202,36 +552,409 @@ impl EndorsementPool {
         ));
 
         // drop removed endorsements from storage
-        self.storage.drop_endorsement_refs(&removed);
+        self.drop_endorsements(&removed, EvictionReason::Capacity);
+
+        // evict the first added endorsements if the pool is full
+        self.evict_oldest_endorsements();
+        self.publish_endorsement_count();
+    }
+
+    /// Check whether all the new endorsements of `endorsement_storage` would be pruned as soon as
+    /// they are added, their thread holding `max_endorsements_pool_size_per_thread` endorsements
+    /// that can all be included earlier.
+    /// Returns the number of endorsements of the thread of the first one and the per-thread limit
+    /// in that case.
+    pub(crate) fn check_capacity(&self, endorsement_storage: &Storage) -> Option<(usize, usize)> {
+        let limit = self.config.max_endorsements_pool_size_per_thread;
+        let endo_store = endorsement_storage.read_endorsements();
+        let mut current = None;
+        for endo in endorsement_storage
+            .get_endorsement_refs()
+            .iter()
+            .filter(|endo_id| !self.contains(endo_id))
+            .filter_map(|endo_id| endo_store.get(endo_id))
+        {
+            let thread_endos = self
+                .endorsements_sorted
+                .get(endo.content.slot.thread as usize)?;
+            let key = (
+                endo.content.slot,
+                endo.content.index,
+                endo.content.endorsed_block,
+            );
+            match thread_endos.last_key_value() {
+                Some((last_key, _)) if thread_endos.len() >= limit && &key > last_key => {
+                    current.get_or_insert(thread_endos.len());
+                }
+                _ => return None,
+            }
+        }
+        current.map(|current| (current, limit))
     }
 
-    /// get endorsements for block creation
-    pub fn get_block_endorsements(
+    /// Rebuild the indexes of the pool from the endorsements it holds in storage,
+    /// e.g. after a panic of the worker that may have left them inconsistent.
+    /// They keep their order of arrival, no event is broadcast and the statistics are kept.
+    /// Stored endorsements equivalent to an already indexed one are dropped from storage.
+    pub(crate) fn rebuild_from_storage(&mut self) {
+        let endorsement_refs = self.storage.get_endorsement_refs().clone();
+        warn!(
+            "rebuilding the endorsement pool indexes from its {} stored endorsements",
+            endorsement_refs.len()
+        );
+        // known endorsements first, in their order of arrival, then the unlisted ones
+        let mut order: Vec<EndorsementId> = Vec::with_capacity(endorsement_refs.len());
+        let mut listed: PreHashSet<EndorsementId> = Default::default();
+        for endo_id in self.insertion_order.iter().chain(endorsement_refs.iter()) {
+            if endorsement_refs.contains(endo_id) && listed.insert(*endo_id) {
+                order.push(*endo_id);
+            }
+        }
+
+        self.endorsement_slots.clear();
+        self.endorsements_sorted = vec![Default::default(); self.config.thread_count as usize];
+        self.endorsements_by_producer = Default::default();
+        let mut duplicates: PreHashSet<EndorsementId> = Default::default();
+        let mut memory_bytes = 0;
+        {
+            let endo_store = self.storage.read_endorsements();
+            for endo in order.iter().filter_map(|endo_id| endo_store.get(endo_id)) {
+                let key = (
+                    endo.content.slot,
+                    endo.content.index,
+                    endo.content.endorsed_block,
+                );
+                if !self.endorsement_slots.insert(key.0, key.1, key.2, endo.id) {
+                    duplicates.insert(endo.id);
+                    continue;
+                }
+                self.endorsements_sorted[endo.content.slot.thread as usize].insert(key, endo.id);
+                let producer_endos = self
+                    .endorsements_by_producer
+                    .entry(endo.content_creator_address)
+                    .or_default();
+                let position =
+                    producer_endos.partition_point(|(slot, _)| slot <= &endo.content.slot);
+                producer_endos.insert(position, (endo.content.slot, endo.id));
+                memory_bytes += endo.serialized_size();
+            }
+        }
+        order.retain(|endo_id| !duplicates.contains(endo_id));
+        self.insertion_order = order.into();
+        self.removed_since_compaction = 0;
+        self.memory_bytes.store(memory_bytes, Ordering::Relaxed);
+        self.storage.drop_endorsement_refs(&duplicates);
+        self.bloom_filter
+            .rebuild(self.storage.get_endorsement_refs().iter());
+        self.publish_endorsement_count();
+    }
+
+    /// Evict the endorsements of the first added batches
+    /// until at most `max_endorsement_pool_size` remain in the pool
+    fn evict_oldest_endorsements(&mut self) {
+        let excess = self
+            .len()
+            .saturating_sub(self.config.max_endorsement_pool_size);
+        let mut evicted = Vec::with_capacity(excess);
+        while evicted.len() < excess {
+            let Some(endo_id) = self.insertion_order.pop_front() else {
+                break;
+            };
+            if self.storage.get_endorsement_refs().contains(&endo_id) {
+                evicted.push(endo_id);
+            }
+        }
+        if !evicted.is_empty() {
+            debug!(
+                "evicting {} endorsements from the full endorsement pool",
+                evicted.len()
+            );
+            self.remove_endorsements(&evicted, EvictionReason::Capacity);
+        }
+        // forget the endorsements removed by other means once they make up most of the queue
+        if self.insertion_order.len() > 2 * self.len() {
+            let endorsement_refs = self.storage.get_endorsement_refs();
+            self.insertion_order
+                .retain(|endo_id| endorsement_refs.contains(endo_id));
+        }
+    }
+
+    /// Get the endorsement indices, among `0..endorsements_per_block`, for which the pool
+    /// has no endorsement of `target_block` to be included in a block at `target_slot`.
+    pub fn get_missing_endorsement_indices(
         &self,
-        slot: &Slot, // slot of the block that will contain the endorsement
+        target_slot: &Slot,
         target_block: &BlockId,
-    ) -> (Vec<Option<EndorsementId>>, Storage) {
-        // init list of selected endorsement IDs
-        let mut endo_ids = Vec::with_capacity(self.config.max_block_endorsement_count as usize);
-
-        // gather endorsements
-        for index in 0..self.config.max_block_endorsement_count {
-            endo_ids.push(
-                self.endorsements_indexed
-                    .get(&(*slot, index, *target_block))
-                    .copied(),
-            );
+        endorsements_per_block: usize,
+    ) -> Vec<u32> {
+        (0..endorsements_per_block as u32)
+            .filter(|index| {
+                !self
+                    .endorsement_slots
+                    .contains(target_slot, *index, target_block)
+            })
+            .collect()
+    }
+
+    /// Get, for each endorsement index among `0..endorsements_per_block`, whether the pool
+    /// has an endorsement of `target_block` to be included in a block at `target_slot`.
+    pub fn get_coverage_for_block(
+        &self,
+        target_block: &BlockId,
+        target_slot: &Slot,
+        endorsements_per_block: usize,
+    ) -> Vec<bool> {
+        (0..endorsements_per_block as u32)
+            .map(|index| {
+                self.endorsement_slots
+                    .contains(target_slot, index, target_block)
+            })
+            .collect()
+    }
+
+    /// Checks whether the pool contains an endorsement for each of the `endorsements_per_block` indices
+    /// of the block at `slot`, whatever the endorsed block.
+    pub fn has_full_endorsement_coverage_for_slot(
+        &self,
+        slot: &Slot,
+        endorsements_per_block: usize,
+    ) -> bool {
+        let Some(thread_endorsements) = self.endorsements_sorted.get(slot.thread as usize) else {
+            return false;
+        };
+        let covered_indices: HashSet<u32> = thread_endorsements
+            .keys()
+            .skip_while(|(inclusion_slot, _, _)| inclusion_slot < slot)
+            .take_while(|(inclusion_slot, _, _)| inclusion_slot == slot)
+            .map(|(_, index, _)| *index)
+            .collect();
+        (0..endorsements_per_block as u32).all(|index| covered_indices.contains(&index))
+    }
+
+    /// Get the number of endorsements of the pool by age, the age of an endorsement being
+    /// the number of periods between its slot and the current one.
+    /// Returns one `(bucket_min_periods, count)` pair per bucket of
+    /// `endorsement_age_histogram_buckets`, an endorsement being counted in the bucket
+    /// of highest lower bound not above its age.
+    pub fn get_age_histogram(&self) -> Vec<(u64, usize)> {
+        let buckets = &self.config.endorsement_age_histogram_buckets;
+        let mut histogram: Vec<(u64, usize)> = buckets.iter().map(|min| (*min, 0)).collect();
+        if buckets.is_empty() {
+            return histogram;
+        }
+        let now = MassaTime::now().expect("could not get current time");
+        let current_period = get_closest_slot_to_timestamp(
+            self.config.thread_count,
+            self.config.t0,
+            self.config.genesis_timestamp,
+            now,
+        )
+        .period;
+        for thread_endorsements in &self.endorsements_sorted {
+            for (slot, _, _) in thread_endorsements.keys() {
+                let age = current_period.saturating_sub(slot.period);
+                // number of buckets whose lower bound is not above the age
+                let bucket_count = buckets.partition_point(|min| *min <= age);
+                if let Some(bucket) = bucket_count.checked_sub(1) {
+                    histogram[bucket].1 += 1;
+                }
+            }
         }
+        histogram
+    }
 
-        // setup endorsement storage
-        let mut endo_storage = self.storage.clone_without_refs();
-        let claim_endos: PreHashSet<EndorsementId> =
-            endo_ids.iter().filter_map(|&opt| opt).collect();
-        let claimed_endos = endo_storage.claim_endorsement_refs(&claim_endos);
-        if claimed_endos.len() != claim_endos.len() {
-            panic!("could not claim all endorsements from storage");
+    /// Get the number of endorsements of the pool for each slot, whatever the endorsed block,
+    /// along with the number of slots having an endorsement for each of the
+    /// `endorsements_per_block` indices
+    pub fn get_endorsement_stats(&self, endorsements_per_block: usize) -> EndorsementStats {
+        let mut covered_indices: HashMap<Slot, HashSet<u32>> = HashMap::new();
+        for thread_endorsements in &self.endorsements_sorted {
+            for (slot, index, _) in thread_endorsements.keys() {
+                covered_indices.entry(*slot).or_default().insert(*index);
+            }
+        }
+        let mut stats = EndorsementStats {
+            total_endorsements: self.len(),
+            min_slot: covered_indices.keys().min().copied(),
+            max_slot: covered_indices.keys().max().copied(),
+            slots_with_full_coverage: covered_indices
+                .values()
+                .filter(|indices| indices.len() >= endorsements_per_block)
+                .count(),
+            ..Default::default()
+        };
+        for thread_endorsements in &self.endorsements_sorted {
+            for (slot, _, _) in thread_endorsements.keys() {
+                *stats.endorsements_per_slot.entry(*slot).or_default() += 1;
+            }
         }
+        stats
+    }
 
-        (endo_ids, endo_storage)
+    /// Get the number of endorsements of the pool for each slot between `from_slot` and `to_slot` included,
+    /// whatever the endorsed block. Slots without endorsements are mapped to 0.
+    pub fn get_endorsement_coverage_map(
+        &self,
+        from_slot: &Slot,
+        to_slot: &Slot,
+    ) -> HashMap<Slot, usize> {
+        let mut coverage = HashMap::new();
+        let mut slot = *from_slot;
+        while slot <= *to_slot {
+            coverage.insert(slot, 0);
+            match slot.get_next_slot(self.config.thread_count) {
+                Ok(next_slot) => slot = next_slot,
+                Err(_) => break,
+            }
+        }
+        // smallest possible key of `from_slot` in `endorsements_sorted`
+        let lower_bound = (
+            *from_slot,
+            0,
+            BlockId::generate_from_hash(Hash::from_bytes(&[0; HASH_SIZE_BYTES])),
+        );
+        for thread_endorsements in &self.endorsements_sorted {
+            for (inclusion_slot, _, _) in thread_endorsements
+                .range(lower_bound..)
+                .map(|(key, _)| key)
+                .take_while(|(inclusion_slot, _, _)| inclusion_slot <= to_slot)
+            {
+                *coverage.entry(*inclusion_slot).or_default() += 1;
+            }
+        }
+        coverage
+    }
+
+    /// Get the slots and IDs of the endorsements of the pool created by `producer`, by increasing slot
+    pub fn get_endorsements_by_producer(&self, producer: &Address) -> Vec<(Slot, EndorsementId)> {
+        self.endorsements_by_producer
+            .get(producer)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Get the IDs of the endorsements of the pool whose inclusion slot is more than
+    /// `max_age_slots` slots before `current_slot`, by increasing slot in each thread.
+    /// Endorsements at or after `current_slot` are never returned.
+    pub fn get_endorsements_older_than(
+        &self,
+        max_age_slots: u64,
+        current_slot: &Slot,
+    ) -> Vec<EndorsementId> {
+        self.endorsements_sorted
+            .iter()
+            .flat_map(|thread_endorsements| {
+                thread_endorsements
+                    .iter()
+                    .take_while(|((slot, _, _), _)| {
+                        current_slot
+                            .slots_since(slot, self.config.thread_count)
+                            .map_or(false, |age| age > max_age_slots)
+                    })
+                    .map(|(_, id)| *id)
+            })
+            .collect()
+    }
+
+    /// Remove all the endorsements of the pool and reset its statistics.
+    /// The pool keeps its subscribers and the bloom filter shared with the controller.
+    #[cfg(feature = "testing")]
+    pub(crate) fn clear(&mut self) {
+        let ids: Vec<EndorsementId> = self
+            .endorsements_sorted
+            .iter()
+            .flat_map(|thread_endos| thread_endos.values().copied())
+            .collect();
+        self.remove_endorsements(&ids, EvictionReason::Purged);
+        self.compact();
+        self.signature_cache.clear();
+        self.reset_statistics();
+    }
+
+    /// Remove all the endorsements of the pool and reset its statistics, e.g. after a hard fork.
+    /// The emptied indexes are replaced by fresh allocations, releasing their fragmented memory.
+    /// The pool keeps its subscribers and the indexes shared with the controller.
+    pub(crate) fn purge_all(&mut self) {
+        info!(
+            "purging the endorsement pool: {} endorsements, {} bytes",
+            self.len(),
+            self.memory_bytes()
+        );
+        let ids: Vec<EndorsementId> = self
+            .endorsements_sorted
+            .iter()
+            .flat_map(|thread_endos| thread_endos.values().copied())
+            .collect();
+        self.remove_endorsements(&ids, EvictionReason::Purged);
+        self.endorsements_sorted = vec![Default::default(); self.config.thread_count as usize];
+        self.insertion_order = Default::default();
+        self.endorsements_by_producer = Default::default();
+        self.endorsement_slots.shrink_to_fit();
+        self.signature_cache.clear();
+        self.removed_since_compaction = 0;
+        self.reset_statistics();
+    }
+
+    /// Reset the counters of the pool statistics
+    fn reset_statistics(&mut self) {
+        for counter in [
+            &self.total_added,
+            &self.total_evicted,
+            &self.signature_checks,
+        ] {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Register a subscriber to the additions and evictions of endorsements
+    pub(crate) fn subscribe_to_events(&mut self, sender: SyncSender<PoolEvent>) {
+        self.event_subscribers.subscribe(sender);
+    }
+
+    /// Get a snapshot of all the endorsements of the pool, by increasing slot in each thread.
+    pub fn snapshot(&self) -> EndorsementPoolSnapshot {
+        let endorsement_ids: Vec<EndorsementId> = self
+            .endorsements_sorted
+            .iter()
+            .flat_map(|thread_endorsements| thread_endorsements.values().copied())
+            .collect();
+        let mut storage = self.storage.clone_without_refs();
+        storage.claim_endorsement_refs(&endorsement_ids.iter().copied().collect());
+        EndorsementPoolSnapshot {
+            endorsement_ids,
+            storage,
+        }
+    }
+
+    /// Get information about the endorsements of the pool, by increasing slot in each thread
+    pub fn dump(&self) -> Vec<EndorsementDump> {
+        self.endorsements_sorted
+            .iter()
+            .flat_map(|thread_endorsements| thread_endorsements.iter())
+            .map(|(&(slot, index, endorsed_block), &id)| EndorsementDump {
+                id,
+                slot,
+                index,
+                endorsed_block,
+            })
+            .collect()
+    }
+
+    /// XOR of the IDs of all the endorsements of the pool, independent of their order
+    pub fn ids_xor(&self) -> [u8; 32] {
+        xor_ids(self.storage.get_endorsement_refs().iter())
+    }
+
+    /// Get a storage containing the endorsements of the pool that match the given IDs.
+    /// IDs that are not in the pool are ignored.
+    pub fn get_endorsements_by_ids(&self, ids: &[EndorsementId]) -> Storage {
+        let claim_endos: PreHashSet<EndorsementId> = ids
+            .iter()
+            .filter(|id| self.storage.get_endorsement_refs().contains(*id))
+            .copied()
+            .collect();
+        let mut endo_storage = self.storage.clone_without_refs();
+        endo_storage.claim_endorsement_refs(&claim_endos);
+        endo_storage
     }
 }
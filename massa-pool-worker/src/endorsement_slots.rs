@@ -0,0 +1,145 @@
+//! Copyright (c) 2022 MASSA LABS <info@massa.net>
+
+//! Index of the endorsements of the pool by inclusion slot, for block assembly reads
+//! that do not contend with the writes of the endorsement pool worker.
+
+use dashmap::DashMap;
+use massa_models::{
+    block_id::BlockId, endorsement::EndorsementId, prehash::PreHashSet, slot::Slot,
+};
+use massa_storage::Storage;
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    mem::size_of,
+};
+
+/// Endorsements of the pool that can be included in a block at a given slot
+#[derive(Debug, Default)]
+pub struct SlotEndorsements {
+    /// endorsement IDs indexed by endorsement index and endorsed block
+    by_index: HashMap<(u32, BlockId), EndorsementId>,
+}
+
+/// Endorsement IDs indexed by inclusion slot, then by endorsement index and endorsed block.
+///
+/// Each slot is locked independently: reading the endorsements of a slot
+/// does not wait for the insertion of endorsements at other slots.
+#[derive(Debug, Default)]
+pub struct EndorsementSlots {
+    slots: DashMap<Slot, SlotEndorsements>,
+}
+
+impl EndorsementSlots {
+    /// Adds an endorsement, unless another one is already indexed for the same slot,
+    /// index and endorsed block. Returns whether it was added.
+    pub fn insert(&self, slot: Slot, index: u32, block_id: BlockId, id: EndorsementId) -> bool {
+        match self
+            .slots
+            .entry(slot)
+            .or_default()
+            .by_index
+            .entry((index, block_id))
+        {
+            Entry::Vacant(entry) => {
+                entry.insert(id);
+                true
+            }
+            Entry::Occupied(_) => false,
+        }
+    }
+
+    /// Removes the endorsement indexed for `(slot, index, block_id)` if it is `id`.
+    /// Returns whether it was removed.
+    pub fn remove(&self, slot: Slot, index: u32, block_id: BlockId, id: &EndorsementId) -> bool {
+        let (removed, slot_is_empty) = {
+            let Some(mut slot_endos) = self.slots.get_mut(&slot) else {
+                return false;
+            };
+            let removed = match slot_endos.by_index.entry((index, block_id)) {
+                Entry::Occupied(entry) if entry.get() == id => {
+                    entry.remove();
+                    true
+                }
+                _ => false,
+            };
+            (removed, slot_endos.by_index.is_empty())
+        };
+        // the entry guard is released before removing the slot, which locks it again
+        if slot_is_empty {
+            self.slots
+                .remove_if(&slot, |_, slot_endos| slot_endos.by_index.is_empty());
+        }
+        removed
+    }
+
+    /// Checks whether an endorsement is indexed for `(slot, index, block_id)`
+    pub fn contains(&self, slot: &Slot, index: u32, block_id: &BlockId) -> bool {
+        self.slots.get(slot).map_or(false, |slot_endos| {
+            slot_endos.by_index.contains_key(&(index, *block_id))
+        })
+    }
+
+    /// Get the number of endorsements indexed for `slot`
+    pub fn count(&self, slot: &Slot) -> usize {
+        self.slots
+            .get(slot)
+            .map_or(0, |slot_endos| slot_endos.by_index.len())
+    }
+
+    /// Get the endorsements of `target_block` for each index among `0..endorsement_count`
+    /// of a block at `slot`, along with a storage claiming them from `storage`.
+    /// Only the entry of `slot` is locked, once.
+    ///
+    /// The endorsements removed from `storage` since they were indexed are left out.
+    pub fn get_block_endorsements(
+        &self,
+        slot: &Slot,
+        target_block: &BlockId,
+        endorsement_count: u32,
+        storage: &Storage,
+    ) -> (Vec<Option<EndorsementId>>, Storage) {
+        let mut endo_ids: Vec<Option<EndorsementId>> = match self.slots.get(slot) {
+            Some(slot_endos) => (0..endorsement_count)
+                .map(|index| slot_endos.by_index.get(&(index, *target_block)).copied())
+                .collect(),
+            None => vec![None; endorsement_count as usize],
+        };
+
+        let mut endo_storage = storage.clone_without_refs();
+        let claim_endos: PreHashSet<EndorsementId> = endo_ids.iter().flatten().copied().collect();
+        let claimed_endos = endo_storage.claim_endorsement_refs(&claim_endos);
+        if claimed_endos.len() != claim_endos.len() {
+            for endo_id in endo_ids.iter_mut() {
+                if endo_id.map_or(false, |id| !claimed_endos.contains(&id)) {
+                    *endo_id = None;
+                }
+            }
+        }
+        (endo_ids, endo_storage)
+    }
+
+    /// Get the memory allocated by the index, unused capacity included
+    pub fn capacity_bytes(&self) -> usize {
+        self.slots.capacity() * size_of::<(Slot, SlotEndorsements)>()
+            + self
+                .slots
+                .iter()
+                .map(|slot_endos| {
+                    slot_endos.by_index.capacity() * size_of::<((u32, BlockId), EndorsementId)>()
+                })
+                .sum::<usize>()
+    }
+
+    /// Remove all the endorsements from the index
+    pub fn clear(&self) {
+        self.slots.clear();
+    }
+
+    /// Release the capacity left unused in the index
+    pub fn shrink_to_fit(&self) {
+        for mut slot_endos in self.slots.iter_mut() {
+            slot_endos.by_index.shrink_to_fit();
+        }
+        self.slots.shrink_to_fit();
+    }
+}
@@ -0,0 +1,196 @@
+//! Copyright (c) 2022 MASSA LABS <info@massa.net>
+
+//! Log of the commands processed by the operation and endorsement pool workers,
+//! replayed offline to reproduce the content of the pools at any point of their history.
+//!
+//! Each line of the log is an `EventLogEntry` serialized as JSON.
+//! The periodic refreshes of the operation pool are not commands, so they are not logged.
+
+use crate::controller_impl::Command;
+use massa_models::{
+    amount::Amount,
+    block_id::BlockId,
+    endorsement::{EndorsementId, SecureShareEndorsement},
+    operation::{OperationId, SecureShareOperation},
+    slot::Slot,
+};
+use massa_storage::Storage;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::warn;
+
+/// Pool whose worker processed a logged command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LoggedPool {
+    /// operation pool
+    Operations,
+    /// endorsement pool
+    Endorsements,
+}
+
+/// Command processed by a pool worker, along with the items it carries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum LoggedCommand {
+    /// operations added to the pool
+    AddOperations(Vec<SecureShareOperation>),
+    /// endorsements added to the pool
+    AddEndorsements(Vec<SecureShareEndorsement>),
+    /// operations removed from the pool
+    RemoveOperations(Vec<OperationId>),
+    /// endorsements removed from the pool
+    RemoveEndorsements(Vec<EndorsementId>),
+    /// new final consensus periods, per thread
+    NotifyFinalCsPeriods(Vec<u64>),
+    /// start of a new slot
+    NotifyNewSlot(Slot),
+    /// block that became final, with the items of the pool it includes
+    BlockFinalized {
+        /// id of the final block
+        block_id: BlockId,
+        /// ids of the operations included in the block
+        operation_ids: Vec<OperationId>,
+        /// ids of the endorsements included in the block
+        endorsement_ids: Vec<EndorsementId>,
+    },
+    /// operations of an orphaned block re-injected into the pool
+    ReInjectOperations {
+        /// id of the orphaned block
+        block_id: BlockId,
+        /// operations of the block
+        operations: Vec<SecureShareOperation>,
+    },
+    /// new minimal fee of the operation pool
+    SetMinFee(Amount),
+    /// purge of all the items of the pool
+    PurgeAll,
+}
+
+impl LoggedCommand {
+    /// Get the logged form of a command processed by the worker of `pool`,
+    /// or `None` if the command does not change the content of the pool
+    fn from_command(pool: LoggedPool, command: &Command) -> Option<Self> {
+        let logged = match command {
+            Command::AddItems(storage) => match pool {
+                LoggedPool::Operations => LoggedCommand::AddOperations(stored_operations(storage)),
+                LoggedPool::Endorsements => {
+                    LoggedCommand::AddEndorsements(stored_endorsements(storage))
+                }
+            },
+            Command::WarmUpEndorsements(storage, _) => {
+                LoggedCommand::AddEndorsements(stored_endorsements(storage))
+            }
+            Command::RemoveOperations(ids) => LoggedCommand::RemoveOperations(ids.clone()),
+            Command::RemoveEndorsements(ids, _) => LoggedCommand::RemoveEndorsements(ids.clone()),
+            Command::NotifyFinalCsPeriods(periods) => {
+                LoggedCommand::NotifyFinalCsPeriods(periods.clone())
+            }
+            Command::NotifyNewSlot(slot) => LoggedCommand::NotifyNewSlot(*slot),
+            Command::BlockFinalized {
+                block_id,
+                operation_ids,
+                endorsement_ids,
+            } => LoggedCommand::BlockFinalized {
+                block_id: *block_id,
+                operation_ids: operation_ids.clone(),
+                endorsement_ids: endorsement_ids.clone(),
+            },
+            Command::ReInjectOperations(block_id, storage) => LoggedCommand::ReInjectOperations {
+                block_id: *block_id,
+                operations: stored_operations(storage),
+            },
+            Command::SetMinFee(min_fee, _) => LoggedCommand::SetMinFee(*min_fee),
+            Command::PurgeAll => LoggedCommand::PurgeAll,
+            _ => return None,
+        };
+        Some(logged)
+    }
+}
+
+/// Line of the event log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventLogEntry {
+    /// time at which the command was processed, in nanoseconds since the UNIX epoch
+    pub timestamp_ns: u64,
+    /// pool whose worker processed the command
+    pub pool: LoggedPool,
+    /// processed command
+    pub command: LoggedCommand,
+}
+
+/// Append-only log of the commands processed by the pool workers
+pub(crate) struct EventLog {
+    /// buffered writer appending to the log file, flushed after each entry
+    writer: BufWriter<File>,
+}
+
+/// Event log shared by the operation and endorsement pool workers
+pub(crate) type SharedEventLog = Arc<Mutex<EventLog>>;
+
+impl EventLog {
+    /// Opens the log at `path` for appending, creating it and its parent directories if needed
+    pub fn open(path: &Path) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(EventLog {
+            writer: BufWriter::new(file),
+        })
+    }
+
+    /// Appends a command processed by the worker of `pool` to the log, then flushes it.
+    /// Commands that do not change the content of the pool are skipped.
+    pub fn append(&mut self, pool: LoggedPool, command: &Command) -> io::Result<()> {
+        let Some(command) = LoggedCommand::from_command(pool, command) else {
+            return Ok(());
+        };
+        let timestamp_ns = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX))
+            .unwrap_or_default();
+        let entry = EventLogEntry {
+            timestamp_ns,
+            pool,
+            command,
+        };
+        serde_json::to_writer(&mut self.writer, &entry)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()
+    }
+}
+
+/// Log a command processed by the worker of `pool`, if the event log is enabled
+pub(crate) fn log_command(event_log: &Option<SharedEventLog>, pool: LoggedPool, command: &Command) {
+    if let Some(event_log) = event_log {
+        if let Err(err) = event_log.lock().append(pool, command) {
+            warn!("could not write to the pool event log: {}", err);
+        }
+    }
+}
+
+/// Get the operations a storage holds references to
+fn stored_operations(storage: &Storage) -> Vec<SecureShareOperation> {
+    let stored_ops = storage.read_operations();
+    storage
+        .get_op_refs()
+        .iter()
+        .filter_map(|id| stored_ops.get(id).cloned())
+        .collect()
+}
+
+/// Get the endorsements a storage holds references to
+fn stored_endorsements(storage: &Storage) -> Vec<SecureShareEndorsement> {
+    let stored_endorsements = storage.read_endorsements();
+    storage
+        .get_endorsement_refs()
+        .iter()
+        .filter_map(|id| stored_endorsements.get(id).cloned())
+        .collect()
+}
@@ -5,17 +5,39 @@
 #![warn(missing_docs)]
 #![warn(unused_crate_dependencies)]
 
+#[cfg(feature = "async")]
+mod async_sender;
+mod bloom_filter;
 mod controller_impl;
 mod denunciation_pool;
 mod endorsement_pool;
+mod endorsement_slots;
+mod event_log;
+mod metrics;
 mod operation_pool;
+mod operation_shards;
+#[cfg(any(test, feature = "testing"))]
+mod replay;
+mod subscribers;
 mod types;
+mod wal;
 mod worker;
 
-pub use worker::start_pool_controller;
+#[cfg(feature = "async")]
+pub use async_sender::AsyncOperationsSender;
+pub use event_log::{EventLogEntry, LoggedCommand, LoggedPool};
+#[cfg(any(test, feature = "testing"))]
+pub use replay::ReplayedPools;
+#[cfg(feature = "async")]
+pub use worker::start_pool_controller_with_async_sender;
+pub use worker::{start_pool_controller, start_pool_controller_with_validator};
 
 #[cfg(test)]
 use mockall as _;
 
+// only used by the benchmarks
+#[cfg(feature = "benchmarking")]
+use criterion as _;
+
 #[cfg(test)]
 mod tests;
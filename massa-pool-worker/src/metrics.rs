@@ -0,0 +1,100 @@
+//! Copyright (c) 2022 MASSA LABS <info@massa.net>
+
+//! Pool telemetry.
+//!
+//! With the `metrics` feature, the pool metrics are registered in the default prometheus registry
+//! (the one exposed by the node metrics server). Without it, all the functions below are no-ops.
+
+#[cfg(feature = "metrics")]
+mod enabled {
+    use lazy_static::lazy_static;
+    use prometheus::{
+        register_histogram, register_int_counter, register_int_counter_vec, register_int_gauge,
+        Histogram, HistogramTimer, IntCounter, IntCounterVec, IntGauge,
+    };
+
+    lazy_static! {
+        static ref OPERATION_COUNT: IntGauge = register_int_gauge!(
+            "massa_pool_operation_count",
+            "number of operations in the pool"
+        )
+        .unwrap();
+        static ref ENDORSEMENT_COUNT: IntGauge = register_int_gauge!(
+            "massa_pool_endorsement_count",
+            "number of endorsements in the pool"
+        )
+        .unwrap();
+        static ref OPERATIONS_ADDED: IntCounter = register_int_counter!(
+            "massa_pool_operations_added_total",
+            "number of operations added to the pool"
+        )
+        .unwrap();
+        static ref OPERATIONS_EVICTED: IntCounter = register_int_counter!(
+            "massa_pool_operations_evicted_total",
+            "number of operations removed from the pool"
+        )
+        .unwrap();
+        static ref CHANNEL_FULL: IntCounterVec = register_int_counter_vec!(
+            "massa_pool_channel_full_total",
+            "number of commands dropped because a pool worker channel was full",
+            &["pool"]
+        )
+        .unwrap();
+        static ref GET_BLOCK_OPERATIONS_DURATION: Histogram = register_histogram!(
+            "massa_pool_get_block_operations_duration_seconds",
+            "duration of the selection of operations for block creation"
+        )
+        .unwrap();
+    }
+
+    pub(crate) type Timer = HistogramTimer;
+
+    pub(crate) fn set_operation_count(count: usize) {
+        OPERATION_COUNT.set(count as i64);
+    }
+
+    pub(crate) fn set_endorsement_count(count: usize) {
+        ENDORSEMENT_COUNT.set(count as i64);
+    }
+
+    pub(crate) fn inc_operations_added(count: usize) {
+        OPERATIONS_ADDED.inc_by(count as u64);
+    }
+
+    pub(crate) fn inc_operations_evicted(count: usize) {
+        OPERATIONS_EVICTED.inc_by(count as u64);
+    }
+
+    pub(crate) fn inc_channel_full(pool: &str) {
+        CHANNEL_FULL.with_label_values(&[pool]).inc();
+    }
+
+    /// The duration is recorded when the returned timer is dropped
+    pub(crate) fn start_get_block_operations_timer() -> Timer {
+        GET_BLOCK_OPERATIONS_DURATION.start_timer()
+    }
+}
+
+#[cfg(not(feature = "metrics"))]
+mod disabled {
+    pub(crate) struct Timer;
+
+    pub(crate) fn set_operation_count(_count: usize) {}
+
+    pub(crate) fn set_endorsement_count(_count: usize) {}
+
+    pub(crate) fn inc_operations_added(_count: usize) {}
+
+    pub(crate) fn inc_operations_evicted(_count: usize) {}
+
+    pub(crate) fn inc_channel_full(_pool: &str) {}
+
+    pub(crate) fn start_get_block_operations_timer() -> Timer {
+        Timer
+    }
+}
+
+#[cfg(not(feature = "metrics"))]
+pub(crate) use disabled::*;
+#[cfg(feature = "metrics")]
+pub(crate) use enabled::*;
@@ -3,37 +3,169 @@
 use massa_models::{
     address::Address,
     amount::Amount,
-    operation::OperationId,
+    block_id::BlockId,
+    operation::{OperationId, OperationTypeId, SecureShareOperation},
     prehash::{CapacityAllocator, PreHashMap, PreHashSet},
     slot::Slot,
-    timeslots::get_latest_block_slot_at_timestamp,
+    timeslots::{get_closest_slot_to_timestamp, get_latest_block_slot_at_timestamp},
+};
+use massa_pool_exports::{
+    BlockFillInfo, EvictionPolicy, EvictionReason, OperationDump, OperationPoolSnapshot,
+    OperationSelectionStrategy, OperationTypeStats, PoolChannels, PoolConfig, PoolEvent,
 };
-use massa_pool_exports::{PoolChannels, PoolConfig};
 use massa_storage::Storage;
 use massa_time::MassaTime;
 use massa_wallet::Wallet;
 use parking_lot::RwLock;
-use std::{cmp::max, cmp::Ordering, cmp::PartialOrd, collections::BTreeSet, sync::Arc};
-use tracing::{debug, trace, warn};
+#[cfg(feature = "parallel")]
+use rayon::{
+    iter::{IntoParallelIterator, ParallelIterator},
+    ThreadPool, ThreadPoolBuilder,
+};
+use std::{
+    cmp::{max, Reverse},
+    collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap},
+    mem::size_of,
+    ops::Bound,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        mpsc::SyncSender,
+        Arc,
+    },
+};
+use tokio::sync::watch;
+use tracing::{debug, info, trace, warn};
 
-use crate::types::OperationInfo;
+use crate::{
+    bloom_filter::BloomFilter,
+    metrics,
+    operation_shards::OperationShards,
+    subscribers::PoolEventSubscribers,
+    types::{id_fingerprint, xor_ids, OperationCursor, OperationInfo, OperationTypeTotals},
+    wal::OperationWal,
+};
+
+/// Memory held by the indexes of the pool for each operation, on top of its serialized size:
+/// its info, its sorting cursor and the copies of its ID, doubled to account for the
+/// unused capacity of the maps and sets
+pub(crate) const OPERATION_INDEX_OVERHEAD_BYTES: usize =
+    2 * (size_of::<OperationInfo>() + size_of::<OperationCursor>() + 8 * size_of::<OperationId>());
+
+/// Operations selected for a block by `OperationPool::select_block_operations`
+struct BlockSelection<'a> {
+    /// selected operations, in the order they were selected
+    operations: Vec<&'a OperationInfo>,
+    /// rank of the last selected operation among the candidates
+    last_selected_rank: Option<usize>,
+    /// gas used by the selected operations
+    gas_used: u64,
+    /// total serialized size of the selected operations
+    size_bytes: usize,
+}
 
 pub struct OperationPool {
     /// configuration
     config: PoolConfig,
 
-    /// operations map
-    sorted_ops: Vec<OperationInfo>,
+    /// operations indexed by ID
+    operations: PreHashMap<OperationId, OperationInfo>,
+
+    /// operations sorted by decreasing fee per byte
+    sorted_ops: BTreeSet<OperationCursor>,
+
+    /// operations of the pool indexed by expiry slot:
+    /// their last valid period, in the thread they can be included in
+    ops_by_expiry: BTreeMap<Slot, PreHashSet<OperationId>>,
+
+    /// number of operations of the pool per raw fee amount, kept sorted for fee percentile queries
+    fee_counts: BTreeMap<u64, usize>,
+
+    /// operations of the pool, indexed by sender address
+    ops_by_sender: PreHashMap<Address, BTreeSet<OperationId>>,
+
+    /// operations sorted by order of arrival into the pool
+    insertion_order: BTreeMap<u64, OperationId>,
+
+    /// insertion index of the next operation added to the pool
+    next_insertion_index: u64,
+
+    /// XOR of the `id_fingerprint` of the operations of the pool, updated on insertion and removal
+    fingerprint: u64,
 
     /// storage instance
     pub(crate) storage: Storage,
 
+    /// bloom filter of the operations in the pool, shared with the controller
+    pub(crate) bloom_filter: Arc<BloomFilter>,
+
+    /// sharded index of the operations in the pool, shared with the controller
+    pub(crate) shards: Arc<OperationShards>,
+
+    /// total serialized size of the operations in the pool
+    memory_bytes: AtomicUsize,
+
+    /// estimated memory held by the operations and indexes of the pool, shared with the controller
+    pub(crate) estimated_memory: Arc<AtomicUsize>,
+
+    /// number of operations added to the pool since startup
+    total_added: AtomicU64,
+
+    /// number of operations removed from the pool since startup
+    total_evicted: AtomicU64,
+
+    /// number of incoming operations skipped because they were already in the pool
+    duplicates_skipped: AtomicU64,
+
+    /// number of operations evicted because their sender exceeded `max_ops_per_sender`
+    sender_limit_evicted: AtomicU64,
+
+    /// number of operations each sender added during `ops_added_slot`,
+    /// capped by `max_ops_added_per_sender_per_slot`
+    ops_added_per_sender: PreHashMap<Address, usize>,
+
+    /// slot of the clock during which the operations counted in `ops_added_per_sender` were added
+    ops_added_slot: Option<Slot>,
+
+    /// number of incoming operations dropped because their size exceeded `max_operation_size_bytes`
+    oversized_ops_dropped: AtomicU64,
+    /// number of incoming operations dropped because their type is not accepted
+    type_filtered_ops_dropped: AtomicU64,
+
+    /// number of blocks filled from the `hot_tier_size` best operations only
+    hot_tier_hits: AtomicU64,
+
+    /// number of blocks filled with operations beyond the `hot_tier_size` best ones
+    hot_tier_misses: AtomicU64,
+
+    /// totals of the operations of the pool, by operation type
+    op_type_totals: HashMap<OperationTypeId, OperationTypeTotals>,
+
+    /// write-ahead log of the added operations, if `wal_path` is set
+    wal: Option<OperationWal>,
+
+    /// threads checking the signatures of incoming operations, if `validation_parallelism` is above 1
+    #[cfg(feature = "parallel")]
+    validation_thread_pool: Option<ThreadPool>,
+
+    /// minimal fee of the operations accepted in the pool, initialized from `min_fee`
+    /// and raised at runtime with `set_min_fee`
+    min_fee: Amount,
+
     /// last consensus final periods, per thread
     last_cs_final_periods: Vec<u64>,
 
+    /// last slot notified with `notify_new_slot`
+    current_slot: Option<Slot>,
+
     /// channels used by the pool worker
     channels: PoolChannels,
 
+    /// subscribers to the pool events
+    event_subscribers: PoolEventSubscribers,
+
+    /// `(operation_count, endorsement_count)` watched by the controller, shared with the endorsement pool
+    pool_sizes: Arc<watch::Sender<(usize, usize)>>,
+
     /// staking wallet, to know which addresses we are using to stake
     wallet: Arc<RwLock<Wallet>>,
 }
@@ -44,18 +176,101 @@ impl OperationPool {
         storage: &Storage,
         channels: PoolChannels,
         wallet: Arc<RwLock<Wallet>>,
+        pool_sizes: Arc<watch::Sender<(usize, usize)>>,
     ) -> Self {
-        OperationPool {
-            sorted_ops: Vec::with_capacity(
+        let wal_path = config.wal_path.clone();
+        #[cfg(not(feature = "parallel"))]
+        if config.validation_parallelism > 1 {
+            warn!(
+                "the operation pool is built without the `parallel` feature: the signatures of the incoming operations are checked on the pool worker thread"
+            );
+        }
+        let mut pool = OperationPool {
+            operations: PreHashMap::with_capacity(
                 config
                     .max_operation_pool_size
                     .saturating_add(config.max_operation_pool_excess_items),
             ),
+            sorted_ops: Default::default(),
+            ops_by_expiry: Default::default(),
+            fee_counts: Default::default(),
+            ops_by_sender: Default::default(),
+            insertion_order: Default::default(),
+            next_insertion_index: 0,
+            fingerprint: 0,
+            bloom_filter: Arc::new(BloomFilter::new(
+                config.operation_bloom_filter_size,
+                config.bloom_filter_false_positive_rate,
+            )),
+            shards: Arc::new(OperationShards::new(config.operation_pool_shard_count)),
+            memory_bytes: Default::default(),
+            estimated_memory: Default::default(),
+            total_added: Default::default(),
+            total_evicted: Default::default(),
+            duplicates_skipped: Default::default(),
+            sender_limit_evicted: Default::default(),
+            ops_added_per_sender: Default::default(),
+            ops_added_slot: None,
+            oversized_ops_dropped: Default::default(),
+            type_filtered_ops_dropped: Default::default(),
+            hot_tier_hits: Default::default(),
+            hot_tier_misses: Default::default(),
+            op_type_totals: Default::default(),
+            wal: None,
+            #[cfg(feature = "parallel")]
+            validation_thread_pool: build_validation_thread_pool(config.validation_parallelism),
+            min_fee: config.min_fee,
             last_cs_final_periods: vec![0u64; config.thread_count as usize],
+            current_slot: None,
             config,
             storage: storage.clone_without_refs(),
             channels,
+            event_subscribers: Default::default(),
+            pool_sizes,
             wallet,
+        };
+
+        // restore the operations of the write-ahead log before logging new ones
+        if let Some(wal_path) = wal_path {
+            match OperationWal::open(&wal_path, storage) {
+                Ok((wal, replayed_ops)) => {
+                    let replayed_count = replayed_ops.get_op_refs().len();
+                    pool.add_operations(replayed_ops);
+                    pool.wal = Some(wal);
+                    pool.compact_wal();
+                    info!(
+                        "replayed {} operations from the operation pool write-ahead log {}",
+                        replayed_count,
+                        wal_path.display()
+                    );
+                }
+                Err(err) => warn!(
+                    "could not open the operation pool write-ahead log {}, the pool will not be persisted: {}",
+                    wal_path.display(),
+                    err
+                ),
+            }
+        }
+        pool
+    }
+
+    /// Replace the content of the write-ahead log by the operations currently in the pool,
+    /// once the records of the operations that left the pool outnumber the live ones.
+    /// This bounds the log to twice the pool size while amortizing the cost of the rewrites.
+    fn compact_wal(&mut self) {
+        let Some(wal) = self.wal.as_mut() else {
+            return;
+        };
+        let live_count = self.operations.len();
+        if wal.record_count().saturating_sub(live_count) <= live_count {
+            return;
+        }
+        let ops = self.storage.read_operations();
+        if let Err(err) = wal.rewrite(self.operations.keys().filter_map(|id| ops.get(id))) {
+            warn!(
+                "could not compact the operation pool write-ahead log: {}",
+                err
+            );
         }
     }
 
@@ -108,7 +323,7 @@ impl OperationPool {
 
     /// Returns the list of executed ops with a boolean indicating whether they are executed as final.
     fn get_execution_statuses(&self) -> PreHashMap<OperationId, bool> {
-        let op_ids: Vec<OperationId> = self.sorted_ops.iter().map(|op_info| op_info.id).collect();
+        let op_ids: Vec<OperationId> = self.operations.keys().copied().collect();
         self.channels
             .execution_controller
             .get_ops_exec_status(&op_ids)
@@ -128,8 +343,8 @@ impl OperationPool {
     /// Addresses that don't exist are not returned.
     fn get_sender_balances(&self) -> PreHashMap<Address, Amount> {
         let addrs: Vec<Address> = self
-            .sorted_ops
-            .iter()
+            .operations
+            .values()
             .map(|op_info| op_info.creator_address)
             .collect::<PreHashSet<Address>>()
             .into_iter()
@@ -152,7 +367,7 @@ impl OperationPool {
         sender_balances: &PreHashMap<Address, Amount>,
     ) {
         let mut removed = PreHashSet::default();
-        self.sorted_ops.retain(|op_info| {
+        for op_info in self.operations.values() {
             // filter out ops that use too much resources
             let mut retain = (op_info.max_gas <= self.config.max_block_gas)
                 && (op_info.size <= self.config.max_block_size as usize);
@@ -181,20 +396,21 @@ impl OperationPool {
 
             if !retain {
                 removed.insert(op_info.id);
-                return false;
             }
-            true
-        });
-        // drop from storage
-        self.storage.drop_operation_refs(&removed);
+        }
+        self.remove_operations(&removed, EvictionReason::Invalid);
     }
 
     /// Eliminate all operations that would cause a sender balance overflow.
-    /// Assumes that the ops are sorted by ascending score.
+    /// Operations are processed from the highest to the lowest fee per byte.
     fn eliminate_balance_overflows(&mut self, sender_balances: &PreHashMap<Address, Amount>) {
         let mut balance_cache = PreHashMap::default();
         let mut removed = PreHashSet::default();
-        self.sorted_ops.retain(|op_info| {
+        for cursor in &self.sorted_ops {
+            let op_info = self
+                .operations
+                .get(&cursor.id)
+                .expect("operation should be in operations at this point");
             let balance = balance_cache
                 .entry(op_info.creator_address)
                 .or_insert_with(|| {
@@ -204,124 +420,239 @@ impl OperationPool {
                         .unwrap_or_default()
                 });
             match balance.checked_sub(op_info.max_spending) {
-                Some(v) => {
-                    *balance = v;
-                    true
-                }
+                Some(v) => *balance = v,
                 None => {
                     removed.insert(op_info.id);
-                    false
                 }
             }
-        });
-        // drop from storage
-        self.storage.drop_operation_refs(&removed);
+        }
+        self.remove_operations(&removed, EvictionReason::Invalid);
     }
 
     /// Truncates the container to the max allowed size
     fn truncate_container(&mut self) {
-        if self.sorted_ops.len() > self.config.max_operation_pool_size {
-            let mut removed = PreHashSet::default();
-            for op_info in self
-                .sorted_ops
-                .iter()
-                .skip(self.config.max_operation_pool_size)
-            {
-                removed.insert(op_info.id);
+        self.evict_operations(self.config.max_operation_pool_size);
+        self.evict_over_memory_limit();
+    }
+
+    /// Iterate over the operations of the pool in the order they are evicted,
+    /// according to the configured eviction policy
+    fn eviction_order(&self) -> Box<dyn Iterator<Item = OperationId> + '_> {
+        match self.config.eviction_policy {
+            EvictionPolicy::LowestFee => {
+                Box::new(self.sorted_ops.iter().rev().map(|cursor| cursor.id))
             }
-            self.sorted_ops
-                .truncate(self.config.max_operation_pool_size);
-            // drop from storage
-            self.storage.drop_operation_refs(&removed);
+            EvictionPolicy::Oldest => Box::new(self.insertion_order.values().copied()),
         }
     }
 
-    /// Score the operations
-    fn score_operations(
-        &self,
-        _exec_statuses: &PreHashMap<OperationId, bool>,
-        pos_draws: &BTreeSet<Slot>,
-    ) -> PreHashMap<OperationId, f32> {
-        let now = MassaTime::now().expect("could not get current time");
-        let now_period = get_latest_block_slot_at_timestamp(
-            self.config.thread_count,
-            self.config.t0,
-            self.config.genesis_timestamp,
-            now,
-        )
-        .expect("could not get current slot")
-        .map_or(0, |s| s.period);
-
-        let mut scores = PreHashMap::with_capacity(self.sorted_ops.len());
-        for op_info in &self.sorted_ops {
-            // fee factor
-            // (we add 1 to still sort zero-fee ops)
-            let fee_factor = op_info.fee.to_raw().saturating_add(1) as f32;
-
-            // size score:
-            //    0% of block size => score 1
-            //    100% of block size => score 0
-            let size_score = 1.0 - (op_info.size as f32) / (self.config.max_block_size as f32);
-
-            // gas score:
-            //    0% of block gas => score 1
-            //    100% of block gas => score 0
-            let gas_score = 1.0 - (op_info.max_gas as f32) / (self.config.max_block_gas as f32);
-
-            // general resource score (mean of gas and size scores)
-            let epsilon_resource_factor = 0.0001; // avoids zero score when gas and size are a perfect fit in the block
-            let resource_factor = (epsilon_resource_factor + size_score + gas_score)
-                / (2.0 + epsilon_resource_factor);
-
-            // inclusion probability factor
-            //    If we are selected to produce a block in a long time,
-            //    there is exponential likelihood that someone includes the op before us.
-            let tau_inclusion = 2.0; // exponential decay factor
-            let earliest_inclusion_opportunity = pos_draws.iter().find_map(|s| {
-                if s.thread == op_info.thread
-                    && op_info.validity_period_range.contains(&s.period)
-                    && s.period >= now_period.saturating_sub(1)
-                {
-                    Some(s.period)
-                } else {
-                    None
-                }
-            });
-            let inclusion_factor =
-                if let Some(earliest_inclusion_opportunity) = earliest_inclusion_opportunity {
-                    // compute the number of slots other stakers have available to include the op before we do
-                    let foreign_opportunities = earliest_inclusion_opportunity.saturating_sub(max(
-                        now_period.saturating_add(1),
-                        *op_info.validity_period_range.start(),
-                    ));
-                    (-(foreign_opportunities as f32) / tau_inclusion).exp()
-                } else {
-                    // no inclusion opportunity => score 0
-                    0.0
-                };
+    /// Evict operations until at most `max_count` remain in the pool.
+    /// The evicted operations are chosen according to the configured eviction policy.
+    fn evict_operations(&mut self, max_count: usize) {
+        let excess = self.operations.len().saturating_sub(max_count);
+        if excess == 0 {
+            return;
+        }
+        let removed: PreHashSet<OperationId> = self.eviction_order().take(excess).collect();
+        for id in &removed {
+            debug!("evicting operation {} from the full operation pool", id);
+        }
+        self.remove_operations(&removed, EvictionReason::Capacity);
+    }
 
-            /* TODO: re-execution followup
-            // If the op was executed previously, there is still an exponentially decaying chance of its block being cancelled
-            // so that it can be reincluded.
-            // We approximate it with a constant factor for simplicity since we don't have the inclusion slot for now.
-            let reexecution_penalty = 1.0 / 1000.0; // re-execution penalty factor
-            let reexecution_factor = if exec_statuses.contains_key(&op_info.id) {
-                // executed previously
-                reexecution_penalty
-            } else {
-                // not executed previously => score 1
-                1.0
+    /// Check whether the pool is full and would evict all the new operations of `ops_storage`
+    /// as soon as they are added, each of them ranking below every operation of the pool.
+    /// Returns the number of operations of the pool and its size limit in that case.
+    ///
+    /// Only the `LowestFee` eviction policy ranks newcomers:
+    /// with `Oldest`, new operations always evict the first added ones.
+    pub(crate) fn check_capacity(&self, ops_storage: &Storage) -> Option<(usize, usize)> {
+        let limit = self.config.max_operation_pool_size;
+        if self.config.eviction_policy != EvictionPolicy::LowestFee || self.operations.len() < limit
+        {
+            return None;
+        }
+        let worst = self.sorted_ops.last()?;
+        let ops = ops_storage.read_operations();
+        let mut new_ops = ops_storage
+            .get_op_refs()
+            .iter()
+            .filter(|id| !self.operations.contains_key(*id))
+            .filter_map(|id| ops.get(id))
+            .peekable();
+        new_ops.peek()?;
+        let all_rejected = new_ops.all(|op| {
+            let cursor = OperationCursor {
+                fee: op.content.fee.to_raw(),
+                size: op.serialized_size(),
+                id: op.id,
             };
-            */
+            cursor > *worst
+        });
+        all_rejected.then_some((self.operations.len(), limit))
+    }
 
-            // compute the score as being the product of all the factors and the fee
-            let score = fee_factor * resource_factor * inclusion_factor;
-            //  * reexecution_factor; // TODO: re-execution followup
+    /// Evict operations while the estimated memory usage of the pool exceeds `memory_limit_bytes`.
+    /// The evicted operations are chosen according to the configured eviction policy.
+    fn evict_over_memory_limit(&mut self) {
+        let Some(memory_limit) = self.config.memory_limit_bytes else {
+            return;
+        };
+        let mut excess = (self.estimated_memory_usage() as u64).saturating_sub(memory_limit);
+        if excess == 0 {
+            return;
+        }
+        let mut removed: PreHashSet<OperationId> = Default::default();
+        for id in self.eviction_order() {
+            if excess == 0 {
+                break;
+            }
+            if let Some(op_info) = self.operations.get(&id) {
+                excess =
+                    excess.saturating_sub((op_info.size + OPERATION_INDEX_OVERHEAD_BYTES) as u64);
+                removed.insert(id);
+            }
+        }
+        debug!(
+            "evicting {} operations from the operation pool exceeding its memory limit of {} bytes",
+            removed.len(),
+            memory_limit
+        );
+        self.remove_operations(&removed, EvictionReason::Capacity);
+    }
 
-            // store the score
-            scores.insert(op_info.id, score);
+    /// Evict the lowest fee per byte operations of each of the given senders
+    /// that has more than `max_ops_per_sender` operations in the pool.
+    fn evict_sender_excess(&mut self, senders: &PreHashSet<Address>) {
+        let mut removed: PreHashSet<OperationId> = Default::default();
+        for sender in senders {
+            let Some(sender_ops) = self.ops_by_sender.get(sender) else {
+                continue;
+            };
+            let excess = sender_ops
+                .len()
+                .saturating_sub(self.config.max_ops_per_sender);
+            if excess == 0 {
+                continue;
+            }
+            let mut cursors: Vec<OperationCursor> = sender_ops
+                .iter()
+                .filter_map(|id| self.operations.get(id))
+                .map(|op_info| op_info.cursor())
+                .collect();
+            cursors.sort_unstable();
+            debug!(
+                "evicting {} operations of sender {} exceeding the per-sender limit",
+                excess, sender
+            );
+            removed.extend(cursors.iter().rev().take(excess).map(|cursor| cursor.id));
         }
-        scores
+        self.sender_limit_evicted
+            .fetch_add(removed.len() as u64, Ordering::Relaxed);
+        self.remove_operations(&removed, EvictionReason::Capacity);
+    }
+
+    /// Publish the number of operations of the pool to the metrics and to the pool sizes watchers
+    fn publish_operation_count(&self) {
+        let count = self.operations.len();
+        metrics::set_operation_count(count);
+        self.pool_sizes.send_if_modified(|(operation_count, _)| {
+            let modified = *operation_count != count;
+            *operation_count = count;
+            modified
+        });
+    }
+
+    /// Remove a set of operations from the pool for `reason` and drop them from storage.
+    /// Unknown IDs are ignored.
+    pub(crate) fn remove_operations(
+        &mut self,
+        ids: &PreHashSet<OperationId>,
+        reason: EvictionReason,
+    ) {
+        if ids.is_empty() {
+            return;
+        }
+        let mut evicted_count = 0;
+        for id in ids {
+            if let Some(op_info) = self.operations.remove(id) {
+                self.sorted_ops.remove(&op_info.cursor());
+                self.fingerprint ^= id_fingerprint(id);
+                self.insertion_order.remove(&op_info.insertion_index);
+                let expiry_slot = op_info.expiry_slot();
+                if let Some(expiring_ops) = self.ops_by_expiry.get_mut(&expiry_slot) {
+                    expiring_ops.remove(id);
+                    if expiring_ops.is_empty() {
+                        self.ops_by_expiry.remove(&expiry_slot);
+                    }
+                }
+                if let Some(count) = self.fee_counts.get_mut(&op_info.fee.to_raw()) {
+                    *count -= 1;
+                    if *count == 0 {
+                        self.fee_counts.remove(&op_info.fee.to_raw());
+                    }
+                }
+                if let Some(totals) = self.op_type_totals.get_mut(&op_info.op_type) {
+                    totals.count -= 1;
+                    totals.total_fee = totals.total_fee.saturating_sub(op_info.fee);
+                    totals.total_size -= op_info.size;
+                    if totals.count == 0 {
+                        self.op_type_totals.remove(&op_info.op_type);
+                    }
+                }
+                if let Some(sender_ops) = self.ops_by_sender.get_mut(&op_info.creator_address) {
+                    sender_ops.remove(id);
+                    if sender_ops.is_empty() {
+                        self.ops_by_sender.remove(&op_info.creator_address);
+                    }
+                }
+                self.memory_bytes.fetch_sub(op_info.size, Ordering::Relaxed);
+                self.estimated_memory.fetch_sub(
+                    op_info.size + OPERATION_INDEX_OVERHEAD_BYTES,
+                    Ordering::Relaxed,
+                );
+                self.total_evicted.fetch_add(1, Ordering::Relaxed);
+                self.event_subscribers
+                    .broadcast(PoolEvent::OperationEvicted { id: *id, reason });
+                evicted_count += 1;
+            }
+        }
+        metrics::inc_operations_evicted(evicted_count);
+        self.publish_operation_count();
+        // drop from storage
+        self.storage.drop_operation_refs(ids);
+        self.shards.remove_all(ids);
+        // forget the removed operations in the bloom filter
+        self.bloom_filter
+            .remove_lazily(evicted_count, self.operations.keys());
+    }
+
+    /// Remove all the operations paying a fee lower than `min_fee` from the pool.
+    /// Returns the number of removed operations.
+    pub(crate) fn prune_below_fee(&mut self, min_fee: Amount) -> usize {
+        let removed: PreHashSet<OperationId> = self
+            .operations
+            .values()
+            .filter(|op_info| op_info.fee < min_fee)
+            .map(|op_info| op_info.id)
+            .collect();
+        if !removed.is_empty() {
+            debug!(
+                "pruning {} operations paying less than {} from the operation pool",
+                removed.len(),
+                min_fee
+            );
+        }
+        self.remove_operations(&removed, EvictionReason::BelowMinFee);
+        removed.len()
+    }
+
+    /// Set the minimal fee of the operations accepted in the pool,
+    /// and remove the operations of the pool paying less.
+    /// Returns the number of removed operations.
+    pub(crate) fn set_min_fee(&mut self, min_fee: Amount) -> usize {
+        self.min_fee = min_fee;
+        self.prune_below_fee(min_fee)
     }
 
     /// Refresh the pool.
@@ -339,19 +670,6 @@ impl OperationPool {
         // pre-filter to eliminate obviously uninteresting ops
         self.prefilter_ops(&exec_statuses, &pos_draws, &sender_balances);
 
-        // score operations
-        let scores = self.score_operations(&exec_statuses, &pos_draws);
-
-        // sort by score
-        self.sorted_ops.sort_unstable_by(|op1, op2| {
-            // note1: scores are float => we need to use partial_cmp.
-            // note2: operands are reversed to sort from highest to lowest !
-            scores
-                .get(&op2.id)
-                .partial_cmp(&scores.get(&op1.id))
-                .unwrap_or(Ordering::Equal)
-        });
-
         // eliminate balance overflows in sorted ops
         self.eliminate_balance_overflows(&sender_balances);
 
@@ -361,15 +679,196 @@ impl OperationPool {
 
     /// Get the number of stored elements
     pub fn len(&self) -> usize {
-        self.sorted_ops.len()
+        self.operations.len()
+    }
+
+    /// Get the total serialized size of the stored operations
+    pub fn memory_bytes(&self) -> usize {
+        self.memory_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Get a conservative estimation of the memory held by the operations and the indexes of the pool
+    pub fn estimated_memory_usage(&self) -> usize {
+        self.estimated_memory.load(Ordering::Relaxed)
+    }
+
+    /// Get the number of operations added to the pool since startup
+    pub fn total_added(&self) -> u64 {
+        self.total_added.load(Ordering::Relaxed)
+    }
+
+    /// Get the number of operations removed from the pool since startup
+    pub fn total_evicted(&self) -> u64 {
+        self.total_evicted.load(Ordering::Relaxed)
+    }
+
+    /// Get the number of incoming operations skipped because they were already in the pool
+    pub fn duplicates_skipped(&self) -> u64 {
+        self.duplicates_skipped.load(Ordering::Relaxed)
+    }
+
+    /// Get the minimal fee of the operations accepted in the pool
+    #[cfg(any(test, feature = "testing"))]
+    pub(crate) fn min_fee(&self) -> Amount {
+        self.min_fee
+    }
+
+    /// Get the last consensus final periods, per thread
+    #[cfg(any(test, feature = "testing"))]
+    pub(crate) fn final_cs_periods(&self) -> &[u64] {
+        &self.last_cs_final_periods
+    }
+
+    /// Remove all the operations of the pool and reset its statistics.
+    /// The pool keeps its minimal fee, its subscribers and the indexes shared with the controller.
+    #[cfg(feature = "testing")]
+    pub(crate) fn clear(&mut self) {
+        let ids: PreHashSet<OperationId> = self.operations.keys().copied().collect();
+        self.remove_operations(&ids, EvictionReason::Purged);
+        self.compact_wal();
+        self.reset_statistics();
+    }
+
+    /// Remove all the operations of the pool and reset its statistics, e.g. after a hard fork.
+    /// The emptied indexes are replaced by fresh allocations, releasing their fragmented memory.
+    /// The pool keeps its minimal fee, its subscribers and the indexes shared with the controller.
+    pub(crate) fn purge_all(&mut self) {
+        info!(
+            "purging the operation pool: {} operations, {} bytes",
+            self.len(),
+            self.memory_bytes()
+        );
+        let ids: PreHashSet<OperationId> = self.operations.keys().copied().collect();
+        self.remove_operations(&ids, EvictionReason::Purged);
+        self.operations = PreHashMap::with_capacity(
+            self.config
+                .max_operation_pool_size
+                .saturating_add(self.config.max_operation_pool_excess_items),
+        );
+        self.sorted_ops = Default::default();
+        self.ops_by_expiry = Default::default();
+        self.fee_counts = Default::default();
+        self.ops_by_sender = Default::default();
+        self.insertion_order = Default::default();
+        self.next_insertion_index = 0;
+        self.ops_added_per_sender = Default::default();
+        self.ops_added_slot = None;
+        self.op_type_totals = Default::default();
+        self.compact_wal();
+        self.reset_statistics();
+    }
+
+    /// Reset the counters of the pool statistics
+    fn reset_statistics(&mut self) {
+        for counter in [
+            &self.total_added,
+            &self.total_evicted,
+            &self.duplicates_skipped,
+            &self.sender_limit_evicted,
+            &self.oversized_ops_dropped,
+            &self.type_filtered_ops_dropped,
+            &self.hot_tier_hits,
+            &self.hot_tier_misses,
+        ] {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Register a subscriber to the additions and evictions of operations
+    pub(crate) fn subscribe_to_events(&mut self, sender: SyncSender<PoolEvent>) {
+        self.event_subscribers.subscribe(sender);
+    }
+
+    /// Get the number of operations evicted because their sender exceeded `max_ops_per_sender`
+    pub fn sender_limit_evicted(&self) -> u64 {
+        self.sender_limit_evicted.load(Ordering::Relaxed)
+    }
+
+    /// Get the statistics of the operations of the pool, by operation type
+    pub fn operation_type_stats(&self) -> HashMap<OperationTypeId, OperationTypeStats> {
+        self.op_type_totals
+            .iter()
+            .map(|(op_type, totals)| (*op_type, totals.stats()))
+            .collect()
     }
 
-    /// Checks whether an element is stored in the pool.
-    pub fn contains(&self, id: &OperationId) -> bool {
-        self.storage.get_op_refs().contains(id)
+    /// Get the number of incoming operations dropped because their size exceeded `max_operation_size_bytes`
+    pub fn oversized_ops_dropped(&self) -> u64 {
+        self.oversized_ops_dropped.load(Ordering::Relaxed)
     }
 
-    /// notify of new final slot
+    /// Get the number of incoming operations dropped because their type is not accepted
+    pub fn type_filtered_ops_dropped(&self) -> u64 {
+        self.type_filtered_ops_dropped.load(Ordering::Relaxed)
+    }
+
+    /// Checks whether operations of type `op_type` are accepted in the pool.
+    /// `allowed_operation_types` takes precedence over `denied_operation_types`.
+    fn is_accepted_type(&self, op_type: &OperationTypeId) -> bool {
+        match (
+            &self.config.allowed_operation_types,
+            &self.config.denied_operation_types,
+        ) {
+            (Some(allowed), _) => allowed.contains(op_type),
+            (None, Some(denied)) => !denied.contains(op_type),
+            (None, None) => true,
+        }
+    }
+
+    /// Get the number of blocks filled from the `hot_tier_size` best operations only
+    pub fn hot_tier_hits(&self) -> u64 {
+        self.hot_tier_hits.load(Ordering::Relaxed)
+    }
+
+    /// Get the number of blocks filled with operations beyond the `hot_tier_size` best ones
+    pub fn hot_tier_misses(&self) -> u64 {
+        self.hot_tier_misses.load(Ordering::Relaxed)
+    }
+
+    /// Get the number of slots elapsed since an operation was added to the pool,
+    /// up to the last slot notified with `notify_new_slot`.
+    /// Returns `None` if the operation is not in the pool.
+    pub fn get_operation_age(&self, id: &OperationId) -> Option<u64> {
+        let op_info = self.operations.get(id)?;
+        let age = match (self.current_slot, op_info.insertion_slot) {
+            (Some(current_slot), Some(insertion_slot)) => current_slot
+                .slots_since(&insertion_slot, self.config.thread_count)
+                .unwrap_or(0),
+            _ => 0,
+        };
+        Some(age)
+    }
+
+    /// Get the IDs of the operations added to the pool more than `stale_after_slots` slots
+    /// before `current_slot`, from the oldest to the most recent.
+    /// The operations added before the first notified slot are considered added at that slot.
+    ///
+    /// Insertion slots only grow along the insertion order,
+    /// so only the stale operations and the first recent one are visited.
+    pub fn get_stale_operations(
+        &self,
+        current_slot: &Slot,
+        stale_after_slots: u64,
+    ) -> Vec<OperationId> {
+        self.insertion_order
+            .values()
+            .map_while(|id| {
+                // no operation has an insertion slot until a slot is notified
+                let insertion_slot = self
+                    .operations
+                    .get(id)
+                    .expect("operation should be in operations at this point")
+                    .insertion_slot?;
+                let age = current_slot
+                    .slots_since(&insertion_slot, self.config.thread_count)
+                    .unwrap_or(0);
+                (age > stale_after_slots).then_some(*id)
+            })
+            .collect()
+    }
+
+    /// notify of new final slot: drop the operations that cannot be included anymore,
+    /// their last valid period being final in their thread
     pub(crate) fn notify_final_cs_periods(&mut self, final_cs_periods: &[u64]) {
         // update internal final slot counter
         self.last_cs_final_periods = final_cs_periods.to_vec();
@@ -377,41 +876,280 @@ impl OperationPool {
             "notified of new final consensus periods: {:?}",
             self.last_cs_final_periods
         );
+
+        // only walk the expiry slots up to the highest final period
+        let Some(max_final_period) = self.last_cs_final_periods.iter().max() else {
+            return;
+        };
+        let last_slot = Slot::new(
+            *max_final_period,
+            self.config.thread_count.saturating_sub(1),
+        );
+        let removed: PreHashSet<OperationId> = self
+            .ops_by_expiry
+            .range(..=last_slot)
+            .filter(|(expiry_slot, _)| {
+                self.last_cs_final_periods
+                    .get(expiry_slot.thread as usize)
+                    .map_or(false, |final_period| expiry_slot.period <= *final_period)
+            })
+            .flat_map(|(_, op_ids)| op_ids.iter().copied())
+            .collect();
+        if removed.is_empty() {
+            return;
+        }
+        debug!(
+            "dropping {} operations expired in final periods",
+            removed.len()
+        );
+        self.remove_operations(&removed, EvictionReason::Expired);
+
+        // the pruned operations no longer need to be restored
+        self.compact_wal();
+    }
+
+    /// notify that a block became final: drop the operations it includes
+    /// and update the final period of its thread if the block is known
+    pub(crate) fn notify_block_finalized(&mut self, block_id: &BlockId, op_ids: &[OperationId]) {
+        self.remove_operations(&op_ids.iter().copied().collect(), EvictionReason::Included);
+        let block_slot = self
+            .storage
+            .read_blocks()
+            .get(block_id)
+            .map(|block| block.content.header.content.slot);
+        if let Some(slot) = block_slot {
+            let final_period = &mut self.last_cs_final_periods[slot.thread as usize];
+            *final_period = max(*final_period, slot.period);
+        }
+    }
+
+    /// Re-inject the operations of an orphaned block.
+    /// The block and its operations are read from `block_storage`,
+    /// operations that are not available anymore are ignored.
+    pub(crate) fn reinject_operations_from_block(
+        &mut self,
+        block_id: &BlockId,
+        block_storage: Storage,
+    ) {
+        let op_ids: PreHashSet<OperationId> = match block_storage.read_blocks().get(block_id) {
+            Some(block) => block.content.operations.iter().copied().collect(),
+            None => {
+                warn!(
+                    "could not re-inject operations of block {}: block not found in storage",
+                    block_id
+                );
+                return;
+            }
+        };
+        let mut ops_storage = block_storage.clone_without_refs();
+        let claimed_ops = ops_storage.claim_operation_refs(&op_ids);
+        debug!(
+            "re-injecting {} of the {} operations of orphaned block {}",
+            claimed_ops.len(),
+            op_ids.len(),
+            block_id
+        );
+        self.add_operations(ops_storage);
+    }
+
+    /// notify of the start of a new slot: drop the operations that expired
+    pub(crate) fn notify_new_slot(&mut self, slot: &Slot) {
+        // the operations added before the first notified slot are considered added at that slot
+        if self.current_slot.is_none() {
+            for op_info in self.operations.values_mut() {
+                op_info.insertion_slot = Some(*slot);
+            }
+        }
+        self.current_slot = Some(*slot);
+
+        let removed: PreHashSet<OperationId> = self
+            .ops_by_expiry
+            .range(..Slot::new(slot.period, 0))
+            .flat_map(|(_, op_ids)| op_ids.iter().copied())
+            .collect();
+        if !removed.is_empty() {
+            debug!(
+                "dropping {} expired operations at slot {}",
+                removed.len(),
+                slot
+            );
+        }
+        self.remove_operations(&removed, EvictionReason::Expired);
     }
 
-    /// Add a list of operations to the end of the pool.
-    /// They will be cleaned up at the next refresh.
+    /// Get the IDs of the operations whose signature is invalid, logging each of them.
+    /// The signatures are checked on the validation thread pool if there is one,
+    /// and on the calling thread otherwise.
+    fn find_invalid_signatures(&self, ops: Vec<&SecureShareOperation>) -> PreHashSet<OperationId> {
+        let check = |op: &SecureShareOperation| match op.verify_signature() {
+            Ok(()) => None,
+            Err(err) => {
+                debug!(
+                    "dropping operation {} with an invalid signature: {}",
+                    op.id, err
+                );
+                Some(op.id)
+            }
+        };
+        #[cfg(feature = "parallel")]
+        if let Some(thread_pool) = &self.validation_thread_pool {
+            let invalid_op_ids: Vec<OperationId> =
+                thread_pool.install(|| ops.into_par_iter().filter_map(check).collect());
+            return invalid_op_ids.into_iter().collect();
+        }
+        ops.into_iter().filter_map(check).collect()
+    }
+
+    /// Add a list of operations to the pool.
+    /// If the pool is full, operations are evicted according to the configured eviction policy.
+    /// The remaining operations will be cleaned up at the next refresh.
     pub(crate) fn add_operations(&mut self, mut ops_storage: Storage) {
-        // List all the new operations
-        let mut new_op_ids = ops_storage.get_op_refs() - self.storage.get_op_refs();
+        // List all the new operations, skipping the ones that are already in the pool.
+        // This check is exact and done under the pool write lock.
+        let incoming_op_ids = ops_storage.get_op_refs();
+        let mut new_op_ids: PreHashSet<OperationId> = incoming_op_ids
+            .iter()
+            .filter(|id| !self.operations.contains_key(*id))
+            .copied()
+            .collect();
+        let duplicates = incoming_op_ids.len() - new_op_ids.len();
+        if duplicates > 0 {
+            trace!("skipping {} operations already in the pool", duplicates);
+            self.duplicates_skipped
+                .fetch_add(duplicates as u64, Ordering::Relaxed);
+        }
 
-        // If there are too many extra operations,
-        // we don't want the container to fill up too much in-between refreshes so we drop any excess.
-        // This is because refreshing the container is very heavy and is only called periodically.
-        let dropped_items = self
-            .sorted_ops
-            .len()
-            .saturating_add(new_op_ids.len())
-            .saturating_sub(self.config.max_operation_pool_size)
-            .saturating_sub(self.config.max_operation_pool_excess_items);
-        for _ in 0..dropped_items {
-            if let Some(id) = new_op_ids.iter().next().copied() {
-                new_op_ids.remove(&id);
-            } else {
-                break;
+        // Drop the new operations that are too big.
+        {
+            let ops = ops_storage.read_operations();
+            let before_count = new_op_ids.len();
+            new_op_ids.retain(|id| {
+                ops.get(id).map_or(false, |op| {
+                    let size = op.serialized_size();
+                    if size > self.config.max_operation_size_bytes {
+                        debug!(
+                            "dropping operation {} of {} bytes, exceeding the max operation size of {} bytes",
+                            id, size, self.config.max_operation_size_bytes
+                        );
+                        return false;
+                    }
+                    true
+                })
+            });
+            self.oversized_ops_dropped
+                .fetch_add((before_count - new_op_ids.len()) as u64, Ordering::Relaxed);
+        }
+
+        // Drop the new operations whose type is not accepted.
+        if self.config.allowed_operation_types.is_some()
+            || self.config.denied_operation_types.is_some()
+        {
+            let ops = ops_storage.read_operations();
+            let before_count = new_op_ids.len();
+            new_op_ids.retain(|id| {
+                ops.get(id)
+                    .map_or(false, |op| self.is_accepted_type(&op.get_type_id()))
+            });
+            let type_filtered = before_count - new_op_ids.len();
+            if type_filtered > 0 {
+                debug!(
+                    "dropping {} operations whose type is not accepted in the pool",
+                    type_filtered
+                );
+                self.type_filtered_ops_dropped
+                    .fetch_add(type_filtered as u64, Ordering::Relaxed);
             }
         }
-        if dropped_items > 0 {
-            warn!(
-                "Operation pool excess limit reached. Dropping {} non-scored operations.",
-                dropped_items
+
+        // Skip the new operations paying less than the minimal fee.
+        if self.min_fee > Amount::zero() {
+            let ops = ops_storage.read_operations();
+            let before_count = new_op_ids.len();
+            new_op_ids.retain(|id| {
+                ops.get(id)
+                    .map_or(false, |op| op.content.fee >= self.min_fee)
+            });
+            let below_min_fee = before_count - new_op_ids.len();
+            if below_min_fee > 0 {
+                trace!(
+                    "skipping {} operations paying less than the minimal fee {}",
+                    below_min_fee,
+                    self.min_fee
+                );
+            }
+        }
+
+        // Drop the new operations with an invalid signature.
+        if self.config.validation_parallelism > 0 {
+            let invalid_op_ids = {
+                let ops = ops_storage.read_operations();
+                self.find_invalid_signatures(
+                    new_op_ids.iter().filter_map(|id| ops.get(id)).collect(),
+                )
+            };
+            new_op_ids.retain(|id| !invalid_op_ids.contains(id));
+        }
+
+        // Skip the new operations of the senders that already added
+        // `max_ops_added_per_sender_per_slot` operations during the current slot.
+        // The slot is read from the clock, so that the counts are reset
+        // whether or not the pool is notified of new slots,
+        // unless a more recent slot was notified.
+        {
+            let clock_slot = get_closest_slot_to_timestamp(
+                self.config.thread_count,
+                self.config.t0,
+                self.config.genesis_timestamp,
+                MassaTime::now().expect("could not get current time"),
             );
+            let slot = self
+                .current_slot
+                .map_or(clock_slot, |slot| slot.max(clock_slot));
+            if self.ops_added_slot != Some(slot) {
+                // the counts of the previous slot are dropped, releasing the entries of its senders
+                self.ops_added_slot = Some(slot);
+                self.ops_added_per_sender = Default::default();
+            }
+            let ops = ops_storage.read_operations();
+            let mut limited_senders: PreHashSet<Address> = Default::default();
+            new_op_ids.retain(|id| {
+                let Some(op) = ops.get(id) else {
+                    return false;
+                };
+                let added = self
+                    .ops_added_per_sender
+                    .entry(op.content_creator_address)
+                    .or_default();
+                if *added >= self.config.max_ops_added_per_sender_per_slot {
+                    limited_senders.insert(op.content_creator_address);
+                    return false;
+                }
+                *added += 1;
+                true
+            });
+            for sender in limited_senders {
+                warn!(
+                    "sender {} added {} operations at slot {}, skipping its other operations until the next slot",
+                    sender, self.config.max_ops_added_per_sender_per_slot, slot
+                );
+            }
+        }
+
+        // Log the new ops before adding them, so that they can be restored after a crash.
+        if let Some(wal) = self.wal.as_mut() {
+            let ops = ops_storage.read_operations();
+            if let Err(err) = wal.append(new_op_ids.iter().filter_map(|id| ops.get(id))) {
+                warn!(
+                    "could not log operations to the operation pool write-ahead log: {}",
+                    err
+                );
+            }
         }
 
         // Add the new ops to the container.
-        // Note that the added items are put at the end of the sorted ops
-        // so that they can still be picked for block production before refresh but with low priority
-        // because in that case we don't know anything about their quality.
+        // They are immediately sorted by fee per byte so that they can be picked for block production,
+        // but they will only be checked for execution status and sender balance at the next refresh.
+        let mut new_op_senders: PreHashSet<Address> = Default::default();
         {
             let ops = ops_storage.read_operations();
             for new_op_id in &new_op_ids {
@@ -426,12 +1164,20 @@ impl OperationPool {
                     }
                 }
 
-                self.sorted_ops.push(OperationInfo::from_op(
+                let op_info = OperationInfo::from_op(
                     op,
                     self.config.operation_validity_periods,
                     self.config.roll_price,
                     self.config.thread_count,
-                ));
+                    self.next_insertion_index,
+                    self.current_slot,
+                );
+                self.next_insertion_index += 1;
+                new_op_senders.insert(op_info.creator_address);
+                self.total_added.fetch_add(1, Ordering::Relaxed);
+                self.event_subscribers
+                    .broadcast(PoolEvent::OperationAdded(op_info.id));
+                self.index_operation(op_info);
             }
         }
 
@@ -446,26 +1192,219 @@ impl OperationPool {
             &new_op_ids,
             &Default::default(),
         ));
+
+        metrics::inc_operations_added(new_op_ids.len());
+
+        // cap the number of operations of the senders of the new operations
+        self.evict_sender_excess(&new_op_senders);
+
+        // we don't want the container to fill up in-between refreshes,
+        // so we immediately evict operations if the pool is full
+        self.evict_operations(self.config.max_operation_pool_size);
+        self.evict_over_memory_limit();
+        self.publish_operation_count();
+    }
+
+    /// Insert an operation in the container and in all the indexes of the pool
+    fn index_operation(&mut self, op_info: OperationInfo) {
+        self.bloom_filter.insert(&op_info.id);
+        self.shards.insert(op_info.id);
+        self.ops_by_expiry
+            .entry(op_info.expiry_slot())
+            .or_default()
+            .insert(op_info.id);
+        *self.fee_counts.entry(op_info.fee.to_raw()).or_default() += 1;
+        let totals = self.op_type_totals.entry(op_info.op_type).or_default();
+        totals.count += 1;
+        totals.total_fee = totals.total_fee.saturating_add(op_info.fee);
+        totals.total_size += op_info.size;
+        self.ops_by_sender
+            .entry(op_info.creator_address)
+            .or_default()
+            .insert(op_info.id);
+        self.sorted_ops.insert(op_info.cursor());
+        self.fingerprint ^= id_fingerprint(&op_info.id);
+        self.insertion_order
+            .insert(op_info.insertion_index, op_info.id);
+        self.memory_bytes.fetch_add(op_info.size, Ordering::Relaxed);
+        self.estimated_memory.fetch_add(
+            op_info.size + OPERATION_INDEX_OVERHEAD_BYTES,
+            Ordering::Relaxed,
+        );
+        self.operations.insert(op_info.id, op_info);
+    }
+
+    /// Rebuild the container and the indexes of the pool from the operations it holds in storage,
+    /// e.g. after a panic of the worker that may have left them inconsistent.
+    /// They keep their order of arrival, no event is broadcast and the statistics are kept.
+    pub(crate) fn rebuild_from_storage(&mut self) {
+        let op_infos: Vec<OperationInfo> = {
+            let ops = self.storage.read_operations();
+            self.storage
+                .get_op_refs()
+                .iter()
+                .filter_map(|id| ops.get(id))
+                .map(|op| {
+                    let previous = self.operations.get(&op.id);
+                    let insertion_index = match previous {
+                        Some(op_info) => op_info.insertion_index,
+                        None => {
+                            self.next_insertion_index += 1;
+                            self.next_insertion_index - 1
+                        }
+                    };
+                    OperationInfo::from_op(
+                        op,
+                        self.config.operation_validity_periods,
+                        self.config.roll_price,
+                        self.config.thread_count,
+                        insertion_index,
+                        previous.map_or(self.current_slot, |op_info| op_info.insertion_slot),
+                    )
+                })
+                .collect()
+        };
+        warn!(
+            "rebuilding the operation pool indexes from its {} stored operations",
+            op_infos.len()
+        );
+        self.operations = PreHashMap::with_capacity(
+            self.config
+                .max_operation_pool_size
+                .saturating_add(self.config.max_operation_pool_excess_items),
+        );
+        self.sorted_ops = Default::default();
+        self.ops_by_expiry = Default::default();
+        self.fee_counts = Default::default();
+        self.ops_by_sender = Default::default();
+        self.insertion_order = Default::default();
+        self.op_type_totals = Default::default();
+        self.fingerprint = 0;
+        self.memory_bytes.store(0, Ordering::Relaxed);
+        self.estimated_memory.store(0, Ordering::Relaxed);
+        self.shards.clear();
+        for op_info in op_infos {
+            self.index_operation(op_info);
+        }
+        self.bloom_filter.rebuild(self.operations.keys());
+        self.publish_operation_count();
     }
 
     /// get operations for block creation
     ///
     /// Searches the available operations, and selects the sub-set of operations that:
     /// - fit inside the block
+    /// - fit inside the block gas budget (`max_block_gas`)
     /// - is the most profitable for block producer
+    ///
+    /// The returned operations follow the configured `selection_strategy`:
+    /// by decreasing fee per byte by default, by order of arrival with `Fifo`.
     pub fn get_block_operations(&self, slot: &Slot) -> (Vec<OperationId>, Storage) {
-        // init list of selected operation IDs
-        let mut op_ids = Vec::new();
+        let (op_ids, res_storage, _) = self.get_block_operations_with_fill_info(slot);
+        (op_ids, res_storage)
+    }
+
+    /// Get the operations for block creation, along with how full the block is relative to its limits
+    pub fn get_block_operations_with_fill_info(
+        &self,
+        slot: &Slot,
+    ) -> (Vec<OperationId>, Storage, BlockFillInfo) {
+        let _timer = metrics::start_get_block_operations_timer();
+
+        let selection = self.select_block_operations(
+            slot,
+            self.config.max_operations_per_block as usize,
+            self.config.max_block_gas,
+        );
+        #[cfg(debug_assertions)]
+        if self.config.selection_strategy == OperationSelectionStrategy::FeeDescending {
+            self.check_block_selection_order(slot, &selection, self.config.max_block_gas);
+        }
+        let op_ids: Vec<OperationId> = selection
+            .operations
+            .iter()
+            .map(|op_info| op_info.id)
+            .collect();
+
+        // the hot tier is made of the best operations: it is only relevant when selecting by fee
+        if self.config.selection_strategy == OperationSelectionStrategy::FeeDescending {
+            match selection.last_selected_rank {
+                Some(rank) if rank >= self.config.hot_tier_size => {
+                    self.hot_tier_misses.fetch_add(1, Ordering::Relaxed)
+                }
+                Some(_) => self.hot_tier_hits.fetch_add(1, Ordering::Relaxed),
+                None => 0,
+            };
+        }
+
+        // generate storage
+        let mut res_storage = self.storage.clone_without_refs();
+        let claim_ops: PreHashSet<OperationId> = op_ids.iter().copied().collect();
+        let claimed_ops = res_storage.claim_operation_refs(&claim_ops);
+        if claimed_ops.len() != claim_ops.len() {
+            panic!("could not claim all operations from storage");
+        }
+
+        let fill_info = BlockFillInfo {
+            ops_count: op_ids.len(),
+            ops_limit: self.config.max_operations_per_block as usize,
+            gas_used: selection.gas_used,
+            gas_limit: self.config.max_block_gas,
+            size_bytes: selection.size_bytes,
+        };
+        (op_ids, res_storage, fill_info)
+    }
+
+    /// Get the total raw fee of the operations that would be selected for a block at `slot`
+    /// holding at most `max_ops` operations and `max_gas` gas.
+    ///
+    /// The selection is the one of `get_block_operations`, but no storage is claimed
+    /// and the hot tier statistics are left untouched.
+    pub fn simulate_block_fee_revenue(&self, slot: &Slot, max_ops: usize, max_gas: u64) -> u64 {
+        self.select_block_operations(slot, max_ops, max_gas)
+            .operations
+            .iter()
+            .fold(0u64, |total, op_info| {
+                total.saturating_add(op_info.fee.to_raw())
+            })
+    }
+
+    /// Select the operations of a block at `slot` holding at most `max_ops` operations,
+    /// `max_gas` gas and `max_block_size` bytes, see `get_block_operations`
+    fn select_block_operations(
+        &self,
+        slot: &Slot,
+        max_ops: usize,
+        max_gas: u64,
+    ) -> BlockSelection<'_> {
+        // init list of selected operations
+        let mut operations = Vec::new();
 
         // init remaining space
         let mut remaining_space = self.config.max_block_size as usize;
         // init remaining gas
-        let mut remaining_gas = self.config.max_block_gas;
+        let mut remaining_gas = max_gas;
         // init remaining number of operations
-        let mut remaining_ops = self.config.max_operations_per_block;
+        let mut remaining_ops = max_ops;
+
+        // iterate over pool operations in the order of the selection strategy
+        let candidates: Box<dyn Iterator<Item = &OperationId>> =
+            match self.config.selection_strategy {
+                // from best to worst
+                OperationSelectionStrategy::FeeDescending => {
+                    Box::new(self.sorted_ops.iter().map(|cursor| &cursor.id))
+                }
+                // from first added to last added
+                OperationSelectionStrategy::Fifo => Box::new(self.insertion_order.values()),
+            };
+        // rank of the last selected operation among the candidates
+        let mut last_selected_rank = None;
+        for (rank, id) in candidates.enumerate() {
+            let op_info = self
+                .operations
+                .get(id)
+                .expect("operation should be in operations at this point");
 
-        // iterate over pool operations in the right thread, from best to worst
-        for op_info in &self.sorted_ops {
             // if we have reached the maximum number of operations, stop
             if remaining_ops == 0 {
                 break;
@@ -476,7 +1415,7 @@ impl OperationPool {
                 continue;
             }
 
-            // exclude ops for which the block slot is outside of their validity range
+            // exclude ops for which the block slot is outside of their validity range (including expired ones)
             if !op_info.validity_period_range.contains(&slot.period) {
                 continue;
             }
@@ -492,7 +1431,8 @@ impl OperationPool {
             }
 
             // here we consider the operation as accepted
-            op_ids.push(op_info.id);
+            operations.push(op_info);
+            last_selected_rank = Some(rank);
 
             // update remaining block space
             remaining_space -= op_info.size;
@@ -504,14 +1444,376 @@ impl OperationPool {
             remaining_ops -= 1;
         }
 
-        // generate storage
+        BlockSelection {
+            operations,
+            last_selected_rank,
+            gas_used: max_gas - remaining_gas,
+            size_bytes: self.config.max_block_size as usize - remaining_space,
+        }
+    }
+
+    /// Check that a selection made by decreasing fee per byte has no priority inversion:
+    /// the selected operations are sorted, and each operation ranked above the worst selected one
+    /// was either selected or too large for the space and gas left by the better ones.
+    ///
+    /// The operations of the pool are read from `operations` rather than from `sorted_ops`,
+    /// so that an inconsistency of the sorted index is caught as well.
+    #[cfg(debug_assertions)]
+    fn check_block_selection_order(
+        &self,
+        slot: &Slot,
+        selection: &BlockSelection<'_>,
+        max_gas: u64,
+    ) {
+        let unsorted = selection
+            .operations
+            .windows(2)
+            .find(|pair| pair[0].cursor() > pair[1].cursor());
+        if let Some(pair) = unsorted {
+            tracing::error!(
+                "block operations not sorted by fee: {} (fee {}, size {}) selected before {} (fee {}, size {})",
+                pair[0].id,
+                pair[0].fee,
+                pair[0].size,
+                pair[1].id,
+                pair[1].fee,
+                pair[1].size
+            );
+        }
+        debug_assert!(unsorted.is_none(), "block operations not sorted by fee");
+
+        let Some(worst) = selection.operations.last() else {
+            return;
+        };
+        let worst_selected = worst.cursor();
+        let selected_ids: PreHashSet<OperationId> = selection
+            .operations
+            .iter()
+            .map(|op_info| op_info.id)
+            .collect();
+        // operations that could be included at this slot, ranked above the worst selected one
+        let mut skipped: Vec<&OperationInfo> = self
+            .operations
+            .values()
+            .filter(|op_info| {
+                op_info.thread == slot.thread
+                    && op_info.validity_period_range.contains(&slot.period)
+                    && !selected_ids.contains(&op_info.id)
+                    && op_info.cursor() < worst_selected
+            })
+            .collect();
+        skipped.sort_unstable_by_key(|op_info| op_info.cursor());
+
+        // walk the skipped and selected operations together, in rank order
+        let mut selected = selection.operations.iter().peekable();
+        let mut used_space = 0usize;
+        let mut used_gas = 0u64;
+        for op_info in skipped {
+            while let Some(better) = selected.next_if(|better| better.cursor() < op_info.cursor()) {
+                used_space += better.size;
+                used_gas += better.max_gas;
+            }
+            let fits = op_info.size
+                <= (self.config.max_block_size as usize).saturating_sub(used_space)
+                && op_info.max_gas <= max_gas.saturating_sub(used_gas);
+            if fits {
+                tracing::error!(
+                    "block operations priority inversion: {} (fee {}, size {}) skipped while {} (fee {}, size {}) selected",
+                    op_info.id,
+                    op_info.fee,
+                    op_info.size,
+                    worst.id,
+                    worst.fee,
+                    worst.size
+                );
+            }
+            debug_assert!(!fits, "block operations priority inversion");
+        }
+    }
+
+    /// Get the IDs of all the operations of the pool, in no particular order
+    pub fn operation_ids(&self) -> Vec<OperationId> {
+        self.operations.keys().copied().collect()
+    }
+
+    /// Get a storage containing the operations of the pool that match the given IDs.
+    /// IDs that are not in the pool, or whose operation is no longer stored, are ignored.
+    /// The operation index is only read-locked while the IDs are looked up, not while claiming.
+    pub fn get_operations_by_ids(&self, ids: &[OperationId]) -> Storage {
+        let claim_ops: PreHashSet<OperationId> = self
+            .storage
+            .batch_get_operations(ids)
+            .filter(|(id, op)| op.is_some() && self.operations.contains_key(id))
+            .map(|(id, _)| id)
+            .collect();
         let mut res_storage = self.storage.clone_without_refs();
-        let claim_ops: PreHashSet<OperationId> = op_ids.iter().copied().collect();
-        let claimed_ops = res_storage.claim_operation_refs(&claim_ops);
-        if claimed_ops.len() != claim_ops.len() {
-            panic!("could not claim all operations from storage");
+        res_storage.claim_operation_refs(&claim_ops);
+        res_storage
+    }
+
+    /// XOR of the IDs of all the operations of the pool, independent of their order
+    pub fn ids_xor(&self) -> [u8; 32] {
+        xor_ids(self.operations.keys())
+    }
+
+    /// Non-cryptographic fingerprint of the set of operations of the pool, independent of their order.
+    /// It is maintained on insertion and removal, so reading it is O(1).
+    /// Meant as a quick equality check between peers: use `ids_xor` when collisions matter.
+    pub fn incremental_pool_fingerprint(&self) -> u64 {
+        self.fingerprint
+    }
+
+    /// Get the raw fee at each of the given percentiles (0 to 100, higher values count as 100)
+    /// of the fees of the operations of the pool, using the nearest-rank method.
+    /// All the returned fees are 0 if the pool is empty.
+    ///
+    /// The fee distribution is maintained on insertion and removal,
+    /// so a query only walks the distinct fee values once.
+    ///
+    /// ```ignore
+    /// let fees = operation_pool.get_fee_percentiles(&[10, 50, 75, 90, 95, 99]);
+    /// ```
+    pub fn get_fee_percentiles(&self, percentiles: &[u8]) -> Vec<u64> {
+        let op_count = self.operations.len();
+        let mut res = vec![0u64; percentiles.len()];
+        if op_count == 0 {
+            return res;
+        }
+        // 1-based rank of the fee of each percentile, sorted by rank
+        let mut ranks: Vec<(usize, usize)> = percentiles
+            .iter()
+            .enumerate()
+            .map(|(position, percentile)| {
+                let percentile = (*percentile).min(100) as usize;
+                let rank = ((percentile * op_count + 99) / 100).max(1);
+                (rank, position)
+            })
+            .collect();
+        ranks.sort_unstable();
+        let mut ranks = ranks.into_iter().peekable();
+        let mut cumulative_count = 0;
+        for (fee, count) in &self.fee_counts {
+            cumulative_count += count;
+            while let Some((_, position)) = ranks.next_if(|(rank, _)| *rank <= cumulative_count) {
+                res[position] = *fee;
+            }
+        }
+        res
+    }
+
+    /// Get the operations of the pool whose last valid period is between the period of `current_slot`
+    /// and `periods` periods later, soonest expiring first, along with a storage containing them.
+    pub fn get_operations_expiring_within_periods(
+        &self,
+        current_slot: &Slot,
+        periods: u64,
+    ) -> (Vec<OperationId>, Storage) {
+        let first_slot = Slot::new(current_slot.period, 0);
+        let last_slot = Slot::new(
+            current_slot.period.saturating_add(periods),
+            self.config.thread_count.saturating_sub(1),
+        );
+        let op_ids: Vec<OperationId> = self
+            .ops_by_expiry
+            .range(first_slot..=last_slot)
+            .flat_map(|(_, op_ids)| op_ids.iter().copied())
+            .collect();
+        let mut res_storage = self.storage.clone_without_refs();
+        res_storage.claim_operation_refs(&op_ids.iter().copied().collect());
+        (op_ids, res_storage)
+    }
+
+    /// Get the operations of the pool whose validity window overlaps the slots from `from` to `to`
+    /// (both included), soonest expiring first, along with a storage containing them.
+    ///
+    /// The validity window of an operation spans its validity periods in its thread.
+    /// Operations expiring before `from` are skipped through the expiry index.
+    pub fn get_operations_for_slot_range(
+        &self,
+        from: &Slot,
+        to: &Slot,
+    ) -> (Vec<OperationId>, Storage) {
+        let op_ids: Vec<OperationId> = self
+            .ops_by_expiry
+            .range(from..)
+            .flat_map(|(_, op_ids)| op_ids.iter())
+            .filter(|id| {
+                self.operations.get(*id).map_or(false, |op_info| {
+                    Slot::new(*op_info.validity_period_range.start(), op_info.thread) <= *to
+                })
+            })
+            .copied()
+            .collect();
+        let mut res_storage = self.storage.clone_without_refs();
+        res_storage.claim_operation_refs(&op_ids.iter().copied().collect());
+        (op_ids, res_storage)
+    }
+
+    /// Get the operations of the pool sent by `address`, along with a storage containing them.
+    pub fn get_operations_by_sender(&self, address: &Address) -> (Vec<OperationId>, Storage) {
+        let op_ids: Vec<OperationId> = self
+            .ops_by_sender
+            .get(address)
+            .map(|sender_ops| sender_ops.iter().copied().collect())
+            .unwrap_or_default();
+        let mut res_storage = self.storage.clone_without_refs();
+        res_storage.claim_operation_refs(&op_ids.iter().copied().collect());
+        (op_ids, res_storage)
+    }
+
+    /// Get the `n` senders having the most operations in the pool, by decreasing operation count.
+    /// The senders are kept in a min-heap of size `n` while walking the sender index,
+    /// for O(sender_count * log(n)).
+    pub fn top_senders_by_operation_count(&self, n: usize) -> Vec<(Address, usize)> {
+        if n == 0 {
+            return Vec::new();
         }
+        let mut top: BinaryHeap<Reverse<(usize, Address)>> = BinaryHeap::with_capacity(n + 1);
+        for (address, sender_ops) in &self.ops_by_sender {
+            if sender_ops.is_empty() {
+                continue;
+            }
+            top.push(Reverse((sender_ops.len(), *address)));
+            if top.len() > n {
+                top.pop();
+            }
+        }
+        top.into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((count, address))| (address, count))
+            .collect()
+    }
 
+    /// Get a page of the operations of the pool, by decreasing fee per byte,
+    /// skipping the first `offset` operations and returning at most `limit` of them.
+    ///
+    /// The sorted index has no random access: the skipped operations are walked,
+    /// so the cost is O(offset + limit).
+    pub fn get_operations_sorted_by_fee(
+        &self,
+        limit: usize,
+        offset: usize,
+    ) -> (Vec<OperationId>, Storage) {
+        let op_ids: Vec<OperationId> = self
+            .sorted_ops
+            .iter()
+            .skip(offset)
+            .take(limit)
+            .map(|cursor| cursor.id)
+            .collect();
+        let mut res_storage = self.storage.clone_without_refs();
+        res_storage.claim_operation_refs(&op_ids.iter().copied().collect());
         (op_ids, res_storage)
     }
+
+    /// Get the IDs of the operations of page `page` of the pool, by decreasing fee per byte,
+    /// with `page_size` operations per page. Pages beyond the end of the pool are empty.
+    ///
+    /// Only the returned IDs are collected, but the operations of the previous pages are walked.
+    pub fn get_operation_ids_page(&self, page: usize, page_size: usize) -> Vec<OperationId> {
+        self.sorted_ops
+            .iter()
+            .skip(page.saturating_mul(page_size))
+            .take(page_size)
+            .map(|cursor| cursor.id)
+            .collect()
+    }
+
+    /// Iterate over the IDs of the operations of the pool paying at least `min_fee`,
+    /// by decreasing fee per byte.
+    ///
+    /// The sorted index is ordered by fee per byte and not by fee, so the operations paying less
+    /// are walked and skipped: reaching the end of the iterator costs O(pool size).
+    pub fn get_operations_above_fee(
+        &self,
+        min_fee: Amount,
+    ) -> impl Iterator<Item = OperationId> + '_ {
+        std::iter::successors(
+            self.next_operation_above_fee(min_fee, None),
+            move |cursor| self.next_operation_above_fee(min_fee, Some(cursor)),
+        )
+        .map(|cursor| cursor.id)
+    }
+
+    /// Get the first operation of the pool paying at least `min_fee`, by decreasing fee per byte,
+    /// among the ones sorted after `after`, or among all of them if it is not set.
+    pub(crate) fn next_operation_above_fee(
+        &self,
+        min_fee: Amount,
+        after: Option<&OperationCursor>,
+    ) -> Option<OperationCursor> {
+        let lower_bound = after.map_or(Bound::Unbounded, Bound::Excluded);
+        self.sorted_ops
+            .range((lower_bound, Bound::Unbounded))
+            .find(|cursor| cursor.fee >= min_fee.to_raw())
+            .copied()
+    }
+
+    /// Get a snapshot of all the operations of the pool, by decreasing fee per byte.
+    pub fn snapshot(&self) -> OperationPoolSnapshot {
+        let operation_ids: Vec<OperationId> =
+            self.sorted_ops.iter().map(|cursor| cursor.id).collect();
+        let mut storage = self.storage.clone_without_refs();
+        storage.claim_operation_refs(&operation_ids.iter().copied().collect());
+        OperationPoolSnapshot {
+            operation_ids,
+            storage,
+        }
+    }
+
+    /// Get information about the operations of the pool, best operations first
+    pub fn dump(&self) -> Vec<OperationDump> {
+        self.sorted_ops
+            .iter()
+            .filter_map(|cursor| self.operations.get(&cursor.id))
+            .map(|op_info| OperationDump {
+                id: op_info.id,
+                fee: op_info.fee,
+                insertion_index: op_info.insertion_index,
+                insertion_slot: op_info.insertion_slot,
+                expiry_slot: op_info.expiry_slot(),
+            })
+            .collect()
+    }
+
+    /// Get up to `max_count` operations of the pool that are not in `peer_known_ids`,
+    /// by decreasing fee per byte.
+    pub fn get_operations_for_propagation(
+        &self,
+        peer_known_ids: &[OperationId],
+        max_count: usize,
+    ) -> (Vec<OperationId>, Storage) {
+        let peer_known_ids: PreHashSet<OperationId> = peer_known_ids.iter().copied().collect();
+        let op_ids: Vec<OperationId> = self
+            .sorted_ops
+            .iter()
+            .map(|cursor| cursor.id)
+            .filter(|id| !peer_known_ids.contains(id))
+            .take(max_count)
+            .collect();
+        let mut res_storage = self.storage.clone_without_refs();
+        res_storage.claim_operation_refs(&op_ids.iter().copied().collect());
+        (op_ids, res_storage)
+    }
+}
+
+/// Build the threads checking the signatures of the incoming operations,
+/// if more than one is requested. A failure to spawn them is logged,
+/// the signatures then being checked on the operation pool worker thread.
+#[cfg(feature = "parallel")]
+fn build_validation_thread_pool(validation_parallelism: usize) -> Option<ThreadPool> {
+    if validation_parallelism <= 1 {
+        return None;
+    }
+    ThreadPoolBuilder::new()
+        .num_threads(validation_parallelism)
+        .thread_name(|index| format!("pool-validation-{}", index))
+        .build()
+        .map_err(|err| {
+            warn!(
+                "could not start the operation validation threads, signatures are checked on the pool worker thread: {}",
+                err
+            )
+        })
+        .ok()
 }
@@ -0,0 +1,127 @@
+//! Copyright (c) 2022 MASSA LABS <info@massa.net>
+
+//! Sharded index of the operation IDs of the pool, for membership queries
+//! that do not contend with the writes of the operation pool worker.
+
+use massa_models::{operation::OperationId, prehash::PreHashSet, secure_share::Id};
+use parking_lot::RwLock;
+#[cfg(feature = "parallel")]
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+/// Set of operation IDs split into independently locked shards.
+///
+/// An ID always belongs to the shard selected by the high bits of its hash,
+/// so a query only locks the shards of the IDs it asks for.
+pub struct OperationShards {
+    shards: Vec<RwLock<PreHashSet<OperationId>>>,
+}
+
+impl OperationShards {
+    /// Creates an empty index of `shard_count` shards
+    pub fn new(shard_count: usize) -> Self {
+        OperationShards {
+            shards: (0..shard_count.max(1))
+                .map(|_| RwLock::new(Default::default()))
+                .collect(),
+        }
+    }
+
+    /// Get the index of the shard an ID belongs to
+    fn shard_index(&self, id: &OperationId) -> usize {
+        let bytes = id.get_hash().to_bytes();
+        let high_bits = u16::from_be_bytes([bytes[0], bytes[1]]);
+        high_bits as usize % self.shards.len()
+    }
+
+    /// Group IDs by shard, keeping their position in the input.
+    /// Returns the non-empty groups along with the index of their shard.
+    fn group_by_shard<'a>(
+        &self,
+        ids: impl IntoIterator<Item = &'a OperationId>,
+    ) -> impl Iterator<Item = (usize, Vec<(usize, &'a OperationId)>)> {
+        let mut groups: Vec<Vec<(usize, &'a OperationId)>> = vec![Vec::new(); self.shards.len()];
+        for (position, id) in ids.into_iter().enumerate() {
+            groups[self.shard_index(id)].push((position, id));
+        }
+        groups
+            .into_iter()
+            .enumerate()
+            .filter(|(_, group)| !group.is_empty())
+    }
+
+    /// Adds an ID to the index
+    pub fn insert(&self, id: OperationId) {
+        self.shards[self.shard_index(&id)].write().insert(id);
+    }
+
+    /// Removes all the IDs from the index
+    pub fn clear(&self) {
+        for shard in &self.shards {
+            shard.write().clear();
+        }
+    }
+
+    /// Removes a set of IDs from the index, locking each involved shard once
+    pub fn remove_all(&self, ids: &PreHashSet<OperationId>) {
+        for (shard_index, group) in self.group_by_shard(ids) {
+            let mut shard = self.shards[shard_index].write();
+            for (_, id) in group {
+                shard.remove(id);
+            }
+        }
+    }
+
+    /// Checks whether all the IDs are in the index, stopping at the first missing one.
+    /// Each involved shard is locked at most once.
+    pub fn contains_every(&self, ids: &[OperationId]) -> bool {
+        self.group_by_shard(ids).all(|(shard_index, group)| {
+            let shard = self.shards[shard_index].read();
+            group.into_iter().all(|(_, id)| shard.contains(id))
+        })
+    }
+
+    /// Checks whether at least one of the IDs is in the index, stopping at the first present one.
+    /// Each involved shard is locked at most once.
+    pub fn contains_any(&self, ids: &[OperationId]) -> bool {
+        self.group_by_shard(ids).any(|(shard_index, group)| {
+            let shard = self.shards[shard_index].read();
+            group.into_iter().any(|(_, id)| shard.contains(id))
+        })
+    }
+
+    /// Returns one boolean per ID, telling whether it is in the index.
+    /// Each involved shard is locked once.
+    #[cfg(not(feature = "parallel"))]
+    pub fn contains_all(&self, ids: &[OperationId]) -> Vec<bool> {
+        let mut res = vec![false; ids.len()];
+        for (shard_index, group) in self.group_by_shard(ids) {
+            let shard = self.shards[shard_index].read();
+            for (position, id) in group {
+                res[position] = shard.contains(id);
+            }
+        }
+        res
+    }
+
+    /// Returns one boolean per ID, telling whether it is in the index.
+    /// Each involved shard is locked once, the shards being queried in parallel.
+    #[cfg(feature = "parallel")]
+    pub fn contains_all(&self, ids: &[OperationId]) -> Vec<bool> {
+        let groups: Vec<_> = self.group_by_shard(ids).collect();
+        let found: Vec<Vec<(usize, bool)>> = groups
+            .into_par_iter()
+            .map(|(shard_index, group)| {
+                let shard = self.shards[shard_index].read();
+                group
+                    .into_iter()
+                    .map(|(position, id)| (position, shard.contains(id)))
+                    .collect()
+            })
+            .collect();
+        let mut res = vec![false; ids.len()];
+        for (position, present) in found.into_iter().flatten() {
+            res[position] = present;
+        }
+        res
+    }
+}
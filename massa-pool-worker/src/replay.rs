@@ -0,0 +1,229 @@
+//! Copyright (c) 2022 MASSA LABS <info@massa.net>
+
+//! Offline replay of the event log through the real operation and endorsement pools,
+//! on the calling thread and without any channel or worker thread:
+//! the rebuilt pools only depend on the logged commands and the pool configuration.
+
+use crate::{
+    endorsement_pool::EndorsementPool,
+    event_log::{EventLogEntry, LoggedCommand, LoggedPool},
+    operation_pool::OperationPool,
+};
+use massa_execution_exports::MockExecutionController;
+use massa_models::{
+    address::Address,
+    amount::Amount,
+    prehash::{PreHashMap, PreHashSet},
+    slot::Slot,
+};
+use massa_pool_exports::{EvictionReason, PoolChannels, PoolConfig};
+use massa_pos_exports::{MockSelectorController, PosError, Selection};
+use massa_signature::KeyPair;
+use massa_storage::Storage;
+use massa_wallet::test_exports::create_test_wallet;
+use parking_lot::{Mutex, RwLock};
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::{broadcast, watch};
+
+/// Channels of a replayed pool, whose execution controller is never queried
+fn replay_channels(selector: MockSelectorController) -> PoolChannels {
+    PoolChannels {
+        execution_controller: Box::new(MockExecutionController::new()),
+        endorsement_sender: broadcast::channel(2000).0,
+        operation_sender: broadcast::channel(5000).0,
+        selector: Box::new(selector),
+    }
+}
+
+impl OperationPool {
+    /// Build an operation pool by applying `commands` in order on the calling thread,
+    /// without any channel or worker thread: the resulting pool only depends on the commands.
+    ///
+    /// The commands are the ones of the event log. Endorsement pool commands are ignored,
+    /// and re-injected operations are added like new ones.
+    /// The pool is never refreshed, so the execution and selector controllers are never queried.
+    pub(crate) fn replay_from_commands(
+        config: PoolConfig,
+        commands: Vec<LoggedCommand>,
+    ) -> OperationPool {
+        let storage = Storage::create_root();
+        let mut pool = OperationPool::init(
+            config,
+            &storage,
+            replay_channels(MockSelectorController::new()),
+            Arc::new(RwLock::new(create_test_wallet(None))),
+            Arc::new(watch::channel((0, 0)).0),
+        );
+        for command in commands {
+            match command {
+                LoggedCommand::AddOperations(operations)
+                | LoggedCommand::ReInjectOperations { operations, .. } => {
+                    let mut ops_storage = storage.clone_without_refs();
+                    ops_storage.store_operations(operations);
+                    pool.add_operations(ops_storage);
+                }
+                LoggedCommand::RemoveOperations(ids) => pool.remove_operations(
+                    &ids.into_iter().collect::<PreHashSet<_>>(),
+                    EvictionReason::Included,
+                ),
+                LoggedCommand::NotifyFinalCsPeriods(final_cs_periods) => {
+                    pool.notify_final_cs_periods(&final_cs_periods)
+                }
+                LoggedCommand::NotifyNewSlot(slot) => pool.notify_new_slot(&slot),
+                LoggedCommand::BlockFinalized {
+                    block_id,
+                    operation_ids,
+                    ..
+                } => pool.notify_block_finalized(&block_id, &operation_ids),
+                LoggedCommand::SetMinFee(min_fee) => {
+                    pool.set_min_fee(min_fee);
+                }
+                LoggedCommand::PurgeAll => pool.purge_all(),
+                LoggedCommand::AddEndorsements(_) | LoggedCommand::RemoveEndorsements(_) => {}
+            }
+        }
+        pool
+    }
+}
+
+impl EndorsementPool {
+    /// Build an endorsement pool by applying `commands` in order on the calling thread,
+    /// without any channel or worker thread: the resulting pool only depends on the commands.
+    ///
+    /// The commands are the ones of the event log. Operation pool commands are ignored.
+    /// The log does not record the PoS draws, so each added endorsement is considered drawn
+    /// at its index, for a block producer of the wallet of the pool:
+    /// of several endorsements of a batch at the same slot and index, only the last one is kept.
+    pub(crate) fn replay_from_commands(
+        config: PoolConfig,
+        commands: Vec<LoggedCommand>,
+    ) -> EndorsementPool {
+        let keypair = KeyPair::generate(0).expect("could not generate the replay key pair");
+        let producer = Address::from_public_key(&keypair.get_public_key());
+        let mut addresses = PreHashMap::default();
+        addresses.insert(producer, keypair);
+
+        let draws: Arc<Mutex<HashMap<Slot, Selection>>> = Default::default();
+        let mut selector = MockSelectorController::new();
+        let selector_draws = draws.clone();
+        selector.expect_get_selection().returning(move |slot| {
+            selector_draws
+                .lock()
+                .get(&slot)
+                .cloned()
+                .ok_or_else(|| PosError::ContainerInconsistency(format!("no draw at {}", slot)))
+        });
+
+        let endorsement_count = config.max_block_endorsement_count as usize;
+        let storage = Storage::create_root();
+        let mut pool = EndorsementPool::init(
+            config,
+            &storage,
+            replay_channels(selector),
+            Arc::new(RwLock::new(create_test_wallet(Some(addresses)))),
+            Arc::new(watch::channel((0, 0)).0),
+        );
+        for command in commands {
+            match command {
+                LoggedCommand::AddEndorsements(endorsements) => {
+                    {
+                        let mut draws = draws.lock();
+                        for endo in &endorsements {
+                            let selection =
+                                draws.entry(endo.content.slot).or_insert_with(|| Selection {
+                                    endorsements: vec![producer; endorsement_count],
+                                    producer,
+                                });
+                            if let Some(endorser) =
+                                selection.endorsements.get_mut(endo.content.index as usize)
+                            {
+                                *endorser = endo.content_creator_address;
+                            }
+                        }
+                    }
+                    let mut endo_storage = storage.clone_without_refs();
+                    endo_storage.store_endorsements(endorsements);
+                    pool.add_endorsements(endo_storage);
+                }
+                LoggedCommand::RemoveEndorsements(ids) => {
+                    pool.remove_endorsements(&ids, EvictionReason::Included);
+                }
+                LoggedCommand::NotifyFinalCsPeriods(final_cs_periods) => {
+                    pool.notify_final_cs_periods(&final_cs_periods)
+                }
+                LoggedCommand::NotifyNewSlot(slot) => pool.notify_new_slot(&slot),
+                LoggedCommand::BlockFinalized {
+                    block_id,
+                    endorsement_ids,
+                    ..
+                } => pool.notify_block_finalized(&block_id, &endorsement_ids),
+                LoggedCommand::PurgeAll => pool.purge_all(),
+                LoggedCommand::AddOperations(_)
+                | LoggedCommand::ReInjectOperations { .. }
+                | LoggedCommand::RemoveOperations(_)
+                | LoggedCommand::SetMinFee(_) => {}
+            }
+        }
+        pool
+    }
+}
+
+/// Operation and endorsement pools rebuilt by replaying an event log
+pub struct ReplayedPools {
+    operations: OperationPool,
+    endorsements: EndorsementPool,
+}
+
+impl ReplayedPools {
+    /// Replay `entries` in order, each pool receiving the commands its worker processed.
+    ///
+    /// The periodic refreshes of the operation pool are not logged, so the evictions they decide
+    /// (sender balances, executed operations) are not replayed.
+    pub fn replay(config: PoolConfig, entries: Vec<EventLogEntry>) -> Self {
+        let (operation_commands, endorsement_commands) = entries.into_iter().fold(
+            (Vec::new(), Vec::new()),
+            |(mut operation_commands, mut endorsement_commands), entry| {
+                match entry.pool {
+                    LoggedPool::Operations => operation_commands.push(entry.command),
+                    LoggedPool::Endorsements => endorsement_commands.push(entry.command),
+                }
+                (operation_commands, endorsement_commands)
+            },
+        );
+        ReplayedPools {
+            operations: OperationPool::replay_from_commands(config.clone(), operation_commands),
+            endorsements: EndorsementPool::replay_from_commands(config, endorsement_commands),
+        }
+    }
+
+    /// Get the number of operations in the operation pool
+    pub fn operation_count(&self) -> usize {
+        self.operations.len()
+    }
+
+    /// Get the sum of the fees of the operations in the operation pool
+    pub fn total_operation_fees(&self) -> Amount {
+        let (ids, storage) = self.operations.get_operations_sorted_by_fee(usize::MAX, 0);
+        let ops = storage.read_operations();
+        ids.iter()
+            .filter_map(|id| ops.get(id))
+            .fold(Amount::zero(), |total, op| {
+                total.saturating_add(op.content.fee)
+            })
+    }
+
+    /// Get the minimal fee of the operations accepted in the operation pool
+    pub fn min_fee(&self) -> Amount {
+        self.operations.min_fee()
+    }
+
+    /// Get the last final consensus periods of the operation pool, per thread
+    pub fn final_cs_periods(&self) -> &[u64] {
+        self.operations.final_cs_periods()
+    }
+
+    /// Get the number of endorsements in the endorsement pool
+    pub fn endorsement_count(&self) -> usize {
+        self.endorsements.len()
+    }
+}
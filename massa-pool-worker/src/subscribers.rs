@@ -0,0 +1,32 @@
+//! Copyright (c) 2022 MASSA LABS <info@massa.net>
+
+//! Subscribers to the pool events
+
+use massa_pool_exports::PoolEvent;
+use std::sync::mpsc::SyncSender;
+use tracing::debug;
+
+/// List of the channels a pool sends its events to.
+/// Subscribers whose channel is full or disconnected are dropped, so that they never block the pool.
+#[derive(Default)]
+pub struct PoolEventSubscribers {
+    senders: Vec<SyncSender<PoolEvent>>,
+}
+
+impl PoolEventSubscribers {
+    /// Registers a new subscriber
+    pub fn subscribe(&mut self, sender: SyncSender<PoolEvent>) {
+        self.senders.push(sender);
+    }
+
+    /// Sends an event to all the subscribers, dropping the ones that cannot receive it
+    pub fn broadcast(&mut self, event: PoolEvent) {
+        self.senders.retain(|sender| match sender.try_send(event) {
+            Ok(()) => true,
+            Err(err) => {
+                debug!("dropping pool event subscriber: {}", err);
+                false
+            }
+        });
+    }
+}
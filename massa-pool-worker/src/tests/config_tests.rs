@@ -0,0 +1,279 @@
+// Copyright (c) 2022 MASSA LABS <info@massa.net>
+//! # Pool configuration tests
+//! Check that inconsistent configurations are rejected with the right error,
+//! and that the pool refuses to start with them.
+
+use std::sync::Arc;
+
+use massa_execution_exports::MockExecutionController;
+use massa_pool_exports::{PoolChannels, PoolConfig, PoolConfigBuilder, PoolConfigError};
+use massa_pos_exports::MockSelectorController;
+use massa_storage::Storage;
+use massa_time::MassaTime;
+use massa_wallet::test_exports::create_test_wallet;
+use parking_lot::RwLock;
+use tokio::sync::broadcast;
+
+use crate::start_pool_controller;
+
+#[test]
+fn test_default_config_is_valid() {
+    assert_eq!(PoolConfig::default().validate(), Ok(()));
+}
+
+#[test]
+fn test_zero_thread_count() {
+    let config = PoolConfig {
+        thread_count: 0,
+        ..Default::default()
+    };
+    assert_eq!(config.validate(), Err(PoolConfigError::ZeroThreadCount));
+}
+
+#[test]
+fn test_zero_channel_size() {
+    let configs = [
+        (
+            PoolConfig {
+                operations_channel_size: 0,
+                ..Default::default()
+            },
+            "operations",
+        ),
+        (
+            PoolConfig {
+                endorsements_channel_size: 0,
+                ..Default::default()
+            },
+            "endorsements",
+        ),
+        (
+            PoolConfig {
+                denunciations_channel_size: 0,
+                ..Default::default()
+            },
+            "denunciations",
+        ),
+    ];
+    for (config, channel) in configs {
+        assert_eq!(
+            config.validate(),
+            Err(PoolConfigError::ZeroChannelSize(channel))
+        );
+    }
+}
+
+#[test]
+fn test_zero_bloom_filter_size() {
+    let config = PoolConfig {
+        operation_bloom_filter_size: 0,
+        ..Default::default()
+    };
+    assert_eq!(
+        config.validate(),
+        Err(PoolConfigError::ZeroBloomFilterSize("operation"))
+    );
+    let config = PoolConfig {
+        endorsement_bloom_filter_size: 0,
+        ..Default::default()
+    };
+    assert_eq!(
+        config.validate(),
+        Err(PoolConfigError::ZeroBloomFilterSize("endorsement"))
+    );
+}
+
+#[test]
+fn test_zero_max_ops_per_sender() {
+    let config = PoolConfig {
+        max_ops_per_sender: 0,
+        ..Default::default()
+    };
+    assert_eq!(config.validate(), Err(PoolConfigError::ZeroMaxOpsPerSender));
+}
+
+#[test]
+fn test_zero_max_ops_added_per_sender_per_slot() {
+    let config = PoolConfig {
+        max_ops_added_per_sender_per_slot: 0,
+        ..Default::default()
+    };
+    assert_eq!(
+        config.validate(),
+        Err(PoolConfigError::ZeroMaxOpsAddedPerSenderPerSlot)
+    );
+}
+
+#[test]
+fn test_zero_max_operation_size() {
+    let config = PoolConfig {
+        max_operation_size_bytes: 0,
+        ..Default::default()
+    };
+    assert_eq!(
+        config.validate(),
+        Err(PoolConfigError::ZeroMaxOperationSize)
+    );
+}
+
+#[test]
+fn test_zero_shard_count() {
+    let config = PoolConfig {
+        operation_pool_shard_count: 0,
+        ..Default::default()
+    };
+    assert_eq!(config.validate(), Err(PoolConfigError::ZeroShardCount));
+}
+
+#[test]
+fn test_invalid_false_positive_rate() {
+    for rate in [0.0, 1.0, -0.5, f64::NAN] {
+        let config = PoolConfig {
+            bloom_filter_false_positive_rate: rate,
+            ..Default::default()
+        };
+        assert!(matches!(
+            config.validate(),
+            Err(PoolConfigError::InvalidFalsePositiveRate(_))
+        ));
+    }
+}
+
+#[test]
+fn test_zero_refresh_interval() {
+    let config = PoolConfig {
+        operation_pool_refresh_interval: MassaTime::from_millis(0),
+        ..Default::default()
+    };
+    assert_eq!(config.validate(), Err(PoolConfigError::ZeroRefreshInterval));
+}
+
+#[test]
+fn test_operation_pool_too_small() {
+    let config = PoolConfig {
+        max_operation_pool_size: 10,
+        max_operations_per_block: 11,
+        ..Default::default()
+    };
+    assert_eq!(
+        config.validate(),
+        Err(PoolConfigError::OperationPoolTooSmall {
+            max_operation_pool_size: 10,
+            max_operations_per_block: 11,
+        })
+    );
+}
+
+#[test]
+fn test_endorsement_pool_too_small() {
+    let config = PoolConfig {
+        max_endorsements_pool_size_per_thread: 1,
+        max_block_endorsement_count: 2,
+        ..Default::default()
+    };
+    assert_eq!(
+        config.validate(),
+        Err(PoolConfigError::EndorsementPoolTooSmall {
+            max_endorsements_pool_size_per_thread: 1,
+            max_block_endorsement_count: 2,
+        })
+    );
+}
+
+#[test]
+fn test_unsorted_histogram_buckets() {
+    for buckets in [vec![0, 2, 1], vec![0, 1, 1]] {
+        let config = PoolConfig {
+            endorsement_age_histogram_buckets: buckets,
+            ..Default::default()
+        };
+        assert_eq!(
+            config.validate(),
+            Err(PoolConfigError::UnsortedHistogramBuckets)
+        );
+    }
+}
+
+#[test]
+fn test_invalid_backpressure_warn_threshold() {
+    for threshold in [-0.1, 1.5, f32::NAN] {
+        let config = PoolConfig {
+            backpressure_warn_threshold: threshold,
+            ..Default::default()
+        };
+        assert!(matches!(
+            config.validate(),
+            Err(PoolConfigError::InvalidBackpressureWarnThreshold(_))
+        ));
+    }
+    for threshold in [0.0, 0.8, 1.0] {
+        let config = PoolConfig {
+            backpressure_warn_threshold: threshold,
+            ..Default::default()
+        };
+        assert_eq!(config.validate(), Ok(()));
+    }
+}
+
+/// The builder sets the given fields and keeps the others
+#[test]
+fn test_builder_sets_fields() {
+    let config = PoolConfigBuilder::default()
+        .with_max_ops_per_sender(3)
+        .with_operation_pool_shard_count(2)
+        .build()
+        .unwrap();
+    assert_eq!(config.max_ops_per_sender, 3);
+    assert_eq!(config.operation_pool_shard_count, 2);
+    assert_eq!(config.thread_count, PoolConfig::default().thread_count);
+}
+
+/// The builder rejects the configurations that do not validate
+#[test]
+fn test_builder_validates() {
+    assert_eq!(
+        PoolConfigBuilder::default()
+            .with_thread_count(0)
+            .build()
+            .err(),
+        Some(PoolConfigError::ZeroThreadCount)
+    );
+    assert_eq!(
+        PoolConfigBuilder::default()
+            .with_max_operation_pool_size(10)
+            .with_max_operations_per_block(11)
+            .build()
+            .err(),
+        Some(PoolConfigError::OperationPoolTooSmall {
+            max_operation_pool_size: 10,
+            max_operations_per_block: 11,
+        })
+    );
+}
+
+/// The pool does not start with an invalid configuration
+#[test]
+fn test_start_with_invalid_config() {
+    let config = PoolConfig {
+        operations_channel_size: 0,
+        ..Default::default()
+    };
+    let storage = Storage::create_root();
+    let wallet = Arc::new(RwLock::new(create_test_wallet(None)));
+    // no expectations: the mocks must not be used when the configuration is rejected
+    let res = start_pool_controller(
+        config,
+        &storage,
+        PoolChannels {
+            execution_controller: Box::new(MockExecutionController::new()),
+            endorsement_sender: broadcast::channel(1).0,
+            operation_sender: broadcast::channel(1).0,
+            selector: Box::new(MockSelectorController::new()),
+        },
+        wallet,
+    );
+    assert!(matches!(
+        res.err(),
+        Some(PoolConfigError::ZeroChannelSize("operations"))
+    ));
+}
@@ -0,0 +1,1192 @@
+// Copyright (c) 2022 MASSA LABS <info@massa.net>
+//! # Endorsement pool units tests
+//! Units tests scenarios that add endorsements to the pool through the
+//! controller API and check what the pool returns.
+
+use crate::{EventLogEntry, LoggedCommand, LoggedPool, ReplayedPools};
+use massa_hash::Hash;
+use massa_models::{
+    address::Address,
+    block_id::BlockId,
+    config::{ENDORSEMENT_COUNT, T0, THREAD_COUNT},
+    endorsement::{EndorsementId, SecureShareEndorsement},
+    slot::Slot,
+};
+use massa_pool_exports::{
+    EvictionReason, PoolConfig, PoolConfigBuilder, PoolController, PoolError, PoolEvent, PoolKind,
+};
+use massa_signature::KeyPair;
+use massa_storage::Storage;
+use massa_time::MassaTime;
+
+use super::tools::{
+    create_endorsement_with, create_some_operations, default_execution_controller,
+    endorsement_selector_controller, endorsers_selector_controller, OpGenerator,
+    PoolTestBoilerPlate,
+};
+
+/// Start a pool whose wallet holds the returned keypair, selected for every endorsement
+fn start_endorsement_pool(config: PoolConfig) -> (PoolTestBoilerPlate, KeyPair) {
+    let keypair = KeyPair::generate(0).unwrap();
+    let address = Address::from_public_key(&keypair.get_public_key());
+    let boilerplate = PoolTestBoilerPlate::pool_test_with_wallet(
+        config,
+        keypair.clone(),
+        default_execution_controller(),
+        endorsement_selector_controller(address),
+    );
+    (boilerplate, keypair)
+}
+
+/// Add endorsements of `endorsed_block` at `slot` for each of the given indices
+fn add_test_endorsements(
+    pool_controller: &mut dyn PoolController,
+    storage: &Storage,
+    keypair: &KeyPair,
+    slot: Slot,
+    endorsed_block: BlockId,
+    indices: &[u32],
+) -> Vec<EndorsementId> {
+    let endorsements: Vec<_> = indices
+        .iter()
+        .map(|index| create_endorsement_with(keypair, slot, *index, endorsed_block))
+        .collect();
+    let ids = endorsements.iter().map(|endo| endo.id).collect();
+    let mut endo_storage = storage.clone_without_refs();
+    endo_storage.store_endorsements(endorsements);
+    pool_controller.add_endorsements(endo_storage).unwrap();
+    ids
+}
+
+/// Get endorsements by IDs, some of the requested IDs being absent from the pool
+#[test]
+fn test_get_endorsements_by_ids() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let address = Address::from_public_key(&keypair.get_public_key());
+    let PoolTestBoilerPlate {
+        mut pool_manager,
+        mut pool_controller,
+        storage,
+    } = PoolTestBoilerPlate::pool_test_with_wallet(
+        PoolConfig::default(),
+        keypair.clone(),
+        default_execution_controller(),
+        endorsement_selector_controller(address),
+    );
+
+    let slot = Slot::new(1, 0);
+    let endorsed_block = BlockId::generate_from_hash(Hash::compute_from("block".as_bytes()));
+    let endorsements: Vec<_> = (0..ENDORSEMENT_COUNT)
+        .map(|index| create_endorsement_with(&keypair, slot, index, endorsed_block))
+        .collect();
+    let mut endo_storage = storage.clone_without_refs();
+    endo_storage.store_endorsements(endorsements.clone());
+    pool_controller.add_endorsements(endo_storage).unwrap();
+    pool_controller.flush_endorsements().unwrap();
+    assert_eq!(
+        pool_controller.get_endorsement_count(),
+        ENDORSEMENT_COUNT as usize
+    );
+
+    // query half of the endorsements, plus one that is not in the pool
+    let absent = create_endorsement_with(&keypair, Slot::new(2, 0), 0, endorsed_block);
+    let mut ids: Vec<EndorsementId> = endorsements
+        .iter()
+        .take(ENDORSEMENT_COUNT as usize / 2)
+        .map(|endo| endo.id)
+        .collect();
+    ids.push(absent.id);
+    let res_storage = pool_controller.get_endorsements_by_ids(&ids);
+    assert_eq!(
+        res_storage.get_endorsement_refs().len(),
+        ENDORSEMENT_COUNT as usize / 2
+    );
+    assert!(!res_storage.get_endorsement_refs().contains(&absent.id));
+
+    pool_manager.stop();
+}
+
+/// Test that removed endorsements are no longer in the pool
+#[test]
+fn test_remove_endorsements() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let address = Address::from_public_key(&keypair.get_public_key());
+    let PoolTestBoilerPlate {
+        mut pool_manager,
+        mut pool_controller,
+        storage,
+    } = PoolTestBoilerPlate::pool_test_with_wallet(
+        PoolConfig::default(),
+        keypair.clone(),
+        default_execution_controller(),
+        endorsement_selector_controller(address),
+    );
+
+    let endorsed_block = BlockId::generate_from_hash(Hash::compute_from("block".as_bytes()));
+    let ids: Vec<EndorsementId> = (0..4)
+        .map(|index| create_endorsement_with(&keypair, Slot::new(1, 0), index, endorsed_block))
+        .map(|endo| {
+            let mut endo_storage = storage.clone_without_refs();
+            let id = endo.id;
+            endo_storage.store_endorsements(vec![endo]);
+            pool_controller.add_endorsements(endo_storage).unwrap();
+            id
+        })
+        .collect();
+    pool_controller.flush_endorsements().unwrap();
+    assert_eq!(pool_controller.contains_endorsements(&ids), vec![true; 4]);
+
+    pool_controller.remove_endorsements(&ids[1..3]).unwrap();
+    pool_controller.flush_endorsements().unwrap();
+    assert_eq!(pool_controller.get_endorsement_count(), 2);
+    assert_eq!(
+        pool_controller.contains_endorsements(&ids),
+        vec![true, false, false, true]
+    );
+
+    pool_manager.stop();
+}
+
+/// Clearing the endorsement pool empties it and resets its statistics
+#[cfg(feature = "testing")]
+#[test]
+fn test_clear_endorsements() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let address = Address::from_public_key(&keypair.get_public_key());
+    let PoolTestBoilerPlate {
+        mut pool_manager,
+        mut pool_controller,
+        storage,
+    } = PoolTestBoilerPlate::pool_test_with_wallet(
+        PoolConfig::default(),
+        keypair.clone(),
+        default_execution_controller(),
+        endorsement_selector_controller(address),
+    );
+
+    let endorsed_block = BlockId::generate_from_hash(Hash::compute_from("block".as_bytes()));
+    let endorsements: Vec<_> = (0..3)
+        .map(|index| create_endorsement_with(&keypair, Slot::new(1, 0), index, endorsed_block))
+        .collect();
+    let ids: Vec<EndorsementId> = endorsements.iter().map(|endo| endo.id).collect();
+    let mut endo_storage = storage.clone_without_refs();
+    endo_storage.store_endorsements(endorsements);
+    pool_controller.add_endorsements(endo_storage).unwrap();
+    pool_controller.flush_endorsements().unwrap();
+    assert_eq!(pool_controller.get_endorsement_count(), 3);
+
+    pool_controller.clear_endorsements().unwrap();
+    pool_controller.flush_endorsements().unwrap();
+    assert_eq!(pool_controller.get_endorsement_count(), 0);
+    assert_eq!(pool_controller.contains_endorsements(&ids), vec![false; 3]);
+    let stats = pool_controller.pool_stats();
+    assert_eq!(
+        (
+            stats.total_endorsements_added,
+            stats.total_endorsements_evicted
+        ),
+        (0, 0)
+    );
+    pool_manager.stop();
+}
+
+/// Only the endorsements present in the pool are counted as removed
+#[test]
+fn test_remove_included_endorsements() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let address = Address::from_public_key(&keypair.get_public_key());
+    let PoolTestBoilerPlate {
+        mut pool_manager,
+        mut pool_controller,
+        storage,
+    } = PoolTestBoilerPlate::pool_test_with_wallet(
+        PoolConfig::default(),
+        keypair.clone(),
+        default_execution_controller(),
+        endorsement_selector_controller(address),
+    );
+
+    let endorsed_block = BlockId::generate_from_hash(Hash::compute_from("block".as_bytes()));
+    let endorsements: Vec<_> = (0..4)
+        .map(|index| create_endorsement_with(&keypair, Slot::new(1, 0), index, endorsed_block))
+        .collect();
+    let ids: Vec<EndorsementId> = endorsements.iter().map(|endo| endo.id).collect();
+    let mut endo_storage = storage.clone_without_refs();
+    endo_storage.store_endorsements(endorsements[..3].to_vec());
+    pool_controller.add_endorsements(endo_storage).unwrap();
+    pool_controller.flush_endorsements().unwrap();
+
+    // the last endorsement was never added, and the first one is listed twice
+    let removed = pool_controller
+        .remove_included_endorsements(&[ids[0], ids[1], ids[3], ids[0]])
+        .unwrap();
+    assert_eq!(removed, 2);
+    assert_eq!(
+        pool_controller.contains_endorsements(&ids),
+        vec![false, false, true, false]
+    );
+    assert_eq!(
+        pool_controller
+            .remove_included_endorsements(&ids[..2])
+            .unwrap(),
+        0
+    );
+    pool_manager.stop();
+}
+
+/// Test that the coverage of a slot is full only once every index has an endorsement
+#[test]
+fn test_has_full_endorsement_coverage() {
+    let (
+        PoolTestBoilerPlate {
+            mut pool_manager,
+            mut pool_controller,
+            storage,
+        },
+        keypair,
+    ) = start_endorsement_pool(PoolConfig::default());
+
+    let slot = Slot::new(1, 0);
+    let endorsed_block = BlockId::generate_from_hash(Hash::compute_from("block".as_bytes()));
+    let indices: Vec<u32> = (0..ENDORSEMENT_COUNT).collect();
+    let (last, first) = indices.split_last().unwrap();
+    add_test_endorsements(
+        pool_controller.as_mut(),
+        &storage,
+        &keypair,
+        slot,
+        endorsed_block,
+        first,
+    );
+    pool_controller.flush_endorsements().unwrap();
+    assert!(!pool_controller.has_full_endorsement_coverage(&slot));
+
+    add_test_endorsements(
+        pool_controller.as_mut(),
+        &storage,
+        &keypair,
+        slot,
+        endorsed_block,
+        &[*last],
+    );
+    pool_controller.flush_endorsements().unwrap();
+    assert!(pool_controller.has_full_endorsement_coverage(&slot));
+    assert!(!pool_controller.has_full_endorsement_coverage(&Slot::new(2, 0)));
+
+    pool_manager.stop();
+}
+
+/// Get the per-slot endorsement statistics of the pool
+#[test]
+fn test_get_endorsement_stats() {
+    let (
+        PoolTestBoilerPlate {
+            mut pool_manager,
+            mut pool_controller,
+            storage,
+        },
+        keypair,
+    ) = start_endorsement_pool(PoolConfig::default());
+    assert_eq!(pool_controller.get_endorsement_stats(), Default::default());
+
+    let full_slot = Slot::new(1, 0);
+    let partial_slot = Slot::new(2, 1);
+    let endorsed_block = BlockId::generate_from_hash(Hash::compute_from("block".as_bytes()));
+    let indices: Vec<u32> = (0..ENDORSEMENT_COUNT).collect();
+    add_test_endorsements(
+        pool_controller.as_mut(),
+        &storage,
+        &keypair,
+        full_slot,
+        endorsed_block,
+        &indices,
+    );
+    add_test_endorsements(
+        pool_controller.as_mut(),
+        &storage,
+        &keypair,
+        partial_slot,
+        endorsed_block,
+        &[0, 1],
+    );
+    pool_controller.flush_endorsements().unwrap();
+
+    let stats = pool_controller.get_endorsement_stats();
+    assert_eq!(stats.total_endorsements, ENDORSEMENT_COUNT as usize + 2);
+    assert_eq!(stats.endorsements_per_slot.len(), 2);
+    assert_eq!(
+        stats.endorsements_per_slot[&full_slot],
+        ENDORSEMENT_COUNT as usize
+    );
+    assert_eq!(stats.endorsements_per_slot[&partial_slot], 2);
+    assert_eq!(stats.min_slot, Some(full_slot));
+    assert_eq!(stats.max_slot, Some(partial_slot));
+    assert_eq!(stats.slots_with_full_coverage, 1);
+
+    pool_manager.stop();
+}
+
+/// Get the endorsement age histogram of the pool, 20 periods after genesis
+#[test]
+fn test_endorsements_by_age() {
+    let (
+        PoolTestBoilerPlate {
+            mut pool_manager,
+            mut pool_controller,
+            storage,
+        },
+        keypair,
+    ) = start_endorsement_pool(
+        PoolConfigBuilder::default()
+            .with_genesis_timestamp(
+                MassaTime::now()
+                    .unwrap()
+                    .saturating_sub(T0.saturating_mul(20)),
+            )
+            .with_endorsement_age_histogram_buckets(vec![1, 4, 10])
+            .build()
+            .unwrap(),
+    );
+    let endorsed_block = BlockId::generate_from_hash(Hash::compute_from("block".as_bytes()));
+    // ages of about 0, 5, 6 and 15 periods
+    for period in [20, 15, 14, 5] {
+        add_test_endorsements(
+            pool_controller.as_mut(),
+            &storage,
+            &keypair,
+            Slot::new(period, 0),
+            endorsed_block,
+            &[0],
+        );
+    }
+    pool_controller.flush_endorsements().unwrap();
+
+    assert_eq!(
+        pool_controller.pool_stats().endorsements_by_age,
+        vec![(1, 0), (4, 2), (10, 1)]
+    );
+
+    pool_manager.stop();
+}
+
+/// Get the missing and covered endorsement indices of a block when only some of them are in the pool
+#[test]
+fn test_get_missing_endorsement_indices() {
+    let (
+        PoolTestBoilerPlate {
+            mut pool_manager,
+            mut pool_controller,
+            storage,
+        },
+        keypair,
+    ) = start_endorsement_pool(
+        PoolConfigBuilder::default()
+            .with_max_block_endorsement_count(5)
+            .build()
+            .unwrap(),
+    );
+
+    let slot = Slot::new(1, 0);
+    let endorsed_block = BlockId::generate_from_hash(Hash::compute_from("block".as_bytes()));
+    let other_block = BlockId::generate_from_hash(Hash::compute_from("other".as_bytes()));
+    add_test_endorsements(
+        pool_controller.as_mut(),
+        &storage,
+        &keypair,
+        slot,
+        endorsed_block,
+        &[0, 2, 4],
+    );
+    pool_controller.flush_endorsements().unwrap();
+
+    assert_eq!(
+        pool_controller.get_missing_endorsement_indices(&slot, &endorsed_block),
+        vec![1, 3]
+    );
+    assert_eq!(
+        pool_controller.get_missing_endorsement_indices(&slot, &other_block),
+        vec![0, 1, 2, 3, 4]
+    );
+    assert_eq!(
+        pool_controller.get_coverage_for_block(&endorsed_block, &slot),
+        vec![true, false, true, false, true]
+    );
+    assert_eq!(
+        pool_controller.get_coverage_for_block(&other_block, &slot),
+        vec![false; 5]
+    );
+
+    pool_manager.stop();
+}
+
+/// The endorsements added before a flush are in the pool once it returns
+#[test]
+fn test_flush_endorsements() {
+    let (
+        PoolTestBoilerPlate {
+            mut pool_manager,
+            mut pool_controller,
+            storage,
+        },
+        keypair,
+    ) = start_endorsement_pool(PoolConfig::default());
+
+    let endorsed_block = BlockId::generate_from_hash(Hash::compute_from("block".as_bytes()));
+    let ids = add_test_endorsements(
+        pool_controller.as_mut(),
+        &storage,
+        &keypair,
+        Slot::new(1, 0),
+        endorsed_block,
+        &[0, 1, 2],
+    );
+    pool_controller.flush_endorsements().unwrap();
+    assert_eq!(pool_controller.contains_endorsements(&ids), vec![true; 3]);
+
+    pool_manager.stop();
+    assert!(pool_controller.flush_endorsements().is_err());
+}
+
+/// Fill the pool to capacity + 1: the first added endorsement is evicted
+#[test]
+fn test_max_endorsement_pool_size() {
+    let (
+        PoolTestBoilerPlate {
+            mut pool_manager,
+            mut pool_controller,
+            storage,
+        },
+        keypair,
+    ) = start_endorsement_pool(
+        PoolConfigBuilder::default()
+            .with_max_endorsement_pool_size(4)
+            .build()
+            .unwrap(),
+    );
+
+    let endorsed_block = BlockId::generate_from_hash(Hash::compute_from("block".as_bytes()));
+    let ids: Vec<EndorsementId> = (0..5)
+        .flat_map(|index| {
+            add_test_endorsements(
+                pool_controller.as_mut(),
+                &storage,
+                &keypair,
+                Slot::new(1, 0),
+                endorsed_block,
+                &[index],
+            )
+        })
+        .collect();
+    pool_controller.flush_endorsements().unwrap();
+
+    assert_eq!(pool_controller.get_endorsement_count(), 4);
+    assert_eq!(
+        pool_controller.contains_endorsements(&ids),
+        vec![false, true, true, true, true]
+    );
+    assert_eq!(pool_controller.pool_stats().total_endorsements_evicted, 1);
+
+    pool_manager.stop();
+}
+
+/// Fill a thread of the pool: an endorsement to be included after all the ones it holds
+/// is rejected with a `CapacityExceeded` error
+#[test]
+fn test_add_endorsement_capacity_exceeded() {
+    let (
+        PoolTestBoilerPlate {
+            mut pool_manager,
+            mut pool_controller,
+            storage,
+        },
+        keypair,
+    ) = start_endorsement_pool(
+        PoolConfigBuilder::default()
+            .with_max_endorsements_pool_size_per_thread(ENDORSEMENT_COUNT as usize)
+            .build()
+            .unwrap(),
+    );
+
+    let endorsed_block = BlockId::generate_from_hash(Hash::compute_from("block".as_bytes()));
+    let indices: Vec<u32> = (0..ENDORSEMENT_COUNT).collect();
+    add_test_endorsements(
+        pool_controller.as_mut(),
+        &storage,
+        &keypair,
+        Slot::new(1, 0),
+        endorsed_block,
+        &indices,
+    );
+    pool_controller.flush_endorsements().unwrap();
+    assert_eq!(
+        pool_controller.get_endorsement_count(),
+        ENDORSEMENT_COUNT as usize
+    );
+
+    let late_endorsement = create_endorsement_with(&keypair, Slot::new(2, 0), 0, endorsed_block);
+    let late_id = late_endorsement.id;
+    let mut endo_storage = storage.clone_without_refs();
+    endo_storage.store_endorsements(vec![late_endorsement]);
+    match pool_controller.add_endorsements(endo_storage) {
+        Err(PoolError::CapacityExceeded {
+            pool,
+            current,
+            limit,
+        }) => {
+            assert_eq!(pool, PoolKind::Endorsements);
+            assert_eq!(current, ENDORSEMENT_COUNT as usize);
+            assert_eq!(limit, ENDORSEMENT_COUNT as usize);
+        }
+        res => panic!("expected a CapacityExceeded error, got {:?}", res),
+    }
+    pool_controller.flush_endorsements().unwrap();
+    assert_eq!(
+        pool_controller.contains_endorsements(&[late_id]),
+        vec![false]
+    );
+
+    pool_manager.stop();
+}
+
+/// Get the endorsement coverage of a range of 10 slots, each slot having a different coverage
+#[test]
+fn test_get_endorsement_coverage_map() {
+    let (
+        PoolTestBoilerPlate {
+            mut pool_manager,
+            mut pool_controller,
+            storage,
+        },
+        keypair,
+    ) = start_endorsement_pool(PoolConfig::default());
+
+    let endorsed_block = BlockId::generate_from_hash(Hash::compute_from("block".as_bytes()));
+    let slots: Vec<Slot> = (0..10).map(|thread| Slot::new(1, thread)).collect();
+    for (count, slot) in slots.iter().enumerate() {
+        let indices: Vec<u32> = (0..count as u32).collect();
+        add_test_endorsements(
+            pool_controller.as_mut(),
+            &storage,
+            &keypair,
+            *slot,
+            endorsed_block,
+            &indices,
+        );
+    }
+    // out of the queried range
+    add_test_endorsements(
+        pool_controller.as_mut(),
+        &storage,
+        &keypair,
+        Slot::new(1, 10),
+        endorsed_block,
+        &[0, 1, 2],
+    );
+    pool_controller.flush_endorsements().unwrap();
+
+    let coverage = pool_controller.get_endorsement_coverage_map(&slots[0], &slots[9]);
+    assert_eq!(coverage.len(), 10);
+    for (count, slot) in slots.iter().enumerate() {
+        assert_eq!(coverage.get(slot), Some(&count));
+    }
+
+    pool_manager.stop();
+}
+
+/// Get the endorsements of each of two producers
+#[test]
+fn test_get_endorsements_by_producer() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let address = Address::from_public_key(&keypair.get_public_key());
+    let other_keypair = KeyPair::generate(0).unwrap();
+    let other_address = Address::from_public_key(&other_keypair.get_public_key());
+    let mut endorsers = vec![address; ENDORSEMENT_COUNT as usize];
+    endorsers[1] = other_address;
+    let PoolTestBoilerPlate {
+        mut pool_manager,
+        mut pool_controller,
+        storage,
+    } = PoolTestBoilerPlate::pool_test_with_wallet(
+        PoolConfig::default(),
+        keypair.clone(),
+        default_execution_controller(),
+        endorsers_selector_controller(address, endorsers),
+    );
+
+    let endorsed_block = BlockId::generate_from_hash(Hash::compute_from("block".as_bytes()));
+    let mut expected = Vec::new();
+    let mut other_expected = Vec::new();
+    for period in 1..=3 {
+        let slot = Slot::new(period, 0);
+        if period < 3 {
+            let ids = add_test_endorsements(
+                pool_controller.as_mut(),
+                &storage,
+                &keypair,
+                slot,
+                endorsed_block,
+                &[0],
+            );
+            expected.push((slot, ids[0]));
+        }
+        let other_ids = add_test_endorsements(
+            pool_controller.as_mut(),
+            &storage,
+            &other_keypair,
+            slot,
+            endorsed_block,
+            &[1],
+        );
+        other_expected.push((slot, other_ids[0]));
+    }
+    pool_controller.flush_endorsements().unwrap();
+
+    assert_eq!(
+        pool_controller.get_endorsements_by_producer(&address),
+        expected
+    );
+    assert_eq!(
+        pool_controller.get_endorsements_by_producer(&other_address),
+        other_expected
+    );
+
+    // pruned endorsements are no longer associated with their producer
+    pool_controller.notify_final_cs_periods(&[1; THREAD_COUNT as usize]);
+    pool_controller.flush_endorsements().unwrap();
+    assert_eq!(
+        pool_controller.get_endorsements_by_producer(&address),
+        expected[1..].to_vec()
+    );
+    assert_eq!(
+        pool_controller.get_endorsements_by_producer(&other_address),
+        other_expected[1..].to_vec()
+    );
+
+    pool_manager.stop();
+}
+
+/// Endorsements of the periods that became final are pruned, later ones are kept
+#[test]
+fn test_notify_final_cs_periods_prunes_old_endorsements() {
+    let (
+        PoolTestBoilerPlate {
+            mut pool_manager,
+            mut pool_controller,
+            storage,
+        },
+        keypair,
+    ) = start_endorsement_pool(PoolConfig::default());
+
+    let endorsed_block = BlockId::generate_from_hash(Hash::compute_from("block".as_bytes()));
+    let mut old_ids = Vec::new();
+    for period in 1..=3 {
+        old_ids.extend(add_test_endorsements(
+            pool_controller.as_mut(),
+            &storage,
+            &keypair,
+            Slot::new(period, 0),
+            endorsed_block,
+            &[0, 1],
+        ));
+    }
+    let recent_ids = add_test_endorsements(
+        pool_controller.as_mut(),
+        &storage,
+        &keypair,
+        Slot::new(10, 0),
+        endorsed_block,
+        &[0, 1],
+    );
+    pool_controller.flush_endorsements().unwrap();
+    assert_eq!(pool_controller.get_endorsement_count(), 8);
+
+    pool_controller.notify_final_cs_periods(&[8; THREAD_COUNT as usize]);
+    pool_controller.flush_endorsements().unwrap();
+    assert_eq!(
+        pool_controller.contains_endorsements(&old_ids),
+        vec![false; old_ids.len()]
+    );
+    assert_eq!(
+        pool_controller.contains_endorsements(&recent_ids),
+        vec![true; recent_ids.len()]
+    );
+
+    pool_manager.stop();
+}
+
+/// Only the endorsements more than the given number of slots before the current slot are listed
+#[test]
+fn test_get_endorsements_older_than() {
+    let (
+        PoolTestBoilerPlate {
+            mut pool_manager,
+            mut pool_controller,
+            storage,
+        },
+        keypair,
+    ) = start_endorsement_pool(PoolConfig::default());
+
+    let endorsed_block = BlockId::generate_from_hash(Hash::compute_from("block".as_bytes()));
+    let ids_by_period: Vec<Vec<EndorsementId>> = [1, 2, 3, 10]
+        .into_iter()
+        .map(|period| {
+            add_test_endorsements(
+                pool_controller.as_mut(),
+                &storage,
+                &keypair,
+                Slot::new(period, 0),
+                endorsed_block,
+                &[0, 1],
+            )
+        })
+        .collect();
+    pool_controller.flush_endorsements().unwrap();
+
+    // the endorsements of period 3 are exactly 7 periods old, so they are not listed
+    let current_slot = Slot::new(10, 0);
+    assert_eq!(
+        pool_controller.get_endorsements_older_than(7 * THREAD_COUNT as u64, &current_slot),
+        ids_by_period[..2].concat()
+    );
+    assert_eq!(
+        pool_controller.get_endorsements_older_than(0, &current_slot),
+        ids_by_period[..3].concat()
+    );
+    assert!(pool_controller
+        .get_endorsements_older_than(0, &Slot::new(1, 0))
+        .is_empty());
+
+    pool_manager.stop();
+}
+
+/// The signature of an endorsement received again is not checked twice,
+/// and an endorsement with an invalid signature is rejected every time
+#[test]
+fn test_endorsement_signature_cache() {
+    let (
+        PoolTestBoilerPlate {
+            mut pool_manager,
+            mut pool_controller,
+            storage,
+        },
+        keypair,
+    ) = start_endorsement_pool(PoolConfig::default());
+
+    let slot = Slot::new(1, 0);
+    let endorsed_block = BlockId::generate_from_hash(Hash::compute_from("block".as_bytes()));
+    let endorsement = create_endorsement_with(&keypair, slot, 0, endorsed_block);
+    let mut forged_endorsement = create_endorsement_with(&keypair, slot, 1, endorsed_block);
+    forged_endorsement.signature = endorsement.signature;
+    let add_endorsements = |pool_controller: &mut Box<dyn PoolController>| {
+        let mut endo_storage = storage.clone_without_refs();
+        endo_storage.store_endorsements(vec![endorsement.clone(), forged_endorsement.clone()]);
+        pool_controller.add_endorsements(endo_storage).unwrap();
+        pool_controller.flush_endorsements().unwrap();
+    };
+
+    add_endorsements(&mut pool_controller);
+    assert_eq!(
+        pool_controller.contains_endorsements(&[endorsement.id, forged_endorsement.id]),
+        vec![true, false]
+    );
+    assert_eq!(pool_controller.pool_stats().endorsement_signature_checks, 2);
+
+    // add both endorsements again once the valid one left the pool
+    pool_controller
+        .remove_endorsements(&[endorsement.id])
+        .unwrap();
+    pool_controller.flush_endorsements().unwrap();
+    add_endorsements(&mut pool_controller);
+    assert_eq!(
+        pool_controller.contains_endorsements(&[endorsement.id, forged_endorsement.id]),
+        vec![true, false]
+    );
+    assert_eq!(pool_controller.pool_stats().endorsement_signature_checks, 2);
+
+    pool_manager.stop();
+}
+
+/// Compact the endorsement pool, automatically and on demand: the remaining endorsements are kept
+#[test]
+fn test_compact_endorsement_pool() {
+    let (
+        PoolTestBoilerPlate {
+            mut pool_manager,
+            mut pool_controller,
+            storage,
+        },
+        keypair,
+    ) = start_endorsement_pool(
+        PoolConfigBuilder::default()
+            .with_compact_after_evictions(2)
+            .build()
+            .unwrap(),
+    );
+
+    let slot = Slot::new(1, 0);
+    let endorsed_block = BlockId::generate_from_hash(Hash::compute_from("block".as_bytes()));
+    let endorsements: Vec<SecureShareEndorsement> = (0..4)
+        .map(|index| create_endorsement_with(&keypair, slot, index, endorsed_block))
+        .collect();
+    let ids: Vec<EndorsementId> = endorsements.iter().map(|endo| endo.id).collect();
+    let mut endo_storage = storage.clone_without_refs();
+    endo_storage.store_endorsements(endorsements);
+    pool_controller.add_endorsements(endo_storage).unwrap();
+    pool_controller.flush_endorsements().unwrap();
+
+    // removing 3 endorsements triggers the automatic compaction
+    pool_controller.remove_endorsements(&ids[1..]).unwrap();
+    pool_controller.compact_endorsement_pool().unwrap();
+    pool_controller.flush_endorsements().unwrap();
+
+    assert_eq!(
+        pool_controller.contains_endorsements(&ids),
+        vec![true, false, false, false]
+    );
+    let (block_endorsements, _) = pool_controller.get_block_endorsements(&endorsed_block, &slot);
+    assert_eq!(block_endorsements[0], Some(ids[0]));
+    assert!(block_endorsements[1..].iter().all(Option::is_none));
+
+    pool_manager.stop();
+}
+
+/// The eviction events tell why each endorsement left the pool
+#[test]
+fn test_endorsement_eviction_reasons() {
+    let (
+        PoolTestBoilerPlate {
+            mut pool_manager,
+            mut pool_controller,
+            storage,
+        },
+        keypair,
+    ) = start_endorsement_pool(PoolConfig::default());
+
+    let slot = Slot::new(1, 0);
+    let endorsed_block = BlockId::generate_from_hash(Hash::compute_from("block".as_bytes()));
+    let ids = add_test_endorsements(
+        pool_controller.as_mut(),
+        &storage,
+        &keypair,
+        slot,
+        endorsed_block,
+        &[0, 1],
+    );
+    pool_controller.flush_endorsements().unwrap();
+    let events = pool_controller.subscribe_to_pool_events();
+
+    assert_eq!(
+        pool_controller
+            .remove_included_endorsements(&ids[..1])
+            .unwrap(),
+        1
+    );
+    assert_eq!(
+        events.try_recv().unwrap(),
+        PoolEvent::EndorsementEvicted {
+            id: ids[0],
+            reason: EvictionReason::Included
+        }
+    );
+
+    // the other endorsement can no longer be included once its slot is over
+    pool_controller.notify_new_slot(Slot::new(2, 0)).unwrap();
+    pool_controller.flush_endorsements().unwrap();
+    assert_eq!(
+        events.try_recv().unwrap(),
+        PoolEvent::EndorsementEvicted {
+            id: ids[1],
+            reason: EvictionReason::Expired
+        }
+    );
+
+    pool_manager.stop();
+}
+
+/// Endorsements of a warmed up pool are available for block production right away
+#[test]
+fn test_warm_up() {
+    let (
+        PoolTestBoilerPlate {
+            mut pool_manager,
+            mut pool_controller,
+            storage,
+        },
+        keypair,
+    ) = start_endorsement_pool(PoolConfig::default());
+
+    let slot = Slot::new(1, 0);
+    let endorsed_block = BlockId::generate_from_hash(Hash::compute_from("block".as_bytes()));
+    let endorsements: Vec<SecureShareEndorsement> = (0..4)
+        .map(|index| create_endorsement_with(&keypair, slot, index, endorsed_block))
+        .collect();
+    let ids: Vec<EndorsementId> = endorsements.iter().map(|endo| endo.id).collect();
+    let mut endo_storage = storage.clone_without_refs();
+    endo_storage.store_endorsements(endorsements);
+    assert_eq!(pool_controller.warm_up(endo_storage.clone()).unwrap(), 4);
+
+    let (block_endorsements, _) = pool_controller.get_block_endorsements(&endorsed_block, &slot);
+    assert_eq!(
+        block_endorsements[..4],
+        ids.into_iter().map(Some).collect::<Vec<_>>()
+    );
+    assert!(block_endorsements[4..].iter().all(Option::is_none));
+
+    // endorsements already in the pool are not counted
+    assert_eq!(pool_controller.warm_up(endo_storage).unwrap(), 0);
+
+    pool_manager.stop();
+}
+
+/// Get the endorsements of several blocks at once: one result per target, in order
+#[test]
+fn test_get_endorsements_for_blocks() {
+    let (
+        PoolTestBoilerPlate {
+            mut pool_manager,
+            mut pool_controller,
+            storage,
+        },
+        keypair,
+    ) = start_endorsement_pool(PoolConfig::default());
+
+    let slot = Slot::new(1, 0);
+    let first_block = BlockId::generate_from_hash(Hash::compute_from("first".as_bytes()));
+    let second_block = BlockId::generate_from_hash(Hash::compute_from("second".as_bytes()));
+    let first_endo = create_endorsement_with(&keypair, slot, 0, first_block);
+    let second_endo = create_endorsement_with(&keypair, slot, 1, second_block);
+    let mut endo_storage = storage.clone_without_refs();
+    endo_storage.store_endorsements(vec![first_endo.clone(), second_endo.clone()]);
+    pool_controller.add_endorsements(endo_storage).unwrap();
+    pool_controller.flush_endorsements().unwrap();
+
+    let unknown_block = BlockId::generate_from_hash(Hash::compute_from("unknown".as_bytes()));
+    let results = pool_controller.get_endorsements_for_blocks(&[
+        (second_block, slot),
+        (first_block, slot),
+        (unknown_block, slot),
+    ]);
+    assert_eq!(results.len(), 3);
+    for (target_block, (endo_ids, endo_storage)) in [second_block, first_block, unknown_block]
+        .iter()
+        .zip(&results)
+    {
+        let (expected_ids, _) = pool_controller.get_block_endorsements(target_block, &slot);
+        assert_eq!(endo_ids, &expected_ids);
+        assert_eq!(
+            endo_storage.get_endorsement_refs().len(),
+            endo_ids.iter().flatten().count()
+        );
+    }
+    assert_eq!(results[0].0[1], Some(second_endo.id));
+    assert_eq!(results[1].0[0], Some(first_endo.id));
+    assert!(results[2].0.iter().all(Option::is_none));
+
+    pool_manager.stop();
+}
+
+/// Drain the pools: the snapshots contain the items added just before stopping
+#[test]
+fn test_drain_and_stop() {
+    let (
+        PoolTestBoilerPlate {
+            mut pool_manager,
+            mut pool_controller,
+            storage,
+        },
+        keypair,
+    ) = start_endorsement_pool(PoolConfig::default());
+
+    let slot = Slot::new(1, 0);
+    let endorsed_block = BlockId::generate_from_hash(Hash::compute_from("block".as_bytes()));
+    let mut endorsement_ids = add_test_endorsements(
+        pool_controller.as_mut(),
+        &storage,
+        &keypair,
+        slot,
+        endorsed_block,
+        &[0, 1, 2],
+    );
+    let ops = create_some_operations(4, &OpGenerator::default().expirery(2));
+    let mut operation_ids: Vec<_> = ops.iter().map(|op| op.id).collect();
+    let mut ops_storage = storage.clone_without_refs();
+    ops_storage.store_operations(ops);
+    pool_controller.add_operations(ops_storage).unwrap();
+
+    // no wait: the snapshots are taken once the pending additions are processed
+    let (operations, endorsements) = pool_manager.drain_and_stop().unwrap();
+    let mut snapshot_operation_ids = operations.operation_ids;
+    snapshot_operation_ids.sort();
+    operation_ids.sort();
+    assert_eq!(snapshot_operation_ids, operation_ids);
+    assert_eq!(operations.storage.get_op_refs().len(), 4);
+    let mut snapshot_endorsement_ids = endorsements.endorsement_ids;
+    snapshot_endorsement_ids.sort();
+    endorsement_ids.sort();
+    assert_eq!(snapshot_endorsement_ids, endorsement_ids);
+    assert_eq!(endorsements.storage.get_endorsement_refs().len(), 3);
+}
+
+/// Test that the block endorsements of each slot are found among many endorsements of other slots
+#[test]
+fn test_get_block_endorsements_many_slots() {
+    let (
+        PoolTestBoilerPlate {
+            mut pool_manager,
+            mut pool_controller,
+            storage,
+        },
+        keypair,
+    ) = start_endorsement_pool(PoolConfig::default());
+
+    // 10 endorsements in each of 100 slots
+    let endorsed_block = BlockId::generate_from_hash(Hash::compute_from("block".as_bytes()));
+    let slots: Vec<Slot> = (0..100u64)
+        .map(|i| Slot::new(1 + i / THREAD_COUNT as u64, (i % THREAD_COUNT as u64) as u8))
+        .collect();
+    let endorsements: Vec<_> = slots
+        .iter()
+        .flat_map(|slot| {
+            (0..10).map(|index| create_endorsement_with(&keypair, *slot, index, endorsed_block))
+        })
+        .collect();
+    let mut endo_storage = storage.clone_without_refs();
+    endo_storage.store_endorsements(endorsements.clone());
+    pool_controller.add_endorsements(endo_storage).unwrap();
+    pool_controller.flush_endorsements().unwrap();
+    assert_eq!(pool_controller.get_endorsement_count(), 1000);
+
+    for (slot, slot_endorsements) in slots.iter().zip(endorsements.chunks(10)) {
+        let (ids, res_storage) = pool_controller.get_block_endorsements(&endorsed_block, slot);
+        let mut expected: Vec<Option<EndorsementId>> =
+            slot_endorsements.iter().map(|endo| Some(endo.id)).collect();
+        expected.resize(ENDORSEMENT_COUNT as usize, None);
+        assert_eq!(ids, expected);
+        assert_eq!(res_storage.get_endorsement_refs().len(), 10);
+    }
+
+    pool_manager.stop();
+}
+
+/// Get the number of endorsements of single slots
+#[test]
+fn test_get_endorsement_count_for_slot() {
+    let (
+        PoolTestBoilerPlate {
+            mut pool_manager,
+            mut pool_controller,
+            storage,
+        },
+        keypair,
+    ) = start_endorsement_pool(PoolConfig::default());
+
+    let slot_a = Slot::new(1, 0);
+    let slot_b = Slot::new(2, 0);
+    let endorsed_block = BlockId::generate_from_hash(Hash::compute_from("block".as_bytes()));
+    add_test_endorsements(
+        pool_controller.as_mut(),
+        &storage,
+        &keypair,
+        slot_a,
+        endorsed_block,
+        &[0, 1, 2],
+    );
+    add_test_endorsements(
+        pool_controller.as_mut(),
+        &storage,
+        &keypair,
+        slot_b,
+        endorsed_block,
+        &[0, 1, 2, 3, 4],
+    );
+    pool_controller.flush_endorsements().unwrap();
+
+    assert_eq!(pool_controller.get_endorsement_count_for_slot(&slot_a), 3);
+    assert_eq!(pool_controller.get_endorsement_count_for_slot(&slot_b), 5);
+    assert_eq!(
+        pool_controller.get_endorsement_count_for_slot(&Slot::new(3, 0)),
+        0
+    );
+    assert_eq!(pool_controller.get_endorsement_count(), 8);
+
+    pool_manager.stop();
+}
+
+/// Purging the pool removes all the endorsements and resets the statistics
+#[test]
+fn test_purge_endorsement_pool() {
+    let (
+        PoolTestBoilerPlate {
+            mut pool_manager,
+            mut pool_controller,
+            storage,
+        },
+        keypair,
+    ) = start_endorsement_pool(PoolConfig::default());
+
+    let slot = Slot::new(1, 0);
+    let endorsed_block = BlockId::generate_from_hash(Hash::compute_from("block".as_bytes()));
+    let ids = add_test_endorsements(
+        pool_controller.as_mut(),
+        &storage,
+        &keypair,
+        slot,
+        endorsed_block,
+        &[0, 1, 2],
+    );
+    pool_controller.flush_endorsements().unwrap();
+    assert_eq!(pool_controller.get_endorsement_count(), 3);
+
+    pool_controller.purge_pool().unwrap();
+    pool_controller.flush_endorsements().unwrap();
+    assert_eq!(pool_controller.get_endorsement_count(), 0);
+    assert_eq!(pool_controller.get_endorsement_count_for_slot(&slot), 0);
+    assert_eq!(pool_controller.contains_endorsements(&ids), vec![false; 3]);
+    let stats = pool_controller.pool_stats();
+    assert_eq!(
+        (
+            stats.total_endorsements_added,
+            stats.total_endorsements_evicted
+        ),
+        (0, 0)
+    );
+
+    add_test_endorsements(
+        pool_controller.as_mut(),
+        &storage,
+        &keypair,
+        slot,
+        endorsed_block,
+        &[0, 1, 2],
+    );
+    pool_controller.flush_endorsements().unwrap();
+    assert_eq!(pool_controller.contains_endorsements(&ids), vec![true; 3]);
+
+    pool_manager.stop();
+}
+
+/// Test that replaying an event log keeps the logged endorsements until their slot passes,
+/// their PoS draws being taken from the endorsements themselves.
+#[test]
+fn test_replay_endorsements() {
+    let endorsed_block = BlockId::generate_from_hash(Hash::compute_from("block".as_bytes()));
+    let endorsements: Vec<SecureShareEndorsement> = (0..3)
+        .map(|index| {
+            let keypair = KeyPair::generate(0).unwrap();
+            create_endorsement_with(&keypair, Slot::new(1, 0), index, endorsed_block)
+        })
+        .collect();
+    let entry = |command| EventLogEntry {
+        timestamp_ns: 0,
+        pool: LoggedPool::Endorsements,
+        command,
+    };
+    let mut entries = vec![entry(LoggedCommand::AddEndorsements(endorsements))];
+
+    let pools = ReplayedPools::replay(PoolConfig::default(), entries.clone());
+    assert_eq!(pools.endorsement_count(), 3);
+
+    entries.push(entry(LoggedCommand::NotifyNewSlot(Slot::new(2, 0))));
+    let pools = ReplayedPools::replay(PoolConfig::default(), entries);
+    assert_eq!(pools.endorsement_count(), 0);
+}
@@ -1,5 +1,7 @@
 // Copyright (c) 2022 MASSA LABS <info@massa.net>
 
+mod config_tests;
+mod endorsement_pool_tests;
 mod operation_pool_tests;
 mod scenario;
 pub(crate) mod tools;
@@ -17,14 +17,46 @@
 //! latest period given his own thread. All operation which doesn't fit these
 //! requirements are "irrelevant"
 //!
-use crate::tests::tools::OpGenerator;
+use crate::{
+    bloom_filter::BloomFilter,
+    event_log::{EventLogEntry, LoggedCommand, LoggedPool},
+    operation_pool::{OperationPool, OPERATION_INDEX_OVERHEAD_BYTES},
+    tests::tools::OpGenerator,
+};
 
-use super::tools::{create_some_operations, operation_pool_test, PoolTestBoilerPlate};
+use super::tools::{
+    create_block, create_some_operations, default_execution_controller,
+    default_selector_controller, operation_pool_test, wait_until, PoolTestBoilerPlate,
+};
 use massa_execution_exports::MockExecutionController;
-use massa_models::{amount::Amount, config::ENDORSEMENT_COUNT, operation::OperationId, slot::Slot};
-use massa_pool_exports::PoolConfig;
+use massa_hash::Hash;
+use massa_models::{
+    address::Address,
+    amount::Amount,
+    block_id::BlockId,
+    config::{
+        ENDORSEMENT_COUNT, MAX_DATASTORE_VALUE_LENGTH, MAX_FUNCTION_NAME_LENGTH,
+        MAX_OPERATIONS_PER_MESSAGE, MAX_OPERATION_DATASTORE_ENTRY_COUNT,
+        MAX_OPERATION_DATASTORE_KEY_LENGTH, MAX_OPERATION_DATASTORE_VALUE_LENGTH,
+        MAX_PARAMETERS_SIZE,
+    },
+    operation::{OperationId, OperationTypeId, SecureShareOperation},
+    prehash::PreHashSet,
+    secure_share::Id,
+    slot::Slot,
+};
+use massa_pool_exports::{
+    BlockFillInfo, EvictionPolicy, EvictionReason, OperationPoolSnapshotDeserializer,
+    OperationPoolSnapshotSerializer, OperationSelectionStrategy, OperationValidator, PoolConfig,
+    PoolConfigBuilder, PoolDump, PoolError, PoolEvent, PoolKind, PoolMemoryUsage, WorkerStatus,
+};
 use massa_pos_exports::{MockSelectorController, Selection};
-use std::{collections::BTreeMap, time::Duration};
+use massa_serialization::{DeserializeError, Deserializer, Serializer};
+use massa_signature::KeyPair;
+use massa_time::MassaTime;
+use parking_lot::Mutex;
+use proptest::prelude::*;
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
 
 #[test]
 fn test_add_operation() {
@@ -93,14 +125,2549 @@ fn test_add_operation() {
         |mut operation_pool, mut storage| {
             let op_gen = OpGenerator::default().expirery(2);
             storage.store_operations(create_some_operations(10, &op_gen));
-            operation_pool.add_operations(storage);
-            // Allow some time for the pool to add the operations
-            std::thread::sleep(Duration::from_secs(3));
+            operation_pool.add_operations(storage).unwrap();
+            operation_pool.flush_operations().unwrap();
             assert_eq!(operation_pool.get_operation_count(), 10);
         },
     );
 }
 
+/// Test that a full pool still accepts a better operation, evicting the worst one.
+#[test]
+fn test_add_operation_to_full_pool() {
+    let pool_config = PoolConfigBuilder::default()
+        .with_max_operation_pool_size(5)
+        .with_max_operations_per_block(5)
+        .with_max_operation_pool_excess_items(0)
+        .build()
+        .unwrap();
+    operation_pool_test(
+        pool_config,
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let op_gen = OpGenerator::default().expirery(2).fee(Amount::from_raw(1));
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(create_some_operations(5, &op_gen));
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(operation_pool.get_operation_count(), 5);
+
+            let op = OpGenerator::default()
+                .expirery(2)
+                .fee(Amount::from_raw(1000))
+                .generate();
+            let op_id = op.id;
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(vec![op]);
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(operation_pool.get_operation_count(), 5);
+            assert_eq!(operation_pool.contains_operations(&[op_id]), vec![true]);
+        },
+    );
+}
+
+/// Test that a full pool rejects an operation ranking below all the ones it holds
+/// with a `CapacityExceeded` error.
+#[test]
+fn test_add_operation_capacity_exceeded() {
+    let pool_config = PoolConfigBuilder::default()
+        .with_max_operation_pool_size(5)
+        .with_max_operations_per_block(5)
+        .with_max_operation_pool_excess_items(0)
+        .build()
+        .unwrap();
+    operation_pool_test(
+        pool_config,
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let op_gen = OpGenerator::default()
+                .expirery(2)
+                .fee(Amount::from_raw(1000));
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(create_some_operations(5, &op_gen));
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(operation_pool.get_operation_count(), 5);
+
+            let op = OpGenerator::default()
+                .expirery(2)
+                .fee(Amount::from_raw(1))
+                .generate();
+            let op_id = op.id;
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(vec![op]);
+            match operation_pool.add_operations(ops_storage) {
+                Err(PoolError::CapacityExceeded {
+                    pool,
+                    current,
+                    limit,
+                }) => {
+                    assert_eq!(pool, PoolKind::Operations);
+                    assert_eq!(current, 5);
+                    assert_eq!(limit, 5);
+                }
+                res => panic!("expected a CapacityExceeded error, got {:?}", res),
+            }
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(operation_pool.get_operation_count(), 5);
+            assert_eq!(operation_pool.contains_operations(&[op_id]), vec![false]);
+        },
+    );
+}
+
+/// Test that operations that expired are dropped when a new slot is notified.
+#[test]
+fn test_notify_new_slot_prunes_expired_operations() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, mut storage| {
+            let op_gen = OpGenerator::default().expirery(2);
+            storage.store_operations(create_some_operations(10, &op_gen));
+            operation_pool.add_operations(storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(operation_pool.get_operation_count(), 10);
+
+            // operations are still valid at period 2
+            operation_pool.notify_new_slot(Slot::new(2, 0)).unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(operation_pool.get_operation_count(), 10);
+
+            // operations expired at period 3
+            operation_pool.notify_new_slot(Slot::new(3, 0)).unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(operation_pool.get_operation_count(), 0);
+        },
+    );
+}
+
+/// Test that the age of an operation counts the slots notified since it was added to the pool.
+#[test]
+fn test_get_operation_age() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let op_gen = OpGenerator::default().expirery(10);
+            let thread_count = PoolConfig::default().thread_count as u64;
+            let first_op = op_gen.generate();
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(vec![first_op.clone()]);
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(operation_pool.get_operation_age(&first_op.id), Some(0));
+
+            // the operation counts as added at the first notified slot
+            operation_pool.notify_new_slot(Slot::new(1, 0)).unwrap();
+            operation_pool.notify_new_slot(Slot::new(1, 5)).unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(operation_pool.get_operation_age(&first_op.id), Some(5));
+
+            let second_op = op_gen.generate();
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(vec![second_op.clone()]);
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.notify_new_slot(Slot::new(2, 5)).unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(
+                operation_pool.get_operation_age(&first_op.id),
+                Some(5 + thread_count)
+            );
+            assert_eq!(
+                operation_pool.get_operation_age(&second_op.id),
+                Some(thread_count)
+            );
+
+            let absent_op = OpGenerator::default().expirery(10).generate();
+            assert_eq!(operation_pool.get_operation_age(&absent_op.id), None);
+        },
+    );
+}
+
+/// Test that `contains_operations` reports the operations of the pool and only them.
+#[test]
+fn test_contains_operations() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let op_gen = OpGenerator::default().expirery(2);
+            let added = create_some_operations(5, &op_gen);
+            let absent = create_some_operations(5, &op_gen);
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(added.clone());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+
+            let ids: Vec<OperationId> = added.iter().chain(absent.iter()).map(|op| op.id).collect();
+            let mut expected = vec![true; 5];
+            expected.extend(vec![false; 5]);
+            assert_eq!(operation_pool.contains_operations(&ids), expected);
+        },
+    );
+}
+
+/// Test that removed operations are no longer in the pool.
+#[test]
+fn test_remove_operations() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let op_gen = OpGenerator::default().expirery(2);
+            let ops = create_some_operations(5, &op_gen);
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops.clone());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+
+            let ids: Vec<OperationId> = ops.iter().map(|op| op.id).collect();
+            operation_pool.remove_operations(&ids[..2]).unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(operation_pool.get_operation_count(), 3);
+            assert_eq!(
+                operation_pool.contains_operations(&ids),
+                vec![false, false, true, true, true]
+            );
+        },
+    );
+}
+
+/// Test that the bloom filter is only rebuilt once the removed IDs outnumber the remaining ones.
+#[test]
+fn test_bloom_filter_lazy_removal() {
+    let filter = BloomFilter::new(1 << 16, 0.001);
+    let ids: Vec<OperationId> = (0..4u8)
+        .map(|i| OperationId::generate_from_hash(Hash::compute_from(&[i])))
+        .collect();
+    ids.iter().for_each(|id| filter.insert(id));
+
+    // the removed ID is still reported as maybe present
+    assert!(!filter.remove_lazily(1, ids[1..].iter()));
+    assert!(filter.contains(&ids[0]));
+
+    // 3 stale IDs for 1 remaining: the filter is rebuilt
+    assert!(filter.remove_lazily(2, ids[3..].iter()));
+    assert!(ids[..3].iter().all(|id| !filter.contains(id)));
+    assert!(filter.contains(&ids[3]));
+
+    // the stale count was reset by the rebuild
+    assert!(!filter.remove_lazily(0, ids[3..].iter()));
+}
+
+/// Test that the operations included in a final block are dropped from the pool.
+#[test]
+fn test_notify_block_finalized() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let op_gen = OpGenerator::default().expirery(2);
+            let ops = create_some_operations(5, &op_gen);
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops.clone());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+
+            let ids: Vec<OperationId> = ops.iter().map(|op| op.id).collect();
+            let block_id = BlockId::generate_from_hash(Hash::compute_from("block".as_bytes()));
+            operation_pool
+                .notify_block_finalized(&block_id, &ids[..3], &[])
+                .unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(
+                operation_pool.contains_operations(&ids),
+                vec![false, false, false, true, true]
+            );
+        },
+    );
+}
+
+/// Test that only the operations unknown to a peer are returned for propagation.
+#[test]
+fn test_get_operations_for_propagation() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let op_gen = OpGenerator::default().expirery(2);
+            let ops = create_some_operations(5, &op_gen);
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops.clone());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+
+            let peer_known_ids = vec![ops[0].id, ops[1].id];
+            let (op_ids, op_storage) =
+                operation_pool.get_operations_for_propagation(&peer_known_ids, 10);
+            assert_eq!(op_ids.len(), 3);
+            assert_eq!(op_storage.get_op_refs().len(), 3);
+            assert!(op_ids.iter().all(|id| !peer_known_ids.contains(id)));
+
+            let (op_ids, op_storage) =
+                operation_pool.get_operations_for_propagation(&peer_known_ids, 2);
+            assert_eq!(op_ids.len(), 2);
+            assert_eq!(op_storage.get_op_refs().len(), 2);
+        },
+    );
+}
+
+/// Test that the operations of an orphaned block are added back to the pool.
+#[test]
+fn test_reinject_operations_from_block() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let keypair = KeyPair::generate(0).unwrap();
+            let op_gen = OpGenerator::default().expirery(2);
+            let ops = create_some_operations(5, &op_gen);
+            let block = create_block(&keypair, Slot::new(1, 0), &ops);
+            let block_id = block.id;
+            let mut block_storage = storage.clone_without_refs();
+            block_storage.store_operations(ops.clone());
+            block_storage.store_block(block);
+
+            operation_pool
+                .reinject_operations_from_block(block_id, block_storage)
+                .unwrap();
+            operation_pool.flush_operations().unwrap();
+            let ids: Vec<OperationId> = ops.iter().map(|op| op.id).collect();
+            assert_eq!(operation_pool.contains_operations(&ids), vec![true; 5]);
+        },
+    );
+}
+
+/// Test that a snapshot of the pool, once serialized, restores the same operations into another pool.
+#[test]
+fn test_operation_pool_snapshot_roundtrip() {
+    let op_gen = OpGenerator::default().expirery(2);
+    let ops = create_some_operations(5, &op_gen);
+    let ids: Vec<OperationId> = ops.iter().map(|op| op.id).collect();
+    let mut buffer = Vec::new();
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops.clone());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+
+            let snapshot = operation_pool.snapshot_operations();
+            assert_eq!(snapshot.operation_ids.len(), 5);
+            OperationPoolSnapshotSerializer::new()
+                .serialize(&snapshot, &mut buffer)
+                .unwrap();
+        },
+    );
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let (rest, snapshot) = OperationPoolSnapshotDeserializer::new(
+                &storage,
+                MAX_OPERATIONS_PER_MESSAGE,
+                MAX_DATASTORE_VALUE_LENGTH,
+                MAX_FUNCTION_NAME_LENGTH,
+                MAX_PARAMETERS_SIZE,
+                MAX_OPERATION_DATASTORE_ENTRY_COUNT,
+                MAX_OPERATION_DATASTORE_KEY_LENGTH,
+                MAX_OPERATION_DATASTORE_VALUE_LENGTH,
+            )
+            .deserialize::<DeserializeError>(&buffer)
+            .unwrap();
+            assert!(rest.is_empty());
+            operation_pool.restore_from_snapshot(snapshot).unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(operation_pool.contains_operations(&ids), vec![true; 5]);
+        },
+    );
+}
+
+/// Test membership queries spread over several shards, after additions and removals.
+#[test]
+fn test_contains_operations_sharded() {
+    let pool_config = PoolConfigBuilder::default()
+        .with_operation_pool_shard_count(4)
+        .build()
+        .unwrap();
+    operation_pool_test(
+        pool_config,
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let op_gen = OpGenerator::default().expirery(2);
+            let ops = create_some_operations(20, &op_gen);
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops[..15].to_vec());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+
+            let ids: Vec<OperationId> = ops.iter().map(|op| op.id).collect();
+            operation_pool.remove_operations(&ids[..5]).unwrap();
+            operation_pool.flush_operations().unwrap();
+
+            let expected: Vec<bool> = (0..20).map(|i| (5..15).contains(&i)).collect();
+            assert_eq!(operation_pool.contains_operations(&ids), expected);
+        },
+    );
+}
+
+/// Test that the operations of a sender are listed, and forgotten once removed from the pool.
+#[test]
+fn test_get_operations_by_sender() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let sender = KeyPair::generate(0).unwrap();
+            let sender_address = Address::from_public_key(&sender.get_public_key());
+            let sender_ops =
+                create_some_operations(3, &OpGenerator::default().expirery(2).creator(sender));
+            let other_ops = create_some_operations(2, &OpGenerator::default().expirery(2));
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(sender_ops.iter().chain(&other_ops).cloned().collect());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+
+            let mut expected: Vec<OperationId> = sender_ops.iter().map(|op| op.id).collect();
+            expected.sort();
+            let (ids, res_storage) = operation_pool.get_operations_by_sender(&sender_address);
+            assert_eq!(ids, expected);
+            assert_eq!(res_storage.get_op_refs().len(), 3);
+
+            operation_pool.remove_operations(&expected).unwrap();
+            operation_pool.flush_operations().unwrap();
+            let (ids, _) = operation_pool.get_operations_by_sender(&sender_address);
+            assert!(ids.is_empty());
+        },
+    );
+}
+
+/// Test the pagination of the operations sorted by fee, including out of range pages.
+#[test]
+fn test_get_operations_sorted_by_fee() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let (ids, res_storage) = operation_pool.get_operations_sorted_by_fee(10, 0);
+            assert!(ids.is_empty());
+            assert!(res_storage.get_op_refs().is_empty());
+
+            // operations of the same size, so that their fee per byte follows their fee
+            let ops: Vec<SecureShareOperation> = (1..=5)
+                .map(|fee| {
+                    OpGenerator::default()
+                        .expirery(2)
+                        .fee(Amount::from_raw(fee))
+                        .generate()
+                })
+                .collect();
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops.clone());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+            let by_decreasing_fee: Vec<OperationId> = ops.iter().rev().map(|op| op.id).collect();
+
+            let (ids, res_storage) = operation_pool.get_operations_sorted_by_fee(2, 0);
+            assert_eq!(ids, by_decreasing_fee[..2]);
+            assert_eq!(res_storage.get_op_refs().len(), 2);
+            let (ids, _) = operation_pool.get_operations_sorted_by_fee(2, 4);
+            assert_eq!(ids, by_decreasing_fee[4..]);
+            let (ids, _) = operation_pool.get_operations_sorted_by_fee(10, 0);
+            assert_eq!(ids, by_decreasing_fee);
+            let (ids, res_storage) = operation_pool.get_operations_sorted_by_fee(10, 6);
+            assert!(ids.is_empty());
+            assert!(res_storage.get_op_refs().is_empty());
+            let (ids, _) = operation_pool.get_operations_sorted_by_fee(0, 0);
+            assert!(ids.is_empty());
+        },
+    );
+}
+
+/// Test that a controller shared with `arc_clone` reads the same pool from another thread.
+#[test]
+fn test_arc_clone() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let shared_pool = operation_pool.arc_clone();
+            let ops = create_some_operations(3, &OpGenerator::default().expirery(2));
+            let ids: Vec<OperationId> = ops.iter().map(|op| op.id).collect();
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops);
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+
+            let reader = std::thread::spawn(move || shared_pool.contains_operations(&ids));
+            assert_eq!(reader.join().unwrap(), vec![true; 3]);
+        },
+    );
+}
+
+/// Test the pages of operation IDs: first, middle, last and beyond the end of the pool.
+#[test]
+fn test_get_operation_ids_page() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            assert!(operation_pool.get_operation_ids_page(0, 2).is_empty());
+
+            // operations of the same size, so that their fee per byte follows their fee
+            let ops: Vec<SecureShareOperation> = (1..=5)
+                .map(|fee| {
+                    OpGenerator::default()
+                        .expirery(2)
+                        .fee(Amount::from_raw(fee))
+                        .generate()
+                })
+                .collect();
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops.clone());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+            let by_decreasing_fee: Vec<OperationId> = ops.iter().rev().map(|op| op.id).collect();
+
+            assert_eq!(
+                operation_pool.get_operation_ids_page(0, 2),
+                by_decreasing_fee[..2]
+            );
+            assert_eq!(
+                operation_pool.get_operation_ids_page(1, 2),
+                by_decreasing_fee[2..4]
+            );
+            // the last page is partial
+            assert_eq!(
+                operation_pool.get_operation_ids_page(2, 2),
+                by_decreasing_fee[4..]
+            );
+            assert!(operation_pool.get_operation_ids_page(3, 2).is_empty());
+            assert!(operation_pool
+                .get_operation_ids_page(usize::MAX, 2)
+                .is_empty());
+            assert!(operation_pool.get_operation_ids_page(0, 0).is_empty());
+        },
+    );
+}
+
+/// Test that pools with the same contents have the same hash, whatever the insertion order,
+/// and that adding an operation changes it.
+#[test]
+fn test_compute_pool_hash() {
+    let op_gen = OpGenerator::default().expirery(2);
+    let ops = create_some_operations(5, &op_gen);
+    let extra_op = op_gen.generate();
+    let start_pool = |ops: Vec<SecureShareOperation>| {
+        let PoolTestBoilerPlate {
+            pool_manager,
+            mut pool_controller,
+            storage,
+        } = PoolTestBoilerPlate::pool_test(
+            PoolConfig::default(),
+            default_execution_controller(),
+            default_selector_controller(),
+        );
+        let mut ops_storage = storage.clone_without_refs();
+        ops_storage.store_operations(ops);
+        pool_controller.add_operations(ops_storage).unwrap();
+        (pool_manager, pool_controller, storage)
+    };
+    let (mut manager_a, mut pool_a, storage_a) = start_pool(ops.clone());
+    let (mut manager_b, pool_b, _) = start_pool(ops.iter().rev().cloned().collect());
+    pool_a.flush_operations().unwrap();
+    pool_b.flush_operations().unwrap();
+    let hash = pool_a.compute_pool_hash();
+    assert_eq!(hash, pool_b.compute_pool_hash());
+
+    let mut ops_storage = storage_a.clone_without_refs();
+    ops_storage.store_operations(vec![extra_op]);
+    pool_a.add_operations(ops_storage).unwrap();
+    pool_a.flush_operations().unwrap();
+    assert_ne!(pool_a.compute_pool_hash(), hash);
+
+    manager_a.stop();
+    manager_b.stop();
+}
+
+/// Test the operations kept by the pool while cycling through minimal fee thresholds,
+/// setting the same threshold twice being a no-op.
+#[test]
+fn test_set_min_fee_thresholds() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let ops: Vec<_> = (1..=10)
+                .map(|fee| {
+                    OpGenerator::default()
+                        .expirery(2)
+                        .fee(Amount::from_raw(fee))
+                        .generate()
+                })
+                .collect();
+            let ids: Vec<OperationId> = ops.iter().map(|op| op.id).collect();
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops.clone());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+            let in_pool_above = |min_fee: u64| -> Vec<bool> {
+                ops.iter()
+                    .map(|op| op.content.fee >= Amount::from_raw(min_fee))
+                    .collect()
+            };
+
+            for (min_fee, expected_pruned, kept_above) in [
+                (4, 3, 4),
+                (4, 0, 4),
+                (2, 0, 4),
+                (7, 3, 7),
+                (7, 0, 7),
+                (0, 0, 7),
+                (11, 4, 11),
+            ] {
+                assert_eq!(
+                    operation_pool
+                        .set_min_fee(Amount::from_raw(min_fee))
+                        .unwrap(),
+                    expected_pruned,
+                    "pruned operations at minimal fee {}",
+                    min_fee
+                );
+                assert_eq!(
+                    operation_pool.contains_operations(&ids),
+                    in_pool_above(kept_above),
+                    "operations of the pool at minimal fee {}",
+                    min_fee
+                );
+            }
+        },
+    );
+}
+
+/// Test that the operations with an invalid signature are dropped on insertion
+/// when the validation is enabled, on the worker thread or on dedicated threads.
+#[test]
+fn test_validation_parallelism() {
+    for validation_parallelism in [0, 1, 4] {
+        operation_pool_test(
+            PoolConfigBuilder::default()
+                .with_validation_parallelism(validation_parallelism)
+                .build()
+                .unwrap(),
+            default_execution_controller(),
+            default_selector_controller(),
+            |mut operation_pool, storage| {
+                let op_gen = OpGenerator::default().expirery(2);
+                let valid_ops = create_some_operations(5, &op_gen);
+                let mut forged_op = op_gen.generate();
+                forged_op.signature = valid_ops[0].signature;
+                let mut ops_storage = storage.clone_without_refs();
+                ops_storage.store_operations(
+                    valid_ops
+                        .iter()
+                        .chain(std::iter::once(&forged_op))
+                        .cloned()
+                        .collect(),
+                );
+                operation_pool.add_operations(ops_storage).unwrap();
+                operation_pool.flush_operations().unwrap();
+
+                let ids: Vec<OperationId> = valid_ops.iter().map(|op| op.id).collect();
+                assert_eq!(operation_pool.contains_operations(&ids), vec![true; 5]);
+                assert_eq!(
+                    operation_pool.contains_operations(&[forged_op.id]),
+                    vec![validation_parallelism == 0]
+                );
+            },
+        );
+    }
+}
+
+/// Test that the operations fingerprint only depends on the set of operations of the pool:
+/// whatever the order in which they are added, and once an operation is removed then added back.
+#[test]
+fn test_operations_fingerprint() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            assert_eq!(operation_pool.operations_fingerprint(), 0);
+            let ops = create_some_operations(5, &OpGenerator::default().expirery(2));
+            let ids: Vec<OperationId> = ops.iter().map(|op| op.id).collect();
+            let expected = ids.iter().fold(0u64, |fingerprint, id| {
+                let bytes = id.get_hash().to_bytes();
+                fingerprint ^ u64::from_be_bytes(bytes[..8].try_into().unwrap())
+            });
+
+            // add the operations one by one, starting from each of them in turn
+            for rotation in 0..ops.len() {
+                for op in ops.iter().cycle().skip(rotation).take(ops.len()) {
+                    let mut ops_storage = storage.clone_without_refs();
+                    ops_storage.store_operations(vec![op.clone()]);
+                    operation_pool.add_operations(ops_storage).unwrap();
+                }
+                operation_pool.flush_operations().unwrap();
+                assert_eq!(operation_pool.operations_fingerprint(), expected);
+
+                operation_pool.remove_operations(&ids).unwrap();
+                operation_pool.flush_operations().unwrap();
+                assert_eq!(operation_pool.operations_fingerprint(), 0);
+            }
+
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops.clone());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.remove_operations(&ids[2..3]).unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert_ne!(operation_pool.operations_fingerprint(), expected);
+
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(vec![ops[2].clone()]);
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(operation_pool.operations_fingerprint(), expected);
+        },
+    );
+}
+
+/// Test the fee percentiles of a pool holding one operation per raw fee from 1 to 10.
+#[test]
+fn test_get_fee_percentiles() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            assert_eq!(operation_pool.get_fee_percentiles(&[50]), vec![0]);
+
+            let ops: Vec<_> = (1..=10)
+                .map(|fee| {
+                    OpGenerator::default()
+                        .expirery(2)
+                        .fee(Amount::from_raw(fee))
+                        .generate()
+                })
+                .collect();
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops);
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+
+            assert_eq!(
+                operation_pool.get_fee_percentiles(&[10, 50, 75, 90, 95, 99]),
+                vec![1, 5, 8, 9, 10, 10]
+            );
+            assert_eq!(
+                operation_pool.get_fee_percentiles(&[100, 0, 255]),
+                vec![10, 1, 10]
+            );
+        },
+    );
+}
+
+/// Test that an operation one byte over the max operation size is dropped.
+#[test]
+fn test_max_operation_size() {
+    let creator = KeyPair::generate(0).unwrap();
+    let receiver = KeyPair::generate(0).unwrap();
+    // the fee varint takes one more byte from 128
+    let small_op = OpGenerator::default()
+        .creator(creator.clone())
+        .receiver(receiver.clone())
+        .expirery(2)
+        .fee(Amount::from_raw(127))
+        .generate();
+    let big_op = OpGenerator::default()
+        .creator(creator)
+        .receiver(receiver)
+        .expirery(2)
+        .fee(Amount::from_raw(128))
+        .generate();
+    let max_operation_size_bytes = small_op.serialized_size();
+    assert_eq!(big_op.serialized_size(), max_operation_size_bytes + 1);
+    let pool_config = PoolConfigBuilder::default()
+        .with_max_operation_size_bytes(max_operation_size_bytes)
+        .build()
+        .unwrap();
+    operation_pool_test(
+        pool_config,
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(vec![small_op.clone(), big_op.clone()]);
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+
+            assert_eq!(
+                operation_pool.contains_operations(&[small_op.id, big_op.id]),
+                vec![true, false]
+            );
+            assert_eq!(operation_pool.pool_stats().oversized_ops_dropped, 1);
+        },
+    );
+}
+
+/// Test that raising the minimal fee prunes the operations paying less, as below the minimal fee,
+/// and that the operations paying less are rejected afterwards.
+#[test]
+fn test_set_min_fee() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let ops: Vec<_> = (1..=10)
+                .map(|fee| {
+                    OpGenerator::default()
+                        .expirery(2)
+                        .fee(Amount::from_raw(fee))
+                        .generate()
+                })
+                .collect();
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops.clone());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(operation_pool.get_operation_count(), 10);
+            let events = operation_pool.subscribe_to_pool_events();
+
+            assert_eq!(operation_pool.set_min_fee(Amount::from_raw(6)).unwrap(), 5);
+            let ids: Vec<OperationId> = ops.iter().map(|op| op.id).collect();
+            let expected: Vec<bool> = ops
+                .iter()
+                .map(|op| op.content.fee >= Amount::from_raw(6))
+                .collect();
+            assert_eq!(operation_pool.contains_operations(&ids), expected);
+            assert_eq!(operation_pool.get_operation_count(), 5);
+            let mut pruned: Vec<OperationId> = events
+                .try_iter()
+                .map(|event| match event {
+                    PoolEvent::OperationEvicted {
+                        id,
+                        reason: EvictionReason::BelowMinFee,
+                    } => id,
+                    event => panic!("unexpected pool event {:?}", event),
+                })
+                .collect();
+            pruned.sort();
+            let mut expected_pruned: Vec<OperationId> = ids[..5].to_vec();
+            expected_pruned.sort();
+            assert_eq!(pruned, expected_pruned);
+
+            let cheap_op = OpGenerator::default()
+                .expirery(2)
+                .fee(Amount::from_raw(5))
+                .generate();
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(vec![cheap_op.clone()]);
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(
+                operation_pool.contains_operations(&[cheap_op.id]),
+                vec![false]
+            );
+        },
+    );
+}
+
+/// Test that the operations whose last valid period becomes final are dropped at once,
+/// the other ones being kept.
+#[test]
+fn test_notify_final_cs_periods_drops_expired_operations() {
+    let thread_count = PoolConfig::default().thread_count;
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let expiring_ops = create_some_operations(20, &OpGenerator::default().expirery(2));
+            let kept_ops = create_some_operations(30, &OpGenerator::default().expirery(10));
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(expiring_ops.iter().chain(&kept_ops).cloned().collect());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(operation_pool.get_operation_count(), 50);
+
+            operation_pool.notify_final_cs_periods(&vec![5; thread_count as usize]);
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(operation_pool.get_operation_count(), 30);
+            let kept_ids: Vec<OperationId> = kept_ops.iter().map(|op| op.id).collect();
+            assert_eq!(
+                operation_pool.contains_operations(&kept_ids),
+                vec![true; 30]
+            );
+        },
+    );
+}
+
+/// Test that a sender sending twice the per-sender limit only keeps its highest fee operations.
+#[test]
+fn test_max_ops_per_sender() {
+    let max_ops_per_sender = 5;
+    let pool_config = PoolConfigBuilder::default()
+        .with_max_ops_per_sender(max_ops_per_sender)
+        .build()
+        .unwrap();
+    operation_pool_test(
+        pool_config,
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let sender = KeyPair::generate(0).unwrap();
+            let sender_address = Address::from_public_key(&sender.get_public_key());
+            let ops: Vec<_> = (1..=2 * max_ops_per_sender as u64)
+                .map(|fee| {
+                    OpGenerator::default()
+                        .expirery(2)
+                        .creator(sender.clone())
+                        .fee(Amount::from_raw(fee))
+                        .generate()
+                })
+                .collect();
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops.clone());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+
+            let (ids, _) = operation_pool.get_operations_by_sender(&sender_address);
+            assert_eq!(ids.len(), max_ops_per_sender);
+            let mut expected: Vec<OperationId> =
+                ops[max_ops_per_sender..].iter().map(|op| op.id).collect();
+            expected.sort();
+            assert_eq!(ids, expected);
+            assert_eq!(
+                operation_pool.pool_stats().sender_limit_evicted,
+                max_ops_per_sender as u64
+            );
+        },
+    );
+}
+
+/// Test that the senders having the most operations are reported by decreasing operation count.
+#[test]
+fn test_top_senders_by_operation_count() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            assert!(operation_pool.top_senders_by_operation_count(10).is_empty());
+
+            let senders: Vec<KeyPair> = (0..3).map(|_| KeyPair::generate(0).unwrap()).collect();
+            let mut ops = Vec::new();
+            for (sender_index, sender) in senders.iter().enumerate() {
+                // the first sender sends 3 operations, the second 2 and the last one 1
+                for fee in 0..(3 - sender_index) as u64 {
+                    ops.push(
+                        OpGenerator::default()
+                            .expirery(2)
+                            .creator(sender.clone())
+                            .fee(Amount::from_raw(fee + 1))
+                            .generate(),
+                    );
+                }
+            }
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops);
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+
+            let expected: Vec<(Address, usize)> = senders
+                .iter()
+                .zip([3, 2, 1])
+                .map(|(sender, count)| (Address::from_public_key(&sender.get_public_key()), count))
+                .collect();
+            assert_eq!(
+                operation_pool.top_senders_by_operation_count(2),
+                expected[..2]
+            );
+            assert_eq!(operation_pool.top_senders_by_operation_count(10), expected);
+            assert!(operation_pool.top_senders_by_operation_count(0).is_empty());
+            assert_eq!(operation_pool.pool_stats().top_10_senders, expected);
+        },
+    );
+}
+
+/// Test that a sender sending twice the per-slot limit in one slot only gets the limit added,
+/// the other operations being accepted again once a later slot is notified.
+#[test]
+fn test_max_ops_added_per_sender_per_slot() {
+    let max_ops_added_per_sender_per_slot = 5;
+    let pool_config = PoolConfigBuilder::default()
+        .with_max_ops_added_per_sender_per_slot(max_ops_added_per_sender_per_slot)
+        .build()
+        .unwrap();
+    operation_pool_test(
+        pool_config,
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let sender = KeyPair::generate(0).unwrap();
+            let sender_address = Address::from_public_key(&sender.get_public_key());
+            let ops: Vec<_> = (1..=2 * max_ops_added_per_sender_per_slot as u64)
+                .map(|fee| {
+                    OpGenerator::default()
+                        .expirery(10)
+                        .creator(sender.clone())
+                        .fee(Amount::from_raw(fee))
+                        .generate()
+                })
+                .collect();
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops.clone());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+
+            let (ids, _) = operation_pool.get_operations_by_sender(&sender_address);
+            assert_eq!(ids.len(), max_ops_added_per_sender_per_slot);
+            assert_eq!(operation_pool.pool_stats().sender_limit_evicted, 0);
+
+            // the skipped operations are added at the next slot,
+            // notified ahead of the clock (the default genesis being now)
+            operation_pool.notify_new_slot(Slot::new(5, 0)).unwrap();
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops.clone());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+            let (ids, _) = operation_pool.get_operations_by_sender(&sender_address);
+            assert_eq!(ids.len(), 2 * max_ops_added_per_sender_per_slot);
+        },
+    );
+}
+
+/// Test that clearing the operation pool empties it and resets its statistics,
+/// the worker still accepting operations afterwards.
+#[cfg(feature = "testing")]
+#[test]
+fn test_clear_operations() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let ops = create_some_operations(3, &OpGenerator::default().expirery(2));
+            let ids: Vec<OperationId> = ops.iter().map(|op| op.id).collect();
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops.clone());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(operation_pool.pool_stats().total_ops_added, 3);
+            let events = operation_pool.subscribe_to_pool_events();
+
+            operation_pool.clear_operations().unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(operation_pool.get_operation_count(), 0);
+            assert_eq!(operation_pool.contains_operations(&ids), vec![false; 3]);
+            let reasons: Vec<EvictionReason> = events
+                .try_iter()
+                .map(|event| match event {
+                    PoolEvent::OperationEvicted { reason, .. } => reason,
+                    event => panic!("unexpected pool event {:?}", event),
+                })
+                .collect();
+            assert_eq!(reasons, vec![EvictionReason::Purged; 3]);
+            let stats = operation_pool.pool_stats();
+            assert_eq!((stats.total_ops_added, stats.total_ops_evicted), (0, 0));
+
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops);
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(operation_pool.contains_operations(&ids), vec![true; 3]);
+        },
+    );
+}
+
+/// Test that the operations sent through the async sender reach the pool.
+#[cfg(feature = "async")]
+#[test]
+fn test_add_operations_async() {
+    use crate::start_pool_controller_with_async_sender;
+    use massa_pool_exports::PoolChannels;
+    use massa_storage::Storage;
+    use massa_wallet::test_exports::create_test_wallet;
+    use parking_lot::RwLock;
+    use std::sync::Arc;
+    use tokio::sync::broadcast;
+
+    let storage = Storage::create_root();
+    let (mut pool_manager, pool_controller, mut async_sender) =
+        start_pool_controller_with_async_sender(
+            PoolConfig::default(),
+            &storage,
+            PoolChannels {
+                execution_controller: default_execution_controller(),
+                endorsement_sender: broadcast::channel(2000).0,
+                operation_sender: broadcast::channel(5000).0,
+                selector: default_selector_controller(),
+            },
+            Arc::new(RwLock::new(create_test_wallet(None))),
+        )
+        .unwrap();
+
+    let ops = create_some_operations(10, &OpGenerator::default().expirery(2));
+    let ids: Vec<OperationId> = ops.iter().map(|op| op.id).collect();
+    let mut ops_storage = storage.clone_without_refs();
+    ops_storage.store_operations(ops);
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    runtime
+        .block_on(async_sender.add_operations_async(ops_storage))
+        .unwrap();
+    // the operations are forwarded to the worker by another thread
+    assert!(wait_until(Duration::from_secs(5), || {
+        pool_controller.contains_operations(&ids) == vec![true; 10]
+    }));
+
+    pool_manager.stop();
+}
+
+/// Test that the operations added before a flush are in the pool once it returns.
+#[test]
+fn test_flush_operations() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let ops = create_some_operations(10, &OpGenerator::default().expirery(2));
+            let ids: Vec<OperationId> = ops.iter().map(|op| op.id).collect();
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops);
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(operation_pool.contains_operations(&ids), vec![true; 10]);
+        },
+    );
+}
+
+/// Test that `sync_get_operation_count` accounts for all the previously queued additions.
+#[test]
+fn test_sync_get_operation_count() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            assert_eq!(operation_pool.sync_get_operation_count().unwrap(), 0);
+            let ops = create_some_operations(10, &OpGenerator::default().expirery(2));
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops);
+            operation_pool.add_operations(ops_storage).unwrap();
+            assert_eq!(operation_pool.sync_get_operation_count().unwrap(), 10);
+        },
+    );
+}
+
+/// Test that the operations whose type is not accepted are dropped,
+/// the allow list taking precedence over the deny list.
+#[test]
+fn test_operation_type_filters() {
+    for (allowed, denied, expected_executions) in [
+        (None, Some(vec![OperationTypeId::ExecuteSC]), 0),
+        (Some(vec![OperationTypeId::Transaction]), None, 0),
+        (
+            Some(vec![
+                OperationTypeId::Transaction,
+                OperationTypeId::ExecuteSC,
+            ]),
+            Some(vec![OperationTypeId::ExecuteSC]),
+            2,
+        ),
+    ] {
+        operation_pool_test(
+            PoolConfigBuilder::default()
+                .with_allowed_operation_types(allowed)
+                .with_denied_operation_types(denied)
+                .build()
+                .unwrap(),
+            default_execution_controller(),
+            default_selector_controller(),
+            |mut operation_pool, storage| {
+                let transactions = create_some_operations(3, &OpGenerator::default().expirery(2));
+                let executions =
+                    create_some_operations(2, &OpGenerator::default().expirery(2).max_gas(1_000));
+                let mut ops_storage = storage.clone_without_refs();
+                ops_storage.store_operations(transactions);
+                ops_storage.store_operations(executions);
+                operation_pool.add_operations(ops_storage).unwrap();
+                operation_pool.flush_operations().unwrap();
+
+                let stats = operation_pool.pool_stats();
+                assert_eq!(stats.operation_count, 3 + expected_executions);
+                assert_eq!(
+                    stats.type_filtered_ops_dropped,
+                    2 - expected_executions as u64
+                );
+            },
+        );
+    }
+}
+
+/// Test `contains_all_operations` and `contains_any_operation` on present and absent operations.
+#[test]
+fn test_contains_all_and_any_operations() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let ops = create_some_operations(4, &OpGenerator::default().expirery(2));
+            let ids: Vec<OperationId> = ops.iter().map(|op| op.id).collect();
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops[..2].to_vec());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+
+            assert!(operation_pool.contains_all_operations(&ids[..2]));
+            assert!(!operation_pool.contains_all_operations(&ids));
+            assert!(operation_pool.contains_all_operations(&[]));
+            assert!(operation_pool.contains_any_operation(&ids[1..]));
+            assert!(!operation_pool.contains_any_operation(&ids[2..]));
+            assert!(!operation_pool.contains_any_operation(&[]));
+        },
+    );
+}
+
+/// Test that `get_operations_above_fee` returns the operations paying at least the given fee,
+/// by decreasing fee, and releases the pool once dropped.
+#[test]
+fn test_get_operations_above_fee() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let creator = KeyPair::generate(0).unwrap();
+            let ops: Vec<SecureShareOperation> = (1..=5)
+                .map(|fee| {
+                    OpGenerator::default()
+                        .creator(creator.clone())
+                        .expirery(2)
+                        .fee(Amount::from_raw(fee))
+                        .generate()
+                })
+                .collect();
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops.clone());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+
+            let above_fee: Vec<OperationId> = operation_pool
+                .get_operations_above_fee(Amount::from_raw(3))
+                .collect();
+            assert_eq!(above_fee, vec![ops[4].id, ops[3].id, ops[2].id]);
+            assert_eq!(
+                operation_pool
+                    .get_operations_above_fee(Amount::from_raw(6))
+                    .count(),
+                0
+            );
+
+            // the dropped iterators do not block the operation pool worker
+            operation_pool.remove_operations(&[ops[4].id]).unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(
+                operation_pool
+                    .get_operations_above_fee(Amount::from_raw(3))
+                    .count(),
+                2
+            );
+        },
+    );
+}
+
+/// Test that the watched pool sizes follow the additions and removals of operations.
+#[test]
+fn test_watch_pool_sizes() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let mut pool_sizes = operation_pool.watch_pool_sizes();
+            assert_eq!(*pool_sizes.borrow_and_update(), (0, 0));
+
+            let ops = create_some_operations(3, &OpGenerator::default().expirery(2));
+            let ids: Vec<OperationId> = ops.iter().map(|op| op.id).collect();
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops);
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert!(pool_sizes.has_changed().unwrap());
+            assert_eq!(*pool_sizes.borrow_and_update(), (3, 0));
+
+            operation_pool.remove_operations(&ids[..1]).unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(*pool_sizes.borrow_and_update(), (2, 0));
+        },
+    );
+}
+
+/// Test that a pool events subscriber is notified of the additions and removals of operations.
+#[test]
+fn test_subscribe_to_pool_events() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let events = operation_pool.subscribe_to_pool_events();
+            let ops = create_some_operations(3, &OpGenerator::default().expirery(2));
+            let mut ids: Vec<OperationId> = ops.iter().map(|op| op.id).collect();
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops);
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.remove_operations(&ids[..1]).unwrap();
+
+            let timeout = Duration::from_millis(500);
+            let mut added: Vec<OperationId> = (0..3)
+                .map(|_| match events.recv_timeout(timeout).unwrap() {
+                    PoolEvent::OperationAdded(id) => id,
+                    event => panic!("unexpected pool event {:?}", event),
+                })
+                .collect();
+            assert_eq!(
+                events.recv_timeout(timeout).unwrap(),
+                PoolEvent::OperationEvicted {
+                    id: ids[0],
+                    reason: EvictionReason::Included
+                }
+            );
+            added.sort();
+            ids.sort();
+            assert_eq!(added, ids);
+        },
+    );
+}
+
+/// Test that the eviction events tell why each operation left the pool.
+#[test]
+fn test_operation_eviction_reasons() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let ops = create_some_operations(3, &OpGenerator::default().expirery(2));
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops.clone());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+            let events = operation_pool.subscribe_to_pool_events();
+
+            operation_pool.remove_operations(&[ops[0].id]).unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(
+                events.try_recv().unwrap(),
+                PoolEvent::OperationEvicted {
+                    id: ops[0].id,
+                    reason: EvictionReason::Included
+                }
+            );
+
+            // the other operations expire once their period is over
+            operation_pool.notify_new_slot(Slot::new(3, 0)).unwrap();
+            operation_pool.flush_operations().unwrap();
+            let mut expired: Vec<OperationId> = events
+                .try_iter()
+                .map(|event| match event {
+                    PoolEvent::OperationEvicted {
+                        id,
+                        reason: EvictionReason::Expired,
+                    } => id,
+                    event => panic!("unexpected pool event {:?}", event),
+                })
+                .collect();
+            expired.sort();
+            let mut expected = vec![ops[1].id, ops[2].id];
+            expected.sort();
+            assert_eq!(expired, expected);
+        },
+    );
+}
+
+/// Test that purging the pool evicts all the operations as purged and resets the statistics,
+/// the pool accepting the same operations again afterwards.
+#[test]
+fn test_purge_pool() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let ops = create_some_operations(3, &OpGenerator::default().expirery(2));
+            let mut ids: Vec<OperationId> = ops.iter().map(|op| op.id).collect();
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops.clone());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+            let events = operation_pool.subscribe_to_pool_events();
+
+            operation_pool.purge_pool().unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(operation_pool.get_operation_count(), 0);
+            assert_eq!(operation_pool.contains_operations(&ids), vec![false; 3]);
+            let stats = operation_pool.pool_stats();
+            assert_eq!((stats.total_ops_added, stats.total_ops_evicted), (0, 0));
+            let mut purged: Vec<OperationId> = events
+                .try_iter()
+                .map(|event| match event {
+                    PoolEvent::OperationEvicted {
+                        id,
+                        reason: EvictionReason::Purged,
+                    } => id,
+                    event => panic!("unexpected pool event {:?}", event),
+                })
+                .collect();
+            purged.sort();
+            ids.sort();
+            assert_eq!(purged, ids);
+
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops);
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(operation_pool.contains_operations(&ids), vec![true; 3]);
+        },
+    );
+}
+
+/// Test that the operations waiting for more than the given number of slots are returned
+/// from the oldest to the most recent.
+#[test]
+fn test_get_stale_operations() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let op_gen = OpGenerator::default().expirery(10);
+            let old_ops = create_some_operations(2, &op_gen);
+            let recent_ops = create_some_operations(1, &op_gen);
+            operation_pool.notify_new_slot(Slot::new(1, 0)).unwrap();
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(old_ops.clone());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.notify_new_slot(Slot::new(1, 5)).unwrap();
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(recent_ops.clone());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.notify_new_slot(Slot::new(1, 10)).unwrap();
+            operation_pool.flush_operations().unwrap();
+
+            let current_slot = Slot::new(1, 10);
+            let mut stale = operation_pool.get_stale_operations(&current_slot, 7);
+            stale.sort();
+            let mut expected: Vec<OperationId> = old_ops.iter().map(|op| op.id).collect();
+            expected.sort();
+            assert_eq!(stale, expected);
+
+            // the operation added last is the most recent one
+            let stale = operation_pool.get_stale_operations(&current_slot, 4);
+            assert_eq!(stale.len(), 3);
+            assert_eq!(stale[2], recent_ops[0].id);
+            assert!(operation_pool
+                .get_stale_operations(&current_slot, 10)
+                .is_empty());
+        },
+    );
+}
+
+/// Test that only the operations expiring within the requested periods are returned.
+#[test]
+fn test_get_operations_expiring_within_periods() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let soon_ops = create_some_operations(3, &OpGenerator::default().expirery(4));
+            let later_ops = create_some_operations(2, &OpGenerator::default().expirery(10));
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(soon_ops.iter().chain(&later_ops).cloned().collect());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+
+            let (mut ids, res_storage) =
+                operation_pool.get_operations_expiring_within_periods(&Slot::new(2, 0), 3);
+            let mut expected: Vec<OperationId> = soon_ops.iter().map(|op| op.id).collect();
+            ids.sort();
+            expected.sort();
+            assert_eq!(ids, expected);
+            assert_eq!(res_storage.get_op_refs().len(), 3);
+
+            let (ids, _) =
+                operation_pool.get_operations_expiring_within_periods(&Slot::new(5, 0), 10);
+            assert_eq!(ids.len(), 2);
+        },
+    );
+}
+
+/// Test that the pool statistics follow additions and removals of operations.
+#[test]
+fn test_pool_stats() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let op_gen = OpGenerator::default().expirery(2);
+            let ops = create_some_operations(5, &op_gen);
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops.clone());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+
+            let ids: Vec<OperationId> = ops.iter().map(|op| op.id).collect();
+            operation_pool.remove_operations(&ids[..2]).unwrap();
+            operation_pool.flush_operations().unwrap();
+
+            let stats = operation_pool.pool_stats();
+            assert_eq!(stats.operation_count, 3);
+            assert_eq!(stats.total_ops_added, 5);
+            assert_eq!(stats.total_ops_evicted, 2);
+            assert_eq!(
+                stats.operation_pool_memory_bytes,
+                ops[2..]
+                    .iter()
+                    .map(|op| op.serialized_size())
+                    .sum::<usize>()
+            );
+            assert_eq!(stats.endorsement_count, 0);
+            assert_eq!(stats.total_endorsements_added, 0);
+        },
+    );
+}
+
+/// Test that the memory usage estimation covers at least the serialized operations.
+#[test]
+fn test_get_memory_usage() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            assert_eq!(
+                operation_pool.get_memory_usage(),
+                PoolMemoryUsage::default()
+            );
+
+            let op_gen = OpGenerator::default().expirery(2);
+            let ops = create_some_operations(5, &op_gen);
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops.clone());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+
+            let usage = operation_pool.get_memory_usage();
+            let serialized_bytes: usize = ops.iter().map(|op| op.serialized_size()).sum();
+            assert!(usage.operation_pool_bytes > serialized_bytes);
+            assert_eq!(usage.endorsement_pool_bytes, 0);
+
+            let ids: Vec<OperationId> = ops.iter().map(|op| op.id).collect();
+            operation_pool.remove_operations(&ids).unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(operation_pool.get_memory_usage().operation_pool_bytes, 0);
+        },
+    );
+}
+
+/// Test that the operations are selected by the overlap of their validity window with a slot range.
+#[test]
+fn test_get_operations_for_slot_range() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let creator = KeyPair::generate(0).unwrap();
+            // validity windows covering periods 0 to 5, 20 to 30 and 2 to 12
+            let ops: Vec<SecureShareOperation> = [5, 30, 12]
+                .into_iter()
+                .map(|expire_period| {
+                    OpGenerator::default()
+                        .creator(creator.clone())
+                        .expirery(expire_period)
+                        .generate()
+                })
+                .collect();
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops.clone());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+
+            let (ids, res_storage) =
+                operation_pool.get_operations_for_slot_range(&Slot::new(6, 0), &Slot::new(15, 0));
+            assert_eq!(ids, vec![ops[2].id]);
+            assert_eq!(res_storage.get_op_refs().len(), 1);
+
+            let (ids, _) =
+                operation_pool.get_operations_for_slot_range(&Slot::new(0, 0), &Slot::new(100, 0));
+            assert_eq!(ids, vec![ops[0].id, ops[2].id, ops[1].id]);
+
+            let (ids, _) =
+                operation_pool.get_operations_for_slot_range(&Slot::new(31, 0), &Slot::new(100, 0));
+            assert!(ids.is_empty());
+        },
+    );
+}
+
+/// Test that the pool dump lists the operations of the pool, best operations first.
+#[test]
+fn test_dump_pool_state() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            assert_eq!(operation_pool.dump_pool_state(), PoolDump::default());
+
+            let ops: Vec<SecureShareOperation> = (1..=3)
+                .map(|fee| {
+                    OpGenerator::default()
+                        .expirery(2)
+                        .fee(Amount::from_raw(fee))
+                        .generate()
+                })
+                .collect();
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops.clone());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+
+            let dump = operation_pool.dump_pool_state();
+            let dumped_ids: Vec<OperationId> = dump.operations.iter().map(|op| op.id).collect();
+            assert_eq!(dumped_ids, vec![ops[2].id, ops[1].id, ops[0].id]);
+            assert_eq!(dump.operations[0].fee, Amount::from_raw(3));
+            assert!(dump.endorsements.is_empty());
+
+            let json = dump.dump_to_json_string().unwrap();
+            assert!(ops.iter().all(|op| json.contains(&op.id.to_string())));
+        },
+    );
+}
+
+/// Test that the lowest-fee operations are evicted once the memory limit of the pool is exceeded.
+#[test]
+fn test_memory_limit_eviction() {
+    let op_size = OpGenerator::default()
+        .expirery(2)
+        .generate()
+        .serialized_size();
+    let cfg = PoolConfigBuilder::default()
+        .with_memory_limit_bytes(Some(
+            (7 * (op_size + OPERATION_INDEX_OVERHEAD_BYTES) / 2) as u64,
+        ))
+        .build()
+        .unwrap();
+    operation_pool_test(
+        cfg,
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let ops: Vec<SecureShareOperation> = (1..=5)
+                .map(|fee| {
+                    OpGenerator::default()
+                        .expirery(2)
+                        .fee(Amount::from_raw(fee))
+                        .generate()
+                })
+                .collect();
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops.clone());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+
+            let ids: Vec<OperationId> = ops.iter().map(|op| op.id).collect();
+            assert_eq!(
+                operation_pool.contains_operations(&ids),
+                vec![false, false, true, true, true]
+            );
+            let usage = operation_pool.get_memory_usage().operation_pool_bytes;
+            assert_eq!(usage, 3 * (op_size + OPERATION_INDEX_OVERHEAD_BYTES));
+
+            // a better operation evicts the lowest-fee one left
+            let best_op = OpGenerator::default()
+                .expirery(2)
+                .fee(Amount::from_raw(9))
+                .generate();
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(vec![best_op.clone()]);
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+
+            assert_eq!(
+                operation_pool.contains_operations(&[ids[2], ids[3], ids[4], best_op.id]),
+                vec![false, true, true, true]
+            );
+        },
+    );
+}
+
+/// Test that the pool statistics are split by operation type.
+#[test]
+fn test_operation_type_stats() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let transactions = create_some_operations(3, &OpGenerator::default().expirery(2));
+            let executions =
+                create_some_operations(2, &OpGenerator::default().expirery(2).max_gas(1_000));
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(transactions.clone());
+            ops_storage.store_operations(executions.clone());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+
+            let stats = operation_pool.pool_stats().operation_type_stats;
+            assert_eq!(stats.len(), 2);
+            assert_eq!(stats[&OperationTypeId::Transaction].count, 3);
+            assert_eq!(stats[&OperationTypeId::ExecuteSC].count, 2);
+            let transactions_fee = transactions
+                .iter()
+                .fold(Amount::zero(), |acc, op| acc.saturating_add(op.content.fee));
+            assert_eq!(
+                stats[&OperationTypeId::Transaction].total_fee,
+                transactions_fee
+            );
+
+            let ids: Vec<OperationId> = executions.iter().map(|op| op.id).collect();
+            operation_pool.remove_operations(&ids).unwrap();
+            operation_pool.flush_operations().unwrap();
+
+            let stats = operation_pool.pool_stats().operation_type_stats;
+            assert_eq!(stats.len(), 1);
+            assert!(!stats.contains_key(&OperationTypeId::ExecuteSC));
+        },
+    );
+}
+
+/// Test that operations received twice are only inserted once.
+#[test]
+fn test_add_duplicate_operations() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let op_gen = OpGenerator::default().expirery(2);
+            let ops = create_some_operations(5, &op_gen);
+            for _ in 0..2 {
+                let mut ops_storage = storage.clone_without_refs();
+                ops_storage.store_operations(ops.clone());
+                operation_pool.add_operations(ops_storage).unwrap();
+                operation_pool.flush_operations().unwrap();
+            }
+
+            let stats = operation_pool.pool_stats();
+            assert_eq!(stats.operation_count, 5);
+            assert_eq!(stats.total_ops_added, 5);
+            assert_eq!(stats.duplicates_skipped, 5);
+        },
+    );
+}
+
+/// Test that the lowest fee operations are evicted when the pool is full.
+#[test]
+fn test_eviction_lowest_fee() {
+    let pool_config = PoolConfigBuilder::default()
+        .with_max_operation_pool_size(5)
+        .with_max_operations_per_block(5)
+        .with_eviction_policy(EvictionPolicy::LowestFee)
+        .build()
+        .unwrap();
+    operation_pool_test(
+        pool_config,
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let ops: Vec<_> = (1..=5)
+                .map(|fee| {
+                    OpGenerator::default()
+                        .expirery(2)
+                        .fee(Amount::from_raw(fee))
+                        .generate()
+                })
+                .collect();
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops.clone());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(operation_pool.get_operation_count(), 5);
+
+            // a higher fee operation evicts the lowest fee one
+            let high_fee_op = OpGenerator::default()
+                .expirery(2)
+                .fee(Amount::from_raw(10))
+                .generate();
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(vec![high_fee_op.clone()]);
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(operation_pool.get_operation_count(), 5);
+            assert_eq!(
+                operation_pool.contains_operations(&[ops[0].id, ops[1].id, high_fee_op.id]),
+                vec![false, true, true]
+            );
+        },
+    );
+}
+
+/// Test that the first added operations are evicted when the pool is full
+/// and the eviction policy is `Oldest`.
+#[test]
+fn test_eviction_oldest() {
+    let pool_config = PoolConfigBuilder::default()
+        .with_max_operation_pool_size(5)
+        .with_max_operations_per_block(5)
+        .with_eviction_policy(EvictionPolicy::Oldest)
+        .build()
+        .unwrap();
+    operation_pool_test(
+        pool_config,
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let op_gen = OpGenerator::default().expirery(2).fee(Amount::from_raw(10));
+            let old_op = op_gen.generate();
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(vec![old_op.clone()]);
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(create_some_operations(4, &op_gen));
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(operation_pool.get_operation_count(), 5);
+
+            // a new operation evicts the oldest one, even with a lower fee
+            let new_op = OpGenerator::default()
+                .expirery(2)
+                .fee(Amount::from_raw(1))
+                .generate();
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(vec![new_op.clone()]);
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(operation_pool.get_operation_count(), 5);
+            assert_eq!(
+                operation_pool.contains_operations(&[old_op.id, new_op.id]),
+                vec![false, true]
+            );
+        },
+    );
+}
+
+/// Add operations of increasing then decreasing fee one by one,
+/// and return the fees of the block operations selected with `selection_strategy`.
+fn get_block_operation_fees(selection_strategy: OperationSelectionStrategy) -> Vec<Amount> {
+    let pool_config = PoolConfigBuilder::default()
+        .with_selection_strategy(selection_strategy)
+        .build()
+        .unwrap();
+    let mut fees = Vec::new();
+    operation_pool_test(
+        pool_config.clone(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let creator = KeyPair::generate(0).unwrap();
+            let creator_thread = Address::from_public_key(&creator.get_public_key())
+                .get_thread(pool_config.thread_count);
+            for fee in [1, 3, 2] {
+                let op = OpGenerator::default()
+                    .creator(creator.clone())
+                    .expirery(2)
+                    .fee(Amount::from_raw(fee))
+                    .generate();
+                let mut ops_storage = storage.clone_without_refs();
+                ops_storage.store_operations(vec![op]);
+                operation_pool.add_operations(ops_storage).unwrap();
+                operation_pool.flush_operations().unwrap();
+            }
+
+            let (ids, res_storage) =
+                operation_pool.get_block_operations(&Slot::new(1, creator_thread));
+            let stored_ops = res_storage.read_operations();
+            fees = ids
+                .iter()
+                .map(|id| stored_ops.get(id).unwrap().content.fee)
+                .collect();
+        },
+    );
+    fees
+}
+
+/// Test that the pool statistics count the blocks filled from the hot tier only.
+#[test]
+fn test_hot_tier_stats() {
+    let pool_config = PoolConfigBuilder::default()
+        .with_hot_tier_size(2)
+        .build()
+        .unwrap();
+    operation_pool_test(
+        pool_config.clone(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let creator = KeyPair::generate(0).unwrap();
+            let creator_thread = Address::from_public_key(&creator.get_public_key())
+                .get_thread(pool_config.thread_count);
+            let ops: Vec<SecureShareOperation> = (1..=3)
+                .map(|fee| {
+                    OpGenerator::default()
+                        .creator(creator.clone())
+                        .expirery(2)
+                        .fee(Amount::from_raw(fee))
+                        .generate()
+                })
+                .collect();
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops.clone());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+
+            // the lowest fee operation is beyond the hot tier
+            let (ids, _) = operation_pool.get_block_operations(&Slot::new(1, creator_thread));
+            assert_eq!(ids.len(), 3);
+            let stats = operation_pool.pool_stats();
+            assert_eq!((stats.hot_tier_hits, stats.hot_tier_misses), (0, 1));
+
+            operation_pool.remove_operations(&[ops[0].id]).unwrap();
+            operation_pool.flush_operations().unwrap();
+            let (ids, _) = operation_pool.get_block_operations(&Slot::new(1, creator_thread));
+            assert_eq!(ids, vec![ops[2].id, ops[1].id]);
+            let stats = operation_pool.pool_stats();
+            assert_eq!((stats.hot_tier_hits, stats.hot_tier_misses), (1, 1));
+        },
+    );
+}
+
+/// Test that block operations are selected by decreasing fee with the `FeeDescending` strategy.
+#[test]
+fn test_selection_strategy_fee_descending() {
+    assert_eq!(
+        get_block_operation_fees(OperationSelectionStrategy::FeeDescending),
+        vec![
+            Amount::from_raw(3),
+            Amount::from_raw(2),
+            Amount::from_raw(1)
+        ]
+    );
+}
+
+/// Test that a large operation paying the highest fee is selected after a smaller one
+/// paying less but more per byte.
+#[test]
+fn test_block_operations_by_fee_per_byte() {
+    let pool_config = PoolConfig::default();
+    operation_pool_test(
+        pool_config.clone(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let creator = KeyPair::generate(0).unwrap();
+            let creator_thread = Address::from_public_key(&creator.get_public_key())
+                .get_thread(pool_config.thread_count);
+            let small_op = OpGenerator::default()
+                .creator(creator.clone())
+                .expirery(2)
+                .fee(Amount::from_raw(1_000))
+                .generate();
+            let large_op = OpGenerator::default()
+                .creator(creator)
+                .expirery(2)
+                .max_gas(1_000)
+                .data(vec![0; 10_000])
+                .fee(Amount::from_raw(5_000))
+                .generate();
+            // the large operation pays more, but less per byte
+            assert!(large_op.content.fee > small_op.content.fee);
+            assert!(
+                large_op.content.fee.to_raw() as usize * small_op.serialized_size()
+                    < small_op.content.fee.to_raw() as usize * large_op.serialized_size()
+            );
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(vec![large_op.clone(), small_op.clone()]);
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+
+            let (ids, _) = operation_pool.get_block_operations(&Slot::new(1, creator_thread));
+            assert_eq!(ids, vec![small_op.id, large_op.id]);
+        },
+    );
+}
+
+/// Test that block operations are selected by order of arrival with the `Fifo` strategy.
+#[test]
+fn test_selection_strategy_fifo() {
+    assert_eq!(
+        get_block_operation_fees(OperationSelectionStrategy::Fifo),
+        vec![
+            Amount::from_raw(1),
+            Amount::from_raw(3),
+            Amount::from_raw(2)
+        ]
+    );
+}
+
+/// Test that the block fill information matches the selected operations.
+#[test]
+fn test_get_block_operations_with_fill_info() {
+    let pool_config = PoolConfig::default();
+    operation_pool_test(
+        pool_config.clone(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let creator = KeyPair::generate(0).unwrap();
+            let creator_thread = Address::from_public_key(&creator.get_public_key())
+                .get_thread(pool_config.thread_count);
+            let op_gen = OpGenerator::default()
+                .creator(creator)
+                .expirery(2)
+                .max_gas(1_000);
+            let ops = create_some_operations(3, &op_gen);
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops.clone());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+
+            let (ids, _, fill_info) =
+                operation_pool.get_block_operations_with_fill_info(&Slot::new(1, creator_thread));
+            assert_eq!(ids.len(), 3);
+            assert_eq!(
+                fill_info,
+                BlockFillInfo {
+                    ops_count: 3,
+                    ops_limit: pool_config.max_operations_per_block as usize,
+                    gas_used: ops.iter().map(|op| op.get_gas_usage()).sum(),
+                    gas_limit: pool_config.max_block_gas,
+                    size_bytes: ops.iter().map(|op| op.serialized_size()).sum(),
+                }
+            );
+        },
+    );
+}
+
+/// Test that the simulated fee revenue is the one of the operations selected for the block.
+#[test]
+fn test_simulate_block_fee_revenue() {
+    let pool_config = PoolConfig::default();
+    operation_pool_test(
+        pool_config.clone(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let creator = KeyPair::generate(0).unwrap();
+            let creator_thread = Address::from_public_key(&creator.get_public_key())
+                .get_thread(pool_config.thread_count);
+            let ops: Vec<SecureShareOperation> = (1..=3)
+                .map(|fee| {
+                    OpGenerator::default()
+                        .creator(creator.clone())
+                        .expirery(2)
+                        .fee(Amount::from_raw(fee * 100))
+                        .max_gas(1_000)
+                        .generate()
+                })
+                .collect();
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops);
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+
+            let slot = Slot::new(1, creator_thread);
+            assert_eq!(
+                operation_pool.simulate_block_fee_revenue(&slot, usize::MAX, u64::MAX),
+                600
+            );
+            // the best operations are selected first
+            assert_eq!(
+                operation_pool.simulate_block_fee_revenue(&slot, 2, u64::MAX),
+                500
+            );
+            assert_eq!(
+                operation_pool.simulate_block_fee_revenue(&slot, 3, 1_000),
+                300
+            );
+            // no operation of another thread is selected
+            let other_slot = Slot::new(1, (creator_thread + 1) % pool_config.thread_count);
+            assert_eq!(
+                operation_pool.simulate_block_fee_revenue(&other_slot, usize::MAX, u64::MAX),
+                0
+            );
+            // the simulation does not count as a block production
+            assert_eq!(operation_pool.pool_stats().hot_tier_hits, 0);
+        },
+    );
+}
+
+/// Test that only the operations that did not expire are re-injected from storage.
+#[test]
+fn test_reinject_operations_from_storage() {
+    operation_pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let expired_ops = create_some_operations(3, &OpGenerator::default().expirery(2));
+            let valid_ops = create_some_operations(4, &OpGenerator::default().expirery(10));
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(expired_ops.clone());
+            ops_storage.store_operations(valid_ops.clone());
+
+            let count = operation_pool
+                .reinject_operations_from_storage(ops_storage, Slot::new(5, 0))
+                .unwrap();
+            assert_eq!(count, 4);
+            operation_pool.flush_operations().unwrap();
+
+            assert_eq!(operation_pool.get_operation_count(), 4);
+            let valid_ids: Vec<OperationId> = valid_ops.iter().map(|op| op.id).collect();
+            assert!(operation_pool
+                .contains_operations(&valid_ids)
+                .into_iter()
+                .all(|present| present));
+            let expired_ids: Vec<OperationId> = expired_ops.iter().map(|op| op.id).collect();
+            assert!(!operation_pool
+                .contains_operations(&expired_ids)
+                .into_iter()
+                .any(|present| present));
+        },
+    );
+}
+
+/// Test that the operations of the write-ahead log are restored when the pool restarts,
+/// until they are pruned in final periods.
+#[test]
+fn test_wal_replay() {
+    let wal_dir = tempfile::tempdir().unwrap();
+    let pool_config = PoolConfigBuilder::default()
+        .with_wal_path(Some(wal_dir.path().join("operations_wal")))
+        .build()
+        .unwrap();
+    let ops = create_some_operations(5, &OpGenerator::default().expirery(2));
+    operation_pool_test(
+        pool_config.clone(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops.clone());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(operation_pool.get_operation_count(), 5);
+        },
+    );
+
+    // the logged operations are restored, then pruned once they expire in final periods
+    operation_pool_test(
+        pool_config.clone(),
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, _| {
+            let ids: Vec<OperationId> = ops.iter().map(|op| op.id).collect();
+            assert_eq!(operation_pool.contains_operations(&ids), vec![true; 5]);
+            operation_pool.notify_final_cs_periods(&vec![2; pool_config.thread_count as usize]);
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(operation_pool.get_operation_count(), 0);
+        },
+    );
+
+    // the pruned operations are no longer restored
+    operation_pool_test(
+        pool_config,
+        default_execution_controller(),
+        default_selector_controller(),
+        |operation_pool, _| {
+            assert_eq!(operation_pool.get_operation_count(), 0);
+        },
+    );
+}
+
+/// Test that the write-ahead log is only compacted once the records of the pruned operations
+/// outnumber the ones of the operations still in the pool.
+#[test]
+fn test_wal_compaction() {
+    let wal_dir = tempfile::tempdir().unwrap();
+    let wal_path = wal_dir.path().join("operations_wal");
+    let pool_config = PoolConfigBuilder::default()
+        .with_wal_path(Some(wal_path.clone()))
+        .build()
+        .unwrap();
+    let thread_count = pool_config.thread_count as usize;
+    let mut ops = create_some_operations(2, &OpGenerator::default().expirery(2));
+    ops.extend(create_some_operations(
+        3,
+        &OpGenerator::default().expirery(4),
+    ));
+    operation_pool_test(
+        pool_config,
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops.clone());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.flush_operations().unwrap();
+            let logged_len = std::fs::metadata(&wal_path).unwrap().len();
+
+            // 2 pruned operations for 3 remaining ones: the log is kept as is
+            operation_pool.notify_final_cs_periods(&vec![2; thread_count]);
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(operation_pool.get_operation_count(), 3);
+            assert_eq!(std::fs::metadata(&wal_path).unwrap().len(), logged_len);
+
+            // all the operations are pruned: the log is rewritten without them
+            operation_pool.notify_final_cs_periods(&vec![4; thread_count]);
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(operation_pool.get_operation_count(), 0);
+            assert!(std::fs::metadata(&wal_path).unwrap().len() < logged_len);
+            assert!(!wal_dir.path().join("operations_wal.tmp").exists());
+        },
+    );
+}
+
+/// Test that the commands processed by the operation pool worker are appended to the event log.
+#[test]
+fn test_event_log() {
+    let log_dir = tempfile::tempdir().unwrap();
+    let log_path = log_dir.path().join("pool_events.jsonl");
+    let pool_config = PoolConfigBuilder::default()
+        .with_event_log_path(Some(log_path.clone()))
+        .build()
+        .unwrap();
+    let ops = create_some_operations(2, &OpGenerator::default().expirery(2));
+    operation_pool_test(
+        pool_config,
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let mut ops_storage = storage.clone_without_refs();
+            ops_storage.store_operations(ops.clone());
+            operation_pool.add_operations(ops_storage).unwrap();
+            operation_pool.remove_operations(&[ops[0].id]).unwrap();
+            operation_pool.flush_operations().unwrap();
+        },
+    );
+
+    let entries: Vec<EventLogEntry> = std::fs::read_to_string(&log_path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(entries.len(), 2);
+    assert!(entries
+        .iter()
+        .all(|entry| entry.pool == LoggedPool::Operations));
+    assert!(entries[0].timestamp_ns <= entries[1].timestamp_ns);
+    match &entries[0].command {
+        LoggedCommand::AddOperations(logged_ops) => {
+            let mut logged_ids: Vec<OperationId> = logged_ops.iter().map(|op| op.id).collect();
+            let mut ids: Vec<OperationId> = ops.iter().map(|op| op.id).collect();
+            logged_ids.sort();
+            ids.sort();
+            assert_eq!(logged_ids, ids);
+        }
+        command => panic!("unexpected logged command {:?}", command),
+    }
+    assert!(matches!(
+        &entries[1].command,
+        LoggedCommand::RemoveOperations(ids) if ids == &vec![ops[0].id]
+    ));
+}
+
+/// Operation validator rejecting a shared set of operations
+struct RejectingValidator(Arc<Mutex<PreHashSet<OperationId>>>);
+
+impl OperationValidator for RejectingValidator {
+    fn validate(&self, op_id: &OperationId) -> bool {
+        !self.0.lock().contains(op_id)
+    }
+}
+
+/// Test that the operations rejected by the validator are evicted
+/// every `revalidation_interval_slots` slots.
+#[test]
+fn test_revalidation() {
+    let rejected: Arc<Mutex<PreHashSet<OperationId>>> = Default::default();
+    let PoolTestBoilerPlate {
+        mut pool_manager,
+        mut pool_controller,
+        storage,
+    } = PoolTestBoilerPlate::pool_test_with_validator(
+        PoolConfigBuilder::default()
+            .with_revalidation_interval_slots(2)
+            .build()
+            .unwrap(),
+        default_execution_controller(),
+        default_selector_controller(),
+        Box::new(RejectingValidator(rejected.clone())),
+    );
+    let ops = create_some_operations(3, &OpGenerator::default().expirery(10));
+    let ids: Vec<OperationId> = ops.iter().map(|op| op.id).collect();
+    let mut ops_storage = storage.clone_without_refs();
+    ops_storage.store_operations(ops);
+    pool_controller.add_operations(ops_storage).unwrap();
+    pool_controller.flush_operations().unwrap();
+    rejected.lock().insert(ids[1]);
+
+    // not re-validated before the second slot
+    pool_controller.notify_new_slot(Slot::new(1, 0)).unwrap();
+    pool_controller.flush_operations().unwrap();
+    assert_eq!(pool_controller.contains_operations(&ids), vec![true; 3]);
+
+    pool_controller.notify_new_slot(Slot::new(1, 1)).unwrap();
+    pool_controller.flush_operations().unwrap();
+    assert_eq!(
+        pool_controller.contains_operations(&ids),
+        vec![true, false, true]
+    );
+
+    pool_manager.stop();
+}
+
+/// Test that `try_add_operations` queues operations while the worker runs
+/// and fails once it is stopped.
+#[test]
+fn test_try_add_operations() {
+    let PoolTestBoilerPlate {
+        mut pool_manager,
+        mut pool_controller,
+        storage,
+    } = PoolTestBoilerPlate::pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+    );
+    let op_gen = OpGenerator::default().expirery(2);
+    let mut ops_storage = storage.clone_without_refs();
+    ops_storage.store_operations(create_some_operations(5, &op_gen));
+    assert!(pool_controller.try_add_operations(ops_storage).unwrap());
+    pool_controller.flush_operations().unwrap();
+    assert_eq!(pool_controller.get_operation_count(), 5);
+    assert!(pool_manager.is_healthy());
+
+    pool_manager.stop();
+    let mut ops_storage = storage.clone_without_refs();
+    ops_storage.store_operations(create_some_operations(5, &op_gen));
+    assert!(matches!(
+        pool_controller.try_add_operations(ops_storage),
+        Err(PoolError::ChannelDisconnected)
+    ));
+}
+
+/// Test that re-injecting operations from storage fails once the worker is stopped,
+/// instead of reporting the dropped operations as submitted.
+#[test]
+fn test_reinject_operations_from_storage_stopped_worker() {
+    let PoolTestBoilerPlate {
+        mut pool_manager,
+        mut pool_controller,
+        storage,
+    } = PoolTestBoilerPlate::pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+    );
+    pool_manager.stop();
+    let mut ops_storage = storage.clone_without_refs();
+    ops_storage.store_operations(create_some_operations(
+        3,
+        &OpGenerator::default().expirery(10),
+    ));
+    assert!(matches!(
+        pool_controller.reinject_operations_from_storage(ops_storage, Slot::new(5, 0)),
+        Err(PoolError::ChannelDisconnected)
+    ));
+}
+
+/// Test that the pool manager reports the worker threads as ready once the pools are started.
+#[test]
+fn test_wait_until_ready() {
+    let PoolTestBoilerPlate {
+        mut pool_manager,
+        pool_controller,
+        ..
+    } = PoolTestBoilerPlate::pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+    );
+    assert!(pool_manager.wait_until_ready(Duration::from_secs(5)));
+    assert_eq!(pool_controller.get_operation_count(), 0);
+    pool_manager.stop();
+}
+
+/// Test that a worker that keeps panicking is restarted a limited number of times,
+/// after which the pool manager reports it as unhealthy and can still be stopped.
+#[test]
+fn test_worker_panic_restart_limit() {
+    let execution_controller = {
+        let mut res = Box::new(MockExecutionController::new());
+        res.expect_clone_box().returning(|| {
+            let mut story = MockExecutionController::new();
+            story
+                .expect_get_ops_exec_status()
+                .returning(|_| panic!("execution controller failure"));
+            Box::new(story)
+        });
+        res
+    };
+    let pool_config = PoolConfigBuilder::default()
+        .with_operation_pool_refresh_interval(MassaTime::from_millis(10))
+        .with_worker_restart_max_attempts(2)
+        .build()
+        .unwrap();
+    let PoolTestBoilerPlate {
+        mut pool_manager, ..
+    } = PoolTestBoilerPlate::pool_test(
+        pool_config,
+        execution_controller,
+        default_selector_controller(),
+    );
+    // each refresh panics, the operation worker gives up after two restarts
+    assert!(wait_until(Duration::from_secs(5), || !pool_manager.is_healthy()));
+    pool_manager.stop();
+}
+
+/// Test that rebuilding the operation pool from storage restores the same indexes,
+/// as done before restarting a panicked worker.
+#[test]
+fn test_rebuild_operation_pool_from_storage() {
+    let ops: Vec<SecureShareOperation> = (1..=5)
+        .map(|fee| {
+            OpGenerator::default()
+                .expirery(2)
+                .fee(Amount::from_raw(fee))
+                .generate()
+        })
+        .collect();
+    let mut operation_pool = OperationPool::replay_from_commands(
+        PoolConfig::default(),
+        vec![LoggedCommand::AddOperations(ops)],
+    );
+    let sorted_ids = operation_pool.get_operations_sorted_by_fee(usize::MAX, 0).0;
+    let ids_xor = operation_pool.ids_xor();
+    let memory_bytes = operation_pool.memory_bytes();
+
+    operation_pool.rebuild_from_storage();
+    assert_eq!(operation_pool.len(), 5);
+    assert_eq!(operation_pool.ids_xor(), ids_xor);
+    assert_eq!(operation_pool.memory_bytes(), memory_bytes);
+    assert_eq!(
+        operation_pool.get_operations_sorted_by_fee(usize::MAX, 0).0,
+        sorted_ids
+    );
+}
+
+/// Test that stopping the pool does not hang when a worker is stuck with a full channel,
+/// the stuck worker join being skipped after `worker_stop_timeout_ms`.
+#[test]
+fn test_stop_hung_worker_timeout() {
+    // signaled by the operation worker when it enters its blocking refresh
+    let (refresh_tx, refresh_rx) = std::sync::mpsc::channel();
+    let execution_controller = {
+        let mut res = Box::new(MockExecutionController::new());
+        res.expect_clone_box().returning(move || {
+            let refresh_tx = refresh_tx.clone();
+            let mut story = MockExecutionController::new();
+            story.expect_get_ops_exec_status().returning(move |ops| {
+                let _ = refresh_tx.send(());
+                std::thread::sleep(Duration::from_secs(5));
+                vec![(None, None); ops.len()]
+            });
+            story
+                .expect_get_final_and_candidate_balance()
+                .returning(|addrs| vec![(None, None); addrs.len()]);
+            Box::new(story)
+        });
+        res
+    };
+    let pool_config = PoolConfigBuilder::default()
+        .with_operation_pool_refresh_interval(MassaTime::from_millis(10))
+        .with_worker_stop_timeout_ms(100)
+        .with_operations_channel_size(2)
+        .build()
+        .unwrap();
+    let PoolTestBoilerPlate {
+        mut pool_manager,
+        mut pool_controller,
+        storage,
+    } = PoolTestBoilerPlate::pool_test(
+        pool_config,
+        execution_controller,
+        default_selector_controller(),
+    );
+    refresh_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    while pool_controller
+        .try_add_operations(storage.clone_without_refs())
+        .unwrap()
+    {}
+    let start = std::time::Instant::now();
+    pool_manager.stop();
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
+
+/// Test that a burst of twice the default channel capacity is absorbed
+/// without loss when the channel is sized accordingly.
+#[test]
+fn test_add_operations_burst() {
+    let default_channel_size = PoolConfig::default().operations_channel_size;
+    let pool_config = PoolConfigBuilder::default()
+        .with_operations_channel_size(2 * default_channel_size)
+        .build()
+        .unwrap();
+    operation_pool_test(
+        pool_config,
+        default_execution_controller(),
+        default_selector_controller(),
+        |mut operation_pool, storage| {
+            let op_gen = OpGenerator::default().expirery(2);
+            let ops = create_some_operations(2 * default_channel_size, &op_gen);
+            for op in ops {
+                let mut ops_storage = storage.clone_without_refs();
+                ops_storage.store_operations(vec![op]);
+                operation_pool.add_operations(ops_storage).unwrap();
+            }
+            operation_pool.flush_operations().unwrap();
+            assert_eq!(
+                operation_pool.get_operation_count(),
+                2 * default_channel_size
+            );
+        },
+    );
+}
+
 /// Test if adding irrelevant operations make simply skip the add.
 /// # Initialization
 #[test]
@@ -173,9 +2740,8 @@ fn test_add_irrelevant_operation() {
             let op_gen = OpGenerator::default().expirery(2);
             storage.store_operations(create_some_operations(10, &op_gen));
             operation_pool.notify_final_cs_periods(&vec![51; thread_count.into()]);
-            operation_pool.add_operations(storage);
-            // Allow some time for the pool to add the operations
-            std::thread::sleep(Duration::from_secs(3));
+            operation_pool.add_operations(storage).unwrap();
+            operation_pool.flush_operations().unwrap();
             assert_eq!(operation_pool.get_operation_count(), 0);
         },
     );
@@ -184,8 +2750,10 @@ fn test_add_irrelevant_operation() {
 /// TODO refactor old tests
 #[test]
 fn test_pool() {
-    let mut pool_config = PoolConfig::default();
-    pool_config.max_operations_per_block = 10;
+    let pool_config = PoolConfigBuilder::default()
+        .with_max_operations_per_block(10)
+        .build()
+        .unwrap();
     let execution_controller = {
         let mut res = Box::new(MockExecutionController::new());
         res.expect_clone_box().returning(|| {
@@ -249,7 +2817,11 @@ fn test_pool() {
         mut pool_manager,
         mut pool_controller,
         storage: storage_base,
-    } = PoolTestBoilerPlate::pool_test(pool_config, execution_controller, selector_controller);
+    } = PoolTestBoilerPlate::pool_test(
+        pool_config.clone(),
+        execution_controller,
+        selector_controller,
+    );
 
     // // generate (id, transactions, range of validity) by threads
     let mut thread_tx_lists = vec![Vec::new(); pool_config.thread_count as usize];
@@ -286,8 +2858,8 @@ fn test_pool() {
         thread_tx_lists[op_thread as usize].push((op, start_period..=expire_period));
     }
 
-    pool_controller.add_operations(storage);
-    std::thread::sleep(Duration::from_secs(3));
+    pool_controller.add_operations(storage).unwrap();
+    pool_controller.flush_operations().unwrap();
     // // sort from bigger fee to smaller and truncate
     for lst in thread_tx_lists.iter_mut() {
         lst.reverse();
@@ -299,6 +2871,16 @@ fn test_pool() {
         let target_slot = Slot::new(0, thread);
         let (ids, storage) = pool_controller.get_block_operations(&target_slot);
 
+        // operations are expected to be returned by decreasing fee
+        let fees: Vec<Amount> = ids
+            .iter()
+            .map(|id| storage.read_operations().get(id).unwrap().content.fee)
+            .collect();
+        assert!(
+            fees.windows(2).all(|w| w[0] >= w[1]),
+            "block operations are not sorted by decreasing fee"
+        );
+
         assert_eq!(
             ids.iter()
                 .map(|id| (
@@ -382,3 +2964,163 @@ fn test_pool() {
     // }
     pool_manager.stop();
 }
+
+#[test]
+fn test_diagnostic_report() {
+    let PoolTestBoilerPlate {
+        mut pool_manager,
+        mut pool_controller,
+        storage,
+    } = PoolTestBoilerPlate::pool_test(
+        PoolConfig::default(),
+        default_execution_controller(),
+        default_selector_controller(),
+    );
+    let mut ops_storage = storage.clone_without_refs();
+    ops_storage.store_operations(create_some_operations(3, &OpGenerator::default()));
+    pool_controller.add_operations(ops_storage).unwrap();
+    pool_controller.flush_operations().unwrap();
+
+    let report = pool_manager.diagnostic_report();
+    assert_eq!(report.workers.len(), 3);
+    let operations_worker = &report.workers[0];
+    assert_eq!(operations_worker.name, "operation pool");
+    assert_eq!(operations_worker.status, WorkerStatus::Running);
+    assert!(operations_worker.commands_processed >= 2);
+    assert!(operations_worker.last_command_timestamp.is_some());
+    assert_eq!(report.stats.operation_count, 3);
+
+    pool_manager.stop();
+    let report = pool_manager.diagnostic_report();
+    assert!(report
+        .workers
+        .iter()
+        .all(|worker| worker.status == WorkerStatus::Finished));
+}
+
+/// Operation pool command generated by the property tests,
+/// the operations being described by their fee, expiry period and creator
+#[derive(Debug, Clone)]
+enum ReplayAction {
+    Add {
+        fee: u64,
+        expire_period: u64,
+        creator: usize,
+    },
+    /// removes the n-th added operation, modulo the number of added operations
+    Remove(usize),
+    NewSlot(u64),
+    FinalPeriods(u64),
+    SetMinFee(u64),
+}
+
+fn replay_action() -> impl Strategy<Value = ReplayAction> {
+    prop_oneof![
+        4 => (0..1_000u64, 0..20u64, 0..3usize).prop_map(|(fee, expire_period, creator)| {
+            ReplayAction::Add {
+                fee,
+                expire_period,
+                creator,
+            }
+        }),
+        1 => any::<usize>().prop_map(ReplayAction::Remove),
+        1 => (0..20u64).prop_map(ReplayAction::NewSlot),
+        1 => (0..20u64).prop_map(ReplayAction::FinalPeriods),
+        1 => (0..500u64).prop_map(ReplayAction::SetMinFee),
+    ]
+}
+
+/// Turn the actions into pool commands, the operations being signed by `creators`.
+/// Also returns the minimal fee in force after the last command.
+fn replay_commands(
+    actions: &[ReplayAction],
+    creators: &[KeyPair],
+    config: &PoolConfig,
+) -> (Vec<LoggedCommand>, Amount) {
+    let mut added_ids: Vec<OperationId> = Vec::new();
+    let mut min_fee = config.min_fee;
+    let mut commands = Vec::with_capacity(actions.len());
+    for action in actions {
+        let command = match action {
+            ReplayAction::Add {
+                fee,
+                expire_period,
+                creator,
+            } => {
+                let op = OpGenerator::default()
+                    .creator(creators[*creator].clone())
+                    .fee(Amount::from_raw(*fee))
+                    .expirery(*expire_period)
+                    .generate();
+                added_ids.push(op.id);
+                LoggedCommand::AddOperations(vec![op])
+            }
+            ReplayAction::Remove(index) => {
+                if added_ids.is_empty() {
+                    continue;
+                }
+                LoggedCommand::RemoveOperations(vec![added_ids[index % added_ids.len()]])
+            }
+            ReplayAction::NewSlot(period) => LoggedCommand::NotifyNewSlot(Slot::new(*period, 0)),
+            ReplayAction::FinalPeriods(period) => {
+                LoggedCommand::NotifyFinalCsPeriods(vec![*period; config.thread_count as usize])
+            }
+            ReplayAction::SetMinFee(fee) => {
+                min_fee = Amount::from_raw(*fee);
+                LoggedCommand::SetMinFee(min_fee)
+            }
+        };
+        commands.push(command);
+    }
+    (commands, min_fee)
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn test_replay_from_commands_invariants(
+        actions in proptest::collection::vec(replay_action(), 1..40)
+    ) {
+        let creators: Vec<KeyPair> = (0..3).map(|_| KeyPair::generate(0).unwrap()).collect();
+        let config = PoolConfigBuilder::default()
+            .with_max_operation_pool_size(10)
+            .with_max_ops_per_sender(5)
+            .build()
+            .unwrap();
+        let (commands, min_fee) = replay_commands(&actions, &creators, &config);
+        let operation_pool = OperationPool::replay_from_commands(config.clone(), commands.clone());
+
+        prop_assert!(operation_pool.len() <= config.max_operation_pool_size);
+        let (ids, ops_storage) = operation_pool.get_operations_sorted_by_fee(usize::MAX, 0);
+        prop_assert_eq!(ids.len(), operation_pool.len());
+        let stored_ops = ops_storage.read_operations();
+        let ops: Vec<&SecureShareOperation> = ids
+            .iter()
+            .map(|id| stored_ops.get(id).expect("sorted operation not in storage"))
+            .collect();
+        for op in &ops {
+            prop_assert!(op.content.fee >= min_fee);
+        }
+        for creator in &creators {
+            let address = Address::from_public_key(&creator.get_public_key());
+            let sender_ops = ops
+                .iter()
+                .filter(|op| op.content_creator_address == address)
+                .count();
+            prop_assert!(sender_ops <= config.max_ops_per_sender);
+        }
+        // by decreasing fee per byte
+        for pair in ops.windows(2) {
+            prop_assert!(
+                pair[0].content.fee.to_raw() as u128 * pair[1].serialized_size() as u128
+                    >= pair[1].content.fee.to_raw() as u128 * pair[0].serialized_size() as u128
+            );
+        }
+
+        // replaying the same commands gives the same pool
+        let replayed_pool = OperationPool::replay_from_commands(config, commands);
+        prop_assert_eq!(replayed_pool.ids_xor(), operation_pool.ids_xor());
+        prop_assert_eq!(replayed_pool.get_operations_sorted_by_fee(usize::MAX, 0).0, ids);
+    }
+}
@@ -11,9 +11,13 @@
 //! Function: [`test_get_operations_overflow`]
 //! Same as the previous test with a low limit of size to check if
 //! configurations are taken into account.
+//!
+//! # Get operations gas limit
+//! Function: [`test_get_operations_gas_limit`]
+//! Same as the previous test with a gas budget allowing only some of the
+//! operations in the block.
 
 use std::collections::BTreeMap;
-use std::time::Duration;
 
 use crate::tests::tools::create_some_operations;
 use crate::tests::tools::OpGenerator;
@@ -24,12 +28,14 @@ use massa_models::config::ENDORSEMENT_COUNT;
 use massa_models::operation::OperationId;
 use massa_models::prehash::PreHashSet;
 use massa_models::slot::Slot;
-use massa_pool_exports::PoolConfig;
+use massa_pool_exports::{PoolConfig, PoolConfigBuilder};
 use massa_pos_exports::MockSelectorController;
 use massa_pos_exports::Selection;
 use massa_signature::KeyPair;
 
-use super::tools::PoolTestBoilerPlate;
+use super::tools::{
+    default_execution_controller, default_selector_controller, PoolTestBoilerPlate,
+};
 
 /// # Test simple get operation
 /// Just try to get some operations stored in pool
@@ -108,14 +114,13 @@ fn test_simple_get_operations() {
         mut pool_manager,
         mut pool_controller,
         mut storage,
-    } = PoolTestBoilerPlate::pool_test(config, execution_controller, selector_controller);
+    } = PoolTestBoilerPlate::pool_test(config.clone(), execution_controller, selector_controller);
 
     // setup storage
     storage.store_operations(ops);
-    pool_controller.add_operations(storage);
+    pool_controller.add_operations(storage).unwrap();
 
-    // Allow some time for the pool to add the operations
-    std::thread::sleep(Duration::from_millis(100));
+    pool_controller.flush_operations().unwrap();
 
     let creator_thread = {
         let creator_address = Address::from_public_key(&keypair.get_public_key());
@@ -182,10 +187,10 @@ fn test_get_operations_overflow() {
         .iter()
         .take(MAX_OP_LEN)
         .fold(0, |acc, op| acc + op.serialized_size() as u32);
-    let config = PoolConfig {
-        max_block_size,
-        ..Default::default()
-    };
+    let config = PoolConfigBuilder::default()
+        .with_max_block_size(max_block_size)
+        .build()
+        .unwrap();
     let creator_thread = creator_address.get_thread(config.thread_count);
 
     let mut execution_controller = Box::new(MockExecutionController::new());
@@ -241,9 +246,8 @@ fn test_get_operations_overflow() {
     } = PoolTestBoilerPlate::pool_test(config, execution_controller, selector_controller);
 
     storage.store_operations(create_some_operations(10, &op_gen));
-    pool_controller.add_operations(storage);
-    // Allow some time for the pool to add the operations
-    std::thread::sleep(Duration::from_millis(100));
+    pool_controller.add_operations(storage).unwrap();
+    pool_controller.flush_operations().unwrap();
 
     // This is what we are testing....
     let block_operations_storage = pool_controller
@@ -254,6 +258,52 @@ fn test_get_operations_overflow() {
     assert_eq!(block_operations_storage.get_op_refs().len(), MAX_OP_LEN);
 }
 
+/// # Test get block operation with a gas limit
+/// Try to get some operations stored in pool for a block, but the pool's operations
+/// require more gas than the block gas budget.
+///
+/// ## Initialization
+/// Create 10 smart contract executions using 100 gas each.
+/// Set `max_block_gas` to 450.
+///
+/// ## Expected result
+/// The block operation storage is expected to have only 4 operations.
+#[test]
+fn test_get_operations_gas_limit() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let creator_address = Address::from_public_key(&keypair.get_public_key());
+    let op_gen = OpGenerator::default()
+        .expirery(1)
+        .creator(keypair)
+        .max_gas(100);
+    let config = PoolConfigBuilder::default()
+        .with_max_block_gas(450)
+        .build()
+        .unwrap();
+    let creator_thread = creator_address.get_thread(config.thread_count);
+
+    let PoolTestBoilerPlate {
+        mut pool_manager,
+        mut pool_controller,
+        mut storage,
+    } = PoolTestBoilerPlate::pool_test(
+        config,
+        default_execution_controller(),
+        default_selector_controller(),
+    );
+
+    storage.store_operations(create_some_operations(10, &op_gen));
+    pool_controller.add_operations(storage).unwrap();
+    pool_controller.flush_operations().unwrap();
+
+    let (op_ids, block_operations_storage) =
+        pool_controller.get_block_operations(&Slot::new(1, creator_thread));
+    pool_manager.stop();
+
+    assert_eq!(op_ids.len(), 4);
+    assert_eq!(block_operations_storage.get_op_refs().len(), 4);
+}
+
 //TODO: Readd
 // #[test]
 // fn test_block_header_denunciation_creation() {
@@ -1,14 +1,21 @@
 // Copyright (c) 2022 MASSA LABS <info@massa.net>
 
-use std::sync::Arc;
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use crate::start_pool_controller;
+use crate::{start_pool_controller, start_pool_controller_with_validator};
 use crossbeam_channel as _;
 use massa_execution_exports::MockExecutionController;
 use massa_hash::Hash;
+use massa_models::config::ENDORSEMENT_COUNT;
 use massa_models::{
     address::Address,
     amount::Amount,
+    block::{Block, BlockSerializer, SecureShareBlock},
+    block_header::{BlockHeader, BlockHeaderSerializer},
     block_id::BlockId,
     endorsement::{Endorsement, EndorsementSerializer, SecureShareEndorsement},
     operation::{Operation, OperationSerializer, OperationType, SecureShareOperation},
@@ -16,8 +23,11 @@ use massa_models::{
     secure_share::SecureShareContent,
     slot::Slot,
 };
-use massa_pool_exports::{PoolChannels, PoolConfig, PoolController, PoolManager};
+use massa_pool_exports::{
+    OperationValidator, PoolChannels, PoolConfig, PoolController, PoolManager,
+};
 use massa_pos_exports::MockSelectorController as AutoMockSelectorController;
+use massa_pos_exports::{PosResult, Selection};
 use massa_signature::KeyPair;
 use massa_storage::Storage;
 use massa_wallet::test_exports::create_test_wallet;
@@ -31,6 +41,8 @@ pub(crate) struct OpGenerator {
     fee: Option<Amount>,
     amount: Option<Amount>,
     expirery: Option<u64>,
+    max_gas: Option<u64>,
+    data: Option<Vec<u8>>,
 }
 
 impl OpGenerator {
@@ -45,6 +57,19 @@ impl OpGenerator {
         self
     }
 
+    /// Generate smart contract executions using `max_gas` instead of transactions
+    pub(crate) fn max_gas(mut self, max_gas: u64) -> Self {
+        self.max_gas = Some(max_gas);
+        self
+    }
+
+    /// Set the bytecode of the smart contract executions generated with `max_gas`,
+    /// to vary the size of the operations
+    pub(crate) fn data(mut self, data: Vec<u8>) -> Self {
+        self.data = Some(data);
+        self
+    }
+
     pub(crate) fn fee(mut self, fee: Amount) -> Self {
         self.fee = Some(fee);
         self
@@ -74,9 +99,17 @@ impl OpGenerator {
         let amount = self.amount.unwrap_or_default();
         let expirery = self.expirery.unwrap_or_default();
 
-        let op = OperationType::Transaction {
-            recipient_address: Address::from_public_key(&receiver.get_public_key()),
-            amount,
+        let op = match self.max_gas {
+            Some(max_gas) => OperationType::ExecuteSC {
+                data: self.data.clone().unwrap_or_default(),
+                max_gas,
+                max_coins: amount,
+                datastore: Default::default(),
+            },
+            None => OperationType::Transaction {
+                recipient_address: Address::from_public_key(&receiver.get_public_key()),
+                amount,
+            },
         };
         let content = Operation {
             fee,
@@ -103,26 +136,68 @@ impl PoolTestBoilerPlate {
         cfg: PoolConfig,
         execution_story: Box<MockExecutionController>,
         selector_story: Box<AutoMockSelectorController>,
+    ) -> Self {
+        Self::pool_test_with_wallet(
+            cfg,
+            KeyPair::generate(0).unwrap(),
+            execution_story,
+            selector_story,
+        )
+    }
+
+    /// Same as `pool_test` but the staking wallet contains the provided keypair
+    pub fn pool_test_with_wallet(
+        cfg: PoolConfig,
+        keypair: KeyPair,
+        execution_story: Box<MockExecutionController>,
+        selector_story: Box<AutoMockSelectorController>,
+    ) -> Self {
+        Self::start(cfg, keypair, execution_story, selector_story, None)
+    }
+
+    /// Same as `pool_test` but the operations of the pool are re-validated by `validator`
+    pub fn pool_test_with_validator(
+        cfg: PoolConfig,
+        execution_story: Box<MockExecutionController>,
+        selector_story: Box<AutoMockSelectorController>,
+        validator: Box<dyn OperationValidator + Send>,
+    ) -> Self {
+        Self::start(
+            cfg,
+            KeyPair::generate(0).unwrap(),
+            execution_story,
+            selector_story,
+            Some(validator),
+        )
+    }
+
+    fn start(
+        cfg: PoolConfig,
+        keypair: KeyPair,
+        execution_story: Box<MockExecutionController>,
+        selector_story: Box<AutoMockSelectorController>,
+        validator: Option<Box<dyn OperationValidator + Send>>,
     ) -> Self {
         let storage: Storage = Storage::create_root();
-        let keypair = KeyPair::generate(0).unwrap();
         let address = Address::from_public_key(&keypair.get_public_key());
         let mut addresses = PreHashMap::default();
         addresses.insert(address, keypair);
         let wallet = Arc::new(RwLock::new(create_test_wallet(Some(addresses))));
         let endorsement_sender = broadcast::channel(2000).0;
         let operation_sender = broadcast::channel(5000).0;
-        let (pool_manager, pool_controller) = start_pool_controller(
-            cfg,
-            &storage,
-            PoolChannels {
-                execution_controller: execution_story,
-                endorsement_sender,
-                operation_sender,
-                selector: selector_story,
-            },
-            wallet,
-        );
+        let channels = PoolChannels {
+            execution_controller: execution_story,
+            endorsement_sender,
+            operation_sender,
+            selector: selector_story,
+        };
+        let (pool_manager, pool_controller) = match validator {
+            Some(validator) => {
+                start_pool_controller_with_validator(cfg, &storage, channels, wallet, validator)
+            }
+            None => start_pool_controller(cfg, &storage, channels, wallet),
+        }
+        .unwrap();
 
         Self {
             pool_manager,
@@ -158,11 +233,170 @@ pub fn operation_pool_test<F>(
             selector,
         },
         wallet,
-    );
+    )
+    .unwrap();
     test(pool_controller, storage);
     pool_manager.stop();
 }
 
+/// Poll `condition` until it holds, returning false if it still does not after `timeout`.
+pub fn wait_until(timeout: Duration, mut condition: impl FnMut() -> bool) -> bool {
+    let deadline = Instant::now() + timeout;
+    while !condition() {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    true
+}
+
+/// Create an execution controller mock that considers all operations as not executed
+/// and all addresses as wealthy.
+pub fn default_execution_controller() -> Box<MockExecutionController> {
+    let mut res = Box::new(MockExecutionController::new());
+    res.expect_clone_box().returning(|| {
+        let mut story = MockExecutionController::new();
+        story
+            .expect_get_ops_exec_status()
+            .returning(|ops| vec![(None, None); ops.len()]);
+        story
+            .expect_get_final_and_candidate_balance()
+            .returning(|addrs| {
+                vec![
+                    (
+                        // Operations need to be paid for
+                        Some(Amount::const_init(1_000_000_000, 0)),
+                        Some(Amount::const_init(1_000_000_000, 0)),
+                    );
+                    addrs.len()
+                ]
+            });
+
+        Box::new(story)
+    });
+    res
+}
+
+/// Create a selector controller mock that selects the filtered address for every slot.
+pub fn default_selector_controller() -> Box<AutoMockSelectorController> {
+    let mut res = Box::new(AutoMockSelectorController::new());
+    res.expect_clone_box().times(2).returning(|| {
+        let mut story = AutoMockSelectorController::new();
+        story
+            .expect_get_available_selections_in_range()
+            .returning(|slot_range, opt_addrs| {
+                let mut all_slots = BTreeMap::new();
+                let addr = *opt_addrs
+                    .expect("No addresses filter given")
+                    .iter()
+                    .next()
+                    .expect("No addresses given");
+                for i in 0..15 {
+                    for j in 0..32 {
+                        let s = Slot::new(i, j);
+                        if slot_range.contains(&s) {
+                            all_slots.insert(
+                                s,
+                                Selection {
+                                    producer: addr,
+                                    endorsements: vec![addr; ENDORSEMENT_COUNT as usize],
+                                },
+                            );
+                        }
+                    }
+                }
+                Ok(all_slots)
+            });
+        Box::new(story)
+    });
+    res
+}
+
+/// Create a selector controller mock that selects `address` as block producer
+/// and endorser for every slot.
+pub fn endorsement_selector_controller(address: Address) -> Box<AutoMockSelectorController> {
+    endorsers_selector_controller(address, vec![address; ENDORSEMENT_COUNT as usize])
+}
+
+/// Create a selector controller mock that selects `producer` as block producer
+/// and `endorsers[index]` as endorser of each index, for every slot.
+pub fn endorsers_selector_controller(
+    producer: Address,
+    endorsers: Vec<Address>,
+) -> Box<AutoMockSelectorController> {
+    let selection = move |_: Slot| -> PosResult<Selection> {
+        Ok(Selection {
+            producer,
+            endorsements: endorsers.clone(),
+        })
+    };
+    // the denunciation pool uses the original mock, the other pools use clones
+    let mut res = Box::new(AutoMockSelectorController::new());
+    res.expect_get_selection().returning(selection);
+    res.expect_clone_box().times(2).returning(move || {
+        let mut story = AutoMockSelectorController::new();
+        story
+            .expect_get_available_selections_in_range()
+            .returning(|_, _| Ok(BTreeMap::new()));
+        story.expect_get_selection().returning(selection);
+        Box::new(story)
+    });
+    res
+}
+
+/// Creates an endorsement signed by `keypair` for use in pool tests.
+pub fn create_endorsement_with(
+    keypair: &KeyPair,
+    slot: Slot,
+    index: u32,
+    endorsed_block: BlockId,
+) -> SecureShareEndorsement {
+    let content = Endorsement {
+        slot,
+        index,
+        endorsed_block,
+    };
+    Endorsement::new_verifiable(content, EndorsementSerializer::new(), keypair).unwrap()
+}
+
+/// Creates a block containing the given operations for use in pool tests.
+pub fn create_block(
+    creator: &KeyPair,
+    slot: Slot,
+    operations: &[SecureShareOperation],
+) -> SecureShareBlock {
+    let operation_merkle_root = Hash::compute_from(
+        &operations
+            .iter()
+            .flat_map(|op| op.serialized_data.clone())
+            .collect::<Vec<u8>>(),
+    );
+    let header = BlockHeader::new_verifiable(
+        BlockHeader {
+            current_version: 0,
+            announced_version: None,
+            slot,
+            parents: vec![],
+            operation_merkle_root,
+            endorsements: vec![],
+            denunciations: vec![],
+        },
+        BlockHeaderSerializer::new(),
+        creator,
+    )
+    .unwrap();
+    Block::new_verifiable(
+        Block {
+            header,
+            operations: operations.iter().map(|op| op.id).collect(),
+        },
+        BlockSerializer::new(),
+        creator,
+    )
+    .unwrap()
+}
+
 /// Creates an endorsement for use in pool tests.
 pub fn _create_endorsement(slot: Slot) -> SecureShareEndorsement {
     let sender_keypair = KeyPair::generate(0).unwrap();
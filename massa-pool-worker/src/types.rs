@@ -1,9 +1,13 @@
+use massa_hash::HASH_SIZE_BYTES;
 use massa_models::{
     address::Address,
     amount::Amount,
-    operation::{OperationId, SecureShareOperation},
+    operation::{OperationId, OperationTypeId, SecureShareOperation},
+    secure_share::Id,
+    slot::Slot,
 };
-use std::ops::RangeInclusive;
+use massa_pool_exports::OperationTypeStats;
+use std::{cmp::Ordering, ops::RangeInclusive};
 
 #[derive(Debug, Clone)]
 pub struct OperationInfo {
@@ -13,9 +17,15 @@ pub struct OperationInfo {
     pub creator_address: Address,
     pub thread: u8,
     pub fee: Amount,
+    pub op_type: OperationTypeId,
     /// max amount that the op might spend from the sender's balance
     pub max_spending: Amount,
     pub validity_period_range: RangeInclusive<u64>,
+    /// rank of the operation in the order of arrival into the pool
+    pub insertion_index: u64,
+    /// slot at which the operation arrived into the pool,
+    /// `None` if the pool was not notified of any slot yet
+    pub insertion_slot: Option<Slot>,
 }
 
 impl OperationInfo {
@@ -24,6 +34,8 @@ impl OperationInfo {
         operation_validity_periods: u64,
         roll_price: Amount,
         thread_count: u8,
+        insertion_index: u64,
+        insertion_slot: Option<Slot>,
     ) -> Self {
         OperationInfo {
             id: op.id,
@@ -31,9 +43,106 @@ impl OperationInfo {
             max_gas: op.get_gas_usage(),
             creator_address: op.content_creator_address,
             fee: op.content.fee,
+            op_type: op.get_type_id(),
             thread: op.content_creator_address.get_thread(thread_count),
             validity_period_range: op.get_validity_range(operation_validity_periods),
             max_spending: op.get_max_spending(roll_price),
+            insertion_index,
+            insertion_slot,
         }
     }
+
+    /// Get the last slot at which this operation can be included in a block
+    pub fn expiry_slot(&self) -> Slot {
+        Slot::new(*self.validity_period_range.end(), self.thread)
+    }
+
+    /// Get the key used to sort this operation in the pool
+    pub fn cursor(&self) -> OperationCursor {
+        OperationCursor {
+            fee: self.fee.to_raw(),
+            size: self.size,
+            id: self.id,
+        }
+    }
+}
+
+/// Running totals of the operations of a given type in the pool
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OperationTypeTotals {
+    /// number of operations
+    pub count: usize,
+    /// sum of the fees
+    pub total_fee: Amount,
+    /// sum of the serialized sizes
+    pub total_size: usize,
+}
+
+impl OperationTypeTotals {
+    /// Get the statistics of the operations of this type
+    pub fn stats(&self) -> OperationTypeStats {
+        OperationTypeStats {
+            count: self.count,
+            total_fee: self.total_fee,
+            avg_fee_per_byte: self
+                .total_fee
+                .checked_div_u64(self.total_size as u64)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Sorting key of an operation in the pool.
+///
+/// Operations are sorted by decreasing fee per byte,
+/// ties are broken by operation ID so that the order is total.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OperationCursor {
+    /// raw fee of the operation
+    pub fee: u64,
+    /// serialized size of the operation
+    pub size: usize,
+    /// operation ID
+    pub id: OperationId,
+}
+
+impl Ord for OperationCursor {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // compare fee/size ratios without divisions:
+        // operands are reversed to sort from highest to lowest fee per byte
+        let self_score = (other.fee as u128).saturating_mul(self.size as u128);
+        let other_score = (self.fee as u128).saturating_mul(other.size as u128);
+        self_score
+            .cmp(&other_score)
+            .then_with(|| self.id.cmp(&other.id))
+    }
+}
+
+impl PartialOrd for OperationCursor {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// First 8 bytes of the hash of an ID, as a big-endian integer.
+/// XORed over a set of IDs, it gives a cheap fingerprint of the set that can be updated incrementally.
+pub fn id_fingerprint<T: Id>(id: &T) -> u64 {
+    let bytes = id.get_hash().to_bytes();
+    u64::from_be_bytes(
+        bytes[..8]
+            .try_into()
+            .expect("hashes are longer than 8 bytes"),
+    )
+}
+
+/// XOR of the hashes of a set of IDs, one byte at a time.
+/// It does not depend on the order of the IDs, and an ID present twice cancels itself out.
+pub fn xor_ids<'a, T: Id + 'a, I: IntoIterator<Item = &'a T>>(ids: I) -> [u8; HASH_SIZE_BYTES] {
+    let mut res = [0u8; HASH_SIZE_BYTES];
+    for id in ids {
+        for (res_byte, id_byte) in res.iter_mut().zip(id.get_hash().to_bytes()) {
+            *res_byte ^= id_byte;
+        }
+    }
+    res
 }
@@ -0,0 +1,219 @@
+//! Copyright (c) 2022 MASSA LABS <info@massa.net>
+
+//! Write-ahead log of the operation pool, replayed on startup
+//! so that the pool content survives a crash of the node.
+//!
+//! The log starts with a header made of `WAL_MAGIC` and the format version `WAL_VERSION`
+//! (little-endian u32), followed by one record per added operation:
+//! the length of the serialized operation (little-endian u32), then the serialized operation.
+//! Operation IDs are not stored: they are computed again from the operations on replay.
+
+use massa_models::{
+    config::{
+        MAX_DATASTORE_VALUE_LENGTH, MAX_FUNCTION_NAME_LENGTH, MAX_OPERATION_DATASTORE_ENTRY_COUNT,
+        MAX_OPERATION_DATASTORE_KEY_LENGTH, MAX_OPERATION_DATASTORE_VALUE_LENGTH,
+        MAX_PARAMETERS_SIZE,
+    },
+    operation::{Operation, OperationDeserializer, SecureShareOperation},
+    secure_share::{SecureShareDeserializer, SecureShareSerializer},
+};
+use massa_serialization::{DeserializeError, Deserializer, Serializer};
+use massa_storage::Storage;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+use tracing::warn;
+
+/// Bytes identifying an operation pool write-ahead log
+const WAL_MAGIC: &[u8; 4] = b"MPWL";
+
+/// Version of the log format, to bump on any incompatible change
+const WAL_VERSION: u32 = 0;
+
+/// Length of the header of the log
+const WAL_HEADER_LEN: usize = WAL_MAGIC.len() + 4;
+
+/// Length of the size prefix of each record
+const RECORD_LEN_BYTES: usize = 4;
+
+/// Append-only log of the operations added to the operation pool
+pub(crate) struct OperationWal {
+    /// path of the log file
+    path: PathBuf,
+    /// buffered writer appending to the log file
+    writer: BufWriter<File>,
+    /// serializer of the logged operations
+    serializer: SecureShareSerializer,
+    /// number of records in the log, including the ones of operations that left the pool
+    record_count: usize,
+}
+
+impl OperationWal {
+    /// Opens the log at `path`, creating it and its parent directories if needed.
+    /// Returns the log along with a new reference set of `storage` holding the logged operations.
+    ///
+    /// Records that cannot be read, typically the last one if the node crashed while writing it,
+    /// are dropped along with all the following ones.
+    pub fn open(path: &Path, storage: &Storage) -> io::Result<(Self, Storage)> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = match fs::read(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err),
+        };
+        let (operations, valid_len) = read_records(&content)?;
+
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        if valid_len == 0 {
+            file.set_len(0)?;
+            file.write_all(WAL_MAGIC)?;
+            file.write_all(&WAL_VERSION.to_le_bytes())?;
+        } else if valid_len < content.len() {
+            warn!(
+                "dropping the last {} unreadable bytes of the operation pool write-ahead log {}",
+                content.len() - valid_len,
+                path.display()
+            );
+            file.set_len(valid_len as u64)?;
+        }
+
+        let record_count = operations.len();
+        let mut replayed = storage.clone_without_refs();
+        replayed.store_operations(operations);
+        Ok((
+            OperationWal {
+                path: path.to_path_buf(),
+                writer: BufWriter::new(file),
+                serializer: SecureShareSerializer::new(),
+                record_count,
+            },
+            replayed,
+        ))
+    }
+
+    /// Get the number of records in the log, including the ones of operations that left the pool
+    pub fn record_count(&self) -> usize {
+        self.record_count
+    }
+
+    /// Appends operations to the log, flushing it before returning
+    pub fn append<'a>(
+        &mut self,
+        operations: impl IntoIterator<Item = &'a SecureShareOperation>,
+    ) -> io::Result<()> {
+        self.record_count += write_records(&mut self.writer, &self.serializer, operations)?;
+        self.writer.flush()
+    }
+
+    /// Replaces the records of the log by `operations`.
+    ///
+    /// The new log is written to a temporary file next to the log, then renamed over it:
+    /// if the node crashes during the rewrite, the previous log is still complete.
+    pub fn rewrite<'a>(
+        &mut self,
+        operations: impl IntoIterator<Item = &'a SecureShareOperation>,
+    ) -> io::Result<()> {
+        self.writer.flush()?;
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        writer.write_all(WAL_MAGIC)?;
+        writer.write_all(&WAL_VERSION.to_le_bytes())?;
+        let record_count = write_records(&mut writer, &self.serializer, operations)?;
+        writer
+            .into_inner()
+            .map_err(|err| err.into_error())?
+            .sync_all()?;
+        fs::rename(&tmp_path, &self.path)?;
+        self.writer = BufWriter::new(OpenOptions::new().append(true).open(&self.path)?);
+        self.record_count = record_count;
+        Ok(())
+    }
+}
+
+/// Writes operations as log records, without flushing `writer`.
+/// Returns the number of written records.
+fn write_records<'a, W: Write>(
+    writer: &mut W,
+    serializer: &SecureShareSerializer,
+    operations: impl IntoIterator<Item = &'a SecureShareOperation>,
+) -> io::Result<usize> {
+    let mut buffer = Vec::new();
+    let mut count = 0;
+    for op in operations {
+        buffer.clear();
+        serializer
+            .serialize(op, &mut buffer)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+        let len = u32::try_from(buffer.len()).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("operation {} is too big to be logged", op.id),
+            )
+        })?;
+        writer.write_all(&len.to_le_bytes())?;
+        writer.write_all(&buffer)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Reads the operations of a log.
+/// Returns them along with the length of the readable part of the log,
+/// which is 0 if the log is empty or its header is incomplete.
+fn read_records(content: &[u8]) -> io::Result<(Vec<SecureShareOperation>, usize)> {
+    if content.len() < WAL_HEADER_LEN {
+        return Ok((Vec::new(), 0));
+    }
+    if &content[..WAL_MAGIC.len()] != WAL_MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not an operation pool write-ahead log",
+        ));
+    }
+    let version = u32::from_le_bytes(
+        content[WAL_MAGIC.len()..WAL_HEADER_LEN]
+            .try_into()
+            .expect("the version is 4 bytes long"),
+    );
+    if version != WAL_VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "unsupported operation pool write-ahead log version {} (expected {})",
+                version, WAL_VERSION
+            ),
+        ));
+    }
+
+    let deserializer: SecureShareDeserializer<Operation, OperationDeserializer> =
+        SecureShareDeserializer::new(OperationDeserializer::new(
+            MAX_DATASTORE_VALUE_LENGTH,
+            MAX_FUNCTION_NAME_LENGTH,
+            MAX_PARAMETERS_SIZE,
+            MAX_OPERATION_DATASTORE_ENTRY_COUNT,
+            MAX_OPERATION_DATASTORE_KEY_LENGTH,
+            MAX_OPERATION_DATASTORE_VALUE_LENGTH,
+        ));
+    let mut operations = Vec::new();
+    let mut offset = WAL_HEADER_LEN;
+    while let Some(len_bytes) = content.get(offset..offset + RECORD_LEN_BYTES) {
+        let len =
+            u32::from_le_bytes(len_bytes.try_into().expect("the length is 4 bytes long")) as usize;
+        let record_start = offset + RECORD_LEN_BYTES;
+        let Some(record) = content.get(record_start..record_start + len) else {
+            break;
+        };
+        match deserializer.deserialize::<DeserializeError>(record) {
+            Ok((rest, op)) if rest.is_empty() => operations.push(op),
+            _ => break,
+        }
+        offset = record_start + len;
+    }
+    Ok((operations, offset))
+}
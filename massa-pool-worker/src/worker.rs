@@ -2,23 +2,67 @@
 
 //! Write worker for the pools, allowing asynchronous writes.
 
-use crate::controller_impl::{Command, PoolManagerImpl};
+#[cfg(feature = "async")]
+use crate::async_sender::AsyncOperationsSender;
+use crate::controller_impl::{Command, PoolManagerImpl, WorkerActivity, WorkerReadiness};
 use crate::denunciation_pool::DenunciationPool;
+use crate::event_log::{log_command, EventLog, LoggedPool, SharedEventLog};
 use crate::operation_pool::OperationPool;
 use crate::{controller_impl::PoolControllerImpl, endorsement_pool::EndorsementPool};
-use massa_pool_exports::PoolConfig;
-use massa_pool_exports::{PoolChannels, PoolController, PoolManager};
+use massa_models::{operation::OperationId, prehash::PreHashSet, slot::Slot};
+use massa_pool_exports::{
+    EvictionReason, OperationValidator, PoolChannels, PoolController, PoolManager,
+};
+use massa_pool_exports::{PoolConfig, PoolConfigError};
 use massa_storage::Storage;
 use massa_wallet::Wallet;
-use parking_lot::RwLock;
-use std::time::Instant;
+use parking_lot::{Mutex, RwLock};
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{Duration, Instant};
 use std::{
     sync::mpsc::{sync_channel, Receiver, RecvError, RecvTimeoutError},
     sync::Arc,
     thread,
     thread::JoinHandle,
 };
-use tracing::warn;
+use tokio::sync::watch;
+use tracing::{debug, error, warn};
+
+/// Runs a worker loop, restarting it if it panics.
+///
+/// The pool locks are not poisoned by a panic, so the pool may have been left half-updated:
+/// `rebuild` is called before each restart to restore its indexes from the items it holds.
+/// Gives up after `max_restarts` restarts, or if `rebuild` panics too:
+/// the thread then exits and the pool manager reports it as unhealthy.
+fn run_with_restarts<F: FnMut(), R: FnMut()>(
+    name: &str,
+    max_restarts: u32,
+    mut run: F,
+    mut rebuild: R,
+) {
+    let mut restarts = 0;
+    while panic::catch_unwind(AssertUnwindSafe(&mut run)).is_err() {
+        if restarts >= max_restarts {
+            error!(
+                "{} worker panicked and reached its restart limit ({}), giving up",
+                name, max_restarts
+            );
+            return;
+        }
+        if panic::catch_unwind(AssertUnwindSafe(&mut rebuild)).is_err() {
+            error!(
+                "{} worker panicked and its pool could not be rebuilt, giving up",
+                name
+            );
+            return;
+        }
+        restarts += 1;
+        error!(
+            "{} worker panicked, restarting it (attempt {}/{})",
+            name, restarts, max_restarts
+        );
+    }
+}
 
 /// Endorsement pool write thread instance
 pub(crate) struct EndorsementPoolThread {
@@ -26,6 +70,10 @@ pub(crate) struct EndorsementPoolThread {
     receiver: Receiver<Command>,
     /// Shared reference to the pool
     endorsement_pool: Arc<RwLock<EndorsementPool>>,
+    /// Log of the processed commands, if `event_log_path` is set
+    event_log: Option<SharedEventLog>,
+    /// Commands received by the thread
+    activity: Arc<WorkerActivity>,
 }
 
 impl EndorsementPoolThread {
@@ -33,23 +81,40 @@ impl EndorsementPoolThread {
     pub(crate) fn spawn(
         receiver: Receiver<Command>,
         endorsement_pool: Arc<RwLock<EndorsementPool>>,
+        max_restarts: u32,
+        readiness: Arc<WorkerReadiness>,
+        event_log: Option<SharedEventLog>,
+        activity: Arc<WorkerActivity>,
     ) -> JoinHandle<()> {
         let thread_builder = thread::Builder::new().name("endorsement-pool".into());
         thread_builder
-            .spawn(|| {
+            .spawn(move || {
                 let this = Self {
                     receiver,
                     endorsement_pool,
+                    event_log,
+                    activity,
                 };
-                this.run()
+                readiness.set_ready();
+                run_with_restarts(
+                    "endorsement pool",
+                    max_restarts,
+                    || this.run(),
+                    || this.endorsement_pool.write().rebuild_from_storage(),
+                )
             })
             .expect("failed to spawn thread : endorsement-pool")
     }
 
     /// Runs the thread
-    fn run(self) {
+    fn run(&self) {
         loop {
-            match self.receiver.recv() {
+            let command = self.receiver.recv();
+            if let Ok(command) = &command {
+                self.activity.record_command(command);
+                log_command(&self.event_log, LoggedPool::Endorsements, command);
+            }
+            match command {
                 Err(RecvError) => break,
                 Ok(Command::Stop) => {
                     break;
@@ -61,6 +126,47 @@ impl EndorsementPoolThread {
                     .endorsement_pool
                     .write()
                     .notify_final_cs_periods(&final_cs_periods),
+                Ok(Command::NotifyNewSlot(slot)) => {
+                    self.endorsement_pool.write().notify_new_slot(&slot)
+                }
+                Ok(Command::RemoveEndorsements(ids, response_tx)) => {
+                    let removed = self
+                        .endorsement_pool
+                        .write()
+                        .remove_endorsements(&ids, EvictionReason::Included);
+                    if let Some(response_tx) = response_tx {
+                        if response_tx.send(removed).is_err() {
+                            warn!("could not send the removed endorsement count: receiver dropped");
+                        }
+                    }
+                }
+                Ok(Command::WarmUpEndorsements(endorsements, response_tx)) => {
+                    let added = self.endorsement_pool.write().warm_up(endorsements);
+                    if response_tx.send(added).is_err() {
+                        warn!("could not send the warmed up endorsement count: receiver dropped");
+                    }
+                }
+                Ok(Command::CompactEndorsements) => self.endorsement_pool.write().compact(),
+                #[cfg(feature = "testing")]
+                Ok(Command::ClearEndorsements) => self.endorsement_pool.write().clear(),
+                Ok(Command::PurgeAll) => self.endorsement_pool.write().purge_all(),
+                Ok(Command::Flush(ack_tx)) => {
+                    let _ = ack_tx.send(());
+                }
+                Ok(Command::SnapshotEndorsements(response_tx)) => {
+                    let snapshot = self.endorsement_pool.read().snapshot();
+                    if response_tx.send(snapshot).is_err() {
+                        warn!("could not send endorsement pool snapshot: receiver dropped");
+                    }
+                }
+                Ok(Command::BlockFinalized {
+                    block_id,
+                    endorsement_ids,
+                    ..
+                }) => self
+                    .endorsement_pool
+                    .write()
+                    .notify_block_finalized(&block_id, &endorsement_ids),
                 _ => {
                     warn!("EndorsementPoolThread received an unexpected command");
                     continue;
@@ -76,6 +182,12 @@ pub(crate) struct OperationPoolThread {
     receiver: Receiver<Command>,
     /// Shared reference to the operation pool
     operation_pool: Arc<RwLock<OperationPool>>,
+    /// Log of the processed commands, if `event_log_path` is set
+    event_log: Option<SharedEventLog>,
+    /// Validator re-checking the operations of the pool every `revalidation_interval_slots` slots
+    validator: Option<Box<dyn OperationValidator + Send>>,
+    /// Commands received by the thread
+    activity: Arc<WorkerActivity>,
 }
 
 impl OperationPoolThread {
@@ -84,6 +196,10 @@ impl OperationPoolThread {
         receiver: Receiver<Command>,
         operation_pool: Arc<RwLock<OperationPool>>,
         config: PoolConfig,
+        readiness: Arc<WorkerReadiness>,
+        event_log: Option<SharedEventLog>,
+        validator: Option<Box<dyn OperationValidator + Send>>,
+        activity: Arc<WorkerActivity>,
     ) -> JoinHandle<()> {
         let thread_builder = thread::Builder::new().name("operation-pool".into());
         thread_builder
@@ -91,20 +207,72 @@ impl OperationPoolThread {
                 let this = Self {
                     receiver,
                     operation_pool,
+                    event_log,
+                    validator,
+                    activity,
                 };
-                this.run(config)
+                readiness.set_ready();
+                run_with_restarts(
+                    "operation pool",
+                    config.worker_restart_max_attempts,
+                    || this.run(&config),
+                    || this.operation_pool.write().rebuild_from_storage(),
+                )
             })
             .expect("failed to spawn thread: operation-pool")
     }
 
+    /// Evict the operations of the pool rejected by the validator, if any.
+    /// The validator is called without holding the pool lock.
+    fn revalidate_operations(&self) {
+        let Some(validator) = &self.validator else {
+            return;
+        };
+        let op_ids = self.operation_pool.read().operation_ids();
+        let invalid: PreHashSet<OperationId> = op_ids
+            .into_iter()
+            .filter(|id| !validator.validate(id))
+            .collect();
+        if !invalid.is_empty() {
+            debug!(
+                "evicting {} operations rejected by the operation validator",
+                invalid.len()
+            );
+            self.operation_pool
+                .write()
+                .remove_operations(&invalid, EvictionReason::Invalid);
+        }
+    }
+
+    /// Warn about the operations waiting in the pool for more than `stale_after_slots` slots
+    fn warn_stale_operations(&self, slot: &Slot, stale_after_slots: u64) {
+        let operation_pool = self.operation_pool.read();
+        let stale = operation_pool.get_stale_operations(slot, stale_after_slots);
+        if let Some(oldest) = stale.first() {
+            warn!(
+                "{} operations have been waiting in the pool for more than {} slots, the oldest one {} for {} slots",
+                stale.len(),
+                stale_after_slots,
+                oldest,
+                operation_pool.get_operation_age(oldest).unwrap_or_default()
+            );
+        }
+    }
+
     /// Run the thread.
-    fn run(self, config: PoolConfig) {
+    fn run(&self, config: &PoolConfig) {
         let mut start_time = Instant::now();
+        let mut slots_since_revalidation = 0;
         let tick = config.operation_pool_refresh_interval.to_duration();
         loop {
             let duration = (start_time + tick).saturating_duration_since(Instant::now());
             if !duration.is_zero() {
-                match self.receiver.recv_timeout(duration) {
+                let command = self.receiver.recv_timeout(duration);
+                if let Ok(command) = &command {
+                    self.activity.record_command(command);
+                    log_command(&self.event_log, LoggedPool::Operations, command);
+                }
+                match command {
                     Err(RecvTimeoutError::Disconnected) | Ok(Command::Stop) => break,
                     Ok(Command::AddItems(operations)) => {
                         self.operation_pool.write().add_operations(operations)
@@ -113,6 +281,60 @@ impl OperationPoolThread {
                         .operation_pool
                         .write()
                         .notify_final_cs_periods(&final_cs_periods),
+                    Ok(Command::NotifyNewSlot(slot)) => {
+                        self.operation_pool.write().notify_new_slot(&slot);
+                        if config.stale_operation_warn_slots > 0 {
+                            self.warn_stale_operations(&slot, config.stale_operation_warn_slots);
+                        }
+                        slots_since_revalidation += 1;
+                        if config.revalidation_interval_slots > 0
+                            && slots_since_revalidation >= config.revalidation_interval_slots
+                        {
+                            slots_since_revalidation = 0;
+                            self.revalidate_operations();
+                        }
+                    }
+                    Ok(Command::ReInjectOperations(block_id, block_storage)) => self
+                        .operation_pool
+                        .write()
+                        .reinject_operations_from_block(&block_id, block_storage),
+                    Ok(Command::RemoveOperations(ids)) => self
+                        .operation_pool
+                        .write()
+                        .remove_operations(&ids.into_iter().collect(), EvictionReason::Included),
+                    #[cfg(feature = "testing")]
+                    Ok(Command::ClearOperations) => self.operation_pool.write().clear(),
+                    Ok(Command::PurgeAll) => self.operation_pool.write().purge_all(),
+                    Ok(Command::SetMinFee(min_fee, response_tx)) => {
+                        let pruned = self.operation_pool.write().set_min_fee(min_fee);
+                        debug!(
+                            "operation pool minimal fee set to {}, {} operations pruned",
+                            min_fee, pruned
+                        );
+                        if response_tx.send(pruned).is_err() {
+                            warn!("could not send the pruned operation count: receiver dropped");
+                        }
+                    }
+                    Ok(Command::Flush(ack_tx)) => {
+                        let _ = ack_tx.send(());
+                    }
+                    Ok(Command::QueryOperationCount(response_tx)) => {
+                        let _ = response_tx.send(self.operation_pool.read().len());
+                    }
+                    Ok(Command::SnapshotOperations(response_tx)) => {
+                        let snapshot = self.operation_pool.read().snapshot();
+                        if response_tx.send(snapshot).is_err() {
+                            warn!("could not send operation pool snapshot: receiver dropped");
+                        }
+                    }
+                    Ok(Command::BlockFinalized {
+                        block_id,
+                        operation_ids,
+                        ..
+                    }) => self
+                        .operation_pool
+                        .write()
+                        .notify_block_finalized(&block_id, &operation_ids),
                     Ok(_) => {
                         warn!("OperationPoolThread received an unexpected command");
                         continue;
@@ -133,6 +355,8 @@ pub(crate) struct DenunciationPoolThread {
     receiver: Receiver<Command>,
     /// Shared reference to the denunciation pool
     denunciation_pool: Arc<RwLock<DenunciationPool>>,
+    /// Commands received by the thread
+    activity: Arc<WorkerActivity>,
 }
 
 impl DenunciationPoolThread {
@@ -140,23 +364,33 @@ impl DenunciationPoolThread {
     pub(crate) fn spawn(
         receiver: Receiver<Command>,
         denunciation_pool: Arc<RwLock<DenunciationPool>>,
+        max_restarts: u32,
+        readiness: Arc<WorkerReadiness>,
+        activity: Arc<WorkerActivity>,
     ) -> JoinHandle<()> {
         let thread_builder = thread::Builder::new().name("denunciation-pool".into());
         thread_builder
-            .spawn(|| {
+            .spawn(move || {
                 let this = Self {
                     receiver,
                     denunciation_pool,
+                    activity,
                 };
-                this.run()
+                readiness.set_ready();
+                // the denunciation pool has a single index, there is nothing to rebuild
+                run_with_restarts("denunciation pool", max_restarts, || this.run(), || {})
             })
             .expect("failed to spawn thread : denunciation-pool")
     }
 
     /// Run the thread.
-    fn run(self) {
+    fn run(&self) {
         loop {
-            match self.receiver.recv() {
+            let command = self.receiver.recv();
+            if let Ok(command) = &command {
+                self.activity.record_command(command);
+            }
+            match command {
                 Err(RecvError) => {
                     break;
                 }
@@ -175,55 +409,176 @@ impl DenunciationPoolThread {
                     .denunciation_pool
                     .write()
                     .notify_final_cs_periods(&final_cs_periods),
+                Ok(_) => {
+                    warn!("DenunciationPoolThread received an unexpected command");
+                    continue;
+                }
             };
         }
     }
 }
 
-/// Start pool manager and controller
+/// Start pool manager and controller.
+/// Returns an error if the configuration is inconsistent, before any worker thread is spawned.
 #[allow(clippy::type_complexity)]
 pub fn start_pool_controller(
     config: PoolConfig,
     storage: &Storage,
     channels: PoolChannels,
     wallet: Arc<RwLock<Wallet>>,
-) -> (Box<dyn PoolManager>, Box<dyn PoolController>) {
+) -> Result<(Box<dyn PoolManager>, Box<dyn PoolController>), PoolConfigError> {
+    let (manager, controller) = start_pools(config, storage, channels, wallet, None)?;
+    Ok((Box::new(manager), Box::new(controller)))
+}
+
+/// Start pool manager and controller, the operations of the pool being re-validated
+/// by `validator` every `revalidation_interval_slots` slots.
+/// Returns an error if the configuration is inconsistent, before any worker thread is spawned.
+#[allow(clippy::type_complexity)]
+pub fn start_pool_controller_with_validator(
+    config: PoolConfig,
+    storage: &Storage,
+    channels: PoolChannels,
+    wallet: Arc<RwLock<Wallet>>,
+    validator: Box<dyn OperationValidator + Send>,
+) -> Result<(Box<dyn PoolManager>, Box<dyn PoolController>), PoolConfigError> {
+    let (manager, controller) = start_pools(config, storage, channels, wallet, Some(validator))?;
+    Ok((Box::new(manager), Box::new(controller)))
+}
+
+/// Start pool manager and controller, along with a sender of operations to the pool for async contexts.
+/// Returns an error if the configuration is inconsistent, before any worker thread is spawned.
+#[cfg(feature = "async")]
+#[allow(clippy::type_complexity)]
+pub fn start_pool_controller_with_async_sender(
+    config: PoolConfig,
+    storage: &Storage,
+    channels: PoolChannels,
+    wallet: Arc<RwLock<Wallet>>,
+) -> Result<
+    (
+        Box<dyn PoolManager>,
+        Box<dyn PoolController>,
+        AsyncOperationsSender,
+    ),
+    PoolConfigError,
+> {
+    let operations_channel_size = config.operations_channel_size;
+    let (manager, controller) = start_pools(config, storage, channels, wallet, None)?;
+    let async_sender = AsyncOperationsSender::spawn(
+        controller.operations_input_sender.clone(),
+        controller.operations_activity.clone(),
+        operations_channel_size,
+    );
+    Ok((Box::new(manager), Box::new(controller), async_sender))
+}
+
+/// Spawn the pool worker threads
+fn start_pools(
+    config: PoolConfig,
+    storage: &Storage,
+    channels: PoolChannels,
+    wallet: Arc<RwLock<Wallet>>,
+    validator: Option<Box<dyn OperationValidator + Send>>,
+) -> Result<(PoolManagerImpl, PoolControllerImpl), PoolConfigError> {
+    config.validate()?;
+    let started_at = Instant::now();
     let (operations_input_sender, operations_input_receiver) =
         sync_channel(config.operations_channel_size);
     let (endorsements_input_sender, endorsements_input_receiver) =
         sync_channel(config.endorsements_channel_size);
     let (denunciations_input_sender, denunciations_input_receiver) =
         sync_channel(config.denunciations_channel_size);
+    let (pool_sizes_sender, pool_sizes_receiver) = watch::channel((0, 0));
+    let pool_sizes_sender = Arc::new(pool_sizes_sender);
     let operation_pool = Arc::new(RwLock::new(OperationPool::init(
-        config,
+        config.clone(),
         storage,
         channels.clone(),
         wallet.clone(),
+        pool_sizes_sender.clone(),
     )));
     let endorsement_pool = Arc::new(RwLock::new(EndorsementPool::init(
-        config,
+        config.clone(),
         storage,
         channels.clone(),
         wallet,
+        pool_sizes_sender,
+    )));
+    let denunciation_pool = Arc::new(RwLock::new(DenunciationPool::init(
+        config.clone(),
+        channels,
     )));
-    let denunciation_pool = Arc::new(RwLock::new(DenunciationPool::init(config, channels)));
+    let operations_bloom_filter = operation_pool.read().bloom_filter.clone();
+    let operation_shards = operation_pool.read().shards.clone();
+    let operations_estimated_memory = operation_pool.read().estimated_memory.clone();
+    let endorsements_bloom_filter = endorsement_pool.read().bloom_filter.clone();
+    let endorsement_slots = endorsement_pool.read().endorsement_slots.clone();
+    let operations_activity = Arc::new(WorkerActivity::default());
+    let endorsements_activity = Arc::new(WorkerActivity::default());
+    let denunciations_activity = Arc::new(WorkerActivity::default());
     let controller = PoolControllerImpl {
-        _config: config,
+        config: config.clone(),
         operation_pool: operation_pool.clone(),
         endorsement_pool: endorsement_pool.clone(),
         denunciation_pool: denunciation_pool.clone(),
+        operations_bloom_filter,
+        operation_shards,
+        operations_estimated_memory,
+        endorsements_bloom_filter,
+        endorsement_slots,
+        endorsements_storage: storage.clone_without_refs(),
         operations_input_sender: operations_input_sender.clone(),
         endorsements_input_sender: endorsements_input_sender.clone(),
         denunciations_input_sender: denunciations_input_sender.clone(),
         last_cs_final_periods: vec![0u64; usize::from(config.thread_count)],
+        pool_sizes_receiver,
+        channel_full_events: Default::default(),
+        operations_activity: operations_activity.clone(),
+        endorsements_activity: endorsements_activity.clone(),
+        denunciations_activity: denunciations_activity.clone(),
     };
 
-    let operations_thread_handle =
-        OperationPoolThread::spawn(operations_input_receiver, operation_pool, config);
-    let endorsements_thread_handle =
-        EndorsementPoolThread::spawn(endorsements_input_receiver, endorsement_pool);
-    let denunciations_thread_handle =
-        DenunciationPoolThread::spawn(denunciations_input_receiver, denunciation_pool);
+    let event_log = config
+        .event_log_path
+        .as_ref()
+        .and_then(|path| match EventLog::open(path) {
+            Ok(event_log) => Some(Arc::new(Mutex::new(event_log))),
+            Err(err) => {
+                warn!(
+                    "could not open the pool event log {}, commands will not be logged: {}",
+                    path.display(),
+                    err
+                );
+                None
+            }
+        });
+
+    let readiness = Arc::new(WorkerReadiness::default());
+    let operations_thread_handle = OperationPoolThread::spawn(
+        operations_input_receiver,
+        operation_pool,
+        config.clone(),
+        readiness.clone(),
+        event_log.clone(),
+        validator,
+        operations_activity.clone(),
+    );
+    let endorsements_thread_handle = EndorsementPoolThread::spawn(
+        endorsements_input_receiver,
+        endorsement_pool,
+        config.worker_restart_max_attempts,
+        readiness.clone(),
+        event_log,
+        endorsements_activity.clone(),
+    );
+    let denunciations_thread_handle = DenunciationPoolThread::spawn(
+        denunciations_input_receiver,
+        denunciation_pool,
+        config.worker_restart_max_attempts,
+        readiness.clone(),
+        denunciations_activity.clone(),
+    );
 
     let manager = PoolManagerImpl {
         operations_thread_handle: Some(operations_thread_handle),
@@ -232,6 +587,13 @@ pub fn start_pool_controller(
         operations_input_sender,
         endorsements_input_sender,
         denunciations_input_sender,
+        stop_timeout: Duration::from_millis(config.worker_stop_timeout_ms),
+        readiness,
+        operations_activity,
+        endorsements_activity,
+        denunciations_activity,
+        controller: controller.clone(),
+        started_at,
     };
-    (Box::new(manager), Box::new(controller))
+    Ok((manager, controller))
 }
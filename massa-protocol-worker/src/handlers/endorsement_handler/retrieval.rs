@@ -275,8 +275,8 @@ pub(crate) fn note_endorsements_from_peer(
         );
     }
 
-    // Add to pool
-    pool_controller.add_endorsements(endorsement_store);
+    // Add to pool, which logs the endorsements it could not add
+    let _ = pool_controller.add_endorsements(endorsement_store);
 
     Ok(())
 }
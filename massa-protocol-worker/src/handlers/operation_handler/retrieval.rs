@@ -457,8 +457,8 @@ pub(crate) fn note_operations_from_peer(
             warn!("Error sending operations to propagation channel");
         }
 
-        // Add to pool
-        pool_controller.add_operations(ops);
+        // Add to pool, which logs the operations it could not add
+        let _ = pool_controller.add_operations(ops);
     }
 
     Ok(())
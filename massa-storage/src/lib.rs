@@ -371,6 +371,21 @@ impl Storage {
         self.operations.read()
     }
 
+    /// Looks up several operations in the global operation storage at once, in the order of `ids`,
+    /// without collecting them. Each item is a shared handle on the stored operation,
+    /// or `None` if absent: no operation is copied.
+    ///
+    /// The operation index stays read-locked until the returned iterator is dropped,
+    /// blocking the writers of all the storage instances meanwhile:
+    /// drain it promptly, and do not store operations from the same thread before dropping it.
+    pub fn batch_get_operations<'a>(
+        &'a self,
+        ids: &'a [OperationId],
+    ) -> impl Iterator<Item = (OperationId, Option<Arc<SecureShareOperation>>)> + 'a {
+        let stored_ops = self.operations.read();
+        ids.iter().map(move |id| (*id, stored_ops.get_shared(id)))
+    }
+
     /// Gets a read reference to the endorsements index
     pub fn read_endorsements(&self) -> RwLockReadGuard<EndorsementIndexes> {
         self.endorsements.read()
@@ -1,4 +1,7 @@
-use std::collections::hash_map::{self, Entry};
+use std::{
+    collections::hash_map::{self, Entry},
+    sync::Arc,
+};
 
 use massa_models::{
     address::Address,
@@ -10,8 +13,8 @@ use massa_models::{
 /// Note: The structure can evolve and store more indexes.
 #[derive(Default)]
 pub struct OperationIndexes {
-    /// Operations structure container
-    operations: PreHashMap<OperationId, Box<SecureShareOperation>>,
+    /// Operations structure container, shared so that lookups can outlive the index lock
+    operations: PreHashMap<OperationId, Arc<SecureShareOperation>>,
     /// Structure mapping creators with the created operations
     index_by_creator: PreHashMap<Address, PreHashSet<OperationId>>,
     /// Structure indexing operations by ID prefix
@@ -24,7 +27,7 @@ impl OperationIndexes {
     /// * `operation`: the operation to insert
     pub(crate) fn insert(&mut self, operation: SecureShareOperation) {
         if let Entry::Vacant(vac) = self.operations.entry(operation.id) {
-            let operation = vac.insert(Box::new(operation));
+            let operation = vac.insert(Arc::new(operation));
             // update creator index
             self.index_by_creator
                 .entry(operation.content_creator_address)
@@ -46,7 +49,7 @@ impl OperationIndexes {
     pub(crate) fn remove(
         &mut self,
         operation_id: &OperationId,
-    ) -> Option<Box<SecureShareOperation>> {
+    ) -> Option<Arc<SecureShareOperation>> {
         if let Some(o) = self.operations.remove(operation_id) {
             massa_metrics::set_operations_counter(self.operations.len());
 
@@ -76,6 +79,12 @@ impl OperationIndexes {
         self.operations.get(id).map(|v| v.as_ref())
     }
 
+    /// Gets a shared handle on a stored operation, if any.
+    /// Unlike `get`, the handle remains valid once the index is unlocked.
+    pub fn get_shared(&self, id: &OperationId) -> Option<Arc<SecureShareOperation>> {
+        self.operations.get(id).cloned()
+    }
+
     /// Checks whether an operation exists in global storage.
     pub fn contains(&self, id: &OperationId) -> bool {
         self.operations.contains_key(id)
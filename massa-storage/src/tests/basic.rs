@@ -1,7 +1,15 @@
 use crate::Storage;
 use massa_factory_exports::test_exports::create_empty_block;
-use massa_models::{prehash::PreHashSet, slot::Slot};
+use massa_models::{
+    address::Address,
+    amount::Amount,
+    operation::{Operation, OperationId, OperationSerializer, OperationType},
+    prehash::PreHashSet,
+    secure_share::SecureShareContent,
+    slot::Slot,
+};
 use massa_signature::KeyPair;
+use std::sync::Arc;
 
 #[test]
 /// Store a block and retrieve it.
@@ -37,3 +45,51 @@ fn test_double_insert() {
         assert!(blocks.get(&block.id).is_none());
     };
 }
+
+#[test]
+/// Look up several operations at once, in order, the missing ones included, without copying them.
+fn test_batch_get_operations() {
+    let mut storage = Storage::create_root();
+    let keypair = KeyPair::generate(0).unwrap();
+    let recipient_address = Address::from_public_key(&keypair.get_public_key());
+    let operations: Vec<_> = (0..3)
+        .map(|fee| {
+            let content = Operation {
+                fee: Amount::from_raw(fee),
+                op: OperationType::Transaction {
+                    recipient_address,
+                    amount: Amount::zero(),
+                },
+                expire_period: 10,
+            };
+            Operation::new_verifiable(content, OperationSerializer::new(), &keypair).unwrap()
+        })
+        .collect();
+    // the last operation is not stored
+    storage.store_operations(operations[..2].to_vec());
+
+    let ids: Vec<OperationId> = vec![operations[1].id, operations[2].id, operations[0].id];
+    let found: Vec<(OperationId, Option<OperationId>)> = storage
+        .batch_get_operations(&ids)
+        .map(|(id, op)| (id, op.map(|op| op.id)))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (operations[1].id, Some(operations[1].id)),
+            (operations[2].id, None),
+            (operations[0].id, Some(operations[0].id)),
+        ]
+    );
+
+    // the lookups share the stored operation instead of copying it
+    let lookup = || {
+        storage
+            .batch_get_operations(&ids[..1])
+            .next()
+            .unwrap()
+            .1
+            .unwrap()
+    };
+    assert!(Arc::ptr_eq(&lookup(), &lookup()));
+}